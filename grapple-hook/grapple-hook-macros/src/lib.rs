@@ -228,12 +228,42 @@ pub fn rpc(_attr: TokenStream, input: TokenStream) -> TokenStream {
         }
     });
 
+    let method_info = fns.clone().map(|f| {
+        let name = f.sig.ident.to_string();
+        let docs = doc_string(&f.attrs);
+        let params = f.sig.inputs.iter().filter_map(|x| match x {
+            FnArg::Receiver(_) => None,
+            FnArg::Typed(t) => match t.pat.as_ref() {
+                Pat::Ident(pident) => {
+                    let param_name = pident.ident.to_string();
+                    let param_ty = type_to_string(&t.ty);
+                    Some(quote! { crate::rpc::RpcParamInfo { name: #param_name.to_owned(), ty: #param_ty.to_owned() } })
+                },
+                _ => Err("Unsupported FnArg!").unwrap()
+            }
+        });
+        let returns = match &f.sig.output {
+            syn::ReturnType::Default => "()".to_owned(),
+            syn::ReturnType::Type(_arrow, t) => {
+                let t: &syn::Type = t;
+                type_to_string(extract_type_from_result(t).unwrap_or(t))
+            }
+        };
+        quote! {
+            crate::rpc::RpcMethodInfo { name: #name.to_owned(), docs: #docs.to_owned(), params: vec![#(#params),*], returns: #returns.to_owned() }
+        }
+    });
+
     let rpc_fn = quote! {
         pub async fn rpc_process(&self, msg: #request_enum_ident) -> anyhow::Result<#response_enum_ident> {
             match msg {
                 #(#rpc_call_body),*
             }
         }
+
+        pub fn rpc_methods() -> Vec<crate::rpc::RpcMethodInfo> {
+            vec![#(#method_info),*]
+        }
     };
 
     // TODO: These need to eject the inner type from anyhow::Result.
@@ -270,6 +300,17 @@ pub fn rpc(_attr: TokenStream, input: TokenStream) -> TokenStream {
 
 /* Helpers */
 
+fn doc_string(attrs: &[Attribute]) -> String {
+    attrs.iter().filter_map(|attr| match &attr.meta {
+        syn::Meta::NameValue(MetaNameValue { path, value: syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(s), .. }), .. }) if path.is_ident("doc") => Some(s.value().trim().to_owned()),
+        _ => None
+    }).collect::<Vec<_>>().join("\n")
+}
+
+fn type_to_string(ty: &syn::Type) -> String {
+    quote! { #ty }.to_string().replace(' ', "")
+}
+
 // Adapted from https://stackoverflow.com/a/56264023
 fn extract_type_from_result(ty: &syn::Type) -> Option<&syn::Type> {
   fn extract_type_path(ty: &syn::Type) -> Option<&Path> {
@@ -0,0 +1,192 @@
+use std::{collections::{HashMap, VecDeque}, sync::{Mutex, OnceLock}};
+
+use crate::{events::{events, EventSeverity}, persistence::Persisted};
+
+/* Anomaly detection over telemetry. Detectors are registered against channel names and see every sample recorded on a
+   matching channel, flagging unusual readings as events. Each can be turned off or made more or less sensitive. */
+
+/* Don't flag the same detector on the same channel more often than this */
+const ANOMALY_COOLDOWN_MS: i64 = 10_000;
+
+pub trait Detector: Send {
+  /* Returns a description of what's wrong if this sample is anomalous. Higher sensitivity flags smaller deviations. */
+  fn observe(&mut self, timestamp_ms: i64, value: f64, sensitivity: f64) -> Option<String>;
+}
+
+/* Flags samples more than a number of standard deviations from the recent mean */
+pub struct ZScore {
+  window: VecDeque<f64>,
+  size: usize,
+  threshold: f64,
+}
+
+impl ZScore {
+  pub fn new(size: usize, threshold: f64) -> Self {
+    Self { window: VecDeque::new(), size, threshold }
+  }
+}
+
+impl Detector for ZScore {
+  fn observe(&mut self, _timestamp_ms: i64, value: f64, sensitivity: f64) -> Option<String> {
+    let n = self.window.len();
+    let result = if n >= self.size / 4 {
+      let mean = self.window.iter().sum::<f64>() / n as f64;
+      let std = (self.window.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n as f64).sqrt();
+      let z = if std > f64::EPSILON { (value - mean) / std } else { 0.0 };
+      (z.abs() > self.threshold / sensitivity).then(|| format!("reading of {:.1} is {:.1} standard deviations from the recent average of {:.1}", value, z.abs(), mean))
+    } else {
+      None
+    };
+
+    self.window.push_back(value);
+    while self.window.len() > self.size {
+      self.window.pop_front();
+    }
+    result
+  }
+}
+
+/* Flags a channel that sits at (or past) a limit for too long, e.g. a distance sensor stuck reading its maximum */
+pub struct AtLimit {
+  limit: f64,
+  hold_ms: i64,
+  since: Option<i64>,
+}
+
+impl AtLimit {
+  pub fn new(limit: f64, hold_ms: i64) -> Self {
+    Self { limit, hold_ms, since: None }
+  }
+}
+
+impl Detector for AtLimit {
+  fn observe(&mut self, timestamp_ms: i64, value: f64, sensitivity: f64) -> Option<String> {
+    if value < self.limit {
+      self.since = None;
+      return None;
+    }
+    let since = *self.since.get_or_insert(timestamp_ms);
+    let held = timestamp_ms - since;
+    (held as f64 >= self.hold_ms as f64 / sensitivity).then(|| format!("has read {:.0} or more for {:.1}s", self.limit, held as f64 / 1000.0))
+  }
+}
+
+pub struct DetectorSpec {
+  pub id: &'static str,
+  pub description: &'static str,
+  /* Channel name, optionally with a single * wildcard (e.g. "channel*_voltage") */
+  pub channel: &'static str,
+  pub make: fn() -> Box<dyn Detector>,
+}
+
+pub const DETECTORS: &[DetectorSpec] = &[
+  DetectorSpec {
+    id: "distance_stuck_at_max",
+    description: "LaserCAN distance stuck at the top of its range (lens covered, sensor misaligned or nothing in view)",
+    channel: "distance_mm",
+    make: || Box::new(AtLimit::new(4000.0, 5000)),
+  },
+  DetectorSpec {
+    id: "rail_voltage_outlier",
+    description: "MitoCANdria rail voltage far from its recent average",
+    channel: "channel*_voltage",
+    make: || Box::new(ZScore::new(200, 5.0)),
+  },
+  DetectorSpec {
+    id: "channel_current_spike",
+    description: "MitoCANdria channel current far above or below its recent average",
+    channel: "channel*_current",
+    make: || Box::new(ZScore::new(200, 6.0)),
+  },
+];
+
+fn channel_matches(pattern: &str, channel: &str) -> bool {
+  match pattern.split_once('*') {
+    Some((prefix, suffix)) => channel.len() >= prefix.len() + suffix.len() && channel.starts_with(prefix) && channel.ends_with(suffix),
+    None => pattern == channel
+  }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct DetectorSettings {
+  pub enabled: bool,
+  /* 1.0 is the default, 2.0 flags deviations half the size */
+  pub sensitivity: f64,
+}
+
+impl Default for DetectorSettings {
+  fn default() -> Self {
+    Self { enabled: true, sensitivity: 1.0 }
+  }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct DetectorInfo {
+  pub id: String,
+  pub description: String,
+  pub channel: String,
+  pub settings: DetectorSettings,
+}
+
+struct DetectorState {
+  detector: Box<dyn Detector>,
+  last_flagged_ms: Option<i64>,
+}
+
+pub struct AnomalyDetection {
+  settings: Persisted<HashMap<String, DetectorSettings>>,
+  states: Mutex<HashMap<(u32, String, &'static str), DetectorState>>,
+}
+
+impl AnomalyDetection {
+  pub fn settings(&self, id: &str) -> DetectorSettings {
+    self.settings.read(|s| s.get(id).cloned()).unwrap_or_default()
+  }
+
+  pub fn detectors(&self) -> Vec<DetectorInfo> {
+    DETECTORS.iter().map(|d| DetectorInfo {
+      id: d.id.to_owned(), description: d.description.to_owned(), channel: d.channel.to_owned(), settings: self.settings(d.id)
+    }).collect()
+  }
+
+  pub fn configure(&self, id: &str, enabled: Option<bool>, sensitivity: Option<f64>) -> anyhow::Result<()> {
+    if !DETECTORS.iter().any(|d| d.id == id) {
+      anyhow::bail!("No anomaly detector called {}", id);
+    }
+    if let Some(sensitivity) = sensitivity.filter(|s| !(*s > 0.0 && s.is_finite())) {
+      anyhow::bail!("Sensitivity must be above zero (got {})", sensitivity);
+    }
+    self.settings.update(|s| {
+      let settings = s.entry(id.to_owned()).or_default();
+      if let Some(enabled) = enabled { settings.enabled = enabled; }
+      if let Some(sensitivity) = sensitivity { settings.sensitivity = sensitivity; }
+    });
+    // Start afresh, so the new sensitivity doesn't act on a window gathered under the old one
+    self.states.lock().unwrap().retain(|k, _| k.2 != id);
+    Ok(())
+  }
+
+  pub fn observe(&self, serial: u32, channel: &str, timestamp_ms: i64, value: f64) {
+    for spec in DETECTORS.iter().filter(|d| channel_matches(d.channel, channel)) {
+      let settings = self.settings(spec.id);
+      if !settings.enabled {
+        continue;
+      }
+
+      let mut states = self.states.lock().unwrap();
+      let state = states.entry((serial, channel.to_owned(), spec.id)).or_insert_with(|| DetectorState { detector: (spec.make)(), last_flagged_ms: None });
+      if let Some(problem) = state.detector.observe(timestamp_ms, value, settings.sensitivity) {
+        if state.last_flagged_ms.map(|t| timestamp_ms - t >= ANOMALY_COOLDOWN_MS).unwrap_or(true) {
+          state.last_flagged_ms = Some(timestamp_ms);
+          drop(states);
+          events().emit(Some(serial), "anomaly", EventSeverity::Warning, format!("Unusual {}: {} {}", spec.id, channel, problem));
+        }
+      }
+    }
+  }
+}
+
+pub fn anomalies() -> &'static AnomalyDetection {
+  static DETECTION: OnceLock<AnomalyDetection> = OnceLock::new();
+  DETECTION.get_or_init(|| AnomalyDetection { settings: Persisted::load("anomaly_detectors"), states: Mutex::new(HashMap::new()) })
+}
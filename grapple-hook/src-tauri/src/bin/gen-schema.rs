@@ -1,39 +1,63 @@
-use std::{path::Path, fs, env};
-
-use grapple_hook::{devices::{flexican::{FlexiCanRequest, FlexiCanResponse}, lasercan::{LaserCanRequest, LaserCanResponse}, mitocandria::{MitocandriaRequest, MitocandriaResponse}, provider_manager::{ProviderManagerRequest, ProviderManagerResponse}, roborio::daemon::{RoboRioDaemonRequest, RoboRioDaemonResponse}, FirmwareUpgradeDeviceRequest, FirmwareUpgradeDeviceResponse, OldVersionDeviceRequest, OldVersionDeviceResponse}, updates::LightReleaseResponse};
-
-#[derive(schemars::JsonSchema)]
-#[allow(unused)]
-struct MegaSchema {
-  provider_manager_req: ProviderManagerRequest,
-  provider_manager_rsp: ProviderManagerResponse,
-  
-  old_version_req: OldVersionDeviceRequest,
-  old_version_rsp: OldVersionDeviceResponse,
-
-  firmware_req: FirmwareUpgradeDeviceRequest,
-  firmware_rsp: FirmwareUpgradeDeviceResponse,
-
-  lasercan_req: LaserCanRequest,
-  lasercan_rsp: LaserCanResponse,
-
-  flexican_req: FlexiCanRequest,
-  flexican_rsp: FlexiCanResponse,
-
-  mitocandria_req: MitocandriaRequest,
-  mitocandria_rsp: MitocandriaResponse,
-
-  roborio_req: RoboRioDaemonRequest,
-  roborio_rsp: RoboRioDaemonResponse,
-
-  light_release_response: LightReleaseResponse,
-}
-
-fn main() -> anyhow::Result<()> {
-  let args: Vec<String> = env::args().collect();
-  let file = Path::new(args.get(1).expect("No path provided"));
-  let schema = schemars::schema_for!(MegaSchema);
-
-  fs::write(file, serde_json::to_string_pretty(&schema)?)?;
-  Ok(())
+use std::{path::Path, fs, env};
+
+use grapple_hook::{devices::{changes::{DeviceChange, Hotplug}, flexican::{FlexiCanRequest, FlexiCanResponse}, generic_grapple::{GenericGrappleDeviceRequest, GenericGrappleDeviceResponse}, lasercan::{LaserCanRequest, LaserCanResponse}, mitocandria::{MitocandriaRequest, MitocandriaResponse}, provider_manager::{ProviderManagerRequest, ProviderManagerResponse}, roborio::daemon::{RoboRioDaemonRequest, RoboRioDaemonResponse}, slcan::{SlcanRequest, SlcanResponse}, gs_usb::{GsUsbRequest, GsUsbResponse}, pcan::usb::{PcanUsbRequest, PcanUsbResponse}, spiderlan::{SpiderLanRequest, SpiderLanResponse}, FirmwareUpgradeDeviceRequest, FirmwareUpgradeDeviceResponse, OldVersionDeviceRequest, OldVersionDeviceResponse}, telemetry_stream::TelemetryPush, updates::LightReleaseResponse};
+
+#[derive(schemars::JsonSchema)]
+#[allow(unused)]
+struct MegaSchema {
+  provider_manager_req: ProviderManagerRequest,
+  provider_manager_rsp: ProviderManagerResponse,
+  
+  old_version_req: OldVersionDeviceRequest,
+  old_version_rsp: OldVersionDeviceResponse,
+
+  firmware_req: FirmwareUpgradeDeviceRequest,
+  firmware_rsp: FirmwareUpgradeDeviceResponse,
+
+  lasercan_req: LaserCanRequest,
+  lasercan_rsp: LaserCanResponse,
+
+  flexican_req: FlexiCanRequest,
+  flexican_rsp: FlexiCanResponse,
+
+  mitocandria_req: MitocandriaRequest,
+  mitocandria_rsp: MitocandriaResponse,
+
+  spiderlan_req: SpiderLanRequest,
+  spiderlan_rsp: SpiderLanResponse,
+
+  roborio_req: RoboRioDaemonRequest,
+  roborio_rsp: RoboRioDaemonResponse,
+
+  // SocketCAN only exists on Linux, so update the schema from a Linux machine to keep these in it
+  #[cfg(target_os = "linux")]
+  socketcan_req: grapple_hook::devices::socketcan::SocketCanRequest,
+  #[cfg(target_os = "linux")]
+  socketcan_rsp: grapple_hook::devices::socketcan::SocketCanResponse,
+
+  slcan_req: SlcanRequest,
+  slcan_rsp: SlcanResponse,
+
+  gs_usb_req: GsUsbRequest,
+  gs_usb_rsp: GsUsbResponse,
+
+  pcan_usb_req: PcanUsbRequest,
+  pcan_usb_rsp: PcanUsbResponse,
+
+  generic_grapple_req: GenericGrappleDeviceRequest,
+  generic_grapple_rsp: GenericGrappleDeviceResponse,
+
+  light_release_response: LightReleaseResponse,
+  device_change: DeviceChange,
+  hotplug: Hotplug,
+  telemetry_push: TelemetryPush,
+}
+
+fn main() -> anyhow::Result<()> {
+  let args: Vec<String> = env::args().collect();
+  let file = Path::new(args.get(1).expect("No path provided"));
+  let schema = schemars::schema_for!(MegaSchema);
+
+  fs::write(file, serde_json::to_string_pretty(&schema)?)?;
+  Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,317 @@
+use std::{env, time::Duration};
+
+use grapple_hook::{devices::{device_manager::DeviceManager, flexican::FlexiCan, generic_grapple::GenericGrappleDevice, lasercan::LaserCan, mitocandria::Mitocandria, provider::WrappedDeviceProvider, provider_manager::ProviderManager, roborio::daemon::RoboRioDaemon, slcan::Slcan, gs_usb::GsUsb, pcan::usb::PcanUsb, soak::{run_soak, SoakConfig}, spiderlan::SpiderLan, FirmwareUpgradeDevice, GrappleDevice, OldVersionDevice}, rpc::{RpcBase, RpcMethodInfo}};
+use serde_json::{json, Value};
+
+#[cfg(target_os = "linux")]
+use grapple_hook::devices::socketcan::SocketCan;
+
+/* How long to wait for a device to answer enumeration after connecting to its provider */
+const DEVICE_WAIT: Duration = Duration::from_secs(5);
+
+/* How a call to each target gets to it. Everything goes in through a ProviderManager, as it does from the app. */
+enum Route {
+  ProviderManager,
+  /* The provider itself (connect, info and so on), by address */
+  Provider,
+  /* The provider's own RPCs, e.g. a roboRIO's or an adapter's settings */
+  Transport,
+  DeviceManager,
+  /* A device, by provider address, domain and serial */
+  Device,
+}
+
+/* Builds targets() and route() from the one list, so a target can't be listed without a route. The test at the
+   bottom checks every #[rpc] type is in it. */
+macro_rules! targets {
+  ($($(#[$attr:meta])* $name:literal => $ty:ty, $route:ident;)*) => {
+    fn targets() -> Vec<(&'static str, Vec<RpcMethodInfo>)> {
+      let mut targets = Vec::new();
+      $( $(#[$attr])* targets.push(($name, <$ty>::rpc_methods())); )*
+      targets
+    }
+
+    fn route(target: &str) -> Route {
+      match target {
+        $( $(#[$attr])* $name => Route::$route, )*
+        _ => Route::Device
+      }
+    }
+
+    #[cfg(test)]
+    fn target_types() -> Vec<&'static str> {
+      let mut types = Vec::new();
+      $( $(#[$attr])* types.push(stringify!($ty)); )*
+      types
+    }
+  }
+}
+
+targets! {
+  "provider-manager" => ProviderManager, ProviderManager;
+  "provider" => WrappedDeviceProvider, Provider;
+  "device-manager" => DeviceManager, DeviceManager;
+  "roborio" => RoboRioDaemon, Transport;
+  "slcan" => Slcan, Transport;
+  "gs-usb" => GsUsb, Transport;
+  "pcan-usb" => PcanUsb, Transport;
+  // SocketCAN only exists on Linux
+  #[cfg(target_os = "linux")]
+  "socketcan" => SocketCan, Transport;
+  "grapple" => GrappleDevice, Device;
+  "lasercan" => LaserCan, Device;
+  "mitocandria" => Mitocandria, Device;
+  "flexican" => FlexiCan, Device;
+  "spiderlan" => SpiderLan, Device;
+  "firmware-upgrade" => FirmwareUpgradeDevice<LaserCan>, Device;
+  "old-version" => OldVersionDevice, Device;
+  "generic-grapple" => GenericGrappleDevice, Device;
+}
+
+fn print_method(method: &RpcMethodInfo) {
+  let params = method.params.iter().map(|p| format!("--{} <{}>", p.name, p.ty)).collect::<Vec<_>>().join(" ");
+  println!("  {} {}", method.name, params);
+  if !method.docs.is_empty() {
+    for line in method.docs.lines() {
+      println!("      {}", line);
+    }
+  }
+  println!("      -> {}", method.returns);
+}
+
+fn help(target: Option<&str>, method: Option<&str>) -> anyhow::Result<()> {
+  let targets = targets();
+  match target {
+    None => {
+      println!("Usage: grapple-hook-cli <target> [<method>] --help");
+      println!("       grapple-hook-cli <target> <method> [--via <provider>] [--domain <domain> --serial <serial>] [--<param> <value> ...]");
+      println!("       grapple-hook-cli completions bash");
+      println!("       grapple-hook-cli metadata");
+      println!();
+      println!("Targets:");
+      for (name, _) in targets.iter() {
+        println!("  {}", name);
+      }
+    },
+    Some(target) => {
+      let (_, methods) = targets.iter().find(|(name, _)| *name == target).ok_or(anyhow::anyhow!("Unknown target: {}", target))?;
+      match method {
+        None => {
+          println!("Methods for {}:", target);
+          methods.iter().for_each(print_method);
+        },
+        Some(method) => print_method(methods.iter().find(|m| m.name == method).ok_or(anyhow::anyhow!("Unknown method: {}", method))?)
+      }
+    }
+  }
+  Ok(())
+}
+
+fn bash_completions() -> String {
+  let targets = targets();
+  let mut cases = String::new();
+  for (name, methods) in targets.iter() {
+    let method_names = methods.iter().map(|m| m.name.clone()).collect::<Vec<_>>().join(" ");
+    cases += &format!("    {}) COMPREPLY=( $(compgen -W \"{}\" -- \"$cur\") ) ;;\n", name, method_names);
+    for method in methods {
+      let params = method.params.iter().map(|p| format!("--{}", p.name)).collect::<Vec<_>>().join(" ");
+      cases += &format!("    {}:{}) COMPREPLY=( $(compgen -W \"{} --help\" -- \"$cur\") ) ;;\n", name, method.name, params);
+    }
+  }
+  let target_names = targets.iter().map(|(n, _)| *n).collect::<Vec<_>>().join(" ");
+
+  format!(r#"_grapple_hook_cli() {{
+  local cur="${{COMP_WORDS[COMP_CWORD]}}"
+  if [ "$COMP_CWORD" -eq 1 ]; then
+    COMPREPLY=( $(compgen -W "{} completions metadata --help" -- "$cur") )
+    return
+  fi
+  local key="${{COMP_WORDS[1]}}"
+  if [ "$COMP_CWORD" -gt 2 ]; then
+    key="${{COMP_WORDS[1]}}:${{COMP_WORDS[2]}}"
+  fi
+  case "$key" in
+{}  esac
+}}
+complete -F _grapple_hook_cli grapple-hook-cli
+"#, target_names, cases)
+}
+
+fn routing_flag<'a>(flags: &[(&str, &'a str)], name: &str, target: &str) -> anyhow::Result<&'a str> {
+  flags.iter().find(|(n, _)| *n == name).map(|(_, v)| *v).ok_or(anyhow::anyhow!("{} needs --{}", target, name))
+}
+
+/* Run one RPC. Parameters are given as --name value, and each value is taken as JSON if it parses as JSON, or as a
+   string if it doesn't. --via, --domain and --serial say where the call goes, for targets below the provider manager. */
+fn call(target: &str, method: &str, args: &[&str]) -> anyhow::Result<()> {
+  let targets = targets();
+  let (_, methods) = targets.iter().find(|(name, _)| *name == target).ok_or(anyhow::anyhow!("Unknown target: {}", target))?;
+  let info = methods.iter().find(|m| m.name == method).ok_or(anyhow::anyhow!("Unknown method: {}", method))?;
+
+  if args.len() % 2 != 0 {
+    anyhow::bail!("Parameters are given as --name value pairs");
+  }
+  let mut data = serde_json::Map::new();
+  let mut flags = vec![];
+  for pair in args.chunks(2) {
+    let name = pair[0].strip_prefix("--").ok_or(anyhow::anyhow!("Expected --name, got {}", pair[0]))?;
+    let value = serde_json::from_str(pair[1]).unwrap_or(Value::String(pair[1].to_owned()));
+    match (name, info.params.iter().any(|p| p.name == name)) {
+      (_, true) => { data.insert(name.to_owned(), value); },
+      ("via" | "domain" | "serial", false) => flags.push((name, pair[1])),
+      (_, false) => anyhow::bail!("{} has no parameter {}", method, name)
+    }
+  }
+  if let Some(missing) = info.params.iter().find(|p| !data.contains_key(&p.name)) {
+    anyhow::bail!("Missing --{} <{}>", missing.name, missing.ty);
+  }
+
+  // Each layer of routing wraps the request, and the response comes back wrapped the same number of times
+  let mut request = json!({ "method": method, "data": data });
+  let mut layers = 1;
+  let route = route(target);
+  if target == "grapple" {
+    // The common methods every Grapple device has are reached through the device's own grapple method
+    request = json!({ "method": "grapple", "data": { "msg": request } });
+    layers += 1;
+  }
+  if let Route::Device = route {
+    let serial = routing_flag(&flags, "serial", target)?;
+    let serial = u32::from_str_radix(serial.trim_start_matches("0x"), 16).map_err(|_| anyhow::anyhow!("Serials are in hex, not {}", serial))?;
+    request = json!({ "method": "call", "data": { "domain": routing_flag(&flags, "domain", target)?, "device_id": { "Serial": serial }, "data": request } });
+    layers += 1;
+  }
+  match route {
+    Route::Transport => { request = json!({ "method": "call", "data": { "req": request } }); layers += 1; },
+    Route::DeviceManager | Route::Device => { request = json!({ "method": "device_manager_call", "data": { "req": request } }); layers += 1; },
+    _ => ()
+  }
+  let via = match route {
+    Route::ProviderManager => None,
+    _ => Some(routing_flag(&flags, "via", target)?.to_owned())
+  };
+  if let Some(address) = &via {
+    request = json!({ "method": "provider", "data": { "address": address, "msg": request } });
+    layers += 1;
+  }
+
+  tokio::runtime::Runtime::new()?.block_on(async move {
+    let manager = ProviderManager::new().await;
+    manager.detect_devices().await?;
+
+    // Devices only show up once their provider is connected and they've answered enumeration
+    if let (Some(address), Route::Device | Route::DeviceManager) = (&via, &route) {
+      let providers = manager.rpc_call(json!({ "method": "providers", "data": {} })).await?;
+      let connected = providers["data"].get(address).ok_or(anyhow::anyhow!("No provider at {}", address))?["connected"].as_bool().unwrap_or(false);
+      if !connected {
+        manager.rpc_call(json!({ "method": "provider", "data": { "address": address, "msg": { "method": "connect", "data": {} } } })).await?;
+      }
+      if let Route::Device = route {
+        let start = std::time::Instant::now();
+        while !has_device(&manager, address, flags.iter().find(|(n, _)| *n == "domain").map(|(_, v)| *v).unwrap_or_default()).await && start.elapsed() < DEVICE_WAIT {
+          tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+      }
+    } else if let Some(address) = &via {
+      let providers = manager.rpc_call(json!({ "method": "providers", "data": {} })).await?;
+      providers["data"].get(address).ok_or(anyhow::anyhow!("No provider at {}", address))?;
+    }
+
+    let mut response = manager.rpc_call(request).await?;
+    for _ in 0..layers {
+      response = response.get_mut("data").map(Value::take).unwrap_or(Value::Null);
+    }
+    println!("{}", serde_json::to_string_pretty(&response)?);
+    Ok(())
+  })
+}
+
+async fn has_device(manager: &ProviderManager, address: &str, domain: &str) -> bool {
+  let request = json!({ "method": "provider", "data": { "address": address, "msg": { "method": "device_manager_call", "data": { "req": { "method": "devices", "data": {} } } } } });
+  match manager.rpc_call(request).await {
+    Ok(response) => response["data"]["data"]["data"].get(domain).and_then(|d| d.as_array()).map(|d| !d.is_empty()).unwrap_or(false),
+    Err(_) => false
+  }
+}
+
+/* Not listed in the help, since it's only of use to maintainers. Runs against a throwaway data directory unless
+   GRAPPLEHOOK_DATA_DIR is set, so the soak's renames and flashes don't end up in the real metadata and library. */
+fn soak(args: &[&str]) -> anyhow::Result<()> {
+  let mut config = SoakConfig::default();
+  let mut report_path = "soak-report.json".to_owned();
+  for pair in args.chunks(2) {
+    match pair {
+      ["--hours", v] => config.duration_s = (v.parse::<f64>()? * 3600.0) as u64,
+      ["--seed", v] => config.seed = v.parse()?,
+      ["--devices", v] => config.devices = v.parse()?,
+      ["--report", v] => report_path = v.to_string(),
+      _ => anyhow::bail!("Usage: grapple-hook-cli soak [--hours <h>] [--seed <n>] [--devices <n>] [--report <path>]")
+    }
+  }
+
+  if env::var("GRAPPLEHOOK_DATA_DIR").is_err() {
+    env::set_var("GRAPPLEHOOK_DATA_DIR", env::temp_dir().join(format!("grapple-hook-soak-{}", config.seed)));
+  }
+  env_logger::init();
+
+  let report = tokio::runtime::Runtime::new()?.block_on(run_soak(config))?;
+  std::fs::write(&report_path, serde_json::to_string_pretty(&report)?)?;
+  println!("{} failures over {} RPC calls, {} firmware updates. Report written to {}", report.total_failures, report.rpc_calls, report.firmware_updates, report_path);
+  if !report.passed() {
+    std::process::exit(1);
+  }
+  Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+  let args: Vec<String> = env::args().skip(1).collect();
+  let args: Vec<&str> = args.iter().map(|x| x.as_str()).collect();
+
+  match &args[..] {
+    [] | ["--help"] | ["help"] => help(None, None),
+    ["completions", "bash"] => { print!("{}", bash_completions()); Ok(()) },
+    ["completions", shell] => anyhow::bail!("Unsupported shell: {}", shell),
+    ["soak", rest @ ..] => soak(rest),
+    ["metadata"] => {
+      let metadata = targets().into_iter().collect::<std::collections::BTreeMap<_, _>>();
+      println!("{}", serde_json::to_string_pretty(&metadata)?);
+      Ok(())
+    },
+    [target] | [target, "--help"] => help(Some(*target), None),
+    [target, method, .., "--help"] => help(Some(*target), Some(*method)),
+    [target, method, rest @ ..] => call(target, method, rest),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::Path;
+
+  fn rpc_types(dir: &Path, found: &mut Vec<String>) {
+    let re = regex::Regex::new(r"#\[rpc\]\s*impl(?:<[^{]*?>)?\s+(\w+)").unwrap();
+    for entry in std::fs::read_dir(dir).unwrap() {
+      let path = entry.unwrap().path();
+      if path.is_dir() {
+        rpc_types(&path, found);
+      } else if path.extension().is_some_and(|e| e == "rs") {
+        if !cfg!(target_os = "linux") && path.file_stem().is_some_and(|s| s == "socketcan") {
+          continue;
+        }
+        let src = std::fs::read_to_string(&path).unwrap();
+        found.extend(re.captures_iter(&src).map(|c| c[1].to_owned()));
+      }
+    }
+  }
+
+  #[test]
+  fn every_rpc_type_is_a_target() {
+    let registered = super::target_types().iter().map(|ty| ty.split('<').next().unwrap().trim().to_owned()).collect::<Vec<_>>();
+
+    let mut found = vec![];
+    rpc_types(&Path::new(env!("CARGO_MANIFEST_DIR")).join("src"), &mut found);
+    assert!(!found.is_empty());
+    for ty in found {
+      assert!(registered.contains(&ty), "{} has RPCs but isn't a grapple-hook-cli target", ty);
+    }
+  }
+}
\ No newline at end of file
@@ -0,0 +1,139 @@
+use std::borrow::Cow;
+
+use grapple_frc_msgs::{binmarshal::{LengthTaggedPayload, LengthTaggedPayloadOwned}, bridge::BridgedCANMessage, MessageId};
+
+/* The gs_usb protocol spoken by candleLight firmware (candleLight, CANable 2.0 and friends). Setup is done with vendor
+   control requests, and frames go back and forth over a pair of bulk endpoints as fixed-size little-endian structs. */
+
+pub const BREQ_HOST_FORMAT: u8 = 0;
+pub const BREQ_BITTIMING: u8 = 1;
+pub const BREQ_MODE: u8 = 2;
+pub const BREQ_BT_CONST: u8 = 4;
+
+pub const ENDPOINT_IN: u8 = 0x81;
+pub const ENDPOINT_OUT: u8 = 0x02;
+
+/* Tells the device we're little-endian */
+pub const HOST_FORMAT: u32 = 0x0000_beef;
+pub const MODE_RESET: u32 = 0;
+pub const MODE_START: u32 = 1;
+
+const CAN_EFF_FLAG: u32 = 0x8000_0000;
+const CAN_RTR_FLAG: u32 = 0x4000_0000;
+const CAN_ERR_FLAG: u32 = 0x2000_0000;
+const CAN_EFF_MASK: u32 = 0x1FFF_FFFF;
+
+/* Frames the device received have this echo ID. Anything else is one of ours being echoed back once it's sent. */
+const ECHO_ID_RX: u32 = 0xFFFF_FFFF;
+/* echo_id, can_id, can_dlc, channel, flags, reserved, data[8] */
+pub const HOST_FRAME_LEN: usize = 20;
+
+/* What the device can do with its bit timing, from BREQ_BT_CONST */
+#[derive(Debug, Clone)]
+pub struct BitTimingConst {
+  pub fclk_can: u32,
+  pub tseg1_min: u32,
+  pub tseg1_max: u32,
+  pub tseg2_min: u32,
+  pub tseg2_max: u32,
+  pub sjw_max: u32,
+  pub brp_min: u32,
+  pub brp_max: u32,
+  pub brp_inc: u32,
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+  data.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+impl BitTimingConst {
+  pub fn parse(data: &[u8]) -> Option<Self> {
+    // The first field is the feature bitmask, which we don't need
+    Some(Self {
+      fclk_can: u32_at(data, 4)?,
+      tseg1_min: u32_at(data, 8)?, tseg1_max: u32_at(data, 12)?,
+      tseg2_min: u32_at(data, 16)?, tseg2_max: u32_at(data, 20)?,
+      sjw_max: u32_at(data, 24)?,
+      brp_min: u32_at(data, 28)?, brp_max: u32_at(data, 32)?, brp_inc: u32_at(data, 36)?,
+    })
+  }
+
+  /* The BREQ_BITTIMING payload (prop_seg, phase_seg1, phase_seg2, sjw, brp) for a bitrate, aiming for the 87.5% sample
+     point CAN in Automation recommends. Tries the most time quanta per bit first, as that gets closest to it. */
+  pub fn bit_timing(&self, bitrate: u32) -> Option<[u8; 20]> {
+    for tq in (8..=(1 + self.tseg1_max + self.tseg2_max)).rev() {
+      let per_bit = bitrate.checked_mul(tq)?;
+      if self.fclk_can % per_bit != 0 {
+        continue;
+      }
+      let brp = self.fclk_can / per_bit;
+      if brp < self.brp_min || brp > self.brp_max || (brp - self.brp_min) % self.brp_inc.max(1) != 0 {
+        continue;
+      }
+      let tseg2 = (tq / 8).max(self.tseg2_min);
+      let tseg1 = tq - 1 - tseg2;
+      if tseg2 > self.tseg2_max || tseg1 < self.tseg1_min.max(2) || tseg1 > self.tseg1_max {
+        continue;
+      }
+
+      let mut payload = [0u8; 20];
+      for (i, v) in [1, tseg1 - 1, tseg2, self.sjw_max.min(tseg2).max(1), brp].iter().enumerate() {
+        payload[i * 4..i * 4 + 4].copy_from_slice(&v.to_le_bytes());
+      }
+      return Some(payload);
+    }
+    None
+  }
+}
+
+pub fn mode(mode: u32) -> [u8; 8] {
+  let mut payload = [0u8; 8];
+  payload[0..4].copy_from_slice(&mode.to_le_bytes());
+  payload
+}
+
+pub enum GsUsbFrame {
+  Frame(BridgedCANMessage<'static>),
+  /* The device telling us about a bus error, with the error class bits */
+  Error(u32),
+  /* One of ours, echoed back once it's on the bus, or a frame that isn't ours (standard or remote) */
+  Ignored,
+}
+
+pub fn decode_frame(data: &[u8]) -> Option<GsUsbFrame> {
+  let echo_id = u32_at(data, 0)?;
+  let can_id = u32_at(data, 4)?;
+  let len = (*data.get(8)? as usize).min(8);
+  let payload = data.get(12..12 + len)?;
+
+  if echo_id != ECHO_ID_RX {
+    return Some(GsUsbFrame::Ignored);
+  }
+  if can_id & CAN_ERR_FLAG != 0 {
+    return Some(GsUsbFrame::Error(can_id & CAN_EFF_MASK));
+  }
+  // FRC devices only use extended data frames
+  if can_id & CAN_EFF_FLAG == 0 || can_id & CAN_RTR_FLAG != 0 {
+    return Some(GsUsbFrame::Ignored);
+  }
+
+  Some(GsUsbFrame::Frame(BridgedCANMessage {
+    id: MessageId::from(can_id & CAN_EFF_MASK), timestamp: 0,
+    data: Cow::<LengthTaggedPayload<u8>>::Owned(LengthTaggedPayloadOwned::new(payload.to_vec())).into()
+  }))
+}
+
+pub fn encode_frame(msg: &BridgedCANMessage<'_>, echo_id: u32) -> anyhow::Result<Vec<u8>> {
+  let data = &msg.data[..];
+  if data.len() > 8 {
+    anyhow::bail!("gs_usb frames carry at most 8 bytes, not {}", data.len());
+  }
+  let id: u32 = msg.id.clone().into();
+
+  let mut frame = vec![0u8; HOST_FRAME_LEN];
+  frame[0..4].copy_from_slice(&echo_id.to_le_bytes());
+  frame[4..8].copy_from_slice(&((id & CAN_EFF_MASK) | CAN_EFF_FLAG).to_le_bytes());
+  frame[8] = data.len() as u8;
+  frame[12..12 + data.len()].copy_from_slice(data);
+  Ok(frame)
+}
@@ -1,2 +1,4 @@
+pub mod gs_usb;
+pub mod slcan_codec;
 pub mod tcp_can_bridge;
 pub mod usb_codec;
\ No newline at end of file
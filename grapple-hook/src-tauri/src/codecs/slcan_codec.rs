@@ -0,0 +1,100 @@
+use std::borrow::Cow;
+
+use bytes::{Buf, BufMut};
+use grapple_frc_msgs::{binmarshal::{LengthTaggedPayload, LengthTaggedPayloadOwned}, bridge::BridgedCANMessage, MessageId};
+use log::warn;
+use tokio_util::codec::{Decoder, Encoder};
+
+/* The SLCAN (Lawicel) ASCII protocol spoken by most cheap USB-CAN sticks. Everything is a line ending in \r, e.g.
+   "T1234567830A0B0C\r" is an extended frame with ID 0x12345678 and 3 bytes of data. The adapter answers commands with
+   \r (OK) or \a (refused), and some answer each transmitted frame with z\r. */
+
+/* The S<n> setup codes, by bitrate */
+pub const SLCAN_BITRATES: &[(u32, char)] = &[
+  (10_000, '0'), (20_000, '1'), (50_000, '2'), (100_000, '3'), (125_000, '4'),
+  (250_000, '5'), (500_000, '6'), (800_000, '7'), (1_000_000, '8'),
+];
+
+const MAX_LINE_LEN: usize = 64;
+
+pub enum SlcanCommand {
+  Close,
+  Bitrate(u32),
+  Open,
+}
+
+pub struct SlcanCodec;
+
+fn parse_frame(line: &[u8]) -> Option<BridgedCANMessage<'static>> {
+  let line = std::str::from_utf8(line).ok()?;
+  // FRC devices only use extended IDs, so standard ('t') and remote ('r', 'R') frames aren't ours
+  let rest = line.strip_prefix('T')?;
+  let id = u32::from_str_radix(rest.get(0..8)?, 16).ok()?;
+  let len = usize::from_str_radix(rest.get(8..9)?, 16).ok().filter(|l| *l <= 8)?;
+  let data = (0..len).map(|i| rest.get(9 + i*2..11 + i*2).and_then(|b| u8::from_str_radix(b, 16).ok())).collect::<Option<Vec<u8>>>()?;
+
+  Some(BridgedCANMessage { id: MessageId::from(id), timestamp: 0, data: Cow::<LengthTaggedPayload<u8>>::Owned(LengthTaggedPayloadOwned::new(data)).into() })
+}
+
+impl Decoder for SlcanCodec {
+  type Item = BridgedCANMessage<'static>;
+  type Error = anyhow::Error;
+
+  fn decode(&mut self, src: &mut bytes::BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+    loop {
+      let end = match src.iter().position(|b| *b == b'\r' || *b == 0x07) {
+        Some(end) => end,
+        None if src.len() > MAX_LINE_LEN => anyhow::bail!("Adapter sent a line longer than {} bytes, is it an SLCAN adapter?", MAX_LINE_LEN),
+        None => return Ok(None)
+      };
+      let refused = src[end] == 0x07;
+      let line = src.split_to(end).to_vec();
+      src.advance(1);
+
+      if refused {
+        warn!("SLCAN adapter refused a command");
+        continue;
+      }
+      match line.first() {
+        // Acknowledgements of a command, or of a frame we sent
+        None | Some(b'z') | Some(b'Z') => continue,
+        Some(_) => match parse_frame(&line) {
+          Some(frame) => return Ok(Some(frame)),
+          None => continue
+        }
+      }
+    }
+  }
+}
+
+impl Encoder<BridgedCANMessage<'_>> for SlcanCodec {
+  type Error = anyhow::Error;
+
+  fn encode(&mut self, item: BridgedCANMessage<'_>, dst: &mut bytes::BytesMut) -> Result<(), Self::Error> {
+    let data = &item.data[..];
+    if data.len() > 8 {
+      anyhow::bail!("SLCAN frames carry at most 8 bytes, not {}", data.len());
+    }
+    let id: u32 = item.id.into();
+    let line = format!("T{:08X}{:X}{}\r", id, data.len(), data.iter().map(|b| format!("{:02X}", b)).collect::<String>());
+    dst.put(line.as_bytes());
+    Ok(())
+  }
+}
+
+impl Encoder<SlcanCommand> for SlcanCodec {
+  type Error = anyhow::Error;
+
+  fn encode(&mut self, item: SlcanCommand, dst: &mut bytes::BytesMut) -> Result<(), Self::Error> {
+    let line = match item {
+      SlcanCommand::Close => "C\r".to_owned(),
+      SlcanCommand::Open => "O\r".to_owned(),
+      SlcanCommand::Bitrate(bitrate) => {
+        let code = SLCAN_BITRATES.iter().find(|(b, _)| *b == bitrate).ok_or(anyhow::anyhow!("SLCAN doesn't support {}bit/s", bitrate))?.1;
+        format!("S{}\r", code)
+      }
+    };
+    dst.put(line.as_bytes());
+    Ok(())
+  }
+}
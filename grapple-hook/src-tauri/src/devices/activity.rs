@@ -0,0 +1,45 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use super::clock::Clock;
+
+pub const ACTIVITY_WINDOW_SECS: u64 = 30;
+
+/* Counts messages received from a device in 1 second buckets, so the device list can show which devices are
+   actively transmitting. */
+pub struct ActivityTracker {
+  clock: Clock,
+  start_ms: i64,
+  buckets: Mutex<VecDeque<(u64, u32)>>,
+}
+
+impl ActivityTracker {
+  pub fn new(clock: Clock) -> Self {
+    Self { start_ms: clock.now_ms(), clock, buckets: Mutex::new(VecDeque::new()) }
+  }
+
+  fn now_secs(&self) -> u64 {
+    (self.clock.now_ms() - self.start_ms).max(0) as u64 / 1000
+  }
+
+  pub fn record(&self) {
+    let now = self.now_secs();
+    let mut buckets = self.buckets.lock().unwrap();
+    match buckets.back_mut() {
+      Some((second, count)) if *second == now => *count += 1,
+      _ => buckets.push_back((now, 1)),
+    }
+    while buckets.front().map(|(second, _)| now - second >= ACTIVITY_WINDOW_SECS).unwrap_or(false) {
+      buckets.pop_front();
+    }
+  }
+
+  /* Messages per second over the last ACTIVITY_WINDOW_SECS seconds, oldest first */
+  pub fn series(&self) -> Vec<u32> {
+    let now = self.now_secs();
+    let buckets = self.buckets.lock().unwrap();
+    (0..ACTIVITY_WINDOW_SECS).rev().map(|ago| {
+      let second = now.checked_sub(ago);
+      buckets.iter().find(|(s, _)| Some(*s) == second).map(|(_, c)| *c).unwrap_or(0)
+    }).collect()
+  }
+}
@@ -0,0 +1,56 @@
+use super::{device_manager::{DeviceId, Domain}, DeviceInfo, DeviceType};
+
+/* Grapple devices ship from the factory with CAN ID 0 */
+pub const DEFAULT_CAN_ID: u8 = 0;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub enum AttentionKind {
+  StuckInDfu,
+  OutdatedFirmware,
+  DefaultCanId,
+  DefaultName,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct AttentionItem {
+  pub domain: Domain,
+  pub device_id: DeviceId,
+  pub kind: AttentionKind,
+  pub message: String,
+}
+
+pub fn is_default_name(info: &DeviceInfo) -> bool {
+  match (&info.name, &info.device_type) {
+    (None, _) => true,
+    (Some(name), _) if name.trim().is_empty() => true,
+    (Some(name), DeviceType::Grapple(model)) => name.eq_ignore_ascii_case(&format!("{:?}", model)),
+    _ => false
+  }
+}
+
+/* Quick checks for the things most worth fixing straight after connecting, most important first */
+pub fn assess(domain: &Domain, id: &DeviceId, info: &DeviceInfo, device_class: &str) -> Vec<AttentionItem> {
+  let mut items = vec![];
+  let mut push = |kind: AttentionKind, message: String| items.push(AttentionItem { domain: domain.clone(), device_id: id.clone(), kind, message });
+
+  if info.is_dfu {
+    if !info.is_dfu_in_progress {
+      push(AttentionKind::StuckInDfu, "Device is in firmware update mode but no update is in progress. Flash a firmware image to recover it.".to_owned());
+    }
+    return items;
+  }
+
+  if device_class == "OldVersionDevice" {
+    push(AttentionKind::OutdatedFirmware, format!("Firmware {} is too old for this version of GrappleHook.", info.firmware_version.as_deref().unwrap_or("unknown")));
+  }
+
+  if info.device_id == Some(DEFAULT_CAN_ID) {
+    push(AttentionKind::DefaultCanId, "Device is still on the factory default CAN ID.".to_owned());
+  }
+
+  if is_default_name(info) {
+    push(AttentionKind::DefaultName, "Device has not been given a name.".to_owned());
+  }
+
+  items
+}
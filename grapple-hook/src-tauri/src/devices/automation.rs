@@ -0,0 +1,306 @@
+use std::{collections::{HashMap, HashSet, VecDeque}, sync::{Arc, Mutex, OnceLock}, time::Duration};
+
+use log::warn;
+
+use super::{attention::{assess, AttentionKind}, changes::{device_changes, HotplugKind}, config_clipboard::DeviceConfig, device_class::resolve_device_class, device_manager::DeviceId, firmware_catalog::firmware_catalog, metadata::metadata, provider_manager::ProviderManager, DeviceInfo};
+use crate::{events::{events, Event, EventSeverity}, persistence::Persisted};
+
+/* User-defined rules for hands-off setup, e.g. "apply our team defaults to any factory-fresh LaserCAN". A rule is a
+   trigger, an optional filter on which devices it applies to, and actions run against the device in order. Rules run
+   in the background whether or not the app is being looked at, and every run is logged so it's clear what was done. */
+
+/* Event kind for notification actions, which are also shown as desktop notifications */
+pub const AUTOMATION_NOTIFICATION: &str = "automation_notification";
+/* How often connected devices are checked for outdated firmware */
+const FIRMWARE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/* A device raising the same fault over and over only runs a rule once in this long */
+const FAULT_COOLDOWN_MS: i64 = 60_000;
+/* How long a newly connected device has to appear in the device list */
+const DISCOVERY_SETTLE: Duration = Duration::from_millis(1000);
+const MAX_RUNS: usize = 200;
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq, Eq, Hash)]
+pub enum AutomationTrigger {
+  DeviceDiscovered,
+  /* Once per firmware version, so updating and then falling behind again runs it again */
+  FirmwareOutdated,
+  /* A warning or error from the device, e.g. a MitoCANdria rail fault or a telemetry anomaly */
+  FaultRaised,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub enum AutomationAction {
+  /* A configuration copied from another device (see copy_config), applied as if it were pasted */
+  ApplyConfig { config: DeviceConfig },
+  /* {device} is replaced with the device's name */
+  Notify { message: String },
+  /* Device RPC requests, as the frontend would send them ({ "method": ..., "data": ... }), stopping at the first failure */
+  RunMacro { requests: Vec<serde_json::Value> },
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct AutomationRule {
+  pub id: String,
+  pub name: String,
+  pub enabled: bool,
+  pub trigger: AutomationTrigger,
+  /* e.g. "LaserCan", None for any kind of device */
+  #[serde(default)]
+  pub device_class: Option<String>,
+  /* Only devices still on the factory default name and CAN ID */
+  #[serde(default)]
+  pub factory_fresh_only: bool,
+  pub actions: Vec<AutomationAction>,
+}
+
+impl AutomationRule {
+  pub fn validate(&self) -> anyhow::Result<()> {
+    if self.name.trim().is_empty() {
+      anyhow::bail!("Give the rule a name");
+    }
+    if self.actions.is_empty() {
+      anyhow::bail!("A rule needs at least one action");
+    }
+    for action in &self.actions {
+      match action {
+        AutomationAction::ApplyConfig { config } => {
+          if let Some(class) = &self.device_class {
+            config.validate_for(class)?;
+          }
+        },
+        AutomationAction::Notify { message } if message.trim().is_empty() => anyhow::bail!("Notifications need a message"),
+        AutomationAction::RunMacro { requests } => {
+          if requests.is_empty() {
+            anyhow::bail!("A macro needs at least one request");
+          }
+          if let Some(bad) = requests.iter().find(|r| r.get("method").and_then(|m| m.as_str()).is_none()) {
+            anyhow::bail!("Macro request {} has no method", bad);
+          }
+        },
+        _ => ()
+      }
+    }
+    Ok(())
+  }
+
+  fn applies_to(&self, info: &DeviceInfo, class: &str) -> bool {
+    if info.is_dfu || self.device_class.as_deref().map(|c| c != class).unwrap_or(false) {
+      return false;
+    }
+    if self.factory_fresh_only {
+      let items = assess(&String::new(), &DeviceId::Serial(info.serial.unwrap_or(0)), info, class);
+      return items.iter().any(|i| matches!(i.kind, AttentionKind::DefaultCanId))
+        && items.iter().any(|i| matches!(i.kind, AttentionKind::DefaultName));
+    }
+    true
+  }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct AutomationRun {
+  pub rule_id: String,
+  pub rule_name: String,
+  pub trigger: AutomationTrigger,
+  pub serial: u32,
+  pub timestamp_ms: i64,
+  /* What each action did, in order */
+  pub steps: Vec<String>,
+  pub error: Option<String>,
+}
+
+pub struct Automation {
+  rules: Persisted<Vec<AutomationRule>>,
+  runs: Mutex<VecDeque<AutomationRun>>,
+  /* (rule, serial, firmware version) the outdated firmware trigger has already run for */
+  outdated_fired: Mutex<HashSet<(String, u32, String)>>,
+  /* (rule, serial) to when the fault trigger last ran */
+  fault_fired: Mutex<HashMap<(String, u32), i64>>,
+}
+
+impl Automation {
+  pub fn rules(&self) -> Vec<AutomationRule> {
+    self.rules.get()
+  }
+
+  pub fn rule(&self, id: &str) -> anyhow::Result<AutomationRule> {
+    self.rules.read(|r| r.iter().find(|r| r.id == id).cloned()).ok_or(anyhow::anyhow!("No automation rule {}", id))
+  }
+
+  pub fn add(&self, mut rule: AutomationRule) -> anyhow::Result<AutomationRule> {
+    rule.id = uuid::Uuid::new_v4().to_string();
+    rule.validate()?;
+    self.rules.update(|r| r.push(rule.clone()));
+    Ok(rule)
+  }
+
+  pub fn replace(&self, rule: AutomationRule) -> anyhow::Result<()> {
+    rule.validate()?;
+    self.rules.update(|r| match r.iter_mut().find(|r| r.id == rule.id) {
+      Some(existing) => { *existing = rule; Ok(()) },
+      None => Err(anyhow::anyhow!("No automation rule {}", rule.id))
+    })
+  }
+
+  pub fn remove(&self, id: &str) {
+    self.rules.update(|r| r.retain(|r| r.id != id));
+  }
+
+  /* Most recent first */
+  pub fn runs(&self) -> Vec<AutomationRun> {
+    self.runs.lock().unwrap().iter().rev().cloned().collect()
+  }
+
+  fn record(&self, run: AutomationRun) {
+    let mut runs = self.runs.lock().unwrap();
+    runs.push_back(run);
+    while runs.len() > MAX_RUNS {
+      runs.pop_front();
+    }
+  }
+
+  fn matching(&self, trigger: AutomationTrigger, info: &DeviceInfo, class: &str) -> Vec<AutomationRule> {
+    self.rules.read(|r| r.iter().filter(|r| r.enabled && r.trigger == trigger && r.applies_to(info, class)).cloned().collect())
+  }
+}
+
+pub fn automation() -> &'static Automation {
+  static AUTOMATION: OnceLock<Automation> = OnceLock::new();
+  AUTOMATION.get_or_init(|| Automation {
+    rules: Persisted::load("automation"),
+    runs: Mutex::new(VecDeque::new()),
+    outdated_fired: Mutex::new(HashSet::new()),
+    fault_fired: Mutex::new(HashMap::new()),
+  })
+}
+
+fn device_name(serial: u32, info: &DeviceInfo, class: &str) -> String {
+  metadata().get(serial).nickname
+    .or(info.name.clone().filter(|n| !n.trim().is_empty()))
+    .unwrap_or(format!("{} {:x}", class, serial))
+}
+
+/* Run a rule's actions against a device, whether or not its trigger fired (so rules can be tried out) */
+pub async fn execute(manager: &ProviderManager, rule: &AutomationRule, serial: u32) -> AutomationRun {
+  let mut run = AutomationRun {
+    rule_id: rule.id.clone(), rule_name: rule.name.clone(), trigger: rule.trigger, serial,
+    timestamp_ms: chrono::Utc::now().timestamp_millis(), steps: vec![], error: None
+  };
+
+  let result: anyhow::Result<()> = async {
+    let (_, _, _, info, class) = manager.find_device(&DeviceId::Serial(serial)).await?;
+    for action in &rule.actions {
+      match action {
+        AutomationAction::ApplyConfig { config } => {
+          config.validate_for(&class)?;
+          manager.call_device(DeviceId::Serial(serial), serde_json::json!({ "method": "apply_config", "data": { "config": config.fields } })).await?;
+          run.steps.push(format!("Applied {} configuration", config.model));
+        },
+        AutomationAction::Notify { message } => {
+          let message = message.replace("{device}", &device_name(serial, &info, &class));
+          events().emit(Some(serial), AUTOMATION_NOTIFICATION, EventSeverity::Info, message.clone());
+          run.steps.push(format!("Notified: {}", message));
+        },
+        AutomationAction::RunMacro { requests } => {
+          for request in requests {
+            manager.call_device(DeviceId::Serial(serial), request.clone()).await?;
+          }
+          run.steps.push(format!("Ran {} request(s)", requests.len()));
+        }
+      }
+    }
+    Ok(())
+  }.await;
+
+  if let Err(e) = result {
+    // Not a device fault, so it mustn't set off FaultRaised rules
+    events().emit(Some(serial), "automation_failed", EventSeverity::Warning, format!("Automation \"{}\" failed: {}", rule.name, e));
+    run.error = Some(e.to_string());
+  }
+  automation().record(run.clone());
+  run
+}
+
+async fn on_discovered(manager: &ProviderManager, serial: u32) {
+  // The hot-plug goes out just before the device is added to the list
+  let mut found = None;
+  let deadline = tokio::time::Instant::now() + DISCOVERY_SETTLE;
+  while found.is_none() && tokio::time::Instant::now() < deadline {
+    found = manager.find_device(&DeviceId::Serial(serial)).await.ok();
+    if found.is_none() {
+      tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+  }
+  let Some((_, _, _, info, class)) = found else { return };
+
+  for rule in automation().matching(AutomationTrigger::DeviceDiscovered, &info, &class) {
+    execute(manager, &rule, serial).await;
+  }
+}
+
+async fn check_firmware(manager: &ProviderManager) {
+  let catalog = firmware_catalog().get();
+  for (_, domain, id, info, class) in manager.all_devices().await {
+    let (DeviceId::Serial(serial), Some(version)) = (&id, info.firmware_version.clone()) else { continue };
+    let outdated = assess(&domain, &id, &info, &class).iter().any(|i| matches!(i.kind, AttentionKind::OutdatedFirmware))
+      || resolve_device_class(&info.device_type).and_then(|c| catalog.update_available(c, &version)).is_some();
+    if !outdated {
+      continue;
+    }
+
+    for rule in automation().matching(AutomationTrigger::FirmwareOutdated, &info, &class) {
+      if automation().outdated_fired.lock().unwrap().insert((rule.id.clone(), *serial, version.clone())) {
+        execute(manager, &rule, *serial).await;
+      }
+    }
+  }
+}
+
+async fn on_event(manager: &ProviderManager, event: Event) {
+  let Some(serial) = event.serial else { return };
+  if event.severity == EventSeverity::Info || event.kind.starts_with("automation") {
+    return;
+  }
+  let Ok((_, _, _, info, class)) = manager.find_device(&DeviceId::Serial(serial)).await else { return };
+
+  for rule in automation().matching(AutomationTrigger::FaultRaised, &info, &class) {
+    let due = {
+      let mut fired = automation().fault_fired.lock().unwrap();
+      let last = fired.entry((rule.id.clone(), serial)).or_insert(i64::MIN);
+      let due = event.timestamp_ms.saturating_sub(*last) >= FAULT_COOLDOWN_MS;
+      if due {
+        *last = event.timestamp_ms;
+      }
+      due
+    };
+    if due {
+      execute(manager, &rule, serial).await;
+    }
+  }
+}
+
+/* Watch for triggers for as long as the app is running */
+pub async fn run(manager: Arc<ProviderManager>) {
+  let mut hotplugs = device_changes().subscribe_hotplug();
+  let mut device_events = events().subscribe();
+  let mut firmware_interval = tokio::time::interval(FIRMWARE_CHECK_INTERVAL);
+
+  loop {
+    tokio::select! {
+      hotplug = hotplugs.recv() => match hotplug {
+        Ok(hotplug) if matches!(hotplug.kind, HotplugKind::Connected) => {
+          let manager = manager.clone();
+          tokio::task::spawn(async move { on_discovered(&manager, hotplug.serial).await });
+        },
+        Ok(_) => (),
+        Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => warn!("Automation missed {} hot-plug events", n),
+        Err(tokio::sync::broadcast::error::RecvError::Closed) => break
+      },
+      event = device_events.recv() => match event {
+        Ok(event) => on_event(&manager, event).await,
+        Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => warn!("Automation missed {} events", n),
+        Err(tokio::sync::broadcast::error::RecvError::Closed) => break
+      },
+      _ = firmware_interval.tick() => check_firmware(&manager).await,
+    }
+  }
+}
@@ -0,0 +1,102 @@
+use std::{collections::{HashMap, HashSet}, sync::OnceLock};
+
+use crate::persistence::Persisted;
+
+use super::{device_manager::{DeviceId, Domain}, DeviceInfo, DeviceType};
+
+/* A team's bill of materials: how many of each model should be on the robot (or in the spares box) */
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct BomEntry {
+  pub model: String,
+  pub quantity: usize,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct BomLine {
+  pub model: String,
+  pub expected: usize,
+  pub found: usize,
+  pub serials: Vec<u32>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct BomReconciliation {
+  pub lines: Vec<BomLine>,
+  /* Models we found fewer of than expected, with how many are missing */
+  pub missing: Vec<(String, usize)>,
+  /* Models we found more of than expected (or that aren't on the BOM at all), with how many extra */
+  pub surplus: Vec<(String, usize)>,
+}
+
+/* "LaserCAN", "lasercan" and "Laser CAN" are all the same model */
+pub fn normalise(model: &str) -> String {
+  model.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_lowercase()
+}
+
+pub fn model_name(device_type: &DeviceType) -> String {
+  match device_type {
+    DeviceType::Grapple(model) => format!("{:?}", model),
+    DeviceType::RoboRIO => "RoboRIO".to_owned(),
+    DeviceType::Unknown => "Unknown".to_owned(),
+  }
+}
+
+/* Parse a BOM from CSV lines of "model,quantity". A header row and blank lines are skipped. */
+pub fn parse_csv(csv: &str) -> anyhow::Result<Vec<BomEntry>> {
+  let mut entries = vec![];
+  for (i, line) in csv.lines().enumerate() {
+    let line = line.trim();
+    if line.is_empty() {
+      continue;
+    }
+    let (model, quantity) = line.rsplit_once(',').ok_or(anyhow::anyhow!("Line {}: expected \"model,quantity\"", i + 1))?;
+    match quantity.trim().parse() {
+      Ok(quantity) => entries.push(BomEntry { model: model.trim().trim_matches('"').to_owned(), quantity }),
+      Err(_) if i == 0 => (),   // Header
+      Err(_) => anyhow::bail!("Line {}: {} isn't a quantity", i + 1, quantity.trim())
+    }
+  }
+  Ok(entries)
+}
+
+pub fn reconcile(bom: &[BomEntry], devices: &[(String, Domain, DeviceId, DeviceInfo, String)]) -> BomReconciliation {
+  let mut lines: Vec<BomLine> = vec![];
+  let mut index = HashMap::new();
+  for entry in bom {
+    let key = normalise(&entry.model);
+    match index.get(&key) {
+      Some(&i) => lines[i].expected += entry.quantity,
+      None => {
+        index.insert(key, lines.len());
+        lines.push(BomLine { model: entry.model.clone(), expected: entry.quantity, found: 0, serials: vec![] });
+      }
+    }
+  }
+
+  // A device can be visible on more than one domain, but it's still only one device
+  let mut seen = HashSet::new();
+  for (_, _, id, info, _) in devices {
+    let serial = match id { DeviceId::Dfu(serial) | DeviceId::Serial(serial) => *serial };
+    if matches!(info.device_type, DeviceType::RoboRIO) || !seen.insert(serial) {
+      continue;
+    }
+
+    let model = model_name(&info.device_type);
+    let i = *index.entry(normalise(&model)).or_insert_with(|| {
+      lines.push(BomLine { model: model.clone(), expected: 0, found: 0, serials: vec![] });
+      lines.len() - 1
+    });
+    lines[i].found += 1;
+    lines[i].serials.push(serial);
+  }
+
+  let missing = lines.iter().filter(|l| l.found < l.expected).map(|l| (l.model.clone(), l.expected - l.found)).collect();
+  let surplus = lines.iter().filter(|l| l.found > l.expected).map(|l| (l.model.clone(), l.found - l.expected)).collect();
+  BomReconciliation { lines, missing, surplus }
+}
+
+/* The team's current BOM, kept between sessions */
+pub fn bom() -> &'static Persisted<Vec<BomEntry>> {
+  static BOM: OnceLock<Persisted<Vec<BomEntry>>> = OnceLock::new();
+  BOM.get_or_init(|| Persisted::load("bom"))
+}
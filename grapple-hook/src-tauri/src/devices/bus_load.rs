@@ -0,0 +1,59 @@
+use grapple_frc_msgs::grapple::lasercan::LaserCanTimingBudget;
+
+use super::device_manager::DeviceId;
+
+/* FRC CAN runs at 1Mbit/s */
+pub const CAN_BITRATE: f64 = 1_000_000.0;
+/* An extended frame with an 8 byte payload, including a typical amount of bit stuffing and the interframe space */
+pub const BITS_PER_FRAME: f64 = 135.0;
+/* Above this we start warning. The roboRIO itself already puts a good amount of traffic on the bus. */
+pub const BUS_LOAD_WARNING: f64 = 0.5;
+
+/* Fraction of the bus consumed by a device sending `rate_hz` single-frame messages */
+pub fn utilization(rate_hz: f64) -> f64 {
+  rate_hz * BITS_PER_FRAME / CAN_BITRATE
+}
+
+/* LaserCAN sends one measurement frame at the end of every timing budget */
+pub fn lasercan_rate_hz(budget: &LaserCanTimingBudget) -> f64 {
+  1000.0 / match budget {
+    LaserCanTimingBudget::TB20ms => 20.0,
+    LaserCanTimingBudget::TB33ms => 33.0,
+    LaserCanTimingBudget::TB50ms => 50.0,
+    LaserCanTimingBudget::TB100ms => 100.0,
+  }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct OutputRateOption {
+  pub budget: LaserCanTimingBudget,
+  pub rate_hz: f64,
+  pub bus_utilization: f64,
+}
+
+pub fn lasercan_output_rates() -> Vec<OutputRateOption> {
+  [LaserCanTimingBudget::TB20ms, LaserCanTimingBudget::TB33ms, LaserCanTimingBudget::TB50ms, LaserCanTimingBudget::TB100ms]
+    .into_iter()
+    .map(|budget| {
+      let rate_hz = lasercan_rate_hz(&budget);
+      OutputRateOption { budget, rate_hz, bus_utilization: utilization(rate_hz) }
+    })
+    .collect()
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct BusLoadReport {
+  pub devices: Vec<(DeviceId, f64)>,
+  pub total: f64,
+  pub warning: Option<String>,
+}
+
+impl BusLoadReport {
+  pub fn new(devices: Vec<(DeviceId, f64)>) -> Self {
+    let total = devices.iter().map(|(_, u)| u).sum::<f64>();
+    let warning = (total >= BUS_LOAD_WARNING).then(|| format!(
+      "Grapple devices are configured to use {:.0}% of the CAN bus. Consider slowing some of them down.", total * 100.0
+    ));
+    Self { devices, total, warning }
+  }
+}
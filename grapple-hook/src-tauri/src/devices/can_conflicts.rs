@@ -0,0 +1,37 @@
+use std::collections::{BTreeMap, HashSet};
+
+use super::attention::DEFAULT_CAN_ID;
+use super::id_plan::MAX_CAN_ID;
+
+/* Two devices on the same bus with the same CAN ID answer each other's requests and trample each other's status, which
+   shows up as very confusing behaviour rather than an obvious error. */
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct CanIdConflict {
+  pub can_id: u8,
+  pub serials: Vec<u32>,
+  /* The lowest ID nothing on the bus is using. Never the factory default, since new devices turn up on that. */
+  pub suggested_id: Option<u8>,
+}
+
+/* ids holds the (serial, CAN ID) of every device on the bus */
+pub fn find_conflicts(ids: &[(u32, u8)]) -> Vec<CanIdConflict> {
+  let mut by_id: BTreeMap<u8, Vec<u32>> = BTreeMap::new();
+  for (serial, id) in ids {
+    let serials = by_id.entry(*id).or_default();
+    if !serials.contains(serial) {
+      serials.push(*serial);
+    }
+  }
+
+  let used: HashSet<u8> = by_id.keys().cloned().collect();
+  let suggested_id = (0..=MAX_CAN_ID).find(|id| *id != DEFAULT_CAN_ID && !used.contains(id));
+
+  by_id.into_iter()
+    .filter(|(_, serials)| serials.len() > 1)
+    .map(|(can_id, mut serials)| {
+      serials.sort();
+      CanIdConflict { can_id, serials, suggested_id }
+    })
+    .collect()
+}
@@ -0,0 +1,112 @@
+/* FRC CAN arbitration IDs are 29 bits, laid out (high to low) as device type (5), manufacturer (8), API class (6),
+   API index (4) and device number (6). See https://docs.wpilib.org/en/stable/docs/software/can-devices/can-addressing.html */
+
+pub const MAX_ARBITRATION_ID: u32 = 0x1FFF_FFFF;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct FrcCanId {
+  pub arbitration_id: u32,
+  pub device_type: u8,
+  pub device_type_name: String,
+  pub manufacturer: u8,
+  pub manufacturer_name: String,
+  pub api_class: u8,
+  pub api_index: u8,
+  pub device_number: u8,
+  /* Things that are legal but probably not what was meant, e.g. a reserved device type */
+  pub warnings: Vec<String>,
+}
+
+pub fn manufacturer_name(manufacturer: u8) -> String {
+  match manufacturer {
+    0 => "Broadcast",
+    1 => "NI",
+    2 => "Luminary Micro",
+    3 => "DEKA",
+    4 => "CTR Electronics",
+    5 => "REV Robotics",
+    6 => "Grapple",
+    7 => "MindSensors",
+    8 => "Team Use",
+    9 => "Kauai Labs",
+    10 => "Copperforge",
+    11 => "Playing With Fusion",
+    12 => "Studica",
+    13 => "The Thrifty Bot",
+    14 => "Redux Robotics",
+    15 => "AndyMark",
+    16 => "Vivid Hosting",
+    _ => return format!("Reserved ({})", manufacturer)
+  }.to_owned()
+}
+
+pub fn device_type_name(device_type: u8) -> String {
+  match device_type {
+    0 => "Broadcast",
+    1 => "Robot Controller",
+    2 => "Motor Controller",
+    3 => "Relay Controller",
+    4 => "Gyro Sensor",
+    5 => "Accelerometer",
+    6 => "Ultrasonic Sensor",
+    7 => "Gear Tooth Sensor",
+    8 => "Power Distribution",
+    9 => "Pneumatics Controller",
+    10 => "Miscellaneous",
+    11 => "IO Breakout",
+    31 => "Firmware Update",
+    _ => return format!("Reserved ({})", device_type)
+  }.to_owned()
+}
+
+/* (device type, manufacturer, API class, API index, device number) */
+pub fn fields(arbitration_id: u32) -> (u8, u8, u8, u8, u8) {
+  (
+    ((arbitration_id >> 24) & 0x1F) as u8,
+    ((arbitration_id >> 16) & 0xFF) as u8,
+    ((arbitration_id >> 10) & 0x3F) as u8,
+    ((arbitration_id >> 6) & 0x0F) as u8,
+    (arbitration_id & 0x3F) as u8,
+  )
+}
+
+fn describe(arbitration_id: u32) -> FrcCanId {
+  let (device_type, manufacturer, api_class, api_index, device_number) = fields(arbitration_id);
+
+  let mut warnings = vec![];
+  if (12..=30).contains(&device_type) {
+    warnings.push(format!("Device type {} is reserved", device_type));
+  }
+  if manufacturer > 16 {
+    warnings.push(format!("Manufacturer {} isn't assigned", manufacturer));
+  }
+  if device_number == 0x3F {
+    warnings.push("Device number 63 is the broadcast address".to_owned());
+  }
+
+  FrcCanId {
+    arbitration_id,
+    device_type, device_type_name: device_type_name(device_type),
+    manufacturer, manufacturer_name: manufacturer_name(manufacturer),
+    api_class, api_index, device_number,
+    warnings,
+  }
+}
+
+pub fn decode(arbitration_id: u32) -> anyhow::Result<FrcCanId> {
+  if arbitration_id > MAX_ARBITRATION_ID {
+    anyhow::bail!("0x{:x} is more than 29 bits, so it isn't an extended CAN ID", arbitration_id);
+  }
+  Ok(describe(arbitration_id))
+}
+
+pub fn encode(device_type: u8, manufacturer: u8, api_class: u8, api_index: u8, device_number: u8) -> anyhow::Result<FrcCanId> {
+  for (name, value, max) in [("Device type", device_type, 0x1F), ("API class", api_class, 0x3F), ("API index", api_index, 0x0F), ("Device number", device_number, 0x3F)] {
+    if value > max {
+      anyhow::bail!("{} {} is out of range (0-{})", name, value, max);
+    }
+  }
+  Ok(describe(
+    (device_type as u32) << 24 | (manufacturer as u32) << 16 | (api_class as u32) << 10 | (api_index as u32) << 6 | device_number as u32
+  ))
+}
@@ -0,0 +1,56 @@
+use std::{collections::HashMap, sync::{Mutex, OnceLock}};
+
+use super::{compatibility::{check, COMPATIBILITY_MATRIX}, device_class::DeviceClass, DeviceInfo};
+
+/* Capability bitmaps, worked out from the model and firmware version. Bit N is the Nth entry for the device's class in
+   the COMPATIBILITY_MATRIX. No firmware released so far can be asked what it supports (grapple-frc-msgs 2024.4 has
+   no message for it), so the version is all we have to go on. */
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct DeviceCapabilities {
+  pub bitmap: u64,
+  pub features: Vec<String>,
+}
+
+pub fn features_for(class: DeviceClass) -> Vec<&'static str> {
+  COMPATIBILITY_MATRIX.iter().filter(|r| r.class == class).map(|r| r.feature).collect()
+}
+
+fn from_bitmap(class: DeviceClass, bitmap: u64) -> DeviceCapabilities {
+  let features = features_for(class).into_iter()
+    .enumerate()
+    .filter(|(i, _)| bitmap & (1 << i) != 0)
+    .map(|(_, f)| f.to_owned())
+    .collect();
+  DeviceCapabilities { bitmap, features }
+}
+
+pub fn infer(class: DeviceClass, info: &DeviceInfo) -> DeviceCapabilities {
+  let bitmap = COMPATIBILITY_MATRIX.iter()
+    .filter(|r| r.class == class)
+    .enumerate()
+    .filter(|(_, r)| check(r, info.firmware_version.as_deref()).supported)
+    .fold(0u64, |acc, (i, _)| acc | (1 << i));
+  from_bitmap(class, bitmap)
+}
+
+/* Capabilities for each device, by serial, captured when the device is discovered */
+pub struct CapabilityCache {
+  by_serial: Mutex<HashMap<u32, DeviceCapabilities>>,
+}
+
+impl CapabilityCache {
+  pub fn on_discovered(&self, class: DeviceClass, info: &DeviceInfo) {
+    if let Some(serial) = info.serial {
+      self.by_serial.lock().unwrap().insert(serial, infer(class, info));
+    }
+  }
+
+  pub fn get(&self, serial: u32) -> Option<DeviceCapabilities> {
+    self.by_serial.lock().unwrap().get(&serial).cloned()
+  }
+}
+
+pub fn capability_cache() -> &'static CapabilityCache {
+  static CACHE: OnceLock<CapabilityCache> = OnceLock::new();
+  CACHE.get_or_init(|| CapabilityCache { by_serial: Mutex::new(HashMap::new()) })
+}
@@ -0,0 +1,87 @@
+use std::sync::OnceLock;
+
+use tokio::sync::broadcast;
+
+use crate::events::{events, EventSeverity};
+
+use super::{device_manager::{DeviceId, Domain}, DeviceInfo, DeviceType};
+
+/* Changes to the device list, pushed to the frontend as they happen so it doesn't have to poll devices() to notice a
+   device arriving or leaving. Things that change continuously (state, activity) still come from devices(). */
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub enum DeviceChange {
+  Added { domain: Domain, device_id: DeviceId, info: DeviceInfo },
+  /* The device reported a different name, CAN ID, firmware version or DFU progress */
+  Updated { domain: Domain, device_id: DeviceId, info: DeviceInfo },
+  Removed { domain: Domain, device_id: DeviceId },
+}
+
+pub const DEVICE_CHANGE_EVENT: &str = "device_change";
+
+/* A device turning up on a bus, or dropping off one, for things that want to react to that alone (e.g. automation
+   scripts). Unlike DeviceChange, a device going into or out of DFU isn't a hot-plug, and neither is the whole list
+   being cleared because a domain disconnected. */
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub enum HotplugKind {
+  /* Enumerated for the first time */
+  Connected,
+  /* Aged off after not answering enumeration */
+  Lost,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct Hotplug {
+  pub kind: HotplugKind,
+  pub domain: Domain,
+  pub serial: u32,
+  pub device_type: DeviceType,
+  pub name: Option<String>,
+}
+
+pub const HOTPLUG_EVENT: &str = "hotplug";
+
+pub struct DeviceChanges {
+  tx: broadcast::Sender<DeviceChange>,
+  hotplug_tx: broadcast::Sender<Hotplug>,
+}
+
+impl DeviceChanges {
+  pub fn publish(&self, change: DeviceChange) {
+    self.tx.send(change).ok();    // ok since there may be nobody listening
+  }
+
+  pub fn subscribe(&self) -> broadcast::Receiver<DeviceChange> {
+    self.tx.subscribe()
+  }
+
+  /* Also recorded in the event log, so it shows up for anything polling events() */
+  pub fn hotplug(&self, kind: HotplugKind, domain: &Domain, info: &DeviceInfo) {
+    let Some(serial) = info.serial else { return };
+    let label = match &info.device_type {
+      DeviceType::Grapple(model) => format!("{:?}", model),
+      other => format!("{:?}", other)
+    };
+    let name = info.name.as_deref().filter(|n| !n.trim().is_empty()).map(|n| format!(" ({})", n)).unwrap_or_default();
+    let (event_kind, verb) = match kind {
+      HotplugKind::Connected => ("device_connected", "connected to"),
+      HotplugKind::Lost => ("device_lost", "lost from")
+    };
+    events().emit(Some(serial), event_kind, EventSeverity::Info, format!("{} {:x}{} {} {}", label, serial, name, verb, domain));
+
+    self.hotplug_tx.send(Hotplug { kind, domain: domain.clone(), serial, device_type: info.device_type.clone(), name: info.name.clone() }).ok();
+  }
+
+  pub fn subscribe_hotplug(&self) -> broadcast::Receiver<Hotplug> {
+    self.hotplug_tx.subscribe()
+  }
+}
+
+pub fn device_changes() -> &'static DeviceChanges {
+  static CHANGES: OnceLock<DeviceChanges> = OnceLock::new();
+  CHANGES.get_or_init(|| DeviceChanges { tx: broadcast::channel(256).0, hotplug_tx: broadcast::channel(64).0 })
+}
+
+/* Whether an enumerate response says something the device list shows has changed */
+pub fn info_changed(old: &DeviceInfo, new: &DeviceInfo) -> bool {
+  old.name != new.name || old.device_id != new.device_id || old.firmware_version != new.firmware_version || old.is_dfu_in_progress != new.is_dfu_in_progress
+}
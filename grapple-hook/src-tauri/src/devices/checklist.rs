@@ -0,0 +1,143 @@
+use std::{collections::HashMap, sync::OnceLock};
+
+use crate::{persistence::Persisted, telemetry::telemetry};
+
+use super::{attention::{is_default_name, DEFAULT_CAN_ID}, DeviceInfo};
+
+/* Telemetry newer than this counts as the device reporting in */
+const TELEMETRY_FRESH_MS: i64 = 5000;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub enum ChecklistCheck {
+  /* Ticked off by hand */
+  Manual,
+  /* Checked automatically against the device */
+  CanIdSet,
+  Renamed,
+  TelemetryReporting { channel: String },
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ChecklistStep {
+  pub id: String,
+  pub title: String,
+  pub description: String,
+  pub check: ChecklistCheck,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ChecklistStepStatus {
+  pub step: ChecklistStep,
+  pub complete: bool,
+  pub completed_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ChecklistProgress {
+  pub device_class: String,
+  pub serial: u32,
+  pub steps: Vec<ChecklistStepStatus>,
+  pub complete: usize,
+  pub total: usize,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+struct ChecklistData {
+  /* Per device class, overriding the defaults */
+  #[serde(default)]
+  definitions: HashMap<String, Vec<ChecklistStep>>,
+  /* serial -> step ID -> when it was completed */
+  #[serde(default)]
+  progress: HashMap<u32, HashMap<String, i64>>,
+}
+
+fn step(id: &str, title: &str, description: &str, check: ChecklistCheck) -> ChecklistStep {
+  ChecklistStep { id: id.to_owned(), title: title.to_owned(), description: description.to_owned(), check }
+}
+
+pub fn default_checklist(device_class: &str) -> Vec<ChecklistStep> {
+  let mut steps = vec![
+    step("set_id", "Set CAN ID", "Give the device a unique CAN ID, matching your robot code.", ChecklistCheck::CanIdSet),
+    step("rename", "Name it", "Name the device after where it is on the robot.", ChecklistCheck::Renamed),
+  ];
+
+  match device_class {
+    "LaserCAN" => {
+      steps.push(step("configure", "Configure ranging", "Set the range mode, timing budget and region of interest for its job.", ChecklistCheck::Manual));
+      steps.push(step("telemetry", "Verify distance", "Check the device is reporting distance measurements.", ChecklistCheck::TelemetryReporting { channel: "distance_mm".to_owned() }));
+    },
+    "MitoCANdria" => {
+      steps.push(step("configure", "Configure rails", "Set the adjustable rail voltages and enable the channels you're using.", ChecklistCheck::Manual));
+      steps.push(step("telemetry", "Verify rails", "Check the device is reporting channel currents.", ChecklistCheck::TelemetryReporting { channel: "channel0_current".to_owned() }));
+    },
+    _ => ()
+  }
+
+  steps
+}
+
+/* Bring-up checklists, so new devices get commissioned the same way whoever is doing it */
+pub struct Checklists {
+  data: Persisted<ChecklistData>,
+}
+
+impl Checklists {
+  pub fn definition(&self, device_class: &str) -> Vec<ChecklistStep> {
+    self.data.read(|d| d.definitions.get(device_class).cloned()).unwrap_or_else(|| default_checklist(device_class))
+  }
+
+  pub fn set_definition(&self, device_class: &str, steps: Vec<ChecklistStep>) {
+    self.data.update(|d| { d.definitions.insert(device_class.to_owned(), steps); });
+  }
+
+  pub fn reset_definition(&self, device_class: &str) {
+    self.data.update(|d| { d.definitions.remove(device_class); });
+  }
+
+  pub fn set_complete(&self, serial: u32, step_id: &str, complete: bool) {
+    self.data.update(|d| {
+      let progress = d.progress.entry(serial).or_default();
+      if complete {
+        progress.insert(step_id.to_owned(), chrono::Utc::now().timestamp_millis());
+      } else {
+        progress.remove(step_id);
+      }
+    });
+  }
+
+  fn auto_check(check: &ChecklistCheck, info: &DeviceInfo) -> bool {
+    match check {
+      ChecklistCheck::Manual => false,
+      ChecklistCheck::CanIdSet => info.device_id.map(|id| id != DEFAULT_CAN_ID).unwrap_or(false),
+      ChecklistCheck::Renamed => !is_default_name(info),
+      ChecklistCheck::TelemetryReporting { channel } => info.serial.map(|serial| {
+        let since = chrono::Utc::now().timestamp_millis() - TELEMETRY_FRESH_MS;
+        !telemetry().history(serial, channel, Some(since), None).is_empty()
+      }).unwrap_or(false),
+    }
+  }
+
+  pub fn progress(&self, device_class: &str, info: &DeviceInfo) -> anyhow::Result<ChecklistProgress> {
+    let serial = info.require_serial()?;
+    let completed = self.data.read(|d| d.progress.get(&serial).cloned()).unwrap_or_default();
+
+    let steps = self.definition(device_class).into_iter().map(|step| {
+      let completed_at = completed.get(&step.id).cloned();
+      let complete = completed_at.is_some() || Self::auto_check(&step.check, info);
+      ChecklistStepStatus { step, complete, completed_at }
+    }).collect::<Vec<_>>();
+
+    Ok(ChecklistProgress {
+      device_class: device_class.to_owned(),
+      serial,
+      complete: steps.iter().filter(|s| s.complete).count(),
+      total: steps.len(),
+      steps,
+    })
+  }
+}
+
+pub fn checklists() -> &'static Checklists {
+  static CHECKLISTS: OnceLock<Checklists> = OnceLock::new();
+  CHECKLISTS.get_or_init(|| Checklists { data: Persisted::load("checklists") })
+}
@@ -0,0 +1,119 @@
+use std::{sync::Mutex, time::Duration};
+
+use tokio::sync::watch;
+
+use crate::errors::{coded, ErrorCode};
+
+/* Matches acknowledgements to chunks for a chunked (stop-and-wait) transfer.
+
+   The firmware update protocol has no sequence numbers on UpdatePart / UpdatePartAck, so we can't reorder or
+   window chunks safely, and can't tell which chunk an ack is for. An ack is taken to be for the chunk that's
+   outstanding, and is used up by it. One that arrives with no chunk outstanding (e.g. a duplicate, which some bridges
+   produce under load, landing before the next chunk goes out) is dropped. A duplicate that only turns up after the
+   next chunk has gone out is indistinguishable from that chunk's own ack, and will release it. */
+pub struct AckTracker {
+  /* The chunk most recently acknowledged */
+  acked: watch::Sender<Option<usize>>,
+  outstanding: Mutex<Option<usize>>,
+}
+
+impl AckTracker {
+  pub fn new() -> Self {
+    Self { acked: watch::channel(None).0, outstanding: Mutex::new(None) }
+  }
+
+  pub fn on_ack(&self) {
+    if let Some(chunk) = self.outstanding.lock().unwrap().take() {
+      self.acked.send_replace(Some(chunk));
+    }
+  }
+
+  /* Call right before sending the chunk to be acknowledged */
+  pub fn expect_ack(&self, chunk: usize) -> AckWaiter {
+    *self.outstanding.lock().unwrap() = Some(chunk);
+    AckWaiter { rx: self.acked.subscribe(), chunk }
+  }
+}
+
+/* Per-update transfer statistics, so a flash that keeps failing can be put down to the bus (slow acks) or the device
+   (acks stop altogether) */
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct FlashStats {
+  pub chunks_sent: usize,
+  pub chunks_total: usize,
+  pub ack_latency_min_ms: Option<f64>,
+  pub ack_latency_avg_ms: Option<f64>,
+  pub ack_latency_max_ms: Option<f64>,
+  /* Set if the flash was aborted, with the chunk it was on */
+  #[serde(default)]
+  pub failed_chunk: Option<usize>,
+  #[serde(default)]
+  pub abort_reason: Option<String>,
+}
+
+impl FlashStats {
+  pub fn new(chunks_total: usize) -> Self {
+    Self { chunks_total, ..Default::default() }
+  }
+
+  pub fn on_ack(&mut self, latency_ms: f64) {
+    let acked = self.chunks_sent as f64;
+    self.ack_latency_min_ms = Some(self.ack_latency_min_ms.map(|m| m.min(latency_ms)).unwrap_or(latency_ms));
+    self.ack_latency_max_ms = Some(self.ack_latency_max_ms.map(|m| m.max(latency_ms)).unwrap_or(latency_ms));
+    self.ack_latency_avg_ms = Some(self.ack_latency_avg_ms.map(|avg| avg + (latency_ms - avg) / (acked + 1.0)).unwrap_or(latency_ms));
+    self.chunks_sent += 1;
+  }
+
+  pub fn on_abort(&mut self, chunk: usize, reason: String) {
+    self.failed_chunk = Some(chunk);
+    self.abort_reason = Some(reason);
+  }
+
+  /* How long to wait for the next ack. Slow buses get more time, so a flash that's merely slow isn't aborted. */
+  pub fn ack_timeout(&self) -> Duration {
+    let adaptive = self.ack_latency_avg_ms.map(|avg| avg * 4.0).unwrap_or(0.0);
+    Duration::from_millis(adaptive.max(MIN_ACK_TIMEOUT_MS as f64) as u64)
+  }
+}
+
+pub const MIN_ACK_TIMEOUT_MS: u64 = 1000;
+
+pub struct AckWaiter {
+  rx: watch::Receiver<Option<usize>>,
+  chunk: usize,
+}
+
+impl AckWaiter {
+  pub async fn wait(mut self, timeout: Duration) -> anyhow::Result<()> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+      if *self.rx.borrow_and_update() == Some(self.chunk) {
+        return Ok(());
+      }
+      tokio::time::timeout_at(deadline, self.rx.changed()).await
+        .map_err(|_| coded(ErrorCode::ChunkAckTimeout, "Timed out waiting for chunk acknowledgement"))??;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::errors::has_code;
+
+  const WAIT: Duration = Duration::from_millis(50);
+
+  #[tokio::test]
+  async fn duplicate_ack_between_chunks_is_dropped() {
+    let tracker = AckTracker::new();
+
+    let waiter = tracker.expect_ack(0);
+    tracker.on_ack();
+    waiter.wait(WAIT).await.unwrap();
+
+    // The bridge repeats chunk 0's ack before chunk 1 goes out, which mustn't count for chunk 1
+    tracker.on_ack();
+    let waiter = tracker.expect_ack(1);
+    assert!(has_code(&waiter.wait(WAIT).await.unwrap_err(), ErrorCode::ChunkAckTimeout));
+  }
+}
\ No newline at end of file
@@ -0,0 +1,68 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::sync::watch;
+
+/* Time as a device manager sees it. Normally this is the wall clock, but in replay mode it's a virtual clock that only
+   moves when told to, so time-dependent behaviour (age-off, enumeration rate, telemetry retention, request timeouts)
+   can be driven deterministically and fast-forwarded instead of waited out. */
+#[derive(Clone)]
+pub enum Clock {
+  Real,
+  Virtual(Arc<watch::Sender<i64>>),
+}
+
+impl Clock {
+  pub fn new_virtual(start_ms: i64) -> Self {
+    Clock::Virtual(Arc::new(watch::channel(start_ms).0))
+  }
+
+  pub fn is_virtual(&self) -> bool {
+    matches!(self, Clock::Virtual(..))
+  }
+
+  pub fn now_ms(&self) -> i64 {
+    match self {
+      Clock::Real => chrono::Utc::now().timestamp_millis(),
+      Clock::Virtual(now) => *now.borrow()
+    }
+  }
+
+  /* How long it's been since start_ms, by this clock */
+  pub fn since(&self, start_ms: i64) -> Duration {
+    Duration::from_millis((self.now_ms() - start_ms).max(0) as u64)
+  }
+
+  /* Move a virtual clock forward, returning the new time. Anything sleeping on it wakes if its time has come. */
+  pub fn advance(&self, ms: i64) -> anyhow::Result<i64> {
+    match self {
+      Clock::Real => anyhow::bail!("The real clock can't be moved"),
+      Clock::Virtual(_) if ms < 0 => anyhow::bail!("Time can't go backwards"),
+      Clock::Virtual(now) => {
+        now.send_modify(|now| *now += ms);
+        Ok(*now.borrow())
+      }
+    }
+  }
+
+  pub async fn sleep(&self, duration: Duration) {
+    match self {
+      Clock::Real => tokio::time::sleep(duration).await,
+      Clock::Virtual(_) => self.sleep_until(self.now_ms() + duration.as_millis() as i64).await
+    }
+  }
+
+  pub async fn sleep_until(&self, deadline_ms: i64) {
+    match self {
+      Clock::Real => tokio::time::sleep(Duration::from_millis((deadline_ms - self.now_ms()).max(0) as u64)).await,
+      Clock::Virtual(now) => {
+        let mut rx = now.subscribe();
+        while *rx.borrow_and_update() < deadline_ms {
+          // The sender lives as long as the clock, which we're holding
+          if rx.changed().await.is_err() {
+            return;
+          }
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,129 @@
+use semver::{Version, VersionReq};
+
+use crate::errors::{coded, ErrorCode};
+
+use super::{device_class::DeviceClass, DeviceInfo};
+
+pub const TOOL_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/* A protocol feature (usually backing a single RPC) and the firmware it needs. Calls are rejected up front with
+   the reason instead of timing out against firmware that doesn't understand them, and so are calls to a device
+   whose version we don't know. */
+pub struct FeatureRequirement {
+  pub class: DeviceClass,
+  pub feature: &'static str,
+  pub firmware: &'static str,
+}
+
+/* The first firmware release with each feature. The drivers' version gates (see validate_version) are narrower than
+   most of these, so for now it's mainly devices that didn't report a usable version that get turned away, but the
+   matrix keeps an older firmware from being let through if a gate is ever widened. */
+pub const COMPATIBILITY_MATRIX: &[FeatureRequirement] = &[
+  FeatureRequirement { class: DeviceClass::LaserCan, feature: "set_range", firmware: ">= 2024.0.0" },
+  FeatureRequirement { class: DeviceClass::LaserCan, feature: "set_timing_budget", firmware: ">= 2024.0.0" },
+  FeatureRequirement { class: DeviceClass::LaserCan, feature: "set_roi", firmware: ">= 2024.2.0" },
+  FeatureRequirement { class: DeviceClass::MitoCANdria, feature: "set_switchable_channel", firmware: ">= 2025.0.0" },
+  FeatureRequirement { class: DeviceClass::MitoCANdria, feature: "set_adjustable_channel", firmware: ">= 2025.0.0" },
+];
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct FeatureSupport {
+  pub feature: String,
+  pub supported: bool,
+  pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct CompatibilityReport {
+  pub tool_version: String,
+  pub firmware_version: Option<String>,
+  pub features: Vec<FeatureSupport>,
+}
+
+pub fn check(requirement: &FeatureRequirement, firmware_version: Option<&str>) -> FeatureSupport {
+  let req = VersionReq::parse(requirement.firmware).expect("Invalid version requirement in compatibility matrix");
+
+  match firmware_version.map(Version::parse) {
+    Some(Ok(v)) if req.matches(&v) => FeatureSupport { feature: requirement.feature.to_owned(), supported: true, reason: None },
+    Some(Ok(v)) => FeatureSupport {
+      feature: requirement.feature.to_owned(),
+      supported: false,
+      reason: Some(format!("Requires firmware {} (device has {})", requirement.firmware, v))
+    },
+    // Can't tell, so don't risk it timing out
+    Some(Err(_)) | None => FeatureSupport {
+      feature: requirement.feature.to_owned(),
+      supported: false,
+      reason: Some(format!("Requires firmware {} (device's firmware version is unknown)", requirement.firmware))
+    },
+  }
+}
+
+pub fn compatibility_report(class: DeviceClass, info: &DeviceInfo) -> CompatibilityReport {
+  CompatibilityReport {
+    tool_version: TOOL_VERSION.to_owned(),
+    firmware_version: info.firmware_version.clone(),
+    features: COMPATIBILITY_MATRIX.iter()
+      .filter(|r| r.class == class)
+      .map(|r| check(r, info.firmware_version.as_deref()))
+      .collect()
+  }
+}
+
+pub fn require_feature(class: DeviceClass, feature: &str, info: &DeviceInfo) -> anyhow::Result<()> {
+  match COMPATIBILITY_MATRIX.iter().find(|r| r.class == class && r.feature == feature) {
+    Some(requirement) => {
+      let support = check(requirement, info.firmware_version.as_deref());
+      if !support.supported {
+        return Err(coded(ErrorCode::FeatureUnsupported, format!("{} is not supported by this device: {}", feature, support.reason.unwrap_or_default())));
+      }
+      Ok(())
+    },
+    None => Err(coded(ErrorCode::FeatureUnsupported, format!("{} has no entry in the compatibility matrix", feature)))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::devices::{DeviceState, DeviceType};
+  use crate::errors::has_code;
+
+  fn lasercan(firmware_version: Option<&str>) -> DeviceInfo {
+    DeviceInfo {
+      device_type: DeviceType::Grapple(grapple_frc_msgs::grapple::device_info::GrappleModelId::LaserCan),
+      firmware_version: firmware_version.map(str::to_owned),
+      serial: Some(0x207_0001),
+      is_dfu: false,
+      is_dfu_in_progress: false,
+      name: None,
+      device_id: Some(1),
+      activity: vec![],
+      state: DeviceState::Ready,
+      pinned: false,
+      tags: vec![],
+      domain_display_name: None,
+      domain_color: None,
+    }
+  }
+
+  #[test]
+  fn rejects_firmware_older_than_the_feature() {
+    assert!(require_feature(DeviceClass::LaserCan, "set_roi", &lasercan(Some("2024.2.0"))).is_ok());
+
+    let e = require_feature(DeviceClass::LaserCan, "set_roi", &lasercan(Some("2024.1.0"))).unwrap_err();
+    assert!(has_code(&e, ErrorCode::FeatureUnsupported));
+    // Older than set_roi, but new enough for set_range
+    assert!(require_feature(DeviceClass::LaserCan, "set_range", &lasercan(Some("2024.1.0"))).is_ok());
+  }
+
+  #[test]
+  fn fails_closed() {
+    for version in [None, Some("not a version")] {
+      let e = require_feature(DeviceClass::LaserCan, "set_range", &lasercan(version)).unwrap_err();
+      assert!(has_code(&e, ErrorCode::FeatureUnsupported));
+    }
+    assert!(require_feature(DeviceClass::LaserCan, "set_nonexistent", &lasercan(Some("2024.2.0"))).is_err());
+    assert!(compatibility_report(DeviceClass::LaserCan, &lasercan(None)).features.iter().all(|f| !f.supported));
+  }
+}
@@ -0,0 +1,52 @@
+use crate::errors::{coded, ErrorCode};
+
+/* Device configurations in a form that can go through the OS clipboard, so settings can be copied between devices of
+   the same model, or pasted into GrappleHook on another laptop. The fields are whatever the device's own `config` RPC
+   returns, and are handed back to its `apply_config` RPC, which validates them as it would any other request. */
+pub const CONFIG_FORMAT: &str = "grapplehook-device-config";
+pub const CONFIG_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct DeviceConfig {
+  pub format: String,
+  pub format_version: u32,
+  pub model: String,
+  /* Informational only, the target device checks firmware support for each setting as it's applied */
+  pub firmware_version: Option<String>,
+  pub fields: serde_json::Value,
+}
+
+impl DeviceConfig {
+  pub fn new(model: &str, firmware_version: Option<String>, fields: serde_json::Value) -> Self {
+    Self {
+      format: CONFIG_FORMAT.to_owned(),
+      format_version: CONFIG_FORMAT_VERSION,
+      model: model.to_owned(),
+      firmware_version,
+      fields
+    }
+  }
+
+  pub fn to_clipboard(&self) -> anyhow::Result<String> {
+    Ok(serde_json::to_string_pretty(self)?)
+  }
+
+  pub fn from_clipboard(payload: &str) -> anyhow::Result<Self> {
+    let value: serde_json::Value = serde_json::from_str(payload.trim())
+      .map_err(|_| coded(ErrorCode::IncompatibleConfig, "The clipboard doesn't contain a device configuration"))?;
+    if value.get("format").and_then(|f| f.as_str()) != Some(CONFIG_FORMAT) {
+      return Err(coded(ErrorCode::IncompatibleConfig, "The clipboard doesn't contain a device configuration"));
+    }
+    Ok(serde_json::from_value(value)?)
+  }
+
+  pub fn validate_for(&self, model: &str) -> anyhow::Result<()> {
+    if self.format_version > CONFIG_FORMAT_VERSION {
+      return Err(coded(ErrorCode::IncompatibleConfig, format!("This configuration was copied from a newer version of GrappleHook (format v{})", self.format_version)));
+    }
+    if self.model != model {
+      return Err(coded(ErrorCode::IncompatibleConfig, format!("This configuration is for a {}, it can't be pasted onto a {}", self.model, model)));
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use grapple_frc_msgs::grapple::{lasercan::{LaserCanMeasurement, LaserCanRangingMode, LaserCanRoi, LaserCanTimingBudget}, mitocandria::{MitocandriaChannelStatus, MitocandriaStatusFrame}};
+
+use super::bus_load::{lasercan_rate_hz, utilization};
+use super::lasercan_geometry::{DerivedGeometry, LaserCanGeometry};
+use super::DeviceInfo;
+
+/* Dashboards bundle exactly what each device class's landing view needs into a single call. Bump the
+   version whenever a field is added, removed or changes meaning so the frontend can tell it's out of date. */
+pub const LASERCAN_DASHBOARD_VERSION: u32 = 2;
+pub const MITOCANDRIA_DASHBOARD_VERSION: u32 = 1;
+
+pub fn dashboard_versions() -> HashMap<String, u32> {
+  let mut versions = HashMap::new();
+  versions.insert("LaserCAN".to_owned(), LASERCAN_DASHBOARD_VERSION);
+  versions.insert("MitoCANdria".to_owned(), MITOCANDRIA_DASHBOARD_VERSION);
+  versions
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct LaserCanDashboard {
+  pub version: u32,
+  pub firmware_version: Option<String>,
+  pub has_measurement: bool,
+  pub in_range: bool,
+  pub distance_mm: Option<f64>,
+  pub ambient: Option<f64>,
+  pub mode: Option<LaserCanRangingMode>,
+  pub budget: Option<LaserCanTimingBudget>,
+  pub roi: Option<LaserCanRoi>,
+  pub rate_hz: f64,
+  pub bus_utilization: f64,
+  /* Since v2. Only with a geometry profile and an in-range measurement. */
+  pub derived: Option<DerivedGeometry>,
+}
+
+impl LaserCanDashboard {
+  pub fn new(info: &DeviceInfo, measurement: Option<&LaserCanMeasurement>, geometry: Option<&LaserCanGeometry>) -> Self {
+    let rate_hz = measurement.map(|m| lasercan_rate_hz(&m.budget)).unwrap_or(0.0);
+    Self {
+      version: LASERCAN_DASHBOARD_VERSION,
+      firmware_version: info.firmware_version.clone(),
+      has_measurement: measurement.is_some(),
+      in_range: measurement.map(|m| m.status == 0).unwrap_or(false),
+      distance_mm: measurement.map(|m| m.distance_mm as f64),
+      ambient: measurement.map(|m| m.ambient as f64),
+      mode: measurement.map(|m| m.mode.clone()),
+      budget: measurement.map(|m| m.budget.clone()),
+      roi: measurement.map(|m| m.roi.clone()),
+      rate_hz,
+      bus_utilization: utilization(rate_hz),
+      derived: match (geometry, measurement) {
+        (Some(geometry), Some(m)) if m.status == 0 => Some(geometry.derive(m.distance_mm as f64)),
+        _ => None
+      },
+    }
+  }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub enum ChannelKind {
+  Switchable,
+  NonSwitchable,
+  Adjustable
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct MitocandriaChannelSummary {
+  pub index: usize,
+  pub kind: ChannelKind,
+  pub enabled: bool,
+  pub current_ma: f64,
+  pub voltage_mv: Option<f64>,
+  pub voltage_setpoint_mv: Option<f64>,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct MitocandriaDashboard {
+  pub version: u32,
+  pub firmware_version: Option<String>,
+  pub has_status: bool,
+  pub channels: Vec<MitocandriaChannelSummary>,
+  pub total_current_ma: f64,
+}
+
+impl MitocandriaDashboard {
+  pub fn new(info: &DeviceInfo, status: Option<&MitocandriaStatusFrame>) -> Self {
+    let channels: Vec<MitocandriaChannelSummary> = status.map(|s| s.channels.iter().enumerate().map(|(index, channel)| match channel {
+      MitocandriaChannelStatus::Switchable { current, enabled } => MitocandriaChannelSummary {
+        index, kind: ChannelKind::Switchable, enabled: *enabled, current_ma: *current as f64, voltage_mv: None, voltage_setpoint_mv: None
+      },
+      MitocandriaChannelStatus::NonSwitchable { current } => MitocandriaChannelSummary {
+        index, kind: ChannelKind::NonSwitchable, enabled: true, current_ma: *current as f64, voltage_mv: None, voltage_setpoint_mv: None
+      },
+      MitocandriaChannelStatus::Adjustable { current, enabled, voltage, voltage_setpoint } => MitocandriaChannelSummary {
+        index, kind: ChannelKind::Adjustable, enabled: *enabled, current_ma: *current as f64,
+        voltage_mv: Some(*voltage as f64), voltage_setpoint_mv: Some(*voltage_setpoint as f64)
+      },
+    }).collect()).unwrap_or_default();
+
+    Self {
+      version: MITOCANDRIA_DASHBOARD_VERSION,
+      firmware_version: info.firmware_version.clone(),
+      has_status: status.is_some(),
+      total_current_ma: channels.iter().map(|c| c.current_ma).sum(),
+      channels,
+    }
+  }
+}
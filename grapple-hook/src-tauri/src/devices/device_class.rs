@@ -0,0 +1,37 @@
+use grapple_frc_msgs::grapple::device_info::GrappleModelId;
+
+use super::DeviceType;
+
+/* The driver families GrappleHook knows how to instantiate. Several model IDs (e.g. a hardware respin that
+   reports a new model ID but speaks the same protocol) may map to the same class. */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub enum DeviceClass {
+  LaserCan,
+  MitoCANdria,
+  FlexiCan,
+  SpiderLan,
+}
+
+pub struct DeviceClassAlias {
+  /* The model byte the device enumerates with, which needn't be one grapple-frc-msgs knows (see unknown_model) */
+  pub model_id: u8,
+  pub class: DeviceClass,
+}
+
+/* Add new or rebranded hardware here, by its model byte, to have it handled by an existing driver. Enumerate
+   responses carry no hardware revision, so the model byte is all there is to go on. */
+pub const DEVICE_CLASS_ALIASES: &[DeviceClassAlias] = &[
+  DeviceClassAlias { model_id: GrappleModelId::LaserCan as u8, class: DeviceClass::LaserCan },
+  DeviceClassAlias { model_id: GrappleModelId::MitoCANdria as u8, class: DeviceClass::MitoCANdria },
+  DeviceClassAlias { model_id: GrappleModelId::FlexiCAN as u8, class: DeviceClass::FlexiCan },
+  DeviceClassAlias { model_id: GrappleModelId::SpiderLan as u8, class: DeviceClass::SpiderLan },
+];
+
+pub fn resolve_alias(device_type: &DeviceType) -> Option<&'static DeviceClassAlias> {
+  let model_id = device_type.grapple_model_byte()?;
+  DEVICE_CLASS_ALIASES.iter().find(|alias| alias.model_id == model_id)
+}
+
+pub fn resolve_device_class(device_type: &DeviceType) -> Option<DeviceClass> {
+  resolve_alias(device_type).map(|alias| alias.class)
+}
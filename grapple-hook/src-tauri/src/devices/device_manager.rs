@@ -1,211 +1,1275 @@
-use std::collections::HashMap;
-use std::io::Write;
-use std::str;
-use std::sync::atomic::AtomicBool;
-use std::sync::Arc;
-
-use grapple_frc_msgs::grapple::{TaggedGrappleMessage, GrappleMessageId};
-use grapple_frc_msgs::MessageId;
-use grapple_frc_msgs::DEVICE_ID_BROADCAST;
-use grapple_frc_msgs::grapple::{GrappleDeviceMessage, GrappleBroadcastMessage, device_info::{GrappleDeviceInfo, GrappleModelId}};
-use grapple_hook_macros::rpc;
-use log::{warn, info};
-use serde::{Serialize, Deserialize};
-use tokio::sync::{RwLock, mpsc, oneshot};
-use uuid::Uuid;
-
-use super::flexican::FlexiCan;
-use super::lasercan::LaserCan;
-use super::mitocandria::Mitocandria;
-// use super::powerful_panda::PowerfulPanda;
-use super::{DeviceType, DeviceInfo, VersionGatedDevice, RootDevice, FirmwareUpgradeDevice};
-// use super::{DeviceInfo, spiderlan::SpiderLAN};
-use crate::rpc::RpcBase;
-
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema, Hash, PartialEq, Eq)]
-pub enum DeviceId {
-  Dfu(/* Serial */ u32),      // Only for devices in DFU mode. Distinction is required since DFU devices have a different instantiated type.
-  Serial(/* Serial */ u32)
-}
-
-pub type Domain = String;
-
-pub struct DeviceEntry {
-  device: Box<dyn RootDevice + Send + Sync>,
-  info: Arc<RwLock<DeviceInfo>>,
-  last_seen: std::time::Instant
-}
-
-pub type RepliesWaiting = Arc<RwLock<HashMap<u32, HashMap<Uuid, oneshot::Sender<TaggedGrappleMessage<'static>>>>>>;
-
-pub struct DeviceManager {
-  send: HashMap<Domain, mpsc::Sender<TaggedGrappleMessage<'static>>>,
-  replies_waiting: HashMap<Domain, RepliesWaiting>,
-  devices: RwLock<HashMap<Domain, HashMap<DeviceId, DeviceEntry>>>,
-}
-
-impl DeviceManager {
-  pub fn new(send: HashMap<Domain, mpsc::Sender<TaggedGrappleMessage<'static>>>) -> Self {
-    let mut devices = HashMap::new();
-    let mut replies_waiting = HashMap::new();
-
-    for domain in send.keys() {
-      devices.insert(domain.clone(), HashMap::new());
-      replies_waiting.insert(domain.clone(), Arc::new(RwLock::new(HashMap::new())));
-    }
-
-    Self { send, devices: RwLock::new(devices), replies_waiting }
-  }
-
-  pub async fn reset(&self) {
-    for (_, devices) in self.devices.write().await.iter_mut() {
-      devices.clear();
-    }
-  }
-  
-  async fn on_enumerate_response(&self, domain: &String, info: DeviceInfo) -> anyhow::Result<()> {
-    let id = match info.is_dfu {
-      false => DeviceId::Serial(info.serial.unwrap()),
-      true => DeviceId::Dfu(info.serial.unwrap())
-    };
-
-    let now = std::time::Instant::now();
-
-    // try_write since long-running RPC calls (such as those waiting for a response)
-    // will deadlock until the timeout resolves.
-    if let Ok(mut dev_map) = self.devices.try_write() {
-      let devices = dev_map.get_mut(domain).unwrap();
-
-      if !devices.contains_key(&id) {
-        let device_type = info.device_type.clone();
-        let info_arc = Arc::new(RwLock::new(info));
-
-        let send = super::SendWrapper(self.send.get(domain).unwrap().clone(), self.replies_waiting.get(domain).unwrap().clone());
-
-        let device = match (&id, device_type) {
-          (DeviceId::Dfu(..),     DeviceType::Grapple(GrappleModelId::LaserCan)) => Box::new(FirmwareUpgradeDevice::<LaserCan>::new(send, info_arc.clone(), 8)),
-          (DeviceId::Serial(..),  DeviceType::Grapple(GrappleModelId::LaserCan)) => LaserCan::maybe_gate(send, info_arc.clone(), LaserCan::new).await,
-          // (DeviceId::Dfu(..),     DeviceType::Grapple(GrappleModelId::FlexiCAN)) => Box::new(FirmwareUpgradeDevice::<FlexiCan>::new(send, info_arc.clone(), 64)),
-          // (DeviceId::Serial(..),  DeviceType::Grapple(GrappleModelId::FlexiCAN)) => FlexiCan::maybe_gate(send, info_arc.clone(), FlexiCan::new).await,
-          (DeviceId::Dfu(..),     DeviceType::Grapple(GrappleModelId::MitoCANdria)) => Box::new(FirmwareUpgradeDevice::<Mitocandria>::new(send, info_arc.clone(), 64)),
-          (DeviceId::Serial(..),  DeviceType::Grapple(GrappleModelId::MitoCANdria)) => Mitocandria::maybe_gate(send, info_arc.clone(), Mitocandria::new).await,
-          _ => unreachable!()
-        };
-
-        /* If a device has gone from Serial to DFU, or the reverse, remove the old one so it doesn't linger. */
-        match &id {
-          DeviceId::Dfu(serial) => devices.remove(&DeviceId::Serial(*serial)),
-          DeviceId::Serial(serial) => devices.remove(&DeviceId::Dfu(*serial)),
-        };
-
-        devices.insert(id, DeviceEntry { device, info: info_arc, last_seen: now });
-      } else {
-        let deventry = devices.get_mut(&id).unwrap();
-        *deventry.info.write().await = info;
-        deventry.last_seen = now;
-      }
-    }
-    Ok(())
-  }
-
-  // async fn maybe_add_device(&self, domain: &String, id: &DeviceId, info: DeviceInfo, device: Box<dyn Device + Send + Sync>) -> anyhow::Result<()> {
-  //   let now = std::time::Instant::now();
-
-  //   let mut dev_map = self.devices.write().await;
-  //   let devices = dev_map.get_mut(domain).unwrap();
-  //   if !devices.contains_key(id) {
-  //     devices.insert(id.clone(), DeviceEntry { device, info: Arc::new(RwLock::new(info)), last_seen: now });
-  //   } else {
-  //     let deventry = devices.get_mut(id).unwrap();
-  //     *deventry.info.write().await = info;
-  //     deventry.last_seen = now;
-  //   }
-  //   Ok(())
-  // }
-
-  pub async fn on_message(&self, domain: String, id: GrappleMessageId, message: TaggedGrappleMessage<'static>) -> anyhow::Result<()> {
-    let msg_id_u32: u32 = Into::<MessageId>::into(id).into();
-
-    let waiting = self.replies_waiting.get(&domain).unwrap();
-    if waiting.read().await.contains_key(&msg_id_u32) {
-      let mut w = waiting.write().await;
-      for (_, waiting_element) in w.remove(&msg_id_u32).unwrap() {
-        waiting_element.send(message.clone()).ok();   // ok since it's fine if the channel is closed, e.g. timeouts.
-      }
-    }
-
-    match message.msg.clone() {
-      GrappleDeviceMessage::Broadcast(GrappleBroadcastMessage::DeviceInfo(dinfo)) => match dinfo {
-        GrappleDeviceInfo::EnumerateResponse { model_id, serial, is_dfu, is_dfu_in_progress, name, version } => {
-          self.on_enumerate_response(&domain, DeviceInfo {
-            device_type: DeviceType::Grapple(model_id),
-            firmware_version: Some(version.into_owned()),
-            serial: Some(serial),
-            is_dfu,
-            is_dfu_in_progress,
-            name: Some(name.into_owned()),
-            device_id: Some(message.device_id)
-          }).await?;
-        },
-        _ => ()
-      }
-      _ => (),
-    }
-    
-    for (_, device) in self.devices.read().await.get(&domain).unwrap().iter() {
-      match device.device.handle(message.clone()).await {
-        Ok(()) => (),
-        Err(e) => warn!("Error in message handler: {}", e)
-      }
-    }
-
-    Ok(())
-  }
-
-  pub async fn on_tick(&self) -> anyhow::Result<()> {
-    for (_domain, send) in self.send.iter() {
-      send.send(TaggedGrappleMessage::new(DEVICE_ID_BROADCAST, GrappleDeviceMessage::Broadcast(GrappleBroadcastMessage::DeviceInfo(GrappleDeviceInfo::EnumerateRequest)))).await?;
-    }
-
-    // Check age off
-    if let Ok(mut dev_map) = self.devices.try_write() {
-      for (_domain, devices) in dev_map.iter_mut() {
-        devices.retain(|_, device| {
-          device.last_seen.elapsed().as_secs() < 4
-        });
-      }
-    }
-
-    Ok(())
-  }
-}
-
-#[rpc]
-impl DeviceManager {
-  async fn call(&self, domain: Domain, device_id: DeviceId, data: serde_json::Value) -> anyhow::Result<serde_json::Value> {
-    let result = self.devices.read().await
-      .get(&domain)
-      .unwrap()
-      .get(&device_id)
-      .ok_or(anyhow::anyhow!("No device with ID {:?}", device_id))?
-      .device
-      .rpc_call(data).await;
-
-    Ok(result?)
-  }
-
-  async fn devices(&self) -> anyhow::Result<HashMap<Domain, Vec<(DeviceId, DeviceInfo, String)>>> {
-    let mut device_states = HashMap::new();
-
-    let devices = self.devices.read().await;
-    for (domain, devices) in devices.iter() {
-      let mut vec = vec![];
-      for (id, device) in devices.iter() {
-        vec.push((id.clone(), device.info.read().await.clone(), device.device.device_class().to_owned()));
-      }
-      device_states.insert(domain.clone(), vec);
-    }
-
-    Ok(device_states)
-  }
-}
+use std::collections::HashMap;
+use std::io::Write;
+use std::str;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use grapple_frc_msgs::grapple::{TaggedGrappleMessage, GrappleMessageId};
+use grapple_frc_msgs::MessageId;
+use grapple_frc_msgs::DEVICE_ID_BROADCAST;
+use grapple_frc_msgs::grapple::{GrappleDeviceMessage, GrappleBroadcastMessage, device_info::{GrappleDeviceInfo, GrappleModelId}};
+use grapple_hook_macros::rpc;
+use log::{warn, info};
+use serde::{Serialize, Deserialize};
+use tokio::sync::{RwLock, mpsc};
+
+use super::flexican::FlexiCan;
+use super::lasercan::LaserCan;
+use super::mitocandria::Mitocandria;
+use super::spiderlan::SpiderLan;
+use super::generic_grapple::GenericGrappleDevice;
+use super::activity::ActivityTracker;
+use super::attention::{assess, AttentionItem};
+use super::clock::Clock;
+use super::bus_load::BusLoadReport;
+use super::fixtures::{fixtures, require_developer_mode};
+use super::capabilities::{capability_cache, DeviceCapabilities};
+use super::latency::LatencyEstimator;
+use super::id_plan::{plan, IdMove};
+use super::impairment::{Impairment, SharedImpairment};
+use super::limits::{RequestLimiter, WriteThrottle};
+use super::link_health::{DomainStatus, LinkHealth};
+use super::poller::{PollerConfig, PollerStatus};
+use super::session::{config_change, session_history, SessionStats};
+use super::reply_routing::ReplyRegistry;
+use super::quarantine::{FrameQuarantine, QuarantinedFrame};
+use super::domain_settings::{domain_settings, validate_color, DomainSettings};
+use super::registry::registry;
+use super::metadata::metadata;
+use super::foreign::{ForeignDevice, ForeignDevices};
+use super::can_conflicts::{find_conflicts, CanIdConflict};
+use super::changes::{device_changes, info_changed, DeviceChange, HotplugKind};
+use super::transcript::{Direction, Transcript, TranscriptEntry};
+use super::watchdog::RpcWatchdog;
+// use super::powerful_panda::PowerfulPanda;
+use super::device_class::{resolve_device_class, DeviceClass};
+use super::unknown_model::decode_enumerate_response;
+use super::{DeviceType, DeviceInfo, DeviceState, VersionGatedDevice, RootDevice, FirmwareUpgradeDevice};
+use crate::errors::{coded, ErrorCode};
+use crate::events::{events, EventSeverity};
+use crate::rpc::RpcBase;
+use crate::telemetry::telemetry;
+use crate::visibility::rate_divisor;
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema, Hash, PartialEq, Eq)]
+pub enum DeviceId {
+  Dfu(/* Serial */ u32),      // Only for devices in DFU mode. Distinction is required since DFU devices have a different instantiated type.
+  Serial(/* Serial */ u32)
+}
+
+pub type Domain = String;
+
+pub struct DeviceEntry {
+  device: Box<dyn RootDevice + Send + Sync>,
+  info: Arc<RwLock<DeviceInfo>>,
+  first_seen_ms: i64,
+  last_seen_ms: AtomicI64,
+  activity: ActivityTracker,
+  session: SessionStats,
+}
+
+/* A device that hasn't answered an enumerate for this long is considered lost (it's removed entirely after the domain's
+   age-off, AGE_OFF_MS unless configured). Both stretch while the app is hidden, since we enumerate less often. */
+const LOST_AFTER_MS: i64 = 1500;
+const AGE_OFF_MS: i64 = 4000;
+const AGE_OFF_RANGE_MS: std::ops::RangeInclusive<i64> = 2000..=120_000;
+/* How long a device is shown as newly discovered */
+const DISCOVERED_FOR_MS: i64 = 1000;
+
+impl DeviceEntry {
+  async fn state(&self, now_ms: i64) -> DeviceState {
+    let info = self.info.read().await;
+    if now_ms - self.last_seen_ms.load(Ordering::Relaxed) > LOST_AFTER_MS * rate_divisor() as i64 {
+      // DFU devices go quiet while they reboot into their new firmware
+      return if info.is_dfu { DeviceState::Rebooting } else { DeviceState::Lost };
+    }
+
+    if info.is_dfu {
+      DeviceState::Updating
+    } else if let Some(reason) = self.device.gated_reason() {
+      DeviceState::IncompatibleFirmware(reason)
+    } else if now_ms - self.first_seen_ms < DISCOVERED_FOR_MS {
+      DeviceState::Discovered
+    } else {
+      DeviceState::Ready
+    }
+  }
+}
+
+pub type RepliesWaiting = Arc<ReplyRegistry>;
+
+/* How many received messages may queue up for a domain before its transport has to wait */
+const DOMAIN_INBOX_SIZE: usize = 1024;
+
+/* Work for a domain's processing task, handled strictly in the order it was queued */
+enum DomainCommand {
+  /* With when it arrived, see LatencyEstimator::arrival_ms */
+  Message(GrappleMessageId, TaggedGrappleMessage<'static>, i64),
+  /* An EnumerateResponse that only decoded once its model was set aside, see unknown_model */
+  Enumerated(DeviceInfo),
+  Tick,
+}
+
+/* The device an EnumerateResponse describes, with device_type worked out from its model */
+fn enumerated_info(message: &TaggedGrappleMessage<'static>, device_type: impl FnOnce(GrappleModelId) -> DeviceType) -> Option<DeviceInfo> {
+  match message.msg.clone() {
+    GrappleDeviceMessage::Broadcast(GrappleBroadcastMessage::DeviceInfo(GrappleDeviceInfo::EnumerateResponse { model_id, serial, is_dfu, is_dfu_in_progress, name, version })) => Some(DeviceInfo {
+      device_type: device_type(model_id),
+      firmware_version: Some(version.into_owned()),
+      serial: Some(serial),
+      is_dfu,
+      is_dfu_in_progress,
+      name: Some(name.into_owned()),
+      device_id: Some(message.device_id),
+      activity: vec![],
+      state: DeviceState::Discovered,
+      pinned: false,
+      tags: vec![],
+      domain_display_name: None,
+      domain_color: None,
+    }),
+    _ => None
+  }
+}
+
+/* A malformed message that's really an EnumerateResponse from a model grapple-frc-msgs doesn't know */
+fn unknown_model_info(arbitration_id: u32, payload: &[u8]) -> Option<DeviceInfo> {
+  let (model_id, message) = decode_enumerate_response(arbitration_id, payload)?;
+  enumerated_info(&message, |_| DeviceType::UnknownGrapple(model_id))
+}
+
+/* Everything for one domain (one CAN bus). Each domain has its own device map and processes its received messages
+   and ticks on its own task, so a flood of traffic on one domain can't hold up another.
+
+   Only that task adds or removes devices (apart from end_sessions, when the domain goes away). RPCs take a snapshot of the entries they need (see entry() and entries())
+   and talk to the devices with the map unlocked, so a slow RPC can't hold up enumeration or age-off, and the task
+   never has to skip an update because the map is busy. */
+pub struct DomainState {
+  name: Domain,
+  clock: Clock,
+  send: mpsc::Sender<TaggedGrappleMessage<'static>>,
+  replies_waiting: RepliesWaiting,
+  latency: Arc<LatencyEstimator>,
+  limiter: Arc<RequestLimiter>,
+  write_throttle: WriteThrottle,
+  devices: RwLock<HashMap<DeviceId, Arc<DeviceEntry>>>,
+  /* Which devices are on each CAN ID, so a received message only goes to the device it came from. Usually one device
+     per ID, but there can be more during a conflict or while a device is in DFU. Only changed with devices locked. */
+  by_can_id: std::sync::RwLock<HashMap<u8, Vec<DeviceId>>>,
+  quarantine: FrameQuarantine,
+  foreign: ForeignDevices,
+  /* CAN ID conflicts we've already raised an event for */
+  reported_conflicts: std::sync::Mutex<Vec<CanIdConflict>>,
+  impairment: SharedImpairment,
+  transcript: Arc<Transcript>,
+  link: LinkHealth,
+  ticks: std::sync::atomic::AtomicU32,
+
+  inbox: mpsc::Sender<DomainCommand>,
+  inbox_rx: std::sync::Mutex<Option<mpsc::Receiver<DomainCommand>>>,
+}
+
+impl DomainState {
+  fn new(name: Domain, clock: Clock, send: mpsc::Sender<TaggedGrappleMessage<'static>>) -> Arc<Self> {
+    let (inbox, inbox_rx) = mpsc::channel(DOMAIN_INBOX_SIZE);
+    Arc::new(Self {
+      name,
+      send,
+      replies_waiting: Arc::new(ReplyRegistry::new(clock.clone())),
+      latency: Arc::new(LatencyEstimator::new(clock.clone())),
+      limiter: Arc::new(RequestLimiter::new()),
+      write_throttle: WriteThrottle::new(clock.clone()),
+      devices: RwLock::new(HashMap::new()),
+      by_can_id: std::sync::RwLock::new(HashMap::new()),
+      quarantine: FrameQuarantine::new(),
+      foreign: ForeignDevices::new(),
+      reported_conflicts: std::sync::Mutex::new(vec![]),
+      impairment: Arc::new(std::sync::RwLock::new(Impairment::default())),
+      transcript: Arc::new(Transcript::new()),
+      link: LinkHealth::new(clock.now_ms()),
+      ticks: std::sync::atomic::AtomicU32::new(0),
+      inbox,
+      inbox_rx: std::sync::Mutex::new(Some(inbox_rx)),
+      clock,
+    })
+  }
+
+  /* Start the processing task the first time a message arrives, since we need to be inside the runtime to spawn it */
+  fn ensure_worker(self: &Arc<Self>) {
+    if let Some(mut rx) = self.inbox_rx.lock().unwrap().take() {
+      // Weak, so the task finishes once the domain is removed and the inbox sender is dropped along with it
+      let state = Arc::downgrade(self);
+      tokio::task::spawn(async move {
+        while let Some(command) = rx.recv().await {
+          let Some(state) = state.upgrade() else { break };
+          let result = match command {
+            DomainCommand::Message(id, message, arrival_ms) => state.process(id, message, arrival_ms).await,
+            DomainCommand::Enumerated(info) => state.on_enumerate_response(info).await,
+            DomainCommand::Tick => state.on_tick().await,
+          };
+          if let Err(e) = result {
+            warn!("Error processing {}: {}", state.name, e);
+          }
+        }
+      });
+    }
+  }
+
+  async fn entry(&self, device_id: &DeviceId) -> anyhow::Result<Arc<DeviceEntry>> {
+    self.devices.read().await.get(device_id).cloned()
+      .ok_or(coded(ErrorCode::DeviceNotFound, format!("No device with ID {:?}", device_id)))
+  }
+
+  async fn entries(&self) -> Vec<(DeviceId, Arc<DeviceEntry>)> {
+    self.devices.read().await.iter().map(|(id, entry)| (id.clone(), entry.clone())).collect()
+  }
+
+  fn sender(&self) -> super::SendWrapper {
+    super::SendWrapper::new(self.send.clone(), self.replies_waiting.clone(), self.latency.clone(), self.limiter.clone(), self.impairment.clone(), self.transcript.clone())
+  }
+
+  /* Move a device in the CAN ID index, None meaning it's being added or removed */
+  fn reindex(&self, id: &DeviceId, old: Option<u8>, new: Option<u8>) {
+    if old == new {
+      return;
+    }
+    let mut index = self.by_can_id.write().unwrap();
+    if let Some(old) = old {
+      if let Some(ids) = index.get_mut(&old) {
+        ids.retain(|i| i != id);
+        if ids.is_empty() {
+          index.remove(&old);
+        }
+      }
+    }
+    if let Some(new) = new {
+      index.entry(new).or_default().push(id.clone());
+    }
+  }
+
+  async fn on_enumerate_response(&self, info: DeviceInfo) -> anyhow::Result<()> {
+    let id = match info.is_dfu {
+      false => DeviceId::Serial(info.serial.unwrap()),
+      true => DeviceId::Dfu(info.serial.unwrap())
+    };
+
+    let now = self.clock.now_ms();
+    // Wall clock rather than the domain clock, since the registry outlives any replay
+    registry().observe(&self.name, &info, chrono::Utc::now().timestamp_millis());
+
+    let mut devices = self.devices.write().await;
+    if !devices.contains_key(&id) {
+      if let (false, Some(class)) = (info.is_dfu, resolve_device_class(&info.device_type)) {
+        capability_cache().on_discovered(class, &info);
+      }
+
+      let device_type = info.device_type.clone();
+      let can_id = info.device_id;
+      let added = DeviceChange::Added { domain: self.name.clone(), device_id: id.clone(), info: info.clone() };
+      let info_arc = Arc::new(RwLock::new(info));
+
+      let send = self.sender();
+
+      let device: Box<dyn RootDevice + Send + Sync> = match (&id, resolve_device_class(&device_type)) {
+        (DeviceId::Dfu(..),     Some(DeviceClass::LaserCan)) => Box::new(FirmwareUpgradeDevice::<LaserCan>::new(send, info_arc.clone(), 8)),
+        (DeviceId::Serial(..),  Some(DeviceClass::LaserCan)) => LaserCan::maybe_gate(send, info_arc.clone(), LaserCan::new).await,
+        (DeviceId::Dfu(..),     Some(DeviceClass::FlexiCan)) => Box::new(FirmwareUpgradeDevice::<FlexiCan>::new(send, info_arc.clone(), 64)),
+        (DeviceId::Serial(..),  Some(DeviceClass::FlexiCan)) => FlexiCan::maybe_gate(send, info_arc.clone(), FlexiCan::new).await,
+        (DeviceId::Dfu(..),     Some(DeviceClass::SpiderLan)) => Box::new(FirmwareUpgradeDevice::<GenericGrappleDevice>::new(send, info_arc.clone(), 8)),
+        (DeviceId::Serial(..),  Some(DeviceClass::SpiderLan)) => Box::new(SpiderLan::new(send, info_arc.clone())),
+        (DeviceId::Dfu(..),     Some(DeviceClass::MitoCANdria)) => Box::new(FirmwareUpgradeDevice::<Mitocandria>::new(send, info_arc.clone(), 64)),
+        (DeviceId::Serial(..),  Some(DeviceClass::MitoCANdria)) => Mitocandria::maybe_gate(send, info_arc.clone(), Mitocandria::new).await,
+        (DeviceId::Dfu(..),     None) => Box::new(FirmwareUpgradeDevice::<GenericGrappleDevice>::new(send, info_arc.clone(), 8)),
+        (DeviceId::Serial(..),  None) => {
+          warn!("No driver for device type {:?} ({:?}), falling back to the generic driver", device_type, id);
+          Box::new(GenericGrappleDevice::new(send, info_arc.clone()))
+        }
+      };
+
+      /* If a device has gone from Serial to DFU, or the reverse, remove the old one so it doesn't linger. */
+      let other = match &id {
+        DeviceId::Dfu(serial) => DeviceId::Serial(*serial),
+        DeviceId::Serial(serial) => DeviceId::Dfu(*serial),
+      };
+      let swapped = devices.remove(&other);
+      if let Some(entry) = &swapped {
+        self.reindex(&other, entry.info.read().await.device_id, None);
+        device_changes().publish(DeviceChange::Removed { domain: self.name.clone(), device_id: other });
+      } else {
+        device_changes().hotplug(HotplugKind::Connected, &self.name, &*info_arc.read().await);
+      }
+
+      self.reindex(&id, None, can_id);
+      devices.insert(id, Arc::new(DeviceEntry {
+        device, info: info_arc, first_seen_ms: now, last_seen_ms: AtomicI64::new(now), activity: ActivityTracker::new(self.clock.clone()), session: SessionStats::new()
+      }));
+      device_changes().publish(added);
+    } else {
+      let deventry = devices.get(&id).unwrap();
+      let mut current = deventry.info.write().await;
+      if info_changed(&current, &info) {
+        device_changes().publish(DeviceChange::Updated { domain: self.name.clone(), device_id: id.clone(), info: info.clone() });
+      }
+      self.reindex(&id, current.device_id, info.device_id);
+      *current = info;
+      drop(current);
+      deventry.last_seen_ms.store(now, Ordering::Relaxed);
+    }
+    Ok(())
+  }
+
+  async fn process(&self, id: GrappleMessageId, message: TaggedGrappleMessage<'static>, arrival_ms: i64) -> anyhow::Result<()> {
+    self.latency.begin_processing(arrival_ms);
+    let result = self.handle_message(id, message, arrival_ms).await;
+    self.latency.end_processing();
+    result
+  }
+
+  async fn handle_message(&self, id: GrappleMessageId, message: TaggedGrappleMessage<'static>, arrival_ms: i64) -> anyhow::Result<()> {
+    let msg_id_u32: u32 = Into::<MessageId>::into(id).into();
+    self.transcript.record(Direction::Received, self.latency.timestamp_ms(), &message);
+
+    self.replies_waiting.deliver(msg_id_u32, &message);
+
+    if let Some(info) = enumerated_info(&message, DeviceType::Grapple) {
+      self.latency.on_probe_reply(arrival_ms);
+      self.on_enumerate_response(info).await?;
+    }
+    
+    let targets: Vec<Arc<DeviceEntry>> = {
+      let devices = self.devices.read().await;
+      match message.device_id {
+        DEVICE_ID_BROADCAST => devices.values().cloned().collect(),
+        can_id => {
+          let ids = self.by_can_id.read().unwrap().get(&can_id).cloned().unwrap_or_default();
+          ids.iter().filter_map(|id| devices.get(id).cloned()).collect()
+        }
+      }
+    };
+
+    for device in targets {
+      if message.device_id != DEVICE_ID_BROADCAST {
+        device.activity.record();
+        device.session.record_message();
+      }
+
+      match device.device.handle(message.clone()).await {
+        Ok(()) => (),
+        Err(e) => warn!("Error in message handler: {}", e)
+      }
+    }
+
+    Ok(())
+  }
+
+  /* Move one device to a new CAN ID and wait until it answers enumeration with it */
+  async fn move_device(&self, step: &IdMove) -> anyhow::Result<()> {
+    let info = self.devices.read().await.get(&DeviceId::Serial(step.serial)).map(|e| e.info.clone())
+      .ok_or(coded(ErrorCode::DeviceNotFound, format!("Device {:x} has disappeared", step.serial)))?;
+
+    self.sender().send(TaggedGrappleMessage::new(
+      DEVICE_ID_BROADCAST,
+      GrappleDeviceMessage::Broadcast(GrappleBroadcastMessage::DeviceInfo(GrappleDeviceInfo::SetId { serial: step.serial, new_id: step.to }))
+    )).await?;
+
+    let start = self.clock.now_ms();
+    while self.clock.since(start) < ID_CHANGE_VERIFY_TIMEOUT {
+      if info.read().await.device_id == Some(step.to) {
+        return Ok(());
+      }
+      self.clock.sleep(Duration::from_millis(100)).await;
+    }
+    Err(coded(ErrorCode::RequestTimeout, format!("Device {:x} didn't confirm its move from ID {} to {}", step.serial, step.from, step.to)))
+  }
+
+  /* Re-enumerate until the device reports the given name. The name in an enumerate response is read back from the
+     device's stored config, so this confirms it actually stuck rather than just that the message was sent. */
+  async fn confirm_name(&self, device_id: &DeviceId, name: &str) -> anyhow::Result<()> {
+    let info = self.devices.read().await.get(device_id).map(|e| e.info.clone())
+      .ok_or(coded(ErrorCode::DeviceNotFound, format!("No device with ID {:?}", device_id)))?;
+
+    let start = self.clock.now_ms();
+    while self.clock.since(start) < NAME_CHANGE_VERIFY_TIMEOUT {
+      self.send.send(TaggedGrappleMessage::new(DEVICE_ID_BROADCAST, GrappleDeviceMessage::Broadcast(GrappleBroadcastMessage::DeviceInfo(GrappleDeviceInfo::EnumerateRequest)))).await?;
+      self.clock.sleep(Duration::from_millis(250)).await;
+      if info.read().await.name.as_deref() == Some(name) {
+        return Ok(());
+      }
+    }
+    let current = info.read().await.name.clone().unwrap_or_default();
+    Err(coded(ErrorCode::RequestTimeout, format!("Device {:?} still reports the name \"{}\" after being renamed to \"{}\"", device_id, current, name)))
+  }
+
+  /* Enumerate and wait for this device in particular to answer, for when we need to know what it says right now
+     rather than as of the last tick */
+  async fn probe(&self, device_id: &DeviceId) -> anyhow::Result<DeviceInfo> {
+    let entry = self.entry(device_id).await?;
+    let last_seen = entry.last_seen_ms.load(Ordering::Relaxed);
+
+    let start = self.clock.now_ms();
+    while self.clock.since(start) < PROBE_TIMEOUT {
+      self.send.send(TaggedGrappleMessage::new(DEVICE_ID_BROADCAST, GrappleDeviceMessage::Broadcast(GrappleBroadcastMessage::DeviceInfo(GrappleDeviceInfo::EnumerateRequest)))).await?;
+      self.clock.sleep(Duration::from_millis(250)).await;
+      if entry.last_seen_ms.load(Ordering::Relaxed) != last_seen {
+        return Ok(entry.info.read().await.clone());
+      }
+    }
+    Err(coded(ErrorCode::RequestTimeout, format!("Device {:?} didn't answer enumeration", device_id)))
+  }
+
+  async fn on_tick(&self) -> anyhow::Result<()> {
+    // Only enumerate every few ticks while the app is hidden
+    let tick = self.ticks.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    if tick % rate_divisor() != 0 {
+      return Ok(());
+    }
+
+    let expired = self.replies_waiting.expire();
+    if expired > 0 {
+      warn!("Cleared out {} abandoned reply waiters on {}", expired, self.name);
+    }
+
+    self.latency.on_probe_sent();
+    self.send.send(TaggedGrappleMessage::new(DEVICE_ID_BROADCAST, GrappleDeviceMessage::Broadcast(GrappleBroadcastMessage::DeviceInfo(GrappleDeviceInfo::EnumerateRequest)))).await?;
+
+    // Check age off
+    let mut devices = self.devices.write().await;
+    let age_off = domain_settings().get(&self.name).age_off_ms.unwrap_or(AGE_OFF_MS);
+    let (now, age_off) = (self.clock.now_ms(), age_off * rate_divisor() as i64);
+    let gone = devices.iter().filter(|(_, d)| now - d.last_seen_ms.load(Ordering::Relaxed) >= age_off).map(|(id, _)| id.clone()).collect::<Vec<_>>();
+    let mut summaries = vec![];
+    for id in gone {
+      if let Some(entry) = devices.remove(&id) {
+        let info = entry.info.read().await;
+        self.reindex(&id, info.device_id, None);
+        summaries.push(entry.session.summarise(&self.name, entry.device.device_class(), &*info));
+        device_changes().hotplug(HotplugKind::Lost, &self.name, &info);
+        device_changes().publish(DeviceChange::Removed { domain: self.name.clone(), device_id: id });
+      }
+    }
+    drop(devices);
+    session_history().add(summaries);
+
+    self.check_conflicts().await;
+    Ok(())
+  }
+
+  async fn can_id_conflicts(&self) -> Vec<CanIdConflict> {
+    let mut ids = vec![];
+    for (_, entry) in self.entries().await {
+      let info = entry.info.read().await;
+      if let (false, Some(serial), Some(id)) = (info.is_dfu, info.serial, info.device_id) {
+        ids.push((serial, id));
+      }
+    }
+    find_conflicts(&ids)
+  }
+
+  /* Raise an event the first time each conflict shows up */
+  async fn check_conflicts(&self) {
+    let conflicts = self.can_id_conflicts().await;
+    let mut reported = self.reported_conflicts.lock().unwrap();
+    for conflict in conflicts.iter().filter(|c| !reported.iter().any(|r| r.can_id == c.can_id && r.serials == c.serials)) {
+      let serials = conflict.serials.iter().map(|s| format!("{:x}", s)).collect::<Vec<_>>().join(", ");
+      let suggestion = conflict.suggested_id.map(|id| format!(" CAN ID {} is free.", id)).unwrap_or_default();
+      events().emit(conflict.serials.first().cloned(), "can_id_conflict", EventSeverity::Warning,
+        format!("Devices {} on {} are all using CAN ID {}. Give all but one of them a new ID.{}", serials, self.name, conflict.can_id, suggestion));
+    }
+    *reported = conflicts;
+  }
+
+  /* Close out every device's session, e.g. because the domain has disconnected */
+  async fn end_sessions(&self) {
+    // Nothing is going to answer them now
+    self.replies_waiting.cancel(None);
+    let mut devices = self.devices.write().await;
+    self.by_can_id.write().unwrap().clear();
+    let mut summaries = vec![];
+    for (id, entry) in devices.drain() {
+      summaries.push(entry.session.summarise(&self.name, entry.device.device_class(), &*entry.info.read().await));
+      device_changes().publish(DeviceChange::Removed { domain: self.name.clone(), device_id: id });
+    }
+    session_history().add(summaries);
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct FreezeReport {
+  /* How many outputs each device switched off */
+  pub frozen: Vec<(DeviceId, usize)>,
+  pub failed: Vec<(DeviceId, String)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct BatchReport {
+  pub succeeded: Vec<(DeviceId, serde_json::Value)>,
+  pub failed: Vec<(DeviceId, String)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ReassignReport {
+  pub plan: Vec<IdMove>,
+  pub completed: Vec<IdMove>,
+  pub error: Option<String>,
+  pub rolled_back: bool,
+}
+
+/* How long to wait for a device to show up under its new ID after being told to move */
+const ID_CHANGE_VERIFY_TIMEOUT: Duration = Duration::from_millis(3000);
+/* Likewise for a device to report its new name */
+const NAME_CHANGE_VERIFY_TIMEOUT: Duration = Duration::from_millis(3000);
+/* How long a probed device has to answer enumeration */
+const PROBE_TIMEOUT: Duration = Duration::from_millis(1000);
+/* The longest name the devices will store */
+const MAX_NAME_LEN: usize = 16;
+const MAX_TAG_LEN: usize = 32;
+
+pub struct DeviceManager {
+  // std lock, since it's only ever held briefly to look up or add a domain
+  domains: std::sync::RwLock<HashMap<Domain, Arc<DomainState>>>,
+  watchdog: RpcWatchdog,
+  clock: Clock,
+}
+
+/* Domains come and go with their transports. A provider registers its domain once its transport is up (and nested
+   domains, like the bus behind a FlexiCAN, as they're discovered), and unregisters it when the transport is stopped,
+   so several transports can be connected at once and each one's devices appear as soon as it connects. */
+impl DeviceManager {
+  pub fn new() -> Self {
+    Self::with_clock(Clock::Real)
+  }
+
+  /* Replay mode: time only moves with advance(), and received messages are processed before on_message returns
+     instead of being queued, so the same inputs always produce the same device states */
+  pub fn new_replay(start_ms: i64) -> Self {
+    Self::with_clock(Clock::new_virtual(start_ms))
+  }
+
+  fn with_clock(clock: Clock) -> Self {
+    Self { domains: std::sync::RwLock::new(HashMap::new()), watchdog: RpcWatchdog::new(), clock }
+  }
+
+  pub fn clock(&self) -> &Clock {
+    &self.clock
+  }
+
+  fn domain(&self, domain: &Domain) -> Option<Arc<DomainState>> {
+    self.domains.read().unwrap().get(domain).cloned()
+  }
+
+  fn all_domains(&self) -> Vec<Arc<DomainState>> {
+    self.domains.read().unwrap().values().cloned().collect()
+  }
+
+  /* Messages for a domain are dropped until it's registered. Registering a domain that's already there keeps it as it
+     is, e.g. when a transport reconnects. */
+  pub async fn register_domain(&self, domain: Domain, send: mpsc::Sender<TaggedGrappleMessage<'static>>) {
+    let clock = self.clock.clone();
+    let mut domains = self.domains.write().unwrap();
+    if !domains.contains_key(&domain) {
+      info!("Domain {} registered", domain);
+      domains.insert(domain.clone(), DomainState::new(domain, clock, send));
+    }
+  }
+
+  /* Its devices go with it, and anything waiting on a reply from them is cancelled */
+  pub async fn unregister_domain(&self, domain: &Domain) {
+    let removed = self.domains.write().unwrap().remove(domain);
+    if let Some(state) = removed {
+      info!("Domain {} unregistered", domain);
+      state.end_sessions().await;
+    }
+  }
+
+  pub async fn reset(&self) {
+    for domain in self.all_domains() {
+      domain.end_sessions().await;
+    }
+  }
+
+  /* Queue a received message for its domain's processing task */
+  pub async fn on_message(&self, domain: String, id: GrappleMessageId, message: TaggedGrappleMessage<'static>) -> anyhow::Result<()> {
+    self.receive(domain, id, message, None).await
+  }
+
+  /* A message that came through a bridge, with the bridge's timestamp (in milliseconds, by its own clock) of when it
+     came off the bus */
+  pub async fn on_bridged_message(&self, domain: String, id: GrappleMessageId, message: TaggedGrappleMessage<'static>, bridge_timestamp_ms: u32) -> anyhow::Result<()> {
+    self.receive(domain, id, message, Some(bridge_timestamp_ms)).await
+  }
+
+  async fn receive(&self, domain: String, id: GrappleMessageId, message: TaggedGrappleMessage<'static>, bridge_timestamp_ms: Option<u32>) -> anyhow::Result<()> {
+    let Some(state) = self.domain(&domain) else {
+      return Ok(())
+    };
+    state.link.on_traffic(self.clock.now_ms());
+
+    if self.clock.is_virtual() {
+      return state.process(id, message, state.latency.arrival_ms(bridge_timestamp_ms)).await;
+    }
+
+    state.ensure_worker();
+
+    let hold = state.impairment.read().unwrap().sample();
+    match hold {
+      None => (),
+      Some(delay) if delay.is_zero() => {
+        let arrival_ms = state.latency.arrival_ms(bridge_timestamp_ms);
+        state.inbox.send(DomainCommand::Message(id, message, arrival_ms)).await.map_err(|_| anyhow::anyhow!("Domain {} is no longer processing messages", domain))?;
+      },
+      Some(delay) => {
+        let state = state.clone();
+        tokio::task::spawn(async move {
+          tokio::time::sleep(delay).await;
+          // The hold stands in for a slow link, so the message arrives once it's over
+          let arrival_ms = state.latency.arrival_ms(bridge_timestamp_ms.map(|t| t.wrapping_add(delay.as_millis() as u32)));
+          state.inbox.send(DomainCommand::Message(id, message, arrival_ms)).await.ok();
+        });
+      }
+    }
+    Ok(())
+  }
+
+  /* A frame (or message reassembled from fragments) arrived on the domain that couldn't be decoded. One that turns
+     out to be an unknown model enumerating still gets a device, anything else is quarantined. */
+  pub fn on_malformed(&self, domain: &str, arbitration_id: u32, data: &[u8], error: impl std::fmt::Display) {
+    if let Some(state) = self.domains.read().unwrap().get(domain).cloned() {
+      state.link.on_traffic(self.clock.now_ms());
+      if let Some(info) = unknown_model_info(arbitration_id, data) {
+        state.ensure_worker();
+        if state.inbox.try_send(DomainCommand::Enumerated(info)).is_ok() {
+          return;
+        }
+      }
+      state.quarantine.add(arbitration_id, data, error.to_string(), state.latency.timestamp_ms());
+    }
+  }
+
+  /* A frame from another vendor's device arrived on the domain */
+  pub fn on_foreign(&self, domain: &str, arbitration_id: u32) {
+    if let Some(state) = self.domains.read().unwrap().get(domain).cloned() {
+      state.link.on_traffic(self.clock.now_ms());
+      state.foreign.observe(arbitration_id, state.latency.timestamp_ms());
+    }
+  }
+
+  /* The transport behind the domains failed, e.g. the USB adapter was unplugged or the bridge connection dropped.
+     Every domain shares the one transport, so they all take the error. */
+  pub fn on_transport_error(&self, error: impl std::fmt::Display) {
+    let now = self.clock.now_ms();
+    for domain in self.all_domains() {
+      domain.link.on_error(now, error.to_string());
+    }
+  }
+
+  pub async fn on_tick(&self) -> anyhow::Result<()> {
+    for domain in self.all_domains() {
+      if self.clock.is_virtual() {
+        domain.on_tick().await?;
+      } else {
+        // Queued behind the messages already received, so age-off sees everything that arrived before the tick
+        domain.ensure_worker();
+        domain.inbox.send(DomainCommand::Tick).await.map_err(|_| anyhow::anyhow!("Domain {} is no longer processing messages", domain.name))?;
+      }
+    }
+    Ok(())
+  }
+
+  /* Replay mode only: fast-forward by the given time, ticking every tick_ms along the way as the providers would */
+  pub async fn advance(&self, ms: i64, tick_ms: i64) -> anyhow::Result<()> {
+    if tick_ms <= 0 {
+      anyhow::bail!("Tick interval must be positive");
+    }
+    let mut remaining = ms;
+    while remaining > 0 {
+      let step = remaining.min(tick_ms);
+      self.clock.advance(step)?;
+      remaining -= step;
+      if step == tick_ms {
+        self.on_tick().await?;
+      }
+    }
+    Ok(())
+  }
+}
+
+#[rpc]
+impl DeviceManager {
+  async fn call(&self, domain: Domain, device_id: DeviceId, data: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+    let state = self.domain(&domain).ok_or(coded(ErrorCode::DeviceNotFound, format!("No domain {}", domain)))?;
+    let entry = state.entry(&device_id).await?;
+
+    let serial = entry.info.read().await.serial;
+    let method = data.get("method").and_then(|m| m.as_str()).unwrap_or("unknown").to_owned();
+    let change = config_change(&data);
+
+    // Configuration writes queue up behind the previous one to the same device
+    let _write_permit = match (&change, serial) {
+      (Some(_), Some(serial)) => Some(state.write_throttle.acquire(serial, entry.device.device_class()).await),
+      _ => None
+    };
+
+    if fixtures().is_capturing(&device_id) {
+      let result = self.watchdog.run(&state.replies_waiting, serial, &method, entry.device.rpc_call(data.clone())).await;
+      fixtures().record(&device_id, entry.device.device_class(), &*entry.info.read().await, &data, &result);
+      return result;
+    }
+
+    let result = self.watchdog.run(&state.replies_waiting, serial, &method, entry.device.rpc_call(data)).await?;
+    if let Some(change) = change {
+      if let Some(serial) = serial {
+        telemetry().record_config_change(serial, state.latency.timestamp_ms(), change.clone());
+      }
+      entry.session.record_config_change(change);
+    }
+    Ok(result)
+  }
+
+  /// The same call on several devices at once, e.g. applying one setting to every LaserCAN. One device failing doesn't
+  /// stop the others.
+  async fn call_many(&self, domain: Domain, device_ids: Vec<DeviceId>, data: serde_json::Value) -> anyhow::Result<BatchReport> {
+    self.domain(&domain).ok_or(coded(ErrorCode::DeviceNotFound, format!("No domain {}", domain)))?;
+    let results = futures::future::join_all(device_ids.into_iter().map(|id| {
+      let (domain, data) = (domain.clone(), data.clone());
+      async move { (id.clone(), self.call(domain, id, data).await) }
+    })).await;
+
+    let mut report = BatchReport { succeeded: vec![], failed: vec![] };
+    for (id, result) in results {
+      match result {
+        Ok(value) => report.succeeded.push((id, value)),
+        Err(e) => report.failed.push((id, e.to_string()))
+      }
+    }
+    Ok(report)
+  }
+
+  /// For when the app is closing
+  async fn end_sessions(&self) -> anyhow::Result<()> {
+    self.reset().await;
+    Ok(())
+  }
+
+  /// Give many devices new CAN IDs in one go. layout maps serial to the desired ID; devices not in it keep theirs.
+  /// Each step is verified, and if one fails the steps done so far are undone in reverse.
+  async fn reassign_ids(&self, domain: Domain, layout: HashMap<u32, u8>) -> anyhow::Result<ReassignReport> {
+    let state = self.domain(&domain).ok_or(coded(ErrorCode::DeviceNotFound, format!("No domain {}", domain)))?;
+
+    let mut current = HashMap::new();
+    for (id, entry) in state.entries().await {
+      if let (DeviceId::Serial(serial), Some(can_id)) = (id, entry.info.read().await.device_id) {
+        current.insert(serial, can_id);
+      }
+    }
+
+    let steps = plan(&current, &layout)?;
+    let mut report = ReassignReport { plan: steps.clone(), completed: vec![], error: None, rolled_back: false };
+
+    for step in &steps {
+      if let Err(e) = state.move_device(step).await {
+        warn!("CAN ID reassignment failed, rolling back: {}", e);
+        report.error = Some(e.to_string());
+        let mut rolled_back = true;
+        for done in report.completed.iter().rev() {
+          let undo = IdMove { serial: done.serial, from: done.to, to: done.from };
+          if let Err(e) = state.move_device(&undo).await {
+            warn!("Rollback failed: {}", e);
+            rolled_back = false;
+          }
+        }
+        report.rolled_back = rolled_back;
+        return Ok(report);
+      }
+      report.completed.push(step.clone());
+    }
+
+    Ok(report)
+  }
+
+  /// Blink a device so it can be told apart from others of the same kind
+  async fn identify(&self, domain: Domain, device_id: DeviceId) -> anyhow::Result<()> {
+    let state = self.domain(&domain).ok_or(coded(ErrorCode::DeviceNotFound, format!("No domain {}", domain)))?;
+    state.entry(&device_id).await?.device.identify().await
+  }
+
+  /// Rename a device and check the new name persisted. Drivers all rename through the common Grapple message, but
+  /// don't confirm it took, so we re-enumerate afterwards and only succeed once the device reports the new name.
+  async fn rename(&self, domain: Domain, device_id: DeviceId, name: String) -> anyhow::Result<()> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+      anyhow::bail!("Names must be between 1 and {} characters long", MAX_NAME_LEN);
+    }
+    if let DeviceId::Dfu(serial) = device_id {
+      anyhow::bail!("Device {:x} is in firmware update mode and can't be renamed", serial);
+    }
+
+    let state = self.domain(&domain).ok_or(coded(ErrorCode::DeviceNotFound, format!("No domain {}", domain)))?;
+    self.call(domain, device_id.clone(), serde_json::json!({
+      "method": "grapple",
+      "data": { "msg": { "method": "set_name", "data": { "name": name } } }
+    })).await?;
+    state.confirm_name(&device_id, &name).await
+  }
+
+  /// Re-enumerate a device and return what it reports
+  async fn probe(&self, domain: Domain, device_id: DeviceId) -> anyhow::Result<DeviceInfo> {
+    let state = self.domain(&domain).ok_or(coded(ErrorCode::DeviceNotFound, format!("No domain {}", domain)))?;
+    state.probe(&device_id).await
+  }
+
+  /// None if the device has nothing to poll
+  async fn poller(&self, domain: Domain, device_id: DeviceId) -> anyhow::Result<Option<PollerStatus>> {
+    let state = self.domain(&domain).ok_or(coded(ErrorCode::DeviceNotFound, format!("No domain {}", domain)))?;
+    let entry = state.entry(&device_id).await?;
+    Ok(entry.device.poller().map(|p| p.status()))
+  }
+
+  async fn set_poller_config(&self, domain: Domain, device_id: DeviceId, config: PollerConfig) -> anyhow::Result<()> {
+    let state = self.domain(&domain).ok_or(coded(ErrorCode::DeviceNotFound, format!("No domain {}", domain)))?;
+    let entry = state.entry(&device_id).await?;
+    entry.device.poller().ok_or(anyhow::anyhow!("This device has nothing to poll"))?.configure(config)
+  }
+
+  async fn rpc_deadline(&self) -> anyhow::Result<u64> {
+    Ok(self.watchdog.deadline_ms())
+  }
+
+  async fn set_rpc_deadline(&self, deadline_ms: u64) -> anyhow::Result<()> {
+    self.watchdog.set_deadline_ms(deadline_ms);
+    Ok(())
+  }
+
+  /// Emergency action: switch off every controllable output on the domain at once. The caller must pass confirm, so
+  /// this can't be fired off by accident.
+  async fn freeze_outputs(&self, domain: Domain, confirm: bool) -> anyhow::Result<FreezeReport> {
+    if !confirm {
+      anyhow::bail!("Freezing outputs must be confirmed");
+    }
+
+    let state = self.domain(&domain).ok_or(coded(ErrorCode::DeviceNotFound, format!("No domain {}", domain)))?;
+    let results = futures::future::join_all(state.entries().await.into_iter().map(|(id, entry)| async move {
+      (id, entry.device.freeze_outputs().await)
+    })).await;
+
+    let mut report = FreezeReport { frozen: vec![], failed: vec![] };
+    for (id, result) in results {
+      match result {
+        Ok(0) => (),
+        Ok(n) => report.frozen.push((id, n)),
+        Err(e) => report.failed.push((id, e.to_string()))
+      }
+    }
+
+    let total: usize = report.frozen.iter().map(|(_, n)| n).sum();
+    warn!("Outputs frozen on {}: {} outputs switched off, {} devices failed", domain, total, report.failed.len());
+    let severity = if report.failed.is_empty() { EventSeverity::Warning } else { EventSeverity::Error };
+    events().emit(None, "outputs_frozen", severity, format!("Emergency stop on {}: {} outputs switched off, {} devices didn't respond", domain, total, report.failed.len()));
+
+    Ok(report)
+  }
+
+  async fn devices(&self) -> anyhow::Result<HashMap<Domain, Vec<(DeviceId, DeviceInfo, String)>>> {
+    let mut device_states = HashMap::new();
+
+    for domain in self.all_domains() {
+      let settings = domain_settings().get(&domain.name);
+      let mut vec = vec![];
+      for (id, device) in domain.entries().await {
+        let mut info = device.info.read().await.clone();
+        info.activity = device.activity.series();
+        info.state = device.state(self.clock.now_ms()).await;
+        if let Some(serial) = info.serial {
+          let meta = metadata().get(serial);
+          info.pinned = meta.pinned;
+          info.tags = meta.tags;
+        }
+        info.domain_display_name = settings.display_name.clone();
+        info.domain_color = settings.color.clone();
+        vec.push((id, info, device.device.device_class().to_owned()));
+      }
+      device_states.insert(domain.name.clone(), vec);
+    }
+
+    Ok(device_states)
+  }
+
+  /// Pinned devices are flagged in devices() so they can be listed first. Remembered by serial.
+  async fn set_pinned(&self, serial: u32, pinned: bool) -> anyhow::Result<()> {
+    metadata().update(serial, |m| m.pinned = pinned);
+    Ok(())
+  }
+
+  /// Tags are included in devices() for grouping and filtering. Remembered by serial.
+  async fn set_tags(&self, serial: u32, tags: Vec<String>) -> anyhow::Result<Vec<String>> {
+    let mut normalised: Vec<String> = vec![];
+    for tag in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+      if tag.len() > MAX_TAG_LEN {
+        anyhow::bail!("Tags can be at most {} characters long", MAX_TAG_LEN);
+      }
+      if !normalised.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+        normalised.push(tag.to_owned());
+      }
+    }
+    metadata().update(serial, |m| m.tags = normalised.clone());
+    Ok(normalised)
+  }
+
+  /// Every tag in use, with how many devices (online or not) carry it
+  async fn tags(&self) -> anyhow::Result<Vec<(String, usize)>> {
+    let mut counts: Vec<(String, usize)> = vec![];
+    for meta in metadata().all().into_values() {
+      for tag in meta.tags {
+        match counts.iter_mut().find(|(t, _)| *t == tag) {
+          Some((_, n)) => *n += 1,
+          None => counts.push((tag, 1))
+        }
+      }
+    }
+    counts.sort();
+    Ok(counts)
+  }
+
+  /// Take a tag off every device that has it, returning how many did. Can be undone in one go.
+  async fn clear_tag(&self, tag: String) -> anyhow::Result<usize> {
+    let serials: Vec<u32> = metadata().all().into_iter()
+      .filter(|(_, m)| m.tags.iter().any(|t| t.eq_ignore_ascii_case(&tag)))
+      .map(|(serial, _)| serial)
+      .collect();
+    metadata().batch(|| for serial in &serials {
+      metadata().update(*serial, |m| m.tags.retain(|t| !t.eq_ignore_ascii_case(&tag)));
+    });
+    Ok(serials.len())
+  }
+
+  async fn attention(&self) -> anyhow::Result<Vec<AttentionItem>> {
+    let mut items = vec![];
+    for domain in self.all_domains() {
+      for (id, device) in domain.entries().await {
+        items.extend(assess(&domain.name, &id, &*device.info.read().await, device.device.device_class()));
+      }
+    }
+    Ok(items)
+  }
+
+  async fn capabilities(&self, serial: u32) -> anyhow::Result<Option<DeviceCapabilities>> {
+    Ok(capability_cache().get(serial))
+  }
+
+  /// Frames that failed to decode, and how many more were seen after the quarantine filled up
+  async fn quarantined_frames(&self) -> anyhow::Result<HashMap<Domain, (Vec<QuarantinedFrame>, usize)>> {
+    Ok(self.domains.read().unwrap().iter().map(|(domain, c)| (domain.clone(), (c.quarantine.frames(), c.quarantine.overflowed()))).collect())
+  }
+
+  async fn clear_quarantine(&self) -> anyhow::Result<()> {
+    for domain in self.all_domains() {
+      domain.quarantine.clear();
+    }
+    Ok(())
+  }
+
+  /// Devices sharing a CAN ID with another device on the same domain
+  async fn faults(&self) -> anyhow::Result<HashMap<Domain, Vec<CanIdConflict>>> {
+    let mut faults = HashMap::new();
+    for domain in self.all_domains() {
+      faults.insert(domain.name.clone(), domain.can_id_conflicts().await);
+    }
+    Ok(faults)
+  }
+
+  /// How long a device on the domain can go unheard before it's removed. Lengthen it on lossy buses where devices
+  /// flicker in and out. None restores the default. Remembered across restarts.
+  async fn set_age_off(&self, domain: Domain, age_off_ms: Option<i64>) -> anyhow::Result<()> {
+    if let Some(ms) = age_off_ms {
+      if !AGE_OFF_RANGE_MS.contains(&ms) {
+        anyhow::bail!("Age-off must be between {}ms and {}ms", AGE_OFF_RANGE_MS.start(), AGE_OFF_RANGE_MS.end());
+      }
+    }
+    domain_settings().update(&domain, |s| s.age_off_ms = age_off_ms);
+    Ok(())
+  }
+
+  async fn age_off(&self, domain: Domain) -> anyhow::Result<i64> {
+    Ok(domain_settings().get(&domain).age_off_ms.unwrap_or(AGE_OFF_MS))
+  }
+
+  /// A friendlier name and a colour for a domain, since keys like USB serial ports are hard to tell apart. None clears
+  /// either. Remembered across restarts.
+  async fn set_domain_label(&self, domain: Domain, display_name: Option<String>, color: Option<String>) -> anyhow::Result<()> {
+    let display_name = display_name.map(|n| n.trim().to_owned()).filter(|n| !n.is_empty());
+    if let Some(color) = &color {
+      validate_color(color)?;
+    }
+    domain_settings().update(&domain, |s| { s.display_name = display_name; s.color = color; });
+    Ok(())
+  }
+
+  /// The domains currently registered
+  async fn domains(&self) -> anyhow::Result<Vec<Domain>> {
+    let mut domains = self.domains.read().unwrap().keys().cloned().collect::<Vec<_>>();
+    domains.sort();
+    Ok(domains)
+  }
+
+  /// Settings for every domain that has any, including ones not currently connected
+  async fn domain_settings(&self) -> anyhow::Result<HashMap<Domain, DomainSettings>> {
+    Ok(domain_settings().all())
+  }
+
+  /// Non-Grapple devices we've seen traffic from recently, by domain. Read-only, identified from their CAN IDs alone.
+  async fn foreign_devices(&self) -> anyhow::Result<HashMap<Domain, Vec<ForeignDevice>>> {
+    Ok(self.domains.read().unwrap().iter().map(|(domain, c)| (domain.clone(), c.foreign.list(c.latency.timestamp_ms()))).collect())
+  }
+
+  /// Developer mode only
+  async fn transcript(&self, domain: Domain, device_id: DeviceId) -> anyhow::Result<Vec<TranscriptEntry>> {
+    require_developer_mode()?;
+    let state = self.domain(&domain).ok_or(coded(ErrorCode::DeviceNotFound, format!("No domain {}", domain)))?;
+    let can_id = match state.devices.read().await.get(&device_id) {
+      Some(entry) => entry.info.read().await.require_device_id()?,
+      None => return Err(coded(ErrorCode::DeviceNotFound, format!("No device {:?} on {}", device_id, domain)))
+    };
+    Ok(state.transcript.for_device(can_id))
+  }
+
+  async fn clear_transcript(&self, domain: Domain) -> anyhow::Result<()> {
+    let state = self.domain(&domain).ok_or(coded(ErrorCode::DeviceNotFound, format!("No domain {}", domain)))?;
+    state.transcript.clear();
+    Ok(())
+  }
+
+  async fn impairment(&self, domain: Domain) -> anyhow::Result<Impairment> {
+    let state = self.domain(&domain).ok_or(coded(ErrorCode::DeviceNotFound, format!("No domain {}", domain)))?;
+    let impairment = state.impairment.read().unwrap().clone();
+    Ok(impairment)
+  }
+
+  /// Developer mode only. Set everything to zero to turn it off again.
+  async fn set_impairment(&self, domain: Domain, impairment: Impairment) -> anyhow::Result<()> {
+    require_developer_mode()?;
+    impairment.validate()?;
+    let state = self.domain(&domain).ok_or(coded(ErrorCode::DeviceNotFound, format!("No domain {}", domain)))?;
+    warn!("Impairing {}: {:?}", domain, impairment);
+    *state.impairment.write().unwrap() = impairment;
+    Ok(())
+  }
+
+  /// Requests waiting on a reply, by domain
+  async fn replies_waiting(&self) -> anyhow::Result<HashMap<Domain, usize>> {
+    Ok(self.domains.read().unwrap().iter().map(|(domain, c)| (domain.clone(), c.replies_waiting.pending())).collect())
+  }
+
+  /// Fail the requests waiting on a reply from a CAN ID (or everything on the domain, for None) straight away instead
+  /// of leaving them to time out, e.g. once a device is known to have been unplugged. Returns how many were cancelled.
+  async fn cancel_requests(&self, domain: Domain, can_id: Option<u8>) -> anyhow::Result<usize> {
+    let state = self.domain(&domain).ok_or(coded(ErrorCode::DeviceNotFound, format!("No domain {}", domain)))?;
+    Ok(state.replies_waiting.cancel(can_id))
+  }
+
+  /// Whether the link behind each domain is alive, when we last heard anything on it and what last went wrong
+  async fn domain_status(&self) -> anyhow::Result<HashMap<Domain, DomainStatus>> {
+    let now = self.clock.now_ms();
+    Ok(self.domains.read().unwrap().iter().map(|(domain, c)| (domain.clone(), c.link.status(now, rate_divisor()))).collect())
+  }
+
+  async fn requests_in_flight(&self) -> anyhow::Result<HashMap<Domain, usize>> {
+    Ok(self.domains.read().unwrap().iter().map(|(domain, c)| (domain.clone(), c.limiter.in_flight())).collect())
+  }
+
+  async fn latency(&self) -> anyhow::Result<HashMap<Domain, Option<f64>>> {
+    Ok(self.domains.read().unwrap().iter().map(|(domain, c)| (domain.clone(), c.latency.one_way_ms())).collect())
+  }
+
+  /// Each domain is its own CAN bus, so load is reported per domain
+  async fn bus_load(&self) -> anyhow::Result<HashMap<Domain, BusLoadReport>> {
+    let mut reports = HashMap::new();
+    for domain in self.all_domains() {
+      let mut loads = vec![];
+      for (id, entry) in domain.entries().await {
+        loads.push((id, entry.device.bus_load().await));
+      }
+      reports.insert(domain.name.clone(), BusLoadReport::new(loads));
+    }
+    Ok(reports)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::borrow::Cow;
+
+  use grapple_frc_msgs::binmarshal::{BitView, Demarshal, MarshalUpdate};
+
+  use super::*;
+  use crate::devices::{fixtures::DeviceFixture, simulator::SIMULATED_UPDATE_VERSION, unknown_model::{encode, model_id_offset}};
+  use crate::errors::has_code;
+  use crate::telemetry::TELEMETRY_RETENTION_MS;
+
+  const TICK_MS: i64 = 500;
+
+  /* A replay-mode manager with one domain, whose outgoing messages are thrown away */
+  async fn replay(domain: &str) -> DeviceManager {
+    // Keep the registry, session history and so on out of the real data directory
+    std::env::set_var("GRAPPLEHOOK_DATA_DIR", std::env::temp_dir().join("grapplehook-tests"));
+
+    let manager = DeviceManager::new_replay(1_000_000);
+    let (send, mut sent) = mpsc::channel(100);
+    tokio::task::spawn(async move { while sent.recv().await.is_some() {} });
+    manager.register_domain(domain.to_owned(), send).await;
+    manager
+  }
+
+  fn enumerate_response(serial: u32) -> (GrappleMessageId, TaggedGrappleMessage<'static>) {
+    let mut message = TaggedGrappleMessage::new(3, GrappleDeviceMessage::Broadcast(GrappleBroadcastMessage::DeviceInfo(GrappleDeviceInfo::EnumerateResponse {
+      model_id: GrappleModelId::LaserCan,
+      serial,
+      is_dfu: false,
+      is_dfu_in_progress: false,
+      name: Cow::<str>::Owned("Test".to_owned()).into(),
+      version: Cow::<str>::Owned(SIMULATED_UPDATE_VERSION.to_owned()).into(),
+    })));
+    let mut id = GrappleMessageId::new(message.device_id);
+    message.msg.update(&mut id);
+    (id, message)
+  }
+
+  async fn announce(manager: &DeviceManager, domain: &str, serial: u32) {
+    let (id, message) = enumerate_response(serial);
+    manager.on_message(domain.to_owned(), id, message).await.unwrap();
+  }
+
+  async fn state_of(manager: &DeviceManager, domain: &str, serial: u32) -> Option<DeviceState> {
+    let devices = manager.devices().await.unwrap();
+    devices.get(domain)?.iter().find(|(id, _, _)| *id == DeviceId::Serial(serial)).map(|(_, info, _)| info.state.clone())
+  }
+
+  #[tokio::test]
+  async fn quiet_device_is_lost_then_aged_off() {
+    let (domain, serial) = ("AGEOFF", 0x250_0001);
+    let manager = replay(domain).await;
+    announce(&manager, domain, serial).await;
+    assert_eq!(state_of(&manager, domain, serial).await, Some(DeviceState::Discovered));
+
+    manager.advance(DISCOVERED_FOR_MS, TICK_MS).await.unwrap();
+    assert_eq!(state_of(&manager, domain, serial).await, Some(DeviceState::Ready));
+
+    manager.advance(LOST_AFTER_MS, TICK_MS).await.unwrap();
+    assert_eq!(state_of(&manager, domain, serial).await, Some(DeviceState::Lost));
+
+    manager.advance(AGE_OFF_MS, TICK_MS).await.unwrap();
+    assert_eq!(state_of(&manager, domain, serial).await, None);
+  }
+
+  #[tokio::test]
+  async fn answering_device_is_kept() {
+    let (domain, serial) = ("KEPT", 0x250_0002);
+    let manager = replay(domain).await;
+    for _ in 0..(AGE_OFF_MS * 3 / TICK_MS) {
+      announce(&manager, domain, serial).await;
+      manager.advance(TICK_MS, TICK_MS).await.unwrap();
+    }
+    assert_eq!(state_of(&manager, domain, serial).await, Some(DeviceState::Ready));
+  }
+
+  #[tokio::test]
+  async fn lost_device_recovers_when_it_answers() {
+    let (domain, serial) = ("RECOVER", 0x250_0003);
+    let manager = replay(domain).await;
+    announce(&manager, domain, serial).await;
+    manager.advance(LOST_AFTER_MS + TICK_MS, TICK_MS).await.unwrap();
+    assert_eq!(state_of(&manager, domain, serial).await, Some(DeviceState::Lost));
+
+    announce(&manager, domain, serial).await;
+    assert_eq!(state_of(&manager, domain, serial).await, Some(DeviceState::Ready));
+  }
+
+  #[tokio::test]
+  async fn telemetry_outside_retention_is_dropped() {
+    let (domain, serial) = ("RETENTION", 0x250_0004);
+    let manager = replay(domain).await;
+
+    let first = manager.clock().now_ms();
+    telemetry().record(serial, "distance_mm", first, 1.0);
+    manager.advance(TELEMETRY_RETENTION_MS / 2, 60_000).await.unwrap();
+    telemetry().record(serial, "distance_mm", manager.clock().now_ms(), 2.0);
+    assert_eq!(telemetry().history(serial, "distance_mm", None, None).len(), 2);
+
+    manager.advance(TELEMETRY_RETENTION_MS + 60_000, 60_000).await.unwrap();
+    let last = manager.clock().now_ms();
+    telemetry().record(serial, "distance_mm", last, 3.0);
+    let history = telemetry().history(serial, "distance_mm", None, None);
+    assert_eq!(history.iter().map(|s| s.timestamp_ms).collect::<Vec<_>>(), vec![last]);
+  }
+
+  #[tokio::test]
+  async fn lasercan_replays_its_captured_fixture() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/LaserCAN-2280001-20261016-101500.json");
+    let fixture = DeviceFixture::load(&path).unwrap();
+    let serial = fixture.info.as_ref().and_then(|info| info.serial).unwrap();
+
+    let domain = "FIXTURE";
+    let manager = replay(domain).await;
+    announce(&manager, domain, serial).await;
+    let mismatches = fixture.replay(&manager, &domain.to_owned(), &DeviceId::Serial(serial)).await.unwrap();
+    assert!(mismatches.is_empty(), "{:#?}", mismatches);
+  }
+
+  #[tokio::test]
+  async fn bridged_round_trip_leaves_out_link_jitter() {
+    let (domain, serial) = ("BRIDGED", 0x208_0001);
+    let manager = replay(domain).await;
+
+    // A frame that got here promptly lines the bridge's clock up with ours
+    let (id, message) = enumerate_response(serial);
+    manager.on_bridged_message(domain.to_owned(), id, message, 5_000).await.unwrap();
+
+    // The probe goes out on the tick, and the answer comes off the bus 10ms later but takes another 30ms to reach us
+    manager.advance(TICK_MS, TICK_MS).await.unwrap();
+    manager.clock().advance(40).unwrap();
+    let (id, message) = enumerate_response(serial);
+    manager.on_bridged_message(domain.to_owned(), id, message, 5_000 + TICK_MS as u32 + 10).await.unwrap();
+
+    assert_eq!(manager.latency().await.unwrap()[domain], Some(5.0));
+  }
+
+  #[tokio::test]
+  async fn probe_times_out_on_the_virtual_clock() {
+    let (domain, serial) = ("PROBE", 0x250_0005);
+    let manager = replay(domain).await;
+    announce(&manager, domain, serial).await;
+    let state = manager.domain(&domain.to_owned()).unwrap();
+
+    // Nothing answers, so the probe only gives up once virtual time passes PROBE_TIMEOUT
+    let probe = tokio::task::spawn(async move { state.probe(&DeviceId::Serial(serial)).await });
+    tokio::task::yield_now().await;
+    assert!(!probe.is_finished());
+    manager.clock().advance(PROBE_TIMEOUT.as_millis() as i64 + 250).unwrap();
+    for _ in 0..10 {
+      manager.clock().advance(250).unwrap();
+      tokio::task::yield_now().await;
+    }
+    assert!(has_code(&probe.await.unwrap().unwrap_err(), ErrorCode::RequestTimeout));
+  }
+
+  #[tokio::test]
+  async fn unknown_model_gets_the_generic_driver() {
+    // Not a model grapple-frc-msgs has a GrappleModelId for
+    const UNKNOWN_MODEL: u8 = 0xEE;
+    let (domain, serial) = ("UNKNOWN", 0x250_0006);
+    let manager = replay(domain).await;
+
+    let (_, message) = enumerate_response(serial);
+    let (id, mut payload) = encode(message.msg, message.device_id).unwrap();
+    payload[model_id_offset().expect("the model ID should have a fixed offset")] = UNKNOWN_MODEL;
+    assert!(GrappleDeviceMessage::read(&mut BitView::new(&payload[..]), id.clone()).is_err());
+
+    let arbitration_id: u32 = Into::<MessageId>::into(id).into();
+    let info = unknown_model_info(arbitration_id, &payload).expect("an unknown model should still decode");
+    assert!(matches!(info.device_type, DeviceType::UnknownGrapple(UNKNOWN_MODEL)));
+    assert_eq!(info.serial, Some(serial));
+
+    manager.domain(&domain.to_owned()).unwrap().on_enumerate_response(info).await.unwrap();
+    let devices = manager.devices().await.unwrap();
+    let (_, _, class) = devices[domain].iter().find(|(id, _, _)| *id == DeviceId::Serial(serial)).unwrap();
+    assert_eq!(class, "GenericGrappleDevice");
+  }
+}
@@ -0,0 +1,74 @@
+use crate::{firmware_library::FirmwareImage, updates::LightReleaseResponse};
+
+use super::{device_class::{resolve_device_class, DeviceClass}, firmware_catalog::FirmwareCatalog, registry::RegisteredDevice, DeviceInfo, DeviceType};
+
+/* A board that turns up in its bootloader without us ever having seen it running (e.g. a flash that failed on another
+   laptop) has no name or application firmware version to go on. The bootloader does report the model it was built
+   for when it answers enumeration, which is enough to say which firmware it needs. */
+#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct DfuIdentification {
+  pub serial: u32,
+  pub device_type: DeviceType,
+  pub device_class: Option<DeviceClass>,
+  pub bootloader_version: Option<String>,
+  /* How it was last seen running normally, if it ever was */
+  pub last_known: Option<RegisteredDevice>,
+  /* The newest release for this model, from the firmware catalog */
+  pub recommended_release: Option<LightReleaseResponse>,
+  /* Where to get firmware by hand */
+  pub firmware_url: Option<String>,
+  /* Images in the library that have been flashed to this board before, or were flashed to the same model */
+  pub library_images: Vec<FirmwareImage>,
+  pub summary: String,
+}
+
+/* The library labels images with the model they were flashed to (see FirmwareUpgradeDevice::do_field_upgrade) */
+fn library_label(device_type: &DeviceType) -> Option<String> {
+  match device_type {
+    DeviceType::Grapple(model) => Some(format!("{:?} firmware", model)),
+    _ => None
+  }
+}
+
+pub fn identify(info: &DeviceInfo, last_known: Option<RegisteredDevice>, catalog: &FirmwareCatalog, firmware_url: Option<String>, library: Vec<FirmwareImage>) -> anyhow::Result<DfuIdentification> {
+  let serial = info.require_serial()?;
+  let device_class = resolve_device_class(&info.device_type);
+  let recommended_release = device_class.and_then(|c| catalog.latest(c).cloned());
+
+  let label = library_label(&info.device_type);
+  let mut library_images = library.into_iter()
+    .filter(|i| i.flashed.iter().any(|f| f.serial == serial) || Some(&i.label) == label.as_ref())
+    .collect::<Vec<_>>();
+  // Whatever was on this board before first, then newest first
+  library_images.sort_by_key(|i| (!i.flashed.iter().any(|f| f.serial == serial), -i.added_at));
+
+  let model = match &info.device_type {
+    DeviceType::Grapple(model) => format!("{:?}", model),
+    other => format!("{:?}", other)
+  };
+  let history = match &last_known {
+    Some(known) => {
+      let name = known.name.as_deref().filter(|n| !n.is_empty()).map(|n| format!(" as \"{}\"", n)).unwrap_or_default();
+      format!(" It was last seen running firmware {}{}.", known.firmware_version.as_deref().unwrap_or("unknown"), name)
+    },
+    None => " It hasn't been seen running normal firmware on this computer.".to_owned()
+  };
+  let advice = match (&recommended_release, library_images.first()) {
+    (Some(release), _) => format!(" Flash {} firmware {} to recover it.", model, release.version()),
+    (None, Some(image)) => format!(" Flash \"{}\" from the firmware library to recover it.", image.label),
+    (None, None) => format!(" Download {} firmware and flash it to recover it.", model)
+  };
+  let summary = format!("This is a {} in its bootloader.{}{}", model, history, advice);
+
+  Ok(DfuIdentification {
+    serial,
+    device_type: info.device_type.clone(),
+    device_class,
+    bootloader_version: info.firmware_version.clone(),
+    last_known,
+    recommended_release,
+    firmware_url,
+    library_images,
+    summary,
+  })
+}
@@ -0,0 +1,48 @@
+use std::{collections::HashMap, sync::OnceLock};
+
+use crate::persistence::Persisted;
+
+/* Per-domain preferences, keyed by domain name so they apply whichever provider the domain comes from */
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct DomainSettings {
+  /* How long a device can go without answering an enumerate before it's removed. None for the default. */
+  #[serde(default)]
+  pub age_off_ms: Option<i64>,
+  /* Shown in place of the domain's key, e.g. "Comp Bot rio" */
+  #[serde(default)]
+  pub display_name: Option<String>,
+  /* CSS hex colour, e.g. "#8a2be2" */
+  #[serde(default)]
+  pub color: Option<String>,
+}
+
+pub fn validate_color(color: &str) -> anyhow::Result<()> {
+  let hex = color.strip_prefix('#').unwrap_or("");
+  if !(hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit())) {
+    anyhow::bail!("Colours must be given as #rrggbb, not {}", color);
+  }
+  Ok(())
+}
+
+pub struct DomainSettingsStore {
+  domains: Persisted<HashMap<String, DomainSettings>>,
+}
+
+impl DomainSettingsStore {
+  pub fn get(&self, domain: &str) -> DomainSettings {
+    self.domains.read(|d| d.get(domain).cloned()).unwrap_or_default()
+  }
+
+  pub fn all(&self) -> HashMap<String, DomainSettings> {
+    self.domains.get()
+  }
+
+  pub fn update<R>(&self, domain: &str, f: impl FnOnce(&mut DomainSettings) -> R) -> R {
+    self.domains.update(|d| f(d.entry(domain.to_owned()).or_default()))
+  }
+}
+
+pub fn domain_settings() -> &'static DomainSettingsStore {
+  static STORE: OnceLock<DomainSettingsStore> = OnceLock::new();
+  STORE.get_or_init(|| DomainSettingsStore { domains: Persisted::load("domain_settings") })
+}
@@ -0,0 +1,67 @@
+use super::metadata::metadata;
+
+/* Host-side switches for experimental device functionality, per device serial, so new capabilities can be tried on
+   one device before being turned on for everyone. Overrides live in the metadata store; anything not overridden uses
+   the flag's default. */
+pub struct FeatureFlag {
+  pub name: &'static str,
+  pub description: &'static str,
+  pub default: bool,
+}
+
+pub const FLAGS: &[FeatureFlag] = &[
+  FeatureFlag {
+    name: "lasercan_geometry_telemetry",
+    description: "Record horizontal distance and object height as telemetry channels when a geometry profile is set",
+    default: false,
+  },
+  FeatureFlag {
+    name: "mitocandria_rail_alerts",
+    description: "Watch adjustable rails for sags and oscillation and raise events",
+    default: true,
+  },
+];
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct FeatureFlagState {
+  pub name: String,
+  pub description: String,
+  pub default: bool,
+  pub enabled: bool,
+}
+
+fn find(name: &str) -> anyhow::Result<&'static FeatureFlag> {
+  FLAGS.iter().find(|f| f.name == name).ok_or(anyhow::anyhow!("Unknown feature flag {}", name))
+}
+
+pub fn flag_enabled(serial: u32, name: &str) -> bool {
+  let default = FLAGS.iter().find(|f| f.name == name).map(|f| f.default).unwrap_or(false);
+  metadata().get(serial).feature_flags.get(name).cloned().unwrap_or(default)
+}
+
+pub fn require_flag(serial: u32, name: &str) -> anyhow::Result<()> {
+  if !flag_enabled(serial, name) {
+    anyhow::bail!("{} is experimental and not enabled for this device", name);
+  }
+  Ok(())
+}
+
+pub fn flag_states(serial: u32) -> Vec<FeatureFlagState> {
+  let overrides = metadata().get(serial).feature_flags;
+  FLAGS.iter().map(|f| FeatureFlagState {
+    name: f.name.to_owned(),
+    description: f.description.to_owned(),
+    default: f.default,
+    enabled: overrides.get(f.name).cloned().unwrap_or(f.default),
+  }).collect()
+}
+
+/* None clears the override, going back to the default */
+pub fn set_flag(serial: u32, name: &str, enabled: Option<bool>) -> anyhow::Result<()> {
+  let flag = find(name)?;
+  metadata().update(serial, |m| match enabled {
+    Some(enabled) => { m.feature_flags.insert(flag.name.to_owned(), enabled); },
+    None => { m.feature_flags.remove(flag.name); }
+  });
+  Ok(())
+}
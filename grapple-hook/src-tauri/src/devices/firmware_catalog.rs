@@ -0,0 +1,147 @@
+use std::{sync::OnceLock, time::Duration};
+
+use crate::{events::{events, EventSeverity}, persistence::Persisted, updates::{most_recent_update_available, LightReleaseResponse}};
+
+use super::{device_class::DeviceClass, flexican::FlexiCan, lasercan::LaserCan, mitocandria::Mitocandria, VersionGatedDevice};
+
+/* The newest firmware published for each device class, refreshed in the background so users hear about fixes without
+   having to check each device by hand. Only releases this version of GrappleHook can talk to are considered. */
+
+pub const CATALOG_REFRESH_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct CatalogEntry {
+  pub class: DeviceClass,
+  pub release: LightReleaseResponse,
+  pub fetched_ms: i64,
+}
+
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct FirmwareCatalog {
+  /* While offline, nothing is fetched in the background */
+  #[serde(default)]
+  pub offline: bool,
+  pub last_refresh_ms: Option<i64>,
+  #[serde(default)]
+  pub entries: Vec<CatalogEntry>,
+}
+
+impl FirmwareCatalog {
+  pub fn latest(&self, class: DeviceClass) -> Option<&LightReleaseResponse> {
+    self.entries.iter().find(|e| e.class == class).map(|e| &e.release)
+  }
+
+  /* The newer version a device of this class on the given version could be updated to, if there is one */
+  pub fn update_available(&self, class: DeviceClass, version: &str) -> Option<String> {
+    self.latest(class).filter(|r| is_newer(r, version)).map(|r| r.version())
+  }
+}
+
+async fn latest_release<T: VersionGatedDevice>() -> anyhow::Result<Option<LightReleaseResponse>> {
+  match T::firmware_url() {
+    Some(url) => most_recent_update_available(&url, |r| T::validate_version(Some(r.version())).is_ok()).await,
+    None => Ok(None)
+  }
+}
+
+async fn fetch(class: DeviceClass) -> anyhow::Result<Option<LightReleaseResponse>> {
+  match class {
+    DeviceClass::LaserCan => latest_release::<LaserCan>().await,
+    DeviceClass::MitoCANdria => latest_release::<Mitocandria>().await,
+    DeviceClass::FlexiCan => latest_release::<FlexiCan>().await,
+    // Nothing's been published for it yet
+    DeviceClass::SpiderLan => Ok(None),
+  }
+}
+
+fn is_newer(release: &LightReleaseResponse, version: &str) -> bool {
+  match (semver::Version::parse(&release.version()), semver::Version::parse(version)) {
+    (Ok(release), Ok(current)) => release > current,
+    _ => false
+  }
+}
+
+pub struct FirmwareCatalogStore {
+  catalog: Persisted<FirmwareCatalog>,
+  /* Held while fetching a single class, so a bus full of gated devices looks each class up once */
+  lookup: tokio::sync::Mutex<()>,
+}
+
+impl FirmwareCatalogStore {
+  pub fn get(&self) -> FirmwareCatalog {
+    self.catalog.get()
+  }
+
+  pub fn set_offline(&self, offline: bool) {
+    self.catalog.update(|c| c.offline = offline);
+  }
+
+  /* The newest release for a class, from the catalog if we have it, otherwise fetched once and kept there. Gives None
+     rather than an error when offline or the fetch fails, since callers can only offer the release if there is one. */
+  pub async fn release_for(&self, class: DeviceClass) -> Option<LightReleaseResponse> {
+    let _lookup = self.lookup.lock().await;
+    if let Some(release) = self.catalog.read(|c| c.latest(class).cloned()) {
+      return Some(release);
+    }
+    if self.catalog.read(|c| c.offline) {
+      return None;
+    }
+
+    match fetch(class).await {
+      Ok(Some(release)) => {
+        let now = chrono::Utc::now().timestamp_millis();
+        self.catalog.update(|c| {
+          c.entries.retain(|e| e.class != class);
+          c.entries.push(CatalogEntry { class, release: release.clone(), fetched_ms: now });
+        });
+        Some(release)
+      },
+      Ok(None) => None,
+      Err(e) => {
+        log::info!("Couldn't fetch the latest {:?} firmware: {}", class, e);
+        None
+      }
+    }
+  }
+
+  /* Fetch the latest release for each connected class (with the firmware versions the connected devices of that class
+     are running), raising an event the first time we see a release newer than something that's plugged in. */
+  pub async fn refresh(&self, connected: Vec<(DeviceClass, Vec<String>)>) -> anyhow::Result<FirmwareCatalog> {
+    if self.catalog.read(|c| c.offline) {
+      anyhow::bail!("Offline mode is on, not checking for new firmware");
+    }
+
+    let now = chrono::Utc::now().timestamp_millis();
+    for (class, versions) in connected {
+      let release = match fetch(class).await {
+        Ok(Some(release)) => release,
+        Ok(None) => continue,
+        Err(e) => {
+          log::warn!("Couldn't fetch the latest {:?} firmware: {}", class, e);
+          continue;
+        }
+      };
+
+      let previous = self.catalog.update(|c| {
+        let previous = c.latest(class).map(|r| r.tag_name.clone());
+        c.entries.retain(|e| e.class != class);
+        c.entries.push(CatalogEntry { class, release: release.clone(), fetched_ms: now });
+        previous
+      });
+
+      let outdated = versions.iter().filter(|v| is_newer(&release, v)).count();
+      if previous.as_ref() != Some(&release.tag_name) && outdated > 0 {
+        events().emit(None, "firmware_published", EventSeverity::Info, format!(
+          "New {:?} firmware {} is available, {} connected device(s) are running older firmware", class, release.version(), outdated
+        ));
+      }
+    }
+
+    Ok(self.catalog.update(|c| { c.last_refresh_ms = Some(now); c.clone() }))
+  }
+}
+
+pub fn firmware_catalog() -> &'static FirmwareCatalogStore {
+  static STORE: OnceLock<FirmwareCatalogStore> = OnceLock::new();
+  STORE.get_or_init(|| FirmwareCatalogStore { catalog: Persisted::load("firmware_catalog"), lookup: tokio::sync::Mutex::new(()) })
+}
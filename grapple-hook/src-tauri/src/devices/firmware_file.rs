@@ -0,0 +1,123 @@
+use std::{fs::File, io::{BufReader, Cursor, Read, Seek, SeekFrom}, path::{Path, PathBuf}};
+
+use super::FirmwareUpgradeDeviceRequest;
+
+/* Firmware to be flashed, either as bytes handed to us over RPC or as a file on disk. Files are read a chunk at a time
+   as the flash goes, so a large image is never held in memory all at once (or copied between tasks). Bundles are
+   unpacked to a temporary file first for the same reason. */
+
+/* Enough of the start of an image for any of the validators to find the firmware header */
+const HEADER_LEN: u64 = 0x1000;
+
+pub struct FirmwareFile {
+  /* Kept open from the start, so the image can't change (or go away) part way through */
+  file: File,
+  len: u64,
+  /* Where it came from, which is the bundle for an unpacked bundle */
+  source: PathBuf,
+  /* The unpacked image, removed once we're done with it */
+  unpacked: Option<PathBuf>,
+}
+
+impl FirmwareFile {
+  pub fn open(path: &Path) -> anyhow::Result<Self> {
+    let mut file = File::open(path).map_err(|e| anyhow::anyhow!("Couldn't open {}: {}", path.display(), e))?;
+
+    let unpacked = match zip::ZipArchive::new(BufReader::new(file.try_clone()?)) {
+      Ok(mut archive) => Some(unpack(&mut archive)?),
+      Err(_) => None
+    };
+    if let Some(unpacked) = &unpacked {
+      file = File::open(unpacked)?;
+    }
+
+    let len = file.metadata()?.len();
+    Ok(Self { file, len, source: path.to_owned(), unpacked })
+  }
+
+  pub fn source(&self) -> &Path {
+    &self.source
+  }
+
+  pub fn is_bundle(&self) -> bool {
+    self.unpacked.is_some()
+  }
+
+  fn reader(&self) -> anyhow::Result<BufReader<File>> {
+    let mut file = self.file.try_clone()?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(BufReader::new(file))
+  }
+}
+
+impl Drop for FirmwareFile {
+  fn drop(&mut self) {
+    if let Some(unpacked) = &self.unpacked {
+      std::fs::remove_file(unpacked).ok();
+    }
+  }
+}
+
+/* As maybe_unpack_firmware, but to a file */
+fn unpack<R: Read + Seek>(archive: &mut zip::ZipArchive<R>) -> anyhow::Result<PathBuf> {
+  let mut index_content = String::new();
+  archive.by_name("index")?.read_to_string(&mut index_content)?;
+  let index: serde_json::Value = serde_json::from_str(&index_content)?;
+  let name = index.get("firmware_update_bin").and_then(|x| x.as_str()).ok_or(anyhow::anyhow!("Invalid Index"))?.to_owned();
+
+  let path = std::env::temp_dir().join(format!("grapplehook-firmware-{}.bin", uuid::Uuid::new_v4()));
+  let mut out = File::create(&path)?;
+  if let Err(e) = std::io::copy(&mut archive.by_name(&name)?, &mut out) {
+    std::fs::remove_file(&path).ok();
+    return Err(e.into());
+  }
+  Ok(path)
+}
+
+pub enum FirmwareSource {
+  Memory(Vec<u8>),
+  File(FirmwareFile),
+}
+
+impl FirmwareSource {
+  pub fn len(&self) -> u64 {
+    match self {
+      FirmwareSource::Memory(data) => data.len() as u64,
+      FirmwareSource::File(file) => file.len,
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /* The start of the image, for validate_firmware */
+  pub fn header(&self) -> anyhow::Result<Vec<u8>> {
+    let mut header = vec![];
+    self.reader()?.take(HEADER_LEN).read_to_end(&mut header)?;
+    Ok(header)
+  }
+
+  /* Reads the image from the start. Read it a chunk at a time. */
+  pub fn reader(&self) -> anyhow::Result<Box<dyn Read + Send + '_>> {
+    Ok(match self {
+      FirmwareSource::Memory(data) => Box::new(Cursor::new(&data[..])),
+      FirmwareSource::File(file) => Box::new(file.reader()?),
+    })
+  }
+}
+
+/* How an image is handed to a device in firmware update mode: the bytes themselves, or a path it can stream from */
+pub enum FirmwarePayload {
+  Bytes(Vec<u8>),
+  File(PathBuf),
+}
+
+impl FirmwarePayload {
+  pub fn request(self) -> FirmwareUpgradeDeviceRequest {
+    match self {
+      FirmwarePayload::Bytes(data) => FirmwareUpgradeDeviceRequest::do_field_upgrade { data },
+      FirmwarePayload::File(path) => FirmwareUpgradeDeviceRequest::do_field_upgrade_from_file { path: path.display().to_string() },
+    }
+  }
+}
@@ -0,0 +1,172 @@
+use std::{collections::HashMap, future::Future, sync::{Arc, Mutex, OnceLock}, time::Duration};
+
+use tokio::sync::RwLock;
+
+use crate::events::{events, EventSeverity};
+use super::{chunked::FlashStats, device_manager::DeviceId, firmware_file::FirmwarePayload, provider_manager::{call_device, find_device, ProviderContainer}, FirmwareUpgradeDeviceRequest};
+
+/* The whole firmware update as one operation: put the device into DFU, wait for it to come back as a bootloader, flash
+   it, wait for it to reboot into the new firmware and check it's running the version we expected. Previously the user
+   had to shepherd each of those steps by hand. */
+
+const DFU_ENTRY_TIMEOUT: Duration = Duration::from_secs(15);
+const REBOOT_TIMEOUT: Duration = Duration::from_secs(30);
+/* Give up on a flash that hasn't made any progress in this long */
+const FLASH_STALL_TIMEOUT: Duration = Duration::from_secs(10);
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub enum UpdatePhase {
+  EnteringDfu,
+  WaitingForBootloader,
+  Flashing,
+  Rebooting,
+  Verifying,
+  Done,
+  Failed(String),
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct UpdateStatus {
+  pub serial: u32,
+  pub phase: UpdatePhase,
+  /* Overall progress through every phase, 0-100 */
+  pub progress: f64,
+  pub previous_version: Option<String>,
+  pub new_version: Option<String>,
+  pub started_at_ms: i64,
+  #[serde(default)]
+  pub flash_stats: Option<FlashStats>,
+}
+
+impl UpdateStatus {
+  pub fn is_finished(&self) -> bool {
+    matches!(self.phase, UpdatePhase::Done | UpdatePhase::Failed(..))
+  }
+}
+
+pub fn update_statuses() -> &'static Mutex<HashMap<u32, UpdateStatus>> {
+  static STATUSES: OnceLock<Mutex<HashMap<u32, UpdateStatus>>> = OnceLock::new();
+  STATUSES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn set_phase(serial: u32, phase: UpdatePhase, progress: f64) {
+  if let Some(status) = update_statuses().lock().unwrap().get_mut(&serial) {
+    status.phase = phase;
+    status.progress = progress;
+  }
+}
+
+async fn wait_for<F: Future<Output = bool>>(timeout: Duration, mut check: impl FnMut() -> F) -> bool {
+  let start = std::time::Instant::now();
+  while start.elapsed() < timeout {
+    if check().await {
+      return true;
+    }
+    tokio::time::sleep(POLL_INTERVAL).await;
+  }
+  false
+}
+
+/* Kick off an update in the background. Progress is reported through update_statuses(). If the device is already in
+   DFU (e.g. a previous attempt failed part way) we go straight to flashing. */
+pub async fn start(providers: Arc<RwLock<HashMap<String, ProviderContainer>>>, serial: u32, payload: FirmwarePayload, expected_version: Option<String>) -> anyhow::Result<()> {
+  let (_, _, device_id, info, _) = match find_device(&providers, &DeviceId::Serial(serial)).await {
+    Ok(device) => device,
+    Err(_) => find_device(&providers, &DeviceId::Dfu(serial)).await?
+  };
+
+  {
+    let mut statuses = update_statuses().lock().unwrap();
+    if statuses.get(&serial).map(|s| !s.is_finished()).unwrap_or(false) {
+      anyhow::bail!("This device is already being updated");
+    }
+    statuses.insert(serial, UpdateStatus {
+      serial,
+      phase: UpdatePhase::EnteringDfu,
+      progress: 0.0,
+      previous_version: info.firmware_version.clone().filter(|_| !info.is_dfu),
+      new_version: None,
+      started_at_ms: chrono::Utc::now().timestamp_millis(),
+      flash_stats: None,
+    });
+  }
+
+  tokio::task::spawn(async move {
+    let result = run(&providers, serial, device_id, payload, expected_version).await;
+    match result {
+      Ok(version) => {
+        if let Some(status) = update_statuses().lock().unwrap().get_mut(&serial) {
+          status.new_version = version.clone();
+        }
+        set_phase(serial, UpdatePhase::Done, 100.0);
+        events().emit(Some(serial), "firmware_updated", EventSeverity::Info, format!("Firmware updated to {}", version.unwrap_or("an unknown version".to_owned())));
+      },
+      Err(e) => {
+        let progress = update_statuses().lock().unwrap().get(&serial).map(|s| s.progress).unwrap_or(0.0);
+        set_phase(serial, UpdatePhase::Failed(e.to_string()), progress);
+        events().emit(Some(serial), "firmware_update_failed", EventSeverity::Error, format!("Firmware update failed: {}", e));
+      }
+    }
+  });
+
+  Ok(())
+}
+
+async fn run(providers: &RwLock<HashMap<String, ProviderContainer>>, serial: u32, device_id: DeviceId, payload: FirmwarePayload, expected_version: Option<String>) -> anyhow::Result<Option<String>> {
+  if let DeviceId::Serial(..) = device_id {
+    call_device(providers, device_id, serde_json::json!({ "method": "start_field_upgrade", "data": {} })).await?;
+
+    set_phase(serial, UpdatePhase::WaitingForBootloader, 5.0);
+    let in_dfu = wait_for(DFU_ENTRY_TIMEOUT, || async move { find_device(providers, &DeviceId::Dfu(serial)).await.is_ok() }).await;
+    if !in_dfu {
+      anyhow::bail!("The device didn't enter firmware update mode");
+    }
+  }
+
+  set_phase(serial, UpdatePhase::Flashing, 10.0);
+  call_device(providers, DeviceId::Dfu(serial), serde_json::to_value(payload.request())?).await?;
+
+  // The flash runs in the background on the device manager. It reports no progress once it's finished, or before it
+  // has started, so wait until we've seen it going.
+  let (mut seen, mut last_change) = (None, std::time::Instant::now());
+  loop {
+    tokio::time::sleep(POLL_INTERVAL).await;
+    let response = call_device(providers, DeviceId::Dfu(serial), serde_json::to_value(FirmwareUpgradeDeviceRequest::progress {})?).await;
+    match response.ok().and_then(|r| r.get("data").and_then(|d| d.as_f64())) {
+      Some(p) => {
+        if seen != Some(p) {
+          seen = Some(p);
+          last_change = std::time::Instant::now();
+        }
+        set_phase(serial, UpdatePhase::Flashing, 10.0 + p * 0.75);
+        let stats = call_device(providers, DeviceId::Dfu(serial), serde_json::to_value(FirmwareUpgradeDeviceRequest::flash_stats {})?).await.ok()
+          .and_then(|r| r.get("data").cloned())
+          .and_then(|d| serde_json::from_value::<Option<FlashStats>>(d).ok().flatten());
+        if let (Some(stats), Some(status)) = (stats, update_statuses().lock().unwrap().get_mut(&serial)) {
+          status.flash_stats = Some(stats);
+        }
+      },
+      None if seen.is_some() => break,
+      None => ()
+    }
+    if last_change.elapsed() > FLASH_STALL_TIMEOUT {
+      anyhow::bail!("Flashing stalled");
+    }
+  }
+
+  set_phase(serial, UpdatePhase::Rebooting, 85.0);
+  let back = wait_for(REBOOT_TIMEOUT, || async move { find_device(providers, &DeviceId::Serial(serial)).await.is_ok() }).await;
+  if !back {
+    anyhow::bail!("The device didn't come back after flashing. It may still be in firmware update mode, try flashing it again");
+  }
+
+  set_phase(serial, UpdatePhase::Verifying, 95.0);
+  let (_, _, _, info, _) = find_device(providers, &DeviceId::Serial(serial)).await?;
+  match (&expected_version, &info.firmware_version) {
+    (Some(expected), Some(actual)) if expected.trim_start_matches('v') != actual.trim_start_matches('v') => {
+      anyhow::bail!("Expected version {} after the update, but the device is running {}", expected, actual)
+    },
+    _ => Ok(info.firmware_version)
+  }
+}
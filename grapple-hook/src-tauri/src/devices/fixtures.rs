@@ -0,0 +1,122 @@
+use std::{collections::HashMap, path::{Path, PathBuf}, sync::{Mutex, OnceLock}};
+
+use crate::{persistence::data_dir, rpc::RpcBase};
+
+use super::{device_manager::{DeviceId, DeviceManager, Domain}, DeviceInfo};
+
+/* Developer tools (fixture capture, bus impairment) are only available when GRAPPLEHOOK_DEV is set */
+pub fn developer_mode() -> bool {
+  std::env::var("GRAPPLEHOOK_DEV").map(|v| !v.is_empty() && v != "0").unwrap_or(false)
+}
+
+pub fn require_developer_mode() -> anyhow::Result<()> {
+  if !developer_mode() {
+    anyhow::bail!("This is only available in developer mode (set GRAPPLEHOOK_DEV=1)");
+  }
+  Ok(())
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct FixtureExchange {
+  pub timestamp_ms: i64,
+  pub request: serde_json::Value,
+  pub response: Result<serde_json::Value, String>,
+}
+
+/* A device's RPC traffic, in a form tests can replay: feed each request in and expect the recorded response */
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct DeviceFixture {
+  pub device_class: String,
+  pub info: Option<DeviceInfo>,
+  pub exchanges: Vec<FixtureExchange>,
+}
+
+/* A replayed exchange whose response didn't match the recorded one */
+#[derive(Debug)]
+pub struct FixtureMismatch {
+  pub index: usize,
+  pub request: serde_json::Value,
+  pub expected: Result<serde_json::Value, String>,
+  pub actual: Result<serde_json::Value, String>,
+}
+
+/* Replay only ticks if a gap between exchanges is this long, which a capture made by hand rarely has */
+const REPLAY_TICK_MS: i64 = 500;
+
+impl DeviceFixture {
+  pub fn load(path: &Path) -> anyhow::Result<Self> {
+    let text = std::fs::read_to_string(path)?;
+    serde_json::from_str(&text).map_err(|e| anyhow::anyhow!("Couldn't read fixture {}: {}", path.display(), e))
+  }
+
+  /* Send each recorded request to the device through the manager, the same way the frontend does, and collect every
+     response that differs from the recorded one. A replay-mode manager's clock is moved on by the time between
+     exchanges, so anything time-dependent sees the same gaps it did during the capture. */
+  pub async fn replay(&self, manager: &DeviceManager, domain: &Domain, device_id: &DeviceId) -> anyhow::Result<Vec<FixtureMismatch>> {
+    let mut mismatches = vec![];
+    let mut last_ms = None;
+    for (index, exchange) in self.exchanges.iter().enumerate() {
+      if let (Some(last_ms), true) = (last_ms, manager.clock().is_virtual()) {
+        manager.advance(exchange.timestamp_ms - last_ms, REPLAY_TICK_MS).await?;
+      }
+      last_ms = Some(exchange.timestamp_ms);
+
+      let request = serde_json::json!({
+        "method": "call",
+        "data": { "domain": domain, "device_id": device_id, "data": exchange.request }
+      });
+      let actual = manager.rpc_call(request).await
+        .map(|mut response| response.get_mut("data").map(serde_json::Value::take).unwrap_or_default())
+        .map_err(|e| e.to_string());
+      if actual != exchange.response {
+        mismatches.push(FixtureMismatch { index, request: exchange.request.clone(), expected: exchange.response.clone(), actual });
+      }
+    }
+    Ok(mismatches)
+  }
+}
+
+pub struct FixtureRecorder {
+  /* Active captures, by serial */
+  captures: Mutex<HashMap<u32, DeviceFixture>>,
+}
+
+impl FixtureRecorder {
+  pub fn start(&self, serial: u32) {
+    self.captures.lock().unwrap().insert(serial, DeviceFixture { device_class: String::new(), info: None, exchanges: vec![] });
+  }
+
+  pub fn is_capturing(&self, device_id: &DeviceId) -> bool {
+    let serial = match device_id { DeviceId::Dfu(s) | DeviceId::Serial(s) => *s };
+    self.captures.lock().unwrap().contains_key(&serial)
+  }
+
+  pub fn record(&self, device_id: &DeviceId, device_class: &str, info: &DeviceInfo, request: &serde_json::Value, response: &anyhow::Result<serde_json::Value>) {
+    let serial = match device_id { DeviceId::Dfu(s) | DeviceId::Serial(s) => *s };
+    if let Some(fixture) = self.captures.lock().unwrap().get_mut(&serial) {
+      fixture.device_class = device_class.to_owned();
+      fixture.info = Some(info.clone());
+      fixture.exchanges.push(FixtureExchange {
+        timestamp_ms: chrono::Utc::now().timestamp_millis(),
+        request: request.clone(),
+        response: response.as_ref().map(|v| v.clone()).map_err(|e| e.to_string())
+      });
+    }
+  }
+
+  /* Stop capturing and write the fixture out, returning where it went */
+  pub fn finish(&self, serial: u32) -> anyhow::Result<PathBuf> {
+    let fixture = self.captures.lock().unwrap().remove(&serial).ok_or(anyhow::anyhow!("Not capturing serial 0x{:x}", serial))?;
+
+    let dir = data_dir().join("fixtures");
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}-{:x}-{}.json", fixture.device_class, serial, chrono::Utc::now().format("%Y%m%d-%H%M%S")));
+    std::fs::write(&path, serde_json::to_string_pretty(&fixture)?)?;
+    Ok(path)
+  }
+}
+
+pub fn fixtures() -> &'static FixtureRecorder {
+  static RECORDER: OnceLock<FixtureRecorder> = OnceLock::new();
+  RECORDER.get_or_init(|| FixtureRecorder { captures: Mutex::new(HashMap::new()) })
+}
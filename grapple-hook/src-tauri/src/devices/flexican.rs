@@ -2,8 +2,10 @@ use grapple_frc_msgs::{grapple::{device_info::GrappleModelId, GrappleDeviceMessa
 use grapple_hook_macros::rpc;
 use tokio::sync::RwLock;
 
-use crate::{rpc::RpcBase, updates::LightReleaseResponse};
-use super::{SendWrapper, SharedInfo, GrappleDevice, Device, GrappleDeviceRequest, GrappleDeviceResponse, VersionGatedDevice, RootDevice, start_field_upgrade, FirmwareValidatingDevice};
+use crate::{errors::{coded, ErrorCode}, rpc::RpcBase, updates::{most_recent_update_available, LightReleaseResponse}};
+use super::compatibility::{compatibility_report, CompatibilityReport};
+use super::device_class::DeviceClass;
+use super::{check_for_new_firmware_release_rpc_target, SendWrapper, SharedInfo, GrappleDevice, Device, GrappleDeviceRequest, GrappleDeviceResponse, HasFirmwareUpdateURLDevice, VersionGatedDevice, RootDevice, start_field_upgrade, FirmwareValidatingDevice};
 
 #[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 pub struct FlexiCanStatus {
@@ -32,23 +34,44 @@ impl FlexiCan {
   }
 }
 
-// #[async_trait::async_trait]
-// impl VersionGatedDevice for FlexiCan {
-//   fn validate_version(version: Option<String>) -> anyhow::Result<()> {
-//     // Self::require_version(version, ">= 2024.2.0, < 2024.3.0")
-//     Ok(())
-//   }
+impl HasFirmwareUpdateURLDevice for FlexiCan {
+  fn firmware_url() -> Option<String> {
+    Some("https://github.com/GrappleRobotics/Binaries/releases".to_owned())
+  }
+}
 
-//   async fn check_for_new_firmware_release(current_version: &str) -> Option<LightReleaseResponse>{
-//     None
-//   }
-// }
+#[async_trait::async_trait]
+impl VersionGatedDevice for FlexiCan {
+  fn validate_version(version: Option<String>) -> anyhow::Result<()> {
+    Self::require_version(version, ">= 2025.0.0, < 2025.1.0")
+  }
+
+  async fn check_for_new_firmware_release(current_version: &str) -> Option<LightReleaseResponse>{
+    let regex = regex::Regex::new("^flexican-v?(.+)$").unwrap();
+    let current = semver::Version::parse(&current_version).ok()?;
+
+    most_recent_update_available(
+      "https://api.github.com/repos/GrappleRobotics/Binaries/releases",
+      |release| {
+        let vers = regex.captures(&release.tag_name).and_then(|c| semver::Version::parse(c.get(1)?.as_str()).ok());
+        match vers {
+          Some(vers) => vers > current && Self::validate_version(Some(vers.to_string())).is_ok(),
+          None => false
+        }
+      }
+    ).await.ok().flatten()
+  }
+}
 
 #[async_trait::async_trait]
 impl RootDevice for FlexiCan {
   fn device_class(&self) -> &'static str {
     "FlexiCAN"
   }
+
+  async fn identify(&self) -> anyhow::Result<()> {
+    self.grapple_device.blink().await
+  }
 }
 
 #[async_trait::async_trait]
@@ -69,11 +92,13 @@ impl Device for FlexiCan {
 }
 
 impl FirmwareValidatingDevice for FlexiCan {
-  fn validate_firmware(_info: &super::DeviceInfo, buf: &[u8]) -> anyhow::Result<()> {
-    if &buf[0x200..0x204] == &[0xBEu8, 0xBAu8, 0xFEu8, 0xCAu8] && buf[0x20c] == (GrappleModelId::FlexiCAN as u8) {
+  fn validate_firmware(info: &super::DeviceInfo, buf: &[u8]) -> anyhow::Result<()> {
+    // An aliased model (see device_class) carries its own model byte in its firmware
+    let model_id = info.device_type.grapple_model_byte().unwrap_or(GrappleModelId::FlexiCAN as u8);
+    if &buf[0x200..0x204] == &[0xBEu8, 0xBAu8, 0xFEu8, 0xCAu8] && buf[0x20c] == model_id {
       Ok(())
     } else {
-      anyhow::bail!("Invalid Firmware File. Are you sure this is the correct firmware?")
+      Err(coded(ErrorCode::InvalidFirmware, "Invalid Firmware File. Are you sure this is the correct firmware?"))
     }
   }
 }
@@ -92,4 +117,12 @@ impl FlexiCan {
   async fn status(&self) -> anyhow::Result<FlexiCanStatus> {
     Ok(self.status.read().await.clone())
   }
+
+  async fn check_for_new_firmware(&self) -> anyhow::Result<Option<LightReleaseResponse>> {
+    check_for_new_firmware_release_rpc_target::<Self>(&self.info).await
+  }
+
+  async fn compatibility(&self) -> anyhow::Result<CompatibilityReport> {
+    Ok(compatibility_report(DeviceClass::FlexiCan, &*self.info.read().await))
+  }
 }
@@ -0,0 +1,104 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use super::can_id::{device_type_name, fields, manufacturer_name};
+
+/* Other vendors' devices we've seen traffic from. We can't talk to them, but every FRC CAN ID carries the manufacturer,
+   device type and device number, which is enough to give CSAs a rough picture of what else is on the bus. */
+
+/* Distinct devices kept per domain */
+const MAX_FOREIGN_DEVICES: usize = 128;
+/* Devices that have gone quiet for this long are dropped from the list */
+const FOREIGN_STALE_MS: i64 = 10_000;
+
+const MANUFACTURER_GRAPPLE: u8 = 6;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ForeignDevice {
+  pub manufacturer: u8,
+  pub manufacturer_name: String,
+  pub device_type: u8,
+  pub device_type_name: String,
+  pub device_id: u8,
+  /* Best guess at the product, from the manufacturer and device type alone */
+  pub likely_product: Option<String>,
+  pub frames: u64,
+  pub first_seen_ms: i64,
+  pub last_seen_ms: i64,
+}
+
+fn likely_product(manufacturer: u8, device_type: u8) -> Option<&'static str> {
+  match (manufacturer, device_type) {
+    (1, 1) => Some("roboRIO"),
+    (4, 2) => Some("Talon / Victor"),
+    (4, 4) => Some("Pigeon"),
+    (4, 7) => Some("CANcoder"),
+    (4, 8) => Some("Power Distribution Panel"),
+    (4, 9) => Some("Pneumatics Control Module"),
+    (4, 10) => Some("CANdle / CANrange"),
+    (5, 2) => Some("SPARK MAX / SPARK Flex"),
+    (5, 8) => Some("Power Distribution Hub"),
+    (5, 9) => Some("Pneumatic Hub"),
+    (5, 10) => Some("Servo Hub"),
+    (9, 4) => Some("navX"),
+    (11, 6) => Some("Time of Flight"),
+    (13, 2) => Some("Nova"),
+    (14, 7) => Some("Canandmag"),
+    (14, 4) => Some("Canandgyro"),
+    _ => None
+  }
+}
+
+pub struct ForeignDevices {
+  devices: Mutex<HashMap<(u8, u8, u8), ForeignDevice>>,
+}
+
+impl ForeignDevices {
+  pub fn new() -> Self {
+    Self { devices: Mutex::new(HashMap::new()) }
+  }
+
+  pub fn observe(&self, arbitration_id: u32, timestamp_ms: i64) {
+    let (device_type, manufacturer, _, _, device_id) = fields(arbitration_id);
+    // Broadcast frames don't belong to any one device
+    if manufacturer == MANUFACTURER_GRAPPLE || device_type == 0 {
+      return;
+    }
+
+    let mut devices = self.devices.lock().unwrap();
+    let key = (manufacturer, device_type, device_id);
+    match devices.get_mut(&key) {
+      Some(device) => {
+        device.frames += 1;
+        device.last_seen_ms = timestamp_ms;
+      },
+      None => {
+        if devices.len() >= MAX_FOREIGN_DEVICES {
+          devices.retain(|_, d| timestamp_ms - d.last_seen_ms < FOREIGN_STALE_MS);
+          if devices.len() >= MAX_FOREIGN_DEVICES {
+            return;
+          }
+        }
+        devices.insert(key, ForeignDevice {
+          manufacturer,
+          manufacturer_name: manufacturer_name(manufacturer),
+          device_type,
+          device_type_name: device_type_name(device_type),
+          device_id,
+          likely_product: likely_product(manufacturer, device_type).map(str::to_owned),
+          frames: 1,
+          first_seen_ms: timestamp_ms,
+          last_seen_ms: timestamp_ms,
+        });
+      }
+    }
+  }
+
+  /* Devices heard from recently, ordered by manufacturer, type and ID */
+  pub fn list(&self, now_ms: i64) -> Vec<ForeignDevice> {
+    let mut devices = self.devices.lock().unwrap();
+    devices.retain(|_, d| now_ms - d.last_seen_ms < FOREIGN_STALE_MS);
+    let mut list = devices.values().cloned().collect::<Vec<_>>();
+    list.sort_by_key(|d| (d.manufacturer, d.device_type, d.device_id));
+    list
+  }
+}
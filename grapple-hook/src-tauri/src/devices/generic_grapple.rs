@@ -0,0 +1,89 @@
+use grapple_frc_msgs::grapple::TaggedGrappleMessage;
+use grapple_hook_macros::rpc;
+
+use crate::errors::{coded, ErrorCode};
+use crate::rpc::RpcBase;
+use super::{start_field_upgrade, Device, DeviceInfo, FirmwareValidatingDevice, GrappleDevice, GrappleDeviceRequest, GrappleDeviceResponse, HasFirmwareUpdateURLDevice, RootDevice, SendWrapper, SharedInfo};
+
+/* Fallback for Grapple hardware this build has no driver for, e.g. a product released after this version of
+   GrappleHook. Every Grapple device speaks the common enumerate / rename / DFU messages, so we can still show its
+   details, rename it, and update its firmware (which is often what's needed to get a newer GrappleHook talking to it). */
+pub struct GenericGrappleDevice {
+  sender: SendWrapper,
+  info: SharedInfo,
+
+  grapple_device: GrappleDevice,
+}
+
+impl GenericGrappleDevice {
+  pub fn new(sender: SendWrapper, info: SharedInfo) -> Self {
+    Self {
+      sender: sender.clone(),
+      info: info.clone(),
+
+      grapple_device: GrappleDevice::new(sender.clone(), info.clone()),
+    }
+  }
+}
+
+impl HasFirmwareUpdateURLDevice for GenericGrappleDevice {
+  fn firmware_url() -> Option<String> {
+    Some("https://github.com/GrappleRobotics/Binaries/releases".to_owned())
+  }
+}
+
+impl FirmwareValidatingDevice for GenericGrappleDevice {
+  fn validate_firmware(info: &DeviceInfo, buf: &[u8]) -> anyhow::Result<()> {
+    let model_id = info.device_type.grapple_model_byte().ok_or(anyhow::anyhow!("Not a Grapple device"))?;
+
+    // We don't know where this model keeps its firmware header, so look for the Grapple magic anywhere
+    // in the vector table region and check the model ID that follows it.
+    let header_found = (0..buf.len().min(0x1000).saturating_sub(0x10)).step_by(4).any(|offset| {
+      &buf[offset..offset + 4] == &[0xBEu8, 0xBAu8, 0xFEu8, 0xCAu8] && buf[offset + 0xC] == model_id
+    });
+
+    if header_found {
+      Ok(())
+    } else {
+      Err(coded(ErrorCode::InvalidFirmware, "Invalid Firmware File. Are you sure this is the correct firmware?"))
+    }
+  }
+}
+
+#[async_trait::async_trait]
+impl RootDevice for GenericGrappleDevice {
+  fn device_class(&self) -> &'static str {
+    "GenericGrappleDevice"
+  }
+
+  async fn identify(&self) -> anyhow::Result<()> {
+    self.grapple_device.blink().await
+  }
+}
+
+#[async_trait::async_trait]
+impl Device for GenericGrappleDevice {
+  async fn handle(&self, msg: TaggedGrappleMessage<'static>) -> anyhow::Result<()> {
+    self.grapple_device.handle(msg).await
+  }
+}
+
+#[rpc]
+impl GenericGrappleDevice {
+  async fn info(&self) -> anyhow::Result<DeviceInfo> {
+    Ok(self.info.read().await.clone())
+  }
+
+  async fn get_firmware_url(&self) -> anyhow::Result<Option<String>> {
+    Ok(Self::firmware_url())
+  }
+
+  async fn start_field_upgrade(&self) -> anyhow::Result<()> {
+    let serial = self.info.read().await.require_serial()?;
+    start_field_upgrade(&self.sender, serial).await
+  }
+
+  async fn grapple(&self, msg: GrappleDeviceRequest) -> anyhow::Result<GrappleDeviceResponse> {
+    self.grapple_device.rpc_process(msg).await
+  }
+}
@@ -1,8 +1,8 @@
-use std::{borrow::Cow, collections::HashMap, sync::{atomic::AtomicBool, Arc}, time::Duration};
+use std::{borrow::Cow, sync::{atomic::AtomicBool, Arc}, time::Duration};
 
 use bounded_static::ToBoundedStatic;
 use futures::{SinkExt, StreamExt};
-use grapple_frc_msgs::{binmarshal::{AsymmetricCow, BitView, BitWriter, BufferBitWriter, Demarshal, Marshal, MarshalUpdate}, bridge::BridgedCANMessage, grapple::{fragments::FragmentReassembler, GrappleMessageId, TaggedGrappleMessage}, ManufacturerMessage};
+use grapple_frc_msgs::{binmarshal::{AsymmetricCow, BitView, BitWriter, BufferBitWriter, Demarshal, Marshal, MarshalUpdate}, bridge::BridgedCANMessage, grapple::{device_info::{GrappleDeviceInfo, GrappleModelId}, fragments::FragmentReassembler, GrappleBroadcastMessage, GrappleDeviceMessage, GrappleMessageId, TaggedGrappleMessage}, ManufacturerMessage, DEVICE_ID_BROADCAST};
 use log::{info, warn};
 use serde_json::json;
 use tokio::sync::{mpsc, Mutex};
@@ -10,7 +10,9 @@ use tokio_serial::{SerialPort, SerialStream, UsbPortInfo};
 use tokio_util::codec::Framed;
 
 use crate::codecs::usb_codec::GrappleUsbCodec;
+use crate::errors::{coded, ErrorCode};
 
+use super::usb_permissions::{diagnose, fix_for, UsbIssueKind};
 use super::{device_manager::{DeviceManager, DeviceManagerRequest, DeviceManagerResponse}, provider::{DeviceProvider, ProviderInfo}};
 
 pub struct GenericUSBInner {
@@ -21,7 +23,22 @@ pub struct GenericUSBInner {
   stop_signal_tx: mpsc::Sender<()>,
   stop_signal_rx: Mutex<mpsc::Receiver<()>>,
 
+  send_tx: mpsc::Sender<TaggedGrappleMessage<'static>>,
   send_rx: Mutex<mpsc::Receiver<TaggedGrappleMessage<'static>>>,
+
+  bridge_send_tx: mpsc::Sender<TaggedGrappleMessage<'static>>,
+  bridge_send_rx: Mutex<mpsc::Receiver<TaggedGrappleMessage<'static>>>,
+  bridge: Mutex<Option<BridgeInfo>>,
+
+  last_issue: std::sync::Mutex<Option<UsbIssueKind>>,
+}
+
+/* A FlexiCAN connected over USB acts as a USB-CAN bridge. Once we've seen it, everything on the bus behind it is put
+   in its own nested domain (e.g. USB/flexican-1234) so it isn't confused with the FlexiCAN itself. */
+#[derive(Clone)]
+struct BridgeInfo {
+  domain: String,
+  device_id: u8,
 }
 
 pub struct GenericUSB {
@@ -32,18 +49,19 @@ impl GenericUSB {
   pub fn new(address: String) -> Self {
     let (send_tx, send_rx) = mpsc::channel(100);
     let (stop_signal_tx, stop_signal_rx) = mpsc::channel(5);
-
-    let mut sends = HashMap::new();
-    sends.insert("USB".to_owned(), send_tx);
+    let (bridge_send_tx, bridge_send_rx) = mpsc::channel(100);
 
     Self {
       inner: Arc::new(
         GenericUSBInner {
           address,
           running: AtomicBool::new(false),
-          device_manager: DeviceManager::new(sends),
+          device_manager: DeviceManager::new(),
           stop_signal_tx, stop_signal_rx: Mutex::new(stop_signal_rx),
-          send_rx: Mutex::new(send_rx),
+          send_tx, send_rx: Mutex::new(send_rx),
+          bridge_send_tx, bridge_send_rx: Mutex::new(bridge_send_rx),
+          bridge: Mutex::new(None),
+          last_issue: std::sync::Mutex::new(None),
         }
       )
     }
@@ -52,6 +70,7 @@ impl GenericUSB {
   async fn do_loop(mut framed: Framed<SerialStream, GrappleUsbCodec>, inner: Arc<GenericUSBInner>) -> anyhow::Result<()> {
     let mut send_rx = inner.send_rx.try_lock().map_err(|_| anyhow::anyhow!("This RootDevice is already running!"))?;
     let mut stop_signal_rx = inner.stop_signal_rx.try_lock()?;
+    let mut bridge_send_rx = inner.bridge_send_rx.try_lock()?;
 
     let (mut reassemble_rx, _) = FragmentReassembler::new(1000, 1024).split();
     let mut device_manager_interval = tokio::time::interval(Duration::from_millis(500));
@@ -64,33 +83,31 @@ impl GenericUSB {
             match manufacturer_msg {
               Ok(ManufacturerMessage::Grapple(grpl_msg)) => {
                 let mut storage = Vec::new();
-                if let Ok(Some(grpl_unfragmented)) = reassemble_rx.defragment(0, &msg.id, grpl_msg, &mut storage) {
-                  inner.device_manager.on_message("USB".to_owned(), msg.id.clone().into(), TaggedGrappleMessage::new(msg.id.device_id, grpl_unfragmented.to_static())).await?;
+                match reassemble_rx.defragment(0, &msg.id, grpl_msg, &mut storage) {
+                  Ok(Some(grpl_unfragmented)) => {
+                    let tagged = TaggedGrappleMessage::new(msg.id.device_id, grpl_unfragmented.to_static());
+                    for domain in Self::route(&inner, &tagged).await {
+                      inner.device_manager.on_message(domain, msg.id.clone().into(), tagged.clone()).await?;
+                    }
+                  },
+                  Ok(None) => (),
+                  Err(e) => inner.device_manager.on_malformed("USB", msg.id.clone().into(), &msg.data[..], format!("{:?}", e))
                 }
               },
-              _ => ()
+              Ok(_) => inner.device_manager.on_foreign("USB", msg.id.clone().into()),
+              Err(e) => inner.device_manager.on_malformed("USB", msg.id.clone().into(), &msg.data[..], format!("{:?}", e))
             }
           },
           Some(Err(e)) => anyhow::bail!(e),
           None => ()
         },
         msg = send_rx.recv() => match msg {
-          Some(mut tagged) => {
-            let mut payload = [0u8; 1024];
-            let mut writer = BufferBitWriter::new(&mut payload);
-            let mut id = GrappleMessageId::new(tagged.device_id);
-
-            tagged.msg.update(&mut id);
-            tagged.msg.write(&mut writer, id.clone()).ok();
-
-            let mut msgs = vec![
-              (id.into(), writer.slice().to_vec())
-            ];
-
-            for msg in msgs {
-              framed.send(BridgedCANMessage { id: msg.0, timestamp: 0, data: AsymmetricCow(Cow::Borrowed((&msg.1[..]).into())) }).await?;
-            }
-          },
+          Some(tagged) => Self::write_message(&mut framed, tagged).await?,
+          None => ()
+        },
+        // The FlexiCAN forwards anything we send onto the bus behind it, so bridged traffic goes out the same way
+        msg = bridge_send_rx.recv() => match msg {
+          Some(tagged) => Self::write_message(&mut framed, tagged).await?,
           None => ()
         },
         sig = stop_signal_rx.recv() => match sig {
@@ -108,10 +125,53 @@ impl GenericUSB {
     Ok(())
   }
 
+  async fn write_message(framed: &mut Framed<SerialStream, GrappleUsbCodec>, mut tagged: TaggedGrappleMessage<'static>) -> anyhow::Result<()> {
+    let mut payload = [0u8; 1024];
+    let mut writer = BufferBitWriter::new(&mut payload);
+    let mut id = GrappleMessageId::new(tagged.device_id);
+
+    tagged.msg.update(&mut id);
+    tagged.msg.write(&mut writer, id.clone()).ok();
+
+    let msgs = vec![
+      (id.into(), writer.slice().to_vec())
+    ];
+
+    for msg in msgs {
+      framed.send(BridgedCANMessage { id: msg.0, timestamp: 0, data: AsymmetricCow(Cow::Borrowed((&msg.1[..]).into())) }).await?;
+    }
+    Ok(())
+  }
+
+  /* Work out which domain(s) a message belongs in, registering the bridged domain the first time we see a FlexiCAN */
+  async fn route(inner: &Arc<GenericUSBInner>, msg: &TaggedGrappleMessage<'static>) -> Vec<String> {
+    if let GrappleDeviceMessage::Broadcast(GrappleBroadcastMessage::DeviceInfo(GrappleDeviceInfo::EnumerateResponse { model_id: GrappleModelId::FlexiCAN, serial, .. })) = &msg.msg {
+      let mut bridge = inner.bridge.lock().await;
+      if bridge.is_none() {
+        let domain = format!("USB/flexican-{:x}", serial);
+        info!("FlexiCAN bridge detected, devices behind it will appear under {}", domain);
+        inner.device_manager.register_domain(domain.clone(), inner.bridge_send_tx.clone()).await;
+        *bridge = Some(BridgeInfo { domain, device_id: msg.device_id });
+      }
+    }
+
+    match inner.bridge.lock().await.clone() {
+      None => vec!["USB".to_owned()],
+      Some(bridge) if msg.device_id == DEVICE_ID_BROADCAST => vec!["USB".to_owned(), bridge.domain],
+      Some(bridge) if msg.device_id == bridge.device_id => vec!["USB".to_owned()],
+      Some(bridge) => vec![bridge.domain],
+    }
+  }
+
   async fn do_start(inner: Arc<GenericUSBInner>) -> anyhow::Result<()> {
     info!("Connecting...");
 
-    let mut port = tokio_serial::SerialStream::open(&tokio_serial::new(inner.address.clone(), 115200))?;
+    let mut port = tokio_serial::SerialStream::open(&tokio_serial::new(inner.address.clone(), 115200)).map_err(|e| {
+      let issue = diagnose(&e);
+      *inner.last_issue.lock().unwrap() = Some(issue.clone());
+      coded(ErrorCode::UsbOpenFailed, format!("{} ({})", fix_for(issue).description, e))
+    })?;
+    *inner.last_issue.lock().unwrap() = None;
     port.set_baud_rate(1200)?;
     tokio::time::sleep(Duration::from_millis(100)).await;
     port.set_baud_rate(115200)?;
@@ -120,12 +180,17 @@ impl GenericUSB {
     let framed = Framed::new(port, GrappleUsbCodec);
 
     info!("Connected!");
+    inner.device_manager.register_domain("USB".to_owned(), inner.send_tx.clone()).await;
 
     tokio::task::spawn(async move {
       inner.running.store(true, std::sync::atomic::Ordering::Relaxed);
       let r = Self::do_loop(framed, inner.clone()).await;
       inner.running.store(false, std::sync::atomic::Ordering::Relaxed);
-      inner.device_manager.reset().await;
+      // There's no reconnecting to a USB device, it comes back as a new provider if it's plugged in again
+      if let Some(bridge) = inner.bridge.lock().await.take() {
+        inner.device_manager.unregister_domain(&bridge.domain).await;
+      }
+      inner.device_manager.unregister_domain(&"USB".to_owned()).await;
       match r {
         Ok(_) => info!("GenericUSB runner stopped gracefully"),
         Err(e) => warn!("GenericUSB runner stopped with error: {}", e),
@@ -158,7 +223,9 @@ impl DeviceProvider for GenericUSB {
   }
 
   async fn call(&self, _req: serde_json::Value) -> anyhow::Result<serde_json::Value> {
-    Ok(json!({}))
+    // If the port failed to open, tell the frontend how to fix it
+    let fix = self.inner.last_issue.lock().unwrap().clone().map(fix_for);
+    Ok(json!({ "usb_fix": fix }))
   }
 
   async fn device_manager_call(&self, req: DeviceManagerRequest) -> anyhow::Result<DeviceManagerResponse> {
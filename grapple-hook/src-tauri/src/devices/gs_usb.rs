@@ -0,0 +1,277 @@
+use std::{borrow::Cow, collections::HashMap, sync::{atomic::AtomicBool, Arc, OnceLock}, time::Duration};
+
+use bounded_static::ToBoundedStatic;
+use grapple_frc_msgs::{binmarshal::{BitView, Demarshal, LengthTaggedPayload, LengthTaggedPayloadOwned}, bridge::BridgedCANMessage, grapple::{fragments::FragmentReassembler, TaggedGrappleMessage}, ManufacturerMessage};
+use grapple_hook_macros::rpc;
+use log::{info, warn};
+use nusb::transfer::{ControlIn, ControlOut, ControlType, Recipient, RequestBuffer};
+use tokio::sync::{mpsc, Mutex};
+
+use crate::codecs::gs_usb::{decode_frame, encode_frame, mode, BitTimingConst, GsUsbFrame, BREQ_BITTIMING, BREQ_BT_CONST, BREQ_HOST_FORMAT, BREQ_MODE, ENDPOINT_IN, ENDPOINT_OUT, HOST_FORMAT, HOST_FRAME_LEN, MODE_RESET, MODE_START};
+use crate::errors::{coded, ErrorCode};
+use crate::{persistence::Persisted, rpc::RpcBase};
+
+use super::{device_manager::{DeviceManager, DeviceManagerRequest, DeviceManagerResponse}, provider::{DeviceProvider, ProviderInfo}};
+
+/* A candleLight / gs_usb USB-CAN adapter, driven over raw USB so it works without a driver on Windows and macOS as
+   well as Linux (where we take the device from the kernel's gs_usb driver while we're connected). */
+
+/* candleLight (and the CANable 2.0 running it), candleLight-compatible devices on pid.codes, CES CANext FD and the
+   ABE CANdebugger FD */
+pub const GS_USB_IDS: &[(u16, u16)] = &[(0x1D50, 0x606F), (0x1209, 0x2323), (0x1CD2, 0x606F), (0x16D0, 0x10B8)];
+pub const GS_USB_DOMAIN: &str = "GS_USB";
+pub const GS_USB_BITRATES: &[u32] = &[10_000, 20_000, 50_000, 100_000, 125_000, 250_000, 500_000, 800_000, 1_000_000];
+const FRC_BITRATE: u32 = 1_000_000;
+/* Bulk reads kept in flight, so frames aren't dropped while we're handling one */
+const IN_FLIGHT_READS: usize = 8;
+const CONTROL_TIMEOUT: Duration = Duration::from_secs(1);
+
+/* The bitrate each adapter is started at, by address. FRC_BITRATE if it's never been changed. */
+fn bitrates() -> &'static Persisted<HashMap<String, u32>> {
+  static BITRATES: OnceLock<Persisted<HashMap<String, u32>>> = OnceLock::new();
+  BITRATES.get_or_init(|| Persisted::load("gs_usb_bitrates"))
+}
+
+fn bitrate_for(address: &str) -> u32 {
+  bitrates().read(|b| b.get(address).cloned()).unwrap_or(FRC_BITRATE)
+}
+
+/* Adapters are told apart by serial number, which (unlike the bus address) survives being unplugged */
+pub fn address_of(device: &nusb::DeviceInfo) -> String {
+  match device.serial_number() {
+    Some(serial) => format!("gs_usb:{}", serial),
+    None => format!("gs_usb:{}-{}", device.bus_number(), device.device_address()),
+  }
+}
+
+pub fn gs_usb_devices() -> Vec<nusb::DeviceInfo> {
+  nusb::list_devices().map(|devices| devices.filter(|d| GS_USB_IDS.contains(&(d.vendor_id(), d.product_id()))).collect()).unwrap_or_default()
+}
+
+pub struct GsUsbInner {
+  address: String,
+  running: AtomicBool,
+  device_manager: DeviceManager,
+
+  stop_signal_tx: mpsc::Sender<()>,
+  stop_signal_rx: Mutex<mpsc::Receiver<()>>,
+
+  send_tx: mpsc::Sender<TaggedGrappleMessage<'static>>,
+  send_rx: Mutex<mpsc::Receiver<TaggedGrappleMessage<'static>>>,
+
+  last_error: std::sync::Mutex<Option<String>>,
+}
+
+pub struct GsUsb {
+  inner: Arc<GsUsbInner>
+}
+
+impl GsUsb {
+  pub fn new(address: String) -> Self {
+    let (send_tx, send_rx) = mpsc::channel(100);
+    let (stop_signal_tx, stop_signal_rx) = mpsc::channel(5);
+
+    Self {
+      inner: Arc::new(
+        GsUsbInner {
+          address,
+          running: AtomicBool::new(false),
+          device_manager: DeviceManager::new(),
+          stop_signal_tx, stop_signal_rx: Mutex::new(stop_signal_rx),
+          send_tx, send_rx: Mutex::new(send_rx),
+          last_error: std::sync::Mutex::new(None),
+        }
+      )
+    }
+  }
+
+  async fn control_out(interface: &nusb::Interface, request: u8, data: &[u8]) -> anyhow::Result<()> {
+    let transfer = interface.control_out(ControlOut { control_type: ControlType::Vendor, recipient: Recipient::Interface, request, value: 0, index: 0, data });
+    tokio::time::timeout(CONTROL_TIMEOUT, transfer).await.map_err(|_| anyhow::anyhow!("Adapter didn't answer setup request {}", request))?.into_result()?;
+    Ok(())
+  }
+
+  async fn open(inner: &Arc<GsUsbInner>) -> anyhow::Result<nusb::Interface> {
+    let device = gs_usb_devices().into_iter().find(|d| address_of(d) == inner.address)
+      .ok_or(coded(ErrorCode::DeviceNotFound, format!("{} isn't plugged in", inner.address)))?;
+    let fail = |e: std::io::Error| coded(ErrorCode::UsbOpenFailed, format!("Couldn't open {} ({})", inner.address, e));
+    let interface = device.open().map_err(fail)?.detach_and_claim_interface(0).map_err(fail)?;
+
+    Self::control_out(&interface, BREQ_HOST_FORMAT, &HOST_FORMAT.to_le_bytes()).await?;
+    Self::control_out(&interface, BREQ_MODE, &mode(MODE_RESET)).await?;
+
+    let bt_const = tokio::time::timeout(CONTROL_TIMEOUT, interface.control_in(ControlIn { control_type: ControlType::Vendor, recipient: Recipient::Interface, request: BREQ_BT_CONST, value: 0, index: 0, length: 40 }))
+      .await.map_err(|_| anyhow::anyhow!("Adapter didn't report its bit timing limits"))?.into_result()?;
+    let bt_const = BitTimingConst::parse(&bt_const).ok_or(anyhow::anyhow!("Adapter sent invalid bit timing limits"))?;
+
+    let bitrate = bitrate_for(&inner.address);
+    let timing = bt_const.bit_timing(bitrate).ok_or(anyhow::anyhow!("This adapter can't run at {}bit/s (its CAN clock is {}Hz)", bitrate, bt_const.fclk_can))?;
+    Self::control_out(&interface, BREQ_BITTIMING, &timing).await?;
+    Self::control_out(&interface, BREQ_MODE, &mode(MODE_START)).await?;
+    Ok(interface)
+  }
+
+  async fn do_loop(interface: nusb::Interface, inner: Arc<GsUsbInner>) -> anyhow::Result<()> {
+    let mut send_rx = inner.send_rx.try_lock().map_err(|_| anyhow::anyhow!("This RootDevice is already running!"))?;
+    let mut stop_signal_rx = inner.stop_signal_rx.try_lock()?;
+
+    let (mut reassemble_rx, mut reassemble_tx) = FragmentReassembler::new(1000, 8).split();
+    let mut device_manager_interval = tokio::time::interval(Duration::from_millis(500));
+
+    let mut reads = interface.bulk_in_queue(ENDPOINT_IN);
+    for _ in 0..IN_FLIGHT_READS {
+      reads.submit(RequestBuffer::new(HOST_FRAME_LEN));
+    }
+
+    loop {
+      tokio::select! {
+        completion = reads.next_complete() => {
+          let data = completion.data;
+          completion.status.map_err(|e| anyhow::anyhow!("Adapter disconnected ({})", e))?;
+          match decode_frame(&data) {
+            Some(GsUsbFrame::Frame(msg)) => {
+              let manufacturer_msg = ManufacturerMessage::read(&mut BitView::new(&msg.data[..]), msg.id.clone());
+              match manufacturer_msg {
+                Ok(ManufacturerMessage::Grapple(grpl_msg)) => {
+                  let mut storage = Vec::new();
+                  match reassemble_rx.defragment(chrono::Utc::now().timestamp_millis(), &msg.id, grpl_msg, &mut storage) {
+                    Ok(Some(grpl_unfragmented)) => {
+                      inner.device_manager.on_message(GS_USB_DOMAIN.to_owned(), msg.id.clone().into(), TaggedGrappleMessage::new(msg.id.device_id, grpl_unfragmented.to_static())).await?;
+                    },
+                    Ok(None) => (),
+                    Err(e) => inner.device_manager.on_malformed(GS_USB_DOMAIN, msg.id.clone().into(), &msg.data[..], format!("{:?}", e))
+                  }
+                },
+                Ok(_) => inner.device_manager.on_foreign(GS_USB_DOMAIN, msg.id.clone().into()),
+                Err(e) => inner.device_manager.on_malformed(GS_USB_DOMAIN, msg.id.clone().into(), &msg.data[..], format!("{:?}", e))
+              }
+            },
+            Some(GsUsbFrame::Error(class)) => {
+              let e = anyhow::anyhow!("Bus error (class 0x{:x})", class);
+              warn!("{} reported a bus error: {}", inner.address, e);
+              inner.device_manager.on_transport_error(&e);
+            },
+            Some(GsUsbFrame::Ignored) | None => ()
+          }
+          reads.submit(RequestBuffer::reuse(data, HOST_FRAME_LEN));
+        },
+        msg = send_rx.recv() => match msg {
+          Some(TaggedGrappleMessage { device_id, msg }) => {
+            let mut msgs = vec![];
+            reassemble_tx.maybe_fragment(device_id, msg, &mut |id, buf| {
+              msgs.push(BridgedCANMessage { id, timestamp: 0, data: Cow::<LengthTaggedPayload<u8>>::Owned(LengthTaggedPayloadOwned::new(buf.to_vec())).into() });
+            }).ok();
+
+            for msg in msgs {
+              // We don't track our frames once they're sent, so every one goes with the same echo ID
+              interface.bulk_out(ENDPOINT_OUT, encode_frame(&msg, 0)?).await.into_result()?;
+            }
+          },
+          None => ()
+        },
+        sig = stop_signal_rx.recv() => match sig {
+          Some(()) => {
+            break;
+          },
+          None => ()
+        },
+        _ = device_manager_interval.tick() => {
+          inner.device_manager.on_tick().await?;
+        }
+      }
+    }
+
+    // Take the adapter off the bus, so it isn't left acknowledging frames
+    Self::control_out(&interface, BREQ_MODE, &mode(MODE_RESET)).await.ok();
+    Ok(())
+  }
+
+  async fn do_start(inner: Arc<GsUsbInner>) -> anyhow::Result<()> {
+    info!("Connecting to gs_usb adapter {}...", inner.address);
+
+    let result = Self::open(&inner).await;
+    *inner.last_error.lock().unwrap() = result.as_ref().err().map(|e| e.to_string());
+    let interface = result?;
+
+    info!("Connected!");
+    inner.device_manager.register_domain(GS_USB_DOMAIN.to_owned(), inner.send_tx.clone()).await;
+
+    tokio::task::spawn(async move {
+      inner.running.store(true, std::sync::atomic::Ordering::Relaxed);
+      let r = Self::do_loop(interface, inner.clone()).await;
+      inner.running.store(false, std::sync::atomic::Ordering::Relaxed);
+      inner.device_manager.unregister_domain(&GS_USB_DOMAIN.to_owned()).await;
+      match r {
+        Ok(_) => info!("gs_usb runner stopped gracefully"),
+        Err(e) => {
+          warn!("gs_usb runner stopped with error: {}", e);
+          *inner.last_error.lock().unwrap() = Some(e.to_string());
+        }
+      }
+    });
+
+    Ok(())
+  }
+}
+
+#[async_trait::async_trait]
+impl DeviceProvider for GsUsb {
+  async fn connect(&self) -> anyhow::Result<()> {
+    Self::do_start(self.inner.clone()).await
+  }
+
+  async fn disconnect(&self) -> anyhow::Result<()> {
+    self.inner.stop_signal_tx.send(()).await.ok();
+    Ok(())
+  }
+
+  async fn info(&self) -> anyhow::Result<ProviderInfo> {
+    Ok(ProviderInfo {
+      ty: "GS-USB".to_owned(),
+      description: "candleLight Adapter".to_owned(),
+      address: self.inner.address.clone(),
+      connected: self.inner.running.load(std::sync::atomic::Ordering::Relaxed)
+    })
+  }
+
+  async fn device_manager_call(&self, req: DeviceManagerRequest) -> anyhow::Result<DeviceManagerResponse> {
+    self.inner.device_manager.rpc_process(req).await
+  }
+
+  async fn call(&self, req: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+    self.rpc_call(req).await
+  }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct GsUsbStatus {
+  pub address: String,
+  pub bitrate: u32,
+  pub supported_bitrates: Vec<u32>,
+  /* Why the adapter couldn't be opened, or why the connection dropped */
+  pub last_error: Option<String>,
+}
+
+#[rpc]
+impl GsUsb {
+  async fn status(&self) -> anyhow::Result<GsUsbStatus> {
+    Ok(GsUsbStatus {
+      address: self.inner.address.clone(),
+      bitrate: bitrate_for(&self.inner.address),
+      supported_bitrates: GS_USB_BITRATES.to_vec(),
+      last_error: self.inner.last_error.lock().unwrap().clone(),
+    })
+  }
+
+  /// Choose the bitrate the adapter is started at. Takes effect next time it's connected.
+  async fn set_bitrate(&self, bitrate: u32) -> anyhow::Result<()> {
+    if self.inner.running.load(std::sync::atomic::Ordering::Relaxed) {
+      anyhow::bail!("Disconnect from {} before changing its bitrate", self.inner.address);
+    }
+    if !GS_USB_BITRATES.contains(&bitrate) {
+      anyhow::bail!("Unsupported bitrate {}", bitrate);
+    }
+    bitrates().update(|b| b.insert(self.inner.address.clone(), bitrate));
+    Ok(())
+  }
+}
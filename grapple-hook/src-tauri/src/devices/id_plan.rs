@@ -0,0 +1,69 @@
+use std::collections::{HashMap, HashSet};
+
+/* Planning for reassigning many CAN IDs at once. Devices are moved one at a time, and a device is only ever moved
+   onto an ID nobody is using at that moment, so two devices never share an ID part way through. Swaps and longer
+   cycles are broken by parking one device on a temporary ID first. */
+
+/* 0x3F is the broadcast ID */
+pub const MAX_CAN_ID: u8 = 62;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct IdMove {
+  pub serial: u32,
+  pub from: u8,
+  pub to: u8,
+}
+
+/* current holds the ID of every device on the bus (including those not being moved), desired the new ID for each
+   device being moved. */
+pub fn plan(current: &HashMap<u32, u8>, desired: &HashMap<u32, u8>) -> anyhow::Result<Vec<IdMove>> {
+  let mut seen = HashMap::new();
+  for (serial, id) in desired {
+    if !current.contains_key(serial) {
+      anyhow::bail!("Device {:x} isn't on this bus", serial);
+    }
+    if *id > MAX_CAN_ID {
+      anyhow::bail!("CAN ID {} is out of range (0-{})", id, MAX_CAN_ID);
+    }
+    if let Some(other) = seen.insert(*id, *serial) {
+      anyhow::bail!("Devices {:x} and {:x} would both have CAN ID {}", other, serial, id);
+    }
+  }
+
+  for (serial, id) in current {
+    if !desired.contains_key(serial) {
+      if let Some(other) = seen.get(id) {
+        anyhow::bail!("CAN ID {} for {:x} is already used by {:x}, which isn't being moved", id, other, serial);
+      }
+    }
+  }
+
+  let mut ids = current.clone();
+  let mut pending: Vec<(u32, u8)> = desired.iter()
+    .filter(|(serial, id)| current.get(serial) != Some(id))
+    .map(|(serial, id)| (*serial, *id))
+    .collect();
+  pending.sort();
+
+  let mut moves = vec![];
+  while !pending.is_empty() {
+    let occupied: HashSet<u8> = ids.values().cloned().collect();
+
+    if let Some(idx) = pending.iter().position(|(_, to)| !occupied.contains(to)) {
+      let (serial, to) = pending.remove(idx);
+      moves.push(IdMove { serial, from: ids[&serial], to });
+      ids.insert(serial, to);
+      continue;
+    }
+
+    // Everything left is waiting on someone else, so there's a cycle. Park one device to break it.
+    let reserved: HashSet<u8> = occupied.iter().cloned().chain(desired.values().cloned()).collect();
+    let temp = (0..=MAX_CAN_ID).rev().find(|id| !reserved.contains(id))
+      .ok_or(anyhow::anyhow!("No free CAN ID to use while swapping devices"))?;
+    let serial = pending[0].0;
+    moves.push(IdMove { serial, from: ids[&serial], to: temp });
+    ids.insert(serial, temp);
+  }
+
+  Ok(moves)
+}
@@ -0,0 +1,35 @@
+use std::{sync::{Arc, RwLock}, time::Duration};
+
+use rand::Rng;
+
+/* Artificial latency, jitter and loss for a domain, for testing timeout handling, retries and firmware update pacing
+   without a flaky physical setup. Developer mode only. Applied to both directions. */
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct Impairment {
+  pub latency_ms: u64,
+  /* Extra delay, uniformly distributed in 0..=jitter_ms. Messages can be reordered as a result, as on a real bus. */
+  pub jitter_ms: u64,
+  /* Fraction of messages dropped, 0-1 */
+  pub drop_rate: f64,
+}
+
+pub type SharedImpairment = Arc<RwLock<Impairment>>;
+
+impl Impairment {
+  pub fn validate(&self) -> anyhow::Result<()> {
+    if !(0.0..=1.0).contains(&self.drop_rate) {
+      anyhow::bail!("Drop rate must be between 0 and 1");
+    }
+    Ok(())
+  }
+
+  /* None if the message should be dropped, otherwise how long to hold on to it */
+  pub fn sample(&self) -> Option<Duration> {
+    let mut rng = rand::thread_rng();
+    if self.drop_rate > 0.0 && rng.gen_bool(self.drop_rate) {
+      return None;
+    }
+    let jitter = if self.jitter_ms > 0 { rng.gen_range(0..=self.jitter_ms) } else { 0 };
+    Some(Duration::from_millis(self.latency_ms + jitter))
+  }
+}
@@ -1,149 +1,248 @@
-use grapple_frc_msgs::{grapple::{Request, errors::GrappleError, lasercan::{LaserCanMessage, LaserCanRoi, LaserCanMeasurement, LaserCanRangingMode, LaserCanTimingBudget}, GrappleDeviceMessage, TaggedGrappleMessage, device_info::GrappleModelId}, DEVICE_ID_BROADCAST, request_factory};
-use grapple_hook_macros::rpc;
-use tokio::sync::RwLock;
-
-use crate::{rpc::RpcBase, updates::{most_recent_update_available, LightReleaseResponse}};
-use super::{check_for_new_firmware_release_rpc_target, start_field_upgrade, Device, FirmwareValidatingDevice, GrappleDevice, GrappleDeviceRequest, GrappleDeviceResponse, HasFirmwareUpdateURLDevice, RootDevice, SendWrapper, SharedInfo, VersionGatedDevice};
-
-#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
-pub struct LaserCanStatus {
-  last_update: Option<LaserCanMeasurement>
-}
-
-pub struct LaserCan {
-  sender: SendWrapper,
-  info: SharedInfo,
-
-  grapple_device: GrappleDevice,
-
-  status: RwLock<LaserCanStatus>
-}
-
-impl LaserCan {
-  pub fn new(sender: SendWrapper, info: SharedInfo) -> Self {
-    Self {
-      sender: sender.clone(),
-      info: info.clone(),
-
-      grapple_device: GrappleDevice::new(sender.clone(), info.clone()),
-
-      status: RwLock::new(LaserCanStatus { last_update: None })
-    }
-  }
-}
-
-impl HasFirmwareUpdateURLDevice for LaserCan {
-  fn firmware_url() -> Option<String> {
-    Some("https://api.github.com/repos/GrappleRobotics/LaserCAN/releases".to_owned())
-  }
-}
-
-#[async_trait::async_trait]
-impl VersionGatedDevice for LaserCan {
-  fn validate_version(version: Option<String>) -> anyhow::Result<()> {
-    Self::require_version(version, ">= 2024.2.0, < 2024.3.0")
-  }
-  
-  async fn check_for_new_firmware_release(current_version: &str) -> Option<LightReleaseResponse>{
-    let current = semver::Version::parse(&current_version).ok()?;
-
-    most_recent_update_available(
-      "https://github.com/GrappleRobotics/LaserCAN",
-      |release| {
-        let vers = semver::Version::parse(&release.tag_name[1..]).ok();
-        if let Some(vers) = vers {
-          vers > current && Self::validate_version(Some(vers.to_string())).is_ok()
-        } else {
-          false
-        }
-      }
-    ).await.ok().flatten()
-  }
-}
-
-#[async_trait::async_trait]
-impl RootDevice for LaserCan {
-  fn device_class(&self) -> &'static str {
-    "LaserCAN"
-  }
-}
-
-#[async_trait::async_trait]
-impl Device for LaserCan {
-  async fn handle(&self, msg: TaggedGrappleMessage<'static>) -> anyhow::Result<()> {
-    if msg.device_id == DEVICE_ID_BROADCAST || Some(msg.device_id) == self.info.read().await.device_id {
-      match msg.clone().msg {
-        GrappleDeviceMessage::Broadcast(bcast) => match bcast {
-          _ => ()
-        },
-        GrappleDeviceMessage::DistanceSensor(sensor) => match sensor {
-          LaserCanMessage::Measurement(measurement) => {
-            self.status.write().await.last_update = Some(measurement);
-          },
-          _ => ()
-        },
-        _ => ()
-      }
-    }
-    
-    self.grapple_device.handle(msg.clone()).await?;
-    Ok(())
-  }
-}
-
-impl FirmwareValidatingDevice for LaserCan {
-  fn validate_firmware(_info: &super::DeviceInfo, buf: &[u8]) -> anyhow::Result<()> {
-    if &buf[0x150..0x154] == &[0xBEu8, 0xBAu8, 0xFEu8, 0xCAu8] && buf[0x15c] == (GrappleModelId::LaserCan as u8) {
-      Ok(())
-    } else {
-      anyhow::bail!("Invalid Firmware File. Are you sure this is the correct firmware?")
-    }
-  }
-}
-
-#[rpc]
-impl LaserCan {
-  async fn start_field_upgrade(&self) -> anyhow::Result<()> {
-    let serial = self.info.read().await.require_serial()?;
-    start_field_upgrade(&self.sender, serial).await
-  }
-
-  async fn set_range(&self, mode: LaserCanRangingMode) -> anyhow::Result<()> {
-    let id = self.info.read().await.require_device_id()?;
-    let (encode, decode) = request_factory!(data, GrappleDeviceMessage::DistanceSensor(LaserCanMessage::SetRange(data)));
-
-    let msg = self.sender.request(TaggedGrappleMessage::new(id, encode(mode)), 300, 5).await?;
-    decode(msg.msg)??;
-    Ok(())
-  }
-
-  async fn set_roi(&self, roi: LaserCanRoi) -> anyhow::Result<()> {
-    let id = self.info.read().await.require_device_id()?;
-    let (encode, decode) = request_factory!(data, GrappleDeviceMessage::DistanceSensor(LaserCanMessage::SetRoi(data)));
-
-    let msg = self.sender.request(TaggedGrappleMessage::new(id, encode(roi)), 300, 5).await?;
-    decode(msg.msg)??;
-    Ok(())
-  }
-
-  async fn set_timing_budget(&self, budget: LaserCanTimingBudget) -> anyhow::Result<()> {
-    let id = self.info.read().await.require_device_id()?;
-    let (encode, decode) = request_factory!(data, GrappleDeviceMessage::DistanceSensor(LaserCanMessage::SetTimingBudget(data)));
-
-    let msg = self.sender.request(TaggedGrappleMessage::new(id, encode(budget)), 300, 5).await?;
-    decode(msg.msg)??;
-    Ok(())
-  }
-
-  async fn grapple(&self, msg: GrappleDeviceRequest) -> anyhow::Result<GrappleDeviceResponse> {
-    self.grapple_device.rpc_process(msg).await
-  }
-
-  async fn status(&self) -> anyhow::Result<LaserCanStatus> {
-    Ok(self.status.read().await.clone())
-  }
-
-  async fn check_for_new_firmware(&self) -> anyhow::Result<Option<LightReleaseResponse>> {
-    check_for_new_firmware_release_rpc_target::<Self>(&self.info).await
-  }
-}
+use grapple_frc_msgs::{grapple::{Request, errors::GrappleError, lasercan::{LaserCanMessage, LaserCanRoi, LaserCanMeasurement, LaserCanRangingMode, LaserCanTimingBudget}, GrappleDeviceMessage, TaggedGrappleMessage, device_info::GrappleModelId}, DEVICE_ID_BROADCAST, request_factory};
+use grapple_hook_macros::rpc;
+use tokio::sync::RwLock;
+
+use crate::{errors::{coded, ErrorCode}, rpc::RpcBase, telemetry::telemetry, updates::{most_recent_update_available, LightReleaseResponse}};
+use super::bus_load::{lasercan_output_rates, lasercan_rate_hz, utilization, OutputRateOption};
+use super::dashboard::LaserCanDashboard;
+use super::lasercan_geometry::{DerivedGeometry, LaserCanGeometry};
+use super::feature_flags::flag_enabled;
+use super::metadata::metadata;
+use super::compatibility::{compatibility_report, require_feature, CompatibilityReport};
+use super::device_class::DeviceClass;
+use super::{check_for_new_firmware_release_rpc_target, start_field_upgrade, Device, FirmwareValidatingDevice, GrappleDevice, GrappleDeviceRequest, GrappleDeviceResponse, HasFirmwareUpdateURLDevice, RootDevice, SendWrapper, SharedInfo, VersionGatedDevice};
+
+#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct LaserCanStatus {
+  last_update: Option<LaserCanMeasurement>
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct LaserCanConfig {
+  pub mode: LaserCanRangingMode,
+  pub roi: LaserCanRoi,
+  pub budget: LaserCanTimingBudget,
+}
+
+pub struct LaserCan {
+  sender: SendWrapper,
+  info: SharedInfo,
+
+  grapple_device: GrappleDevice,
+
+  status: RwLock<LaserCanStatus>
+}
+
+impl LaserCan {
+  pub fn new(sender: SendWrapper, info: SharedInfo) -> Self {
+    Self {
+      sender: sender.clone(),
+      info: info.clone(),
+
+      grapple_device: GrappleDevice::new(sender.clone(), info.clone()),
+
+      status: RwLock::new(LaserCanStatus { last_update: None })
+    }
+  }
+}
+
+impl HasFirmwareUpdateURLDevice for LaserCan {
+  fn firmware_url() -> Option<String> {
+    Some("https://api.github.com/repos/GrappleRobotics/LaserCAN/releases".to_owned())
+  }
+}
+
+#[async_trait::async_trait]
+impl VersionGatedDevice for LaserCan {
+  fn validate_version(version: Option<String>) -> anyhow::Result<()> {
+    Self::require_version(version, ">= 2024.2.0, < 2024.3.0")
+  }
+  
+  async fn check_for_new_firmware_release(current_version: &str) -> Option<LightReleaseResponse>{
+    let current = semver::Version::parse(&current_version).ok()?;
+
+    most_recent_update_available(
+      "https://github.com/GrappleRobotics/LaserCAN",
+      |release| {
+        let vers = semver::Version::parse(&release.tag_name[1..]).ok();
+        if let Some(vers) = vers {
+          vers > current && Self::validate_version(Some(vers.to_string())).is_ok()
+        } else {
+          false
+        }
+      }
+    ).await.ok().flatten()
+  }
+}
+
+#[async_trait::async_trait]
+impl RootDevice for LaserCan {
+  fn device_class(&self) -> &'static str {
+    "LaserCAN"
+  }
+
+  async fn identify(&self) -> anyhow::Result<()> {
+    self.grapple_device.blink().await
+  }
+
+  async fn bus_load(&self) -> f64 {
+    match &self.status.read().await.last_update {
+      Some(measurement) => utilization(lasercan_rate_hz(&measurement.budget)),
+      None => 0.0
+    }
+  }
+}
+
+#[async_trait::async_trait]
+impl Device for LaserCan {
+  async fn handle(&self, msg: TaggedGrappleMessage<'static>) -> anyhow::Result<()> {
+    if msg.device_id == DEVICE_ID_BROADCAST || Some(msg.device_id) == self.info.read().await.device_id {
+      match msg.clone().msg {
+        GrappleDeviceMessage::Broadcast(bcast) => match bcast {
+          _ => ()
+        },
+        GrappleDeviceMessage::DistanceSensor(sensor) => match sensor {
+          LaserCanMessage::Measurement(measurement) => {
+            if let Some(serial) = self.info.read().await.serial {
+              let ts = self.sender.timestamp_ms();
+              telemetry().record(serial, "distance_mm", ts, measurement.distance_mm as f64);
+              telemetry().record(serial, "ambient", ts, measurement.ambient as f64);
+              telemetry().record(serial, "status", ts, measurement.status as f64);
+              if let (Some(geometry), 0, true) = (metadata().get(serial).geometry, measurement.status, flag_enabled(serial, "lasercan_geometry_telemetry")) {
+                let derived = geometry.derive(measurement.distance_mm as f64);
+                telemetry().record(serial, "horizontal_mm", ts, derived.horizontal_mm);
+                telemetry().record(serial, "object_height_mm", ts, derived.object_height_mm);
+              }
+            }
+            self.status.write().await.last_update = Some(measurement);
+          },
+          _ => ()
+        },
+        _ => ()
+      }
+    }
+    
+    self.grapple_device.handle(msg.clone()).await?;
+    Ok(())
+  }
+}
+
+impl FirmwareValidatingDevice for LaserCan {
+  fn validate_firmware(info: &super::DeviceInfo, buf: &[u8]) -> anyhow::Result<()> {
+    // An aliased model (see device_class) carries its own model byte in its firmware
+    let model_id = info.device_type.grapple_model_byte().unwrap_or(GrappleModelId::LaserCan as u8);
+    if &buf[0x150..0x154] == &[0xBEu8, 0xBAu8, 0xFEu8, 0xCAu8] && buf[0x15c] == model_id {
+      Ok(())
+    } else {
+      Err(coded(ErrorCode::InvalidFirmware, "Invalid Firmware File. Are you sure this is the correct firmware?"))
+    }
+  }
+}
+
+#[rpc]
+impl LaserCan {
+  async fn start_field_upgrade(&self) -> anyhow::Result<()> {
+    let serial = self.info.read().await.require_serial()?;
+    start_field_upgrade(&self.sender, serial).await
+  }
+
+  async fn set_range(&self, mode: LaserCanRangingMode) -> anyhow::Result<()> {
+    require_feature(DeviceClass::LaserCan, "set_range", &*self.info.read().await)?;
+    let id = self.info.read().await.require_device_id()?;
+    let (encode, decode) = request_factory!(data, GrappleDeviceMessage::DistanceSensor(LaserCanMessage::SetRange(data)));
+
+    let msg = self.sender.request(TaggedGrappleMessage::new(id, encode(mode)), 300, 5).await?;
+    decode(msg.msg)??;
+    Ok(())
+  }
+
+  async fn set_roi(&self, roi: LaserCanRoi) -> anyhow::Result<()> {
+    require_feature(DeviceClass::LaserCan, "set_roi", &*self.info.read().await)?;
+    let id = self.info.read().await.require_device_id()?;
+    let (encode, decode) = request_factory!(data, GrappleDeviceMessage::DistanceSensor(LaserCanMessage::SetRoi(data)));
+
+    let msg = self.sender.request(TaggedGrappleMessage::new(id, encode(roi)), 300, 5).await?;
+    decode(msg.msg)??;
+    Ok(())
+  }
+
+  async fn set_timing_budget(&self, budget: LaserCanTimingBudget) -> anyhow::Result<()> {
+    require_feature(DeviceClass::LaserCan, "set_timing_budget", &*self.info.read().await)?;
+    let id = self.info.read().await.require_device_id()?;
+    let (encode, decode) = request_factory!(data, GrappleDeviceMessage::DistanceSensor(LaserCanMessage::SetTimingBudget(data)));
+
+    let msg = self.sender.request(TaggedGrappleMessage::new(id, encode(budget)), 300, 5).await?;
+    decode(msg.msg)??;
+    Ok(())
+  }
+
+  async fn output_rates(&self) -> anyhow::Result<Vec<OutputRateOption>> {
+    Ok(lasercan_output_rates())
+  }
+
+  async fn grapple(&self, msg: GrappleDeviceRequest) -> anyhow::Result<GrappleDeviceResponse> {
+    self.grapple_device.rpc_process(msg).await
+  }
+
+  async fn status(&self) -> anyhow::Result<LaserCanStatus> {
+    Ok(self.status.read().await.clone())
+  }
+
+  async fn dashboard(&self) -> anyhow::Result<LaserCanDashboard> {
+    let info = self.info.read().await;
+    let geometry = info.serial.and_then(|serial| metadata().get(serial).geometry);
+    Ok(LaserCanDashboard::new(&info, self.status.read().await.last_update.as_ref(), geometry.as_ref()))
+  }
+
+  async fn geometry(&self) -> anyhow::Result<Option<LaserCanGeometry>> {
+    let serial = self.info.read().await.require_serial()?;
+    Ok(metadata().get(serial).geometry)
+  }
+
+  async fn set_geometry(&self, geometry: Option<LaserCanGeometry>) -> anyhow::Result<()> {
+    let serial = self.info.read().await.require_serial()?;
+    if let Some(geometry) = &geometry {
+      geometry.validate()?;
+    }
+    metadata().update(serial, |m| m.geometry = geometry);
+    Ok(())
+  }
+
+  /// None without a geometry profile or an in-range measurement
+  async fn derived_geometry(&self) -> anyhow::Result<Option<DerivedGeometry>> {
+    let serial = self.info.read().await.require_serial()?;
+    let geometry = metadata().get(serial).geometry;
+    Ok(match (geometry, &self.status.read().await.last_update) {
+      (Some(geometry), Some(measurement)) if measurement.status == 0 => Some(geometry.derive(measurement.distance_mm as f64)),
+      _ => None
+    })
+  }
+
+  async fn geometry_constants(&self, name: String) -> anyhow::Result<String> {
+    let serial = self.info.read().await.require_serial()?;
+    let geometry = metadata().get(serial).geometry.ok_or(anyhow::anyhow!("No geometry profile set for this LaserCAN"))?;
+    Ok(geometry.java_constants(&name))
+  }
+
+  /// The device only reports its settings in measurements, so this needs one to have been received
+  async fn config(&self) -> anyhow::Result<LaserCanConfig> {
+    match &self.status.read().await.last_update {
+      Some(m) => Ok(LaserCanConfig { mode: m.mode.clone(), roi: m.roi.clone(), budget: m.budget.clone() }),
+      None => anyhow::bail!("No measurement received from this LaserCAN yet, can't read its configuration")
+    }
+  }
+
+  async fn apply_config(&self, config: LaserCanConfig) -> anyhow::Result<()> {
+    self.set_range(config.mode).await?;
+    self.set_roi(config.roi).await?;
+    self.set_timing_budget(config.budget).await
+  }
+
+  async fn check_for_new_firmware(&self) -> anyhow::Result<Option<LightReleaseResponse>> {
+    check_for_new_firmware_release_rpc_target::<Self>(&self.info).await
+  }
+
+  async fn compatibility(&self) -> anyhow::Result<CompatibilityReport> {
+    Ok(compatibility_report(DeviceClass::LaserCan, &*self.info.read().await))
+  }
+}
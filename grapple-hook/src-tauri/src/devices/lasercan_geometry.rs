@@ -0,0 +1,44 @@
+/* How a LaserCAN is mounted on the robot, so distance readings can be turned into something more useful: how far
+   away the object is horizontally, and how high off the ground the point it hit is. */
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct LaserCanGeometry {
+  /* Height of the sensor above the floor */
+  pub mount_height_mm: f64,
+  /* Angle of the beam below horizontal. 0 looks straight ahead, 90 straight down. */
+  pub pitch_deg: f64,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct DerivedGeometry {
+  pub horizontal_mm: f64,
+  pub object_height_mm: f64,
+}
+
+impl LaserCanGeometry {
+  pub fn validate(&self) -> anyhow::Result<()> {
+    if !(-90.0..=90.0).contains(&self.pitch_deg) {
+      anyhow::bail!("Pitch must be between -90 and 90 degrees");
+    }
+    if self.mount_height_mm < 0.0 {
+      anyhow::bail!("Mounting height can't be negative");
+    }
+    Ok(())
+  }
+
+  pub fn derive(&self, distance_mm: f64) -> DerivedGeometry {
+    let pitch = self.pitch_deg.to_radians();
+    DerivedGeometry {
+      horizontal_mm: distance_mm * pitch.cos(),
+      object_height_mm: self.mount_height_mm - distance_mm * pitch.sin(),
+    }
+  }
+
+  /* The same profile as Java constants, to paste into robot code */
+  pub fn java_constants(&self, name: &str) -> String {
+    let prefix = name.to_uppercase().replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+    format!(
+      "public static final double {p}_MOUNT_HEIGHT_MM = {:.1};\npublic static final double {p}_PITCH_DEG = {:.2};\n",
+      self.mount_height_mm, self.pitch_deg, p = prefix
+    )
+  }
+}
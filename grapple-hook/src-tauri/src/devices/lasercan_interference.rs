@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use crate::telemetry::TelemetrySample;
+
+/* LaserCANs facing overlapping areas can pick up each other's emitters, which shows up as noise spikes on both sensors
+   at the same moments. We look for that by binning each sensor's distance history onto a common time base, marking the
+   bins where the reading jumps unusually far, and checking how often two sensors jump together. */
+
+/* Histories are compared over this bin width, wide enough to line up sensors running at different budgets */
+const BIN_MS: i64 = 100;
+/* A jump is a spike if it's this many median absolute deviations bigger than usual */
+const SPIKE_MADS: f64 = 5.0;
+/* Below this many shared spikes, coincidences are too likely to be chance */
+const MIN_SHARED_SPIKES: usize = 5;
+/* Fraction of either sensor's spikes that must line up with the other's */
+const MIN_OVERLAP: f64 = 0.4;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct InterferenceFinding {
+  pub serials: (u32, u32),
+  pub shared_spikes: usize,
+  /* Shared spikes as a fraction of the smaller sensor's spike count */
+  pub overlap: f64,
+  pub suggestions: Vec<String>,
+}
+
+pub struct SensorHistory {
+  pub serial: u32,
+  pub samples: Vec<TelemetrySample>,
+  /* As reported by the device's config, e.g. "TB33ms" */
+  pub budget: Option<String>,
+}
+
+fn median(values: &mut Vec<f64>) -> f64 {
+  values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+  match values.len() {
+    0 => 0.0,
+    n if n % 2 == 0 => (values[n / 2 - 1] + values[n / 2]) / 2.0,
+    n => values[n / 2]
+  }
+}
+
+/* Bins (by start time) in which the sensor's reading jumped unusually far from the bin before */
+fn spike_bins(samples: &[TelemetrySample]) -> Vec<i64> {
+  let mut bins: Vec<(i64, f64, usize)> = vec![];
+  for sample in samples {
+    let bin = sample.timestamp_ms - sample.timestamp_ms.rem_euclid(BIN_MS);
+    match bins.last_mut() {
+      Some((start, sum, count)) if *start == bin => { *sum += sample.value; *count += 1; },
+      _ => bins.push((bin, sample.value, 1))
+    }
+  }
+
+  let jumps: Vec<(i64, f64)> = bins.windows(2)
+    .filter(|w| w[1].0 - w[0].0 == BIN_MS)
+    .map(|w| (w[1].0, (w[1].1 / w[1].2 as f64 - w[0].1 / w[0].2 as f64).abs()))
+    .collect();
+
+  let mut magnitudes = jumps.iter().map(|(_, j)| *j).collect::<Vec<_>>();
+  let med = median(&mut magnitudes);
+  let mut deviations = magnitudes.iter().map(|m| (m - med).abs()).collect::<Vec<_>>();
+  // A perfectly steady sensor has no spread at all, so don't let the threshold collapse to zero
+  let mad = median(&mut deviations).max(1.0);
+
+  jumps.into_iter().filter(|(_, j)| *j > med + SPIKE_MADS * mad).map(|(bin, _)| bin).collect()
+}
+
+fn suggestions(a: &SensorHistory, b: &SensorHistory) -> Vec<String> {
+  let mut suggestions = vec![];
+  match (&a.budget, &b.budget) {
+    (Some(x), Some(y)) if x == y => suggestions.push(format!(
+      "Both sensors use the same timing budget ({}), so their measurements line up. Give one of them a different budget (e.g. {:x} to TB33ms and {:x} to TB20ms) to stagger them.", x, a.serial, b.serial
+    )),
+    _ => suggestions.push("Try different timing budgets on the two sensors so they measure at different moments.".to_owned())
+  }
+  suggestions.push(format!("Narrow the region of interest on {:x} and {:x} so their fields of view no longer overlap.", a.serial, b.serial));
+  suggestions.push("If possible, angle the sensors slightly away from each other, or add a baffle between them.".to_owned());
+  suggestions
+}
+
+pub fn analyse(sensors: &[SensorHistory]) -> Vec<InterferenceFinding> {
+  let spikes: HashMap<u32, Vec<i64>> = sensors.iter().map(|s| (s.serial, spike_bins(&s.samples))).collect();
+
+  let mut findings = vec![];
+  for (i, a) in sensors.iter().enumerate() {
+    for b in &sensors[i + 1..] {
+      let (sa, sb) = (&spikes[&a.serial], &spikes[&b.serial]);
+      // Spikes within one bin of each other count as shared, since the sensors' measurements aren't synchronised
+      let shared = sa.iter().filter(|t| sb.iter().any(|u| (*t - u).abs() <= BIN_MS)).count();
+      let smaller = sa.len().min(sb.len());
+      if smaller == 0 || shared < MIN_SHARED_SPIKES {
+        continue;
+      }
+
+      let overlap = (shared as f64 / smaller as f64).min(1.0);
+      if overlap >= MIN_OVERLAP {
+        findings.push(InterferenceFinding { serials: (a.serial, b.serial), shared_spikes: shared, overlap, suggestions: suggestions(a, b) });
+      }
+    }
+  }
+  findings.sort_by(|a, b| b.overlap.partial_cmp(&a.overlap).unwrap_or(std::cmp::Ordering::Equal));
+  findings
+}
@@ -0,0 +1,84 @@
+use std::sync::Mutex;
+
+use super::clock::Clock;
+
+/* Weight given to each new round trip measurement */
+const LATENCY_SMOOTHING: f64 = 0.2;
+/* Anything slower than this is a lost probe, not a measurement */
+const MAX_PROBE_RTT_MS: f64 = 1000.0;
+/* A bridge timestamp this far behind where we'd expect it means the bridge's clock started again (e.g. the roboRIO
+   rebooted), not that a frame was slow */
+const BRIDGE_CLOCK_RESET_MS: i64 = 1000;
+
+/* Estimates the one-way latency of a domain's transport from the round trip between our periodic enumerate
+   request and the first response to it. Used to back-date telemetry from remote domains (e.g. over the RIO bridge)
+   so it lines up with robot logs.
+
+   Round trips are measured to when a frame arrived, which is stamped before it waits in the domain's inbox. Frames
+   from a bridge also carry the time the bridge received them off the bus, which is better still: it leaves out the
+   TCP connection's jitter too. The bridge's clock isn't ours, so it's mapped onto ours by the smallest difference seen
+   between the two, i.e. that of the frame that got here fastest. */
+pub struct LatencyEstimator {
+  clock: Clock,
+  probe_sent: Mutex<Option<i64>>,
+  one_way_ms: Mutex<Option<f64>>,
+  bridge_offset_ms: Mutex<Option<i64>>,
+  /* Arrival time of the message the domain is processing, so anything timestamped while handling it uses that */
+  processing: Mutex<Option<i64>>,
+}
+
+impl LatencyEstimator {
+  pub fn new(clock: Clock) -> Self {
+    Self { clock, probe_sent: Mutex::new(None), one_way_ms: Mutex::new(None), bridge_offset_ms: Mutex::new(None), processing: Mutex::new(None) }
+  }
+
+  /* When a frame received just now got to us, by our clock. Call it before the frame is queued. */
+  pub fn arrival_ms(&self, bridge_timestamp_ms: Option<u32>) -> i64 {
+    let now = self.clock.now_ms();
+    let Some(bridge_ms) = bridge_timestamp_ms.map(|t| t as i64) else { return now };
+
+    let mut offset = self.bridge_offset_ms.lock().unwrap();
+    let sample = now - bridge_ms;
+    *offset = Some(match *offset {
+      Some(prev) if sample < prev + BRIDGE_CLOCK_RESET_MS => prev.min(sample),
+      _ => sample
+    });
+    bridge_ms + offset.unwrap()
+  }
+
+  pub fn on_probe_sent(&self) {
+    *self.probe_sent.lock().unwrap() = Some(self.clock.now_ms());
+  }
+
+  pub fn on_probe_reply(&self, arrival_ms: i64) {
+    if let Some(sent) = self.probe_sent.lock().unwrap().take() {
+      let rtt_ms = (arrival_ms - sent).max(0) as f64;
+      if rtt_ms < MAX_PROBE_RTT_MS {
+        let mut one_way = self.one_way_ms.lock().unwrap();
+        *one_way = Some(match *one_way {
+          Some(prev) => prev + LATENCY_SMOOTHING * (rtt_ms / 2.0 - prev),
+          None => rtt_ms / 2.0
+        });
+      }
+    }
+  }
+
+  pub fn one_way_ms(&self) -> Option<f64> {
+    *self.one_way_ms.lock().unwrap()
+  }
+
+  pub fn begin_processing(&self, arrival_ms: i64) {
+    *self.processing.lock().unwrap() = Some(arrival_ms);
+  }
+
+  pub fn end_processing(&self) {
+    *self.processing.lock().unwrap() = None;
+  }
+
+  /* Best guess at when the message being processed (or, outside of that, one received just now) was actually sent by
+     the device */
+  pub fn timestamp_ms(&self) -> i64 {
+    let arrival_ms = self.processing.lock().unwrap().unwrap_or_else(|| self.clock.now_ms());
+    arrival_ms - self.one_way_ms().unwrap_or(0.0) as i64
+  }
+}
@@ -0,0 +1,93 @@
+use std::{collections::HashMap, sync::{Arc, OnceLock}, time::Duration};
+
+use tokio::sync::{Mutex, OwnedMutexGuard, OwnedSemaphorePermit, Semaphore};
+
+use super::clock::Clock;
+
+/* Caps on request/reply RPCs waiting on a device response at once. Fire-and-forget traffic (including firmware
+   update chunks) doesn't go through here, so a burst of UI requests can't hold it up. */
+pub const GLOBAL_MAX_OUTSTANDING_REQUESTS: usize = 32;
+pub const DOMAIN_MAX_OUTSTANDING_REQUESTS: usize = 8;
+
+fn global_limit() -> Arc<Semaphore> {
+  static GLOBAL: OnceLock<Arc<Semaphore>> = OnceLock::new();
+  GLOBAL.get_or_init(|| Arc::new(Semaphore::new(GLOBAL_MAX_OUTSTANDING_REQUESTS))).clone()
+}
+
+pub struct RequestPermit {
+  _domain: OwnedSemaphorePermit,
+  _global: OwnedSemaphorePermit,
+}
+
+/* tokio's Semaphore hands out permits in FIFO order, which gives us fairness between waiting requests for free. */
+pub struct RequestLimiter {
+  domain: Arc<Semaphore>,
+}
+
+impl RequestLimiter {
+  pub fn new() -> Self {
+    Self { domain: Arc::new(Semaphore::new(DOMAIN_MAX_OUTSTANDING_REQUESTS)) }
+  }
+
+  pub async fn acquire(&self) -> anyhow::Result<RequestPermit> {
+    // Always domain first, then global, so two domains can't deadlock each other.
+    let domain = self.domain.clone().acquire_owned().await?;
+    let global = global_limit().acquire_owned().await?;
+    Ok(RequestPermit { _domain: domain, _global: global })
+  }
+
+  pub fn in_flight(&self) -> usize {
+    DOMAIN_MAX_OUTSTANDING_REQUESTS - self.domain.available_permits()
+  }
+}
+
+/* Minimum time between configuration writes to one device. Most settings go straight to the device's flash, so a
+   burst of changes (e.g. from dragging a slider) wears it out, and some firmware locks up if writes arrive faster than
+   it can commit them. */
+const WRITE_INTERVALS_MS: &[(&str, u64)] = &[
+  ("LaserCAN", 100),
+  ("MitoCANdria", 100),
+  ("FlexiCAN", 100),
+  ("SpiderLAN", 250),
+];
+const DEFAULT_WRITE_INTERVAL_MS: u64 = 100;
+
+pub fn write_interval(device_class: &str) -> Duration {
+  let ms = WRITE_INTERVALS_MS.iter().find(|(class, _)| *class == device_class).map(|(_, ms)| *ms).unwrap_or(DEFAULT_WRITE_INTERVAL_MS);
+  Duration::from_millis(ms)
+}
+
+/* Held for the duration of a configuration write. The next write to the same device waits until the interval has
+   passed since this one finished. */
+pub struct WritePermit {
+  clock: Clock,
+  last_write_ms: OwnedMutexGuard<Option<i64>>,
+}
+
+impl Drop for WritePermit {
+  fn drop(&mut self) {
+    *self.last_write_ms = Some(self.clock.now_ms());
+  }
+}
+
+/* Queues configuration writes per device (by serial). Like the Semaphore, tokio's Mutex is FIFO, so queued writes
+   go out in the order they were made. */
+pub struct WriteThrottle {
+  clock: Clock,
+  devices: std::sync::Mutex<HashMap<u32, Arc<Mutex<Option<i64>>>>>,
+}
+
+impl WriteThrottle {
+  pub fn new(clock: Clock) -> Self {
+    Self { clock, devices: std::sync::Mutex::new(HashMap::new()) }
+  }
+
+  pub async fn acquire(&self, serial: u32, device_class: &str) -> WritePermit {
+    let slot = self.devices.lock().unwrap().entry(serial).or_default().clone();
+    let last_write_ms = slot.lock_owned().await;
+    if let Some(last) = *last_write_ms {
+      self.clock.sleep_until(last + write_interval(device_class).as_millis() as i64).await;
+    }
+    WritePermit { clock: self.clock.clone(), last_write_ms }
+  }
+}
@@ -0,0 +1,78 @@
+use std::sync::{atomic::{AtomicI64, Ordering}, Mutex};
+
+/* Whether the transport behind a domain (the USB adapter, or the bridge to the roboRIO) is actually alive. Devices
+   just stop appearing when it isn't, so we keep track of when we last heard anything at all on the domain, and the
+   last error the transport reported. Any frame counts as traffic, even one we can't decode, since it still proves the
+   link is up. */
+
+/* With no traffic for this long the link is degraded, and after DOWN_AFTER_MS it's down. Enumeration goes out every
+   500ms and the adapter itself answers it, so a healthy link is never quiet for long. Both stretch while the app is
+   hidden, since we enumerate less often. */
+const DEGRADED_AFTER_MS: i64 = 2000;
+const DOWN_AFTER_MS: i64 = 10_000;
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq, Eq)]
+pub enum LinkState {
+  Connected,
+  /* Quiet for a while, or the transport has reported an error since we last heard from it */
+  Degraded,
+  Down,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct TransportError {
+  pub timestamp_ms: i64,
+  pub message: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct DomainStatus {
+  pub state: LinkState,
+  /* None if nothing has been received since the domain was created */
+  pub last_traffic_ms: Option<i64>,
+  pub last_error: Option<TransportError>,
+}
+
+pub struct LinkHealth {
+  created_ms: i64,
+  /* 0 until the first frame */
+  last_traffic_ms: AtomicI64,
+  last_error: Mutex<Option<TransportError>>,
+}
+
+impl LinkHealth {
+  pub fn new(now_ms: i64) -> Self {
+    Self { created_ms: now_ms, last_traffic_ms: AtomicI64::new(0), last_error: Mutex::new(None) }
+  }
+
+  pub fn on_traffic(&self, now_ms: i64) {
+    self.last_traffic_ms.store(now_ms, Ordering::Relaxed);
+  }
+
+  pub fn on_error(&self, now_ms: i64, message: String) {
+    *self.last_error.lock().unwrap() = Some(TransportError { timestamp_ms: now_ms, message });
+  }
+
+  pub fn status(&self, now_ms: i64, rate_divisor: u32) -> DomainStatus {
+    let last_traffic_ms = Some(self.last_traffic_ms.load(Ordering::Relaxed)).filter(|t| *t != 0);
+    let last_error = self.last_error.lock().unwrap().clone();
+
+    // A domain that has never heard anything is judged from when it was created, so it isn't down the moment it appears
+    let quiet_for = now_ms - last_traffic_ms.unwrap_or(self.created_ms);
+    let erred_since_traffic = match (&last_error, last_traffic_ms) {
+      (Some(e), Some(t)) => e.timestamp_ms >= t,
+      (Some(_), None) => true,
+      (None, _) => false
+    };
+
+    let state = if quiet_for > DOWN_AFTER_MS * rate_divisor as i64 {
+      LinkState::Down
+    } else if erred_since_traffic || quiet_for > DEGRADED_AFTER_MS * rate_divisor as i64 {
+      LinkState::Degraded
+    } else {
+      LinkState::Connected
+    };
+
+    DomainStatus { state, last_traffic_ms, last_error }
+  }
+}
@@ -0,0 +1,194 @@
+use std::{collections::{HashMap, VecDeque}, sync::{Mutex, OnceLock}};
+
+use crate::persistence::Persisted;
+use super::lasercan_geometry::LaserCanGeometry;
+use super::reminders::Reminder;
+
+/* An RPC call against a device with its parameters filled in, e.g. "Set intake threshold 150mm" */
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct QuickAction {
+  pub id: String,
+  pub label: String,
+  /* The device RPC request, as the frontend would send it ({ "method": ..., "data": ... }) */
+  pub request: serde_json::Value,
+}
+
+/* Everything we remember about a device on the host side, keyed by serial so it follows the device between domains */
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct DeviceMetadata {
+  #[serde(default)]
+  pub nickname: Option<String>,
+  #[serde(default)]
+  pub notes: String,
+  #[serde(default)]
+  pub quick_actions: Vec<QuickAction>,
+  /* LaserCAN only */
+  #[serde(default)]
+  pub geometry: Option<LaserCanGeometry>,
+  /* Overrides only, see feature_flags */
+  #[serde(default)]
+  pub feature_flags: HashMap<String, bool>,
+  #[serde(default)]
+  pub reminders: Vec<Reminder>,
+  /* Shown at the top of the device list */
+  #[serde(default)]
+  pub pinned: bool,
+  /* Free-form groupings, e.g. "intake" or "test bench" */
+  #[serde(default)]
+  pub tags: Vec<String>,
+}
+
+/* How many edits can be undone. Only kept for the session, it isn't saved. */
+const UNDO_HISTORY: usize = 50;
+
+/* One undoable edit, with the metadata of every device it touched (None if it had none) from before and after */
+#[derive(Clone)]
+struct MetadataEdit {
+  description: String,
+  before: HashMap<u32, Option<DeviceMetadata>>,
+  after: HashMap<u32, Option<DeviceMetadata>>,
+}
+
+impl MetadataEdit {
+  fn merge(&mut self, other: MetadataEdit) {
+    for (serial, before) in other.before {
+      self.before.entry(serial).or_insert(before);
+    }
+    self.after.extend(other.after);
+  }
+}
+
+/* e.g. "nickname, tags of 0x1a2b" or "nickname of 3 devices" */
+fn describe(before: &HashMap<u32, Option<DeviceMetadata>>, after: &HashMap<u32, Option<DeviceMetadata>>) -> String {
+  let mut fields: Vec<String> = vec![];
+  for (serial, after) in after {
+    let before = serde_json::to_value(before.get(serial).cloned().flatten().unwrap_or_default()).unwrap_or_default();
+    let after = serde_json::to_value(after.clone().unwrap_or_default()).unwrap_or_default();
+    if let (Some(before), Some(after)) = (before.as_object(), after.as_object()) {
+      for (field, value) in after {
+        if before.get(field) != Some(value) && !fields.contains(field) {
+          fields.push(field.clone());
+        }
+      }
+    }
+  }
+  fields.sort();
+
+  let devices = match after.keys().collect::<Vec<_>>()[..] {
+    [serial] => format!("0x{:x}", serial),
+    _ => format!("{} devices", after.len())
+  };
+  format!("{} of {}", fields.join(", "), devices)
+}
+
+thread_local! {
+  /* Set while inside MetadataStore::batch, so everything it changes is undone in one go */
+  static BATCH: std::cell::RefCell<Option<MetadataEdit>> = const { std::cell::RefCell::new(None) };
+}
+
+pub struct MetadataStore {
+  devices: Persisted<HashMap<u32, DeviceMetadata>>,
+  undo: Mutex<VecDeque<MetadataEdit>>,
+  redo: Mutex<Vec<MetadataEdit>>,
+}
+
+impl MetadataStore {
+  pub fn get(&self, serial: u32) -> DeviceMetadata {
+    self.devices.read(|d| d.get(&serial).cloned()).unwrap_or_default()
+  }
+
+  pub fn all(&self) -> HashMap<u32, DeviceMetadata> {
+    self.devices.get()
+  }
+
+  pub fn update<R>(&self, serial: u32, f: impl FnOnce(&mut DeviceMetadata) -> R) -> R {
+    let (r, before, after) = self.devices.update(|d| {
+      let before = d.get(&serial).cloned();
+      let r = f(d.entry(serial).or_default());
+      (r, before, d.get(&serial).cloned())
+    });
+
+    // Nothing changed (an entry being created with the defaults included), so there's nothing to undo
+    if serde_json::to_value(before.clone().unwrap_or_default()).ok() == serde_json::to_value(after.clone().unwrap_or_default()).ok() {
+      return r;
+    }
+    let edit = MetadataEdit { description: String::new(), before: HashMap::from([(serial, before)]), after: HashMap::from([(serial, after)]) };
+    let batched = BATCH.with(|b| match b.borrow_mut().as_mut() {
+      Some(batch) => { batch.merge(edit.clone()); true },
+      None => false
+    });
+    if !batched {
+      self.record(edit);
+    }
+    r
+  }
+
+  /* Make several updates that are undone (and redone) together, e.g. naming every device from a template */
+  pub fn batch<R>(&self, f: impl FnOnce() -> R) -> R {
+    BATCH.with(|b| *b.borrow_mut() = Some(MetadataEdit { description: String::new(), before: HashMap::new(), after: HashMap::new() }));
+    let r = f();
+    if let Some(edit) = BATCH.with(|b| b.borrow_mut().take()).filter(|e| !e.after.is_empty()) {
+      self.record(edit);
+    }
+    r
+  }
+
+  fn record(&self, mut edit: MetadataEdit) {
+    edit.description = describe(&edit.before, &edit.after);
+    let mut undo = self.undo.lock().unwrap();
+    undo.push_back(edit);
+    if undo.len() > UNDO_HISTORY {
+      undo.pop_front();
+    }
+    // A new edit replaces whatever had been undone
+    self.redo.lock().unwrap().clear();
+  }
+
+  fn restore(&self, state: &HashMap<u32, Option<DeviceMetadata>>) {
+    self.devices.update(|d| {
+      for (serial, meta) in state {
+        match meta {
+          Some(meta) => { d.insert(*serial, meta.clone()); },
+          None => { d.remove(serial); }
+        }
+      }
+    });
+  }
+
+  /* Undo the most recent edit, returning what it was */
+  pub fn undo(&self) -> Option<String> {
+    let edit = self.undo.lock().unwrap().pop_back()?;
+    self.restore(&edit.before);
+    let description = edit.description.clone();
+    self.redo.lock().unwrap().push(edit);
+    Some(description)
+  }
+
+  /* Redo the most recently undone edit, returning what it was */
+  pub fn redo(&self) -> Option<String> {
+    let edit = self.redo.lock().unwrap().pop()?;
+    self.restore(&edit.after);
+    let description = edit.description.clone();
+    self.undo.lock().unwrap().push_back(edit);
+    Some(description)
+  }
+
+  /* What can be undone and redone, most recent first */
+  pub fn history(&self) -> MetadataHistory {
+    MetadataHistory {
+      undo: self.undo.lock().unwrap().iter().rev().map(|e| e.description.clone()).collect(),
+      redo: self.redo.lock().unwrap().iter().rev().map(|e| e.description.clone()).collect(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct MetadataHistory {
+  pub undo: Vec<String>,
+  pub redo: Vec<String>,
+}
+
+pub fn metadata() -> &'static MetadataStore {
+  static STORE: OnceLock<MetadataStore> = OnceLock::new();
+  STORE.get_or_init(|| MetadataStore { devices: Persisted::load("device_metadata"), undo: Mutex::new(VecDeque::new()), redo: Mutex::new(vec![]) })
+}
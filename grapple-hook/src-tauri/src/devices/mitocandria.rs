@@ -1,10 +1,16 @@
-use std::thread::current;
+use std::{collections::HashSet, thread::current};
 
-use grapple_frc_msgs::{grapple::{device_info::GrappleModelId, errors::GrappleError, mitocandria::{self, MitocandriaAdjustableChannelRequest, MitocandriaSwitchableChannelRequest}, GrappleDeviceMessage, Request, TaggedGrappleMessage}, request_factory, DEVICE_ID_BROADCAST};
+use grapple_frc_msgs::{grapple::{device_info::GrappleModelId, errors::GrappleError, mitocandria::{self, MitocandriaAdjustableChannelRequest, MitocandriaChannelStatus, MitocandriaSwitchableChannelRequest}, GrappleDeviceMessage, Request, TaggedGrappleMessage}, request_factory, DEVICE_ID_BROADCAST};
 use grapple_hook_macros::rpc;
 use tokio::sync::RwLock;
 
-use crate::{rpc::RpcBase, updates::{most_recent_update_available, LightReleaseResponse}};
+use crate::{errors::{coded, ErrorCode}, events::{events, EventSeverity}, rpc::RpcBase, telemetry::telemetry, updates::{most_recent_update_available, LightReleaseResponse}};
+use super::dashboard::MitocandriaDashboard;
+use super::compatibility::{compatibility_report, require_feature, CompatibilityReport};
+use super::device_class::DeviceClass;
+use super::feature_flags::flag_enabled;
+use super::mitocandria_faults::{FaultHistory, FaultRecord, MitocandriaFault, SETPOINT_TOLERANCE};
+use super::rail_monitor::{RailAlert, RailAlertConfig, RailMonitor};
 use super::{check_for_new_firmware_release_rpc_target, start_field_upgrade, Device, FirmwareValidatingDevice, GrappleDevice, GrappleDeviceRequest, GrappleDeviceResponse, HasFirmwareUpdateURLDevice, RootDevice, SendWrapper, SharedInfo, VersionGatedDevice};
 
 #[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
@@ -12,13 +18,24 @@ pub struct MitocandriaStatus {
   last_update: Option<mitocandria::MitocandriaStatusFrame>
 }
 
+/* Only settings, not output state: pasting a config shouldn't switch rails on or off */
+#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct MitocandriaConfig {
+  pub adjustable_setpoints: Vec<MitocandriaAdjustableChannelRequest>,
+  pub rail_alerts: RailAlertConfig,
+}
+
 pub struct Mitocandria {
   sender: SendWrapper,
   info: SharedInfo,
 
   grapple_device: GrappleDevice,
 
-  status: RwLock<MitocandriaStatus>
+  status: RwLock<MitocandriaStatus>,
+  rails: std::sync::Mutex<RailMonitor>,
+  faults: std::sync::Mutex<FaultHistory>,
+  /* Rails currently under their setpoint, so the fault is only recorded when it starts */
+  low_rails: std::sync::Mutex<HashSet<usize>>,
 }
 
 impl Mitocandria {
@@ -29,9 +46,18 @@ impl Mitocandria {
 
       grapple_device: GrappleDevice::new(sender.clone(), info.clone()),
 
-      status: RwLock::new(MitocandriaStatus { last_update: None })
+      status: RwLock::new(MitocandriaStatus { last_update: None }),
+      rails: std::sync::Mutex::new(RailMonitor::new()),
+      faults: std::sync::Mutex::new(FaultHistory::new()),
+      low_rails: std::sync::Mutex::new(HashSet::new()),
     }
   }
+
+  fn raise_fault(&self, serial: u32, timestamp_ms: i64, kind: &str, fault: MitocandriaFault) {
+    let record = self.faults.lock().unwrap().record(timestamp_ms, fault);
+    let explanation = record.explanation;
+    events().emit(Some(serial), kind, EventSeverity::Warning, format!("[{}] {}. {}", explanation.code, explanation.summary, explanation.remediation));
+  }
 }
 
 impl HasFirmwareUpdateURLDevice for Mitocandria {
@@ -79,6 +105,38 @@ impl RootDevice for Mitocandria {
   fn device_class(&self) -> &'static str {
     "MitoCANdria"
   }
+
+  async fn identify(&self) -> anyhow::Result<()> {
+    self.grapple_device.blink().await
+  }
+
+  async fn freeze_outputs(&self) -> anyhow::Result<usize> {
+    let id = self.info.read().await.require_device_id()?;
+    let channels = match &self.status.read().await.last_update {
+      Some(status) => status.channels.iter().enumerate()
+        .filter(|(_, c)| !matches!(c, MitocandriaChannelStatus::NonSwitchable { .. }))
+        .map(|(i, _)| i as u8)
+        .collect::<Vec<_>>(),
+      None => anyhow::bail!("No status received from this MitoCANdria yet, can't tell which channels to switch off")
+    };
+
+    // Every channel is switched off regardless of what the last status said, in case it's stale. Deliberately not
+    // feature-gated: in an emergency we'd rather try.
+    let results = futures::future::join_all(channels.iter().map(|&channel| async move {
+      let (encode, decode) = request_factory!(data, GrappleDeviceMessage::PowerDistributionModule(
+        mitocandria::MitocandriaMessage::ChannelRequest(mitocandria::MitocandriaChannelRequest::SetSwitchableChannel(data))
+      ));
+      let msg = self.sender.request(TaggedGrappleMessage::new(id, encode(MitocandriaSwitchableChannelRequest { channel, enabled: false })), 300, 5).await?;
+      decode(msg.msg)??;
+      anyhow::Ok(())
+    })).await;
+
+    let failed = results.iter().filter(|r| r.is_err()).count();
+    if failed > 0 {
+      anyhow::bail!("{} of {} channels didn't confirm they were switched off", failed, channels.len());
+    }
+    Ok(channels.len())
+  }
 }
 
 #[async_trait::async_trait]
@@ -91,6 +149,41 @@ impl Device for Mitocandria {
         },
         GrappleDeviceMessage::PowerDistributionModule(pdm) => match pdm {
           mitocandria::MitocandriaMessage::StatusFrame(status) => {
+            if let Some(serial) = self.info.read().await.serial {
+              let ts = self.sender.timestamp_ms();
+              for (i, channel) in status.channels.iter().enumerate() {
+                match channel {
+                  MitocandriaChannelStatus::Switchable { current, .. } | MitocandriaChannelStatus::NonSwitchable { current } => {
+                    telemetry().record(serial, &format!("channel{}_current", i), ts, *current as f64);
+                  },
+                  MitocandriaChannelStatus::Adjustable { current, voltage, enabled, voltage_setpoint } => {
+                    telemetry().record(serial, &format!("channel{}_current", i), ts, *current as f64);
+                    telemetry().record(serial, &format!("channel{}_voltage", i), ts, *voltage as f64);
+
+                    // Voltages are reported in mV
+                    let alert = match flag_enabled(serial, "mitocandria_rail_alerts") {
+                      true => self.rails.lock().unwrap().observe(i, ts, *voltage as f64 / 1000.0),
+                      false => None
+                    };
+                    match alert {
+                      Some(RailAlert::Sag { dv_dt }) => self.raise_fault(serial, ts, "rail_sag", MitocandriaFault::RailSag { channel: i, dv_dt }),
+                      Some(RailAlert::Oscillation { reversals }) => self.raise_fault(serial, ts, "rail_oscillation", MitocandriaFault::RailOscillation { channel: i, reversals }),
+                      None => ()
+                    }
+
+                    let (voltage_mv, setpoint_mv) = (*voltage as f64, *voltage_setpoint as f64);
+                    let low = *enabled && voltage_mv < setpoint_mv * SETPOINT_TOLERANCE;
+                    let newly_low = match low {
+                      true => self.low_rails.lock().unwrap().insert(i),
+                      false => { self.low_rails.lock().unwrap().remove(&i); false }
+                    };
+                    if newly_low {
+                      self.raise_fault(serial, ts, "rail_under_setpoint", MitocandriaFault::SetpointNotReached { channel: i, voltage_mv, setpoint_mv });
+                    }
+                  }
+                }
+              }
+            }
             self.status.write().await.last_update = Some(status);
           },
           _ => ()
@@ -105,11 +198,13 @@ impl Device for Mitocandria {
 }
 
 impl FirmwareValidatingDevice for Mitocandria {
-  fn validate_firmware(_info: &super::DeviceInfo, buf: &[u8]) -> anyhow::Result<()> {
-    if &buf[0x200..0x204] == &[0xBEu8, 0xBAu8, 0xFEu8, 0xCAu8] && buf[0x20c] == (GrappleModelId::MitoCANdria as u8) {
+  fn validate_firmware(info: &super::DeviceInfo, buf: &[u8]) -> anyhow::Result<()> {
+    // An aliased model (see device_class) carries its own model byte in its firmware
+    let model_id = info.device_type.grapple_model_byte().unwrap_or(GrappleModelId::MitoCANdria as u8);
+    if &buf[0x200..0x204] == &[0xBEu8, 0xBAu8, 0xFEu8, 0xCAu8] && buf[0x20c] == model_id {
       Ok(())
     } else {
-      anyhow::bail!("Invalid Firmware File. Are you sure this is the correct firmware?")
+      Err(coded(ErrorCode::InvalidFirmware, "Invalid Firmware File. Are you sure this is the correct firmware?"))
     }
   }
 }
@@ -122,6 +217,7 @@ impl Mitocandria {
   }
 
   async fn set_switchable_channel(&self, channel: MitocandriaSwitchableChannelRequest) -> anyhow::Result<()> {
+    require_feature(DeviceClass::MitoCANdria, "set_switchable_channel", &*self.info.read().await)?;
     let id = self.info.read().await.require_device_id()?;
     let (encode, decode) = request_factory!(data, GrappleDeviceMessage::PowerDistributionModule(
       mitocandria::MitocandriaMessage::ChannelRequest(mitocandria::MitocandriaChannelRequest::SetSwitchableChannel(data))
@@ -133,6 +229,7 @@ impl Mitocandria {
   }
 
   async fn set_adjustable_channel(&self, channel: MitocandriaAdjustableChannelRequest) -> anyhow::Result<()> {
+    require_feature(DeviceClass::MitoCANdria, "set_adjustable_channel", &*self.info.read().await)?;
     let id = self.info.read().await.require_device_id()?;
     let (encode, decode) = request_factory!(data, GrappleDeviceMessage::PowerDistributionModule(
       mitocandria::MitocandriaMessage::ChannelRequest(mitocandria::MitocandriaChannelRequest::SetAdjustableChannel(data))
@@ -151,7 +248,54 @@ impl Mitocandria {
     Ok(self.status.read().await.clone())
   }
 
+  async fn dashboard(&self) -> anyhow::Result<MitocandriaDashboard> {
+    let info = self.info.read().await;
+    Ok(MitocandriaDashboard::new(&info, self.status.read().await.last_update.as_ref()))
+  }
+
   async fn check_for_new_firmware(&self) -> anyhow::Result<Option<LightReleaseResponse>> {
     check_for_new_firmware_release_rpc_target::<Self>(&self.info).await
   }
+
+  async fn compatibility(&self) -> anyhow::Result<CompatibilityReport> {
+    Ok(compatibility_report(DeviceClass::MitoCANdria, &*self.info.read().await))
+  }
+
+  async fn config(&self) -> anyhow::Result<MitocandriaConfig> {
+    let adjustable_setpoints = match &self.status.read().await.last_update {
+      Some(status) => status.channels.iter().enumerate().filter_map(|(i, c)| match c {
+        MitocandriaChannelStatus::Adjustable { voltage_setpoint, .. } => Some(MitocandriaAdjustableChannelRequest { channel: i as u8, voltage: *voltage_setpoint }),
+        _ => None
+      }).collect(),
+      None => anyhow::bail!("No status received from this MitoCANdria yet, can't read its configuration")
+    };
+    Ok(MitocandriaConfig { adjustable_setpoints, rail_alerts: self.rails.lock().unwrap().config.clone() })
+  }
+
+  async fn apply_config(&self, config: MitocandriaConfig) -> anyhow::Result<()> {
+    for setpoint in config.adjustable_setpoints {
+      self.set_adjustable_channel(setpoint).await?;
+    }
+    self.rails.lock().unwrap().config = config.rail_alerts;
+    Ok(())
+  }
+
+  /// Oldest first
+  async fn fault_history(&self) -> anyhow::Result<Vec<FaultRecord>> {
+    Ok(self.faults.lock().unwrap().records())
+  }
+
+  async fn clear_fault_history(&self) -> anyhow::Result<()> {
+    self.faults.lock().unwrap().clear();
+    Ok(())
+  }
+
+  async fn rail_alert_config(&self) -> anyhow::Result<RailAlertConfig> {
+    Ok(self.rails.lock().unwrap().config.clone())
+  }
+
+  async fn set_rail_alert_config(&self, config: RailAlertConfig) -> anyhow::Result<()> {
+    self.rails.lock().unwrap().config = config;
+    Ok(())
+  }
 }
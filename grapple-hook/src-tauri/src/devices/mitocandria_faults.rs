@@ -0,0 +1,89 @@
+use std::collections::VecDeque;
+
+/* Faults we can pick up on from a MitoCANdria's status, decoded into what probably caused them and what to do next.
+   Codes are stable so they can be searched for in docs and support threads. */
+const FAULT_HISTORY: usize = 100;
+
+/* An enabled adjustable rail sitting this far under its setpoint isn't keeping up with its load */
+pub const SETPOINT_TOLERANCE: f64 = 0.85;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+#[serde(tag = "kind")]
+pub enum MitocandriaFault {
+  RailSag { channel: usize, dv_dt: f64 },
+  RailOscillation { channel: usize, reversals: usize },
+  SetpointNotReached { channel: usize, voltage_mv: f64, setpoint_mv: f64 },
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct FaultExplanation {
+  pub code: String,
+  pub summary: String,
+  pub cause: String,
+  pub remediation: String,
+}
+
+impl MitocandriaFault {
+  pub fn code(&self) -> &'static str {
+    match self {
+      MitocandriaFault::RailSag { .. } => "MITO-001",
+      MitocandriaFault::RailOscillation { .. } => "MITO-002",
+      MitocandriaFault::SetpointNotReached { .. } => "MITO-003",
+    }
+  }
+
+  pub fn explain(&self) -> FaultExplanation {
+    let (summary, cause, remediation) = match self {
+      MitocandriaFault::RailSag { channel, dv_dt } => (
+        format!("Channel {} voltage is dropping quickly ({:.1}V/s)", channel, dv_dt),
+        "The load on this rail suddenly drew far more current than usual, or the rail is being shorted.".to_owned(),
+        "Check the cable to whatever this channel powers (often a Limelight or coprocessor) for pinched or frayed insulation, and that the load isn't failing.".to_owned(),
+      ),
+      MitocandriaFault::RailOscillation { channel, reversals } => (
+        format!("Channel {} voltage is oscillating ({} swings in the last second)", channel, reversals),
+        "The regulator is struggling to hold a steady voltage, usually because of an intermittent connection or a load that cycles rapidly.".to_owned(),
+        "Reseat the connectors on this channel and check for loose crimps. If it persists with the load unplugged, report it to Grapple.".to_owned(),
+      ),
+      MitocandriaFault::SetpointNotReached { channel, voltage_mv, setpoint_mv } => (
+        format!("Channel {} is at {:.2}V, well under its {:.2}V setpoint", channel, voltage_mv / 1000.0, setpoint_mv / 1000.0),
+        "The rail can't supply enough current for its load, or the battery voltage is too low to reach the setpoint.".to_owned(),
+        "Check the battery is charged, and that the load on this channel is within the MitoCANdria's rating. A short in the cable will also cause this.".to_owned(),
+      ),
+    };
+    FaultExplanation { code: self.code().to_owned(), summary, cause, remediation }
+  }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct FaultRecord {
+  pub timestamp_ms: i64,
+  pub fault: MitocandriaFault,
+  pub explanation: FaultExplanation,
+}
+
+pub struct FaultHistory {
+  records: VecDeque<FaultRecord>,
+}
+
+impl FaultHistory {
+  pub fn new() -> Self {
+    Self { records: VecDeque::new() }
+  }
+
+  pub fn record(&mut self, timestamp_ms: i64, fault: MitocandriaFault) -> FaultRecord {
+    let record = FaultRecord { timestamp_ms, explanation: fault.explain(), fault };
+    self.records.push_back(record.clone());
+    while self.records.len() > FAULT_HISTORY {
+      self.records.pop_front();
+    }
+    record
+  }
+
+  pub fn records(&self) -> Vec<FaultRecord> {
+    self.records.iter().cloned().collect()
+  }
+
+  pub fn clear(&mut self) {
+    self.records.clear();
+  }
+}
@@ -1,398 +1,685 @@
-pub mod device_manager;
-pub mod provider;
-pub mod provider_manager;
-pub mod roborio;
-pub mod lasercan;
-pub mod flexican;
-pub mod mitocandria;
-pub mod generic_usb;
-// pub mod powerful_panda;
-
-use std::{borrow::Cow, collections::HashMap, io::{Cursor, Read}, marker::PhantomData, sync::Arc, time::Duration};
-
-use bounded_static::IntoBoundedStatic;
-use grapple_frc_msgs::{Validate, grapple::{device_info::GrappleModelId, GrappleDeviceMessage, firmware::GrappleFirmwareMessage, TaggedGrappleMessage, GrappleMessageId}, DEVICE_ID_BROADCAST, binmarshal::{MarshalUpdate, AsymmetricCow, Payload}, MessageId};
-use grapple_hook_macros::rpc;
-use log::info;
-use semver::{Version, VersionReq};
-use serde::{Serialize, Deserialize};
-use tokio::sync::{mpsc, RwLock, Notify, oneshot};
-use uuid::Uuid;
-
-use crate::{rpc::RpcBase, updates::LightReleaseResponse};
-
-use self::device_manager::RepliesWaiting;
-
-#[derive(Clone)]
-pub struct SendWrapper(mpsc::Sender<TaggedGrappleMessage<'static>>, RepliesWaiting);
-
-impl SendWrapper {
-  async fn send(&self, msg: TaggedGrappleMessage<'static>) -> anyhow::Result<()> {
-    msg.msg.validate()?;
-    self.0.send(msg).await?;
-    Ok(())
-  }
-
-  async fn request_inner(&self, msg: TaggedGrappleMessage<'static>, reply_id: GrappleMessageId, timeout_ms: usize) -> anyhow::Result<TaggedGrappleMessage> {
-    let complement_id_u32: u32 = Into::<MessageId>::into(reply_id).into();
-
-    let uuid = Uuid::new_v4();
-
-    let (tx, rx) = oneshot::channel();
-    {
-      let mut hm = self.1.write().await;
-      if !hm.contains_key(&complement_id_u32) {
-        hm.insert(complement_id_u32, HashMap::new());
-      }
-      hm.get_mut(&complement_id_u32).unwrap().insert(uuid, tx);
-      drop(hm);
-    }
-    self.send(msg).await?;
-
-    match tokio::time::timeout(Duration::from_millis(timeout_ms as u64), rx).await {
-      Ok(result) => result.map_err(|e| anyhow::anyhow!(e)),
-      Err(_) => {
-        // Timed out - remove it from the replies waiting
-        let mut hm = self.1.write().await;
-        hm.get_mut(&complement_id_u32).map(|x| x.remove(&uuid));
-        anyhow::bail!("Timed out waiting for response")
-      },
-    }
-  }
-
-  async fn request(&self, mut msg: TaggedGrappleMessage<'static>, timeout_ms: usize, retry: usize) -> anyhow::Result<TaggedGrappleMessage> {
-    let mut id = GrappleMessageId::new(msg.device_id);
-    msg.msg.update(&mut id);
-
-    let mut complement_id = id.clone();
-    complement_id.ack_flag = true;
-    
-    match self.request_inner(msg.clone(), complement_id, timeout_ms).await {
-      Ok(x) => Ok(x),
-      Err(_) if retry >= 1 => Box::pin(self.request(msg, timeout_ms, retry - 1)).await,
-      Err(e) => Err(e)
-    }
-  }
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
-pub enum DeviceType {
-  Grapple(GrappleModelId),
-  RoboRIO,
-  Unknown
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
-pub struct DeviceInfo {
-  pub device_type: DeviceType,
-  pub firmware_version: Option<String>,
-  pub serial: Option<u32>,
-  pub is_dfu: bool,
-  pub is_dfu_in_progress: bool,
-  pub name: Option<String>,
-  pub device_id: Option<u8>
-}
-
-impl DeviceInfo {
-  pub fn require_serial(&self) -> anyhow::Result<u32> {
-    return self.serial.ok_or(anyhow::anyhow!("No Serial Number for Device!"))
-  }
-
-  pub fn require_device_id(&self) -> anyhow::Result<u8> {
-    return self.device_id.ok_or(anyhow::anyhow!("No Device ID for Device!"))
-  }
-}
-
-#[async_trait::async_trait]
-pub trait Device : RpcBase {
-  async fn handle(&self, _msg: TaggedGrappleMessage<'static>) -> anyhow::Result<()> { Ok(()) }
-}
-
-#[async_trait::async_trait]
-pub trait RootDevice : Device {
-  fn device_class(&self) -> &'static str;
-}
-
-pub type SharedInfo = Arc<RwLock<DeviceInfo>>;
-
-/* GRAPPLE DEVICE */
-
-pub struct GrappleDevice {
-  sender: SendWrapper,
-  info: SharedInfo
-}
-
-impl GrappleDevice {
-  pub fn new(sender: SendWrapper, info: SharedInfo) -> Self {
-    Self { sender, info }
-  }
-}
-
-#[rpc]
-impl GrappleDevice {
-  async fn blink(&self) -> anyhow::Result<()> {
-    self.sender.send(TaggedGrappleMessage::new(
-      DEVICE_ID_BROADCAST,
-      grapple_frc_msgs::grapple::GrappleDeviceMessage::Broadcast(
-        grapple_frc_msgs::grapple::GrappleBroadcastMessage::DeviceInfo(grapple_frc_msgs::grapple::device_info::GrappleDeviceInfo::Blink {
-          serial: self.info.read().await.require_serial()?
-        })
-      )
-    )).await
-  }
-
-  async fn set_id(&self, id: u8) -> anyhow::Result<()>  {
-    self.sender.send(TaggedGrappleMessage::new(
-      DEVICE_ID_BROADCAST,
-      grapple_frc_msgs::grapple::GrappleDeviceMessage::Broadcast(
-        grapple_frc_msgs::grapple::GrappleBroadcastMessage::DeviceInfo(grapple_frc_msgs::grapple::device_info::GrappleDeviceInfo::SetId {
-          serial: self.info.read().await.require_serial()?, new_id: id
-        })
-      )
-    )).await
-  }
-
-  async fn set_name(&self, name: String) -> anyhow::Result<()>  {
-    self.sender.send(TaggedGrappleMessage::new(
-      DEVICE_ID_BROADCAST,
-      grapple_frc_msgs::grapple::GrappleDeviceMessage::Broadcast(
-        grapple_frc_msgs::grapple::GrappleBroadcastMessage::DeviceInfo(grapple_frc_msgs::grapple::device_info::GrappleDeviceInfo::SetName {
-          serial: self.info.read().await.require_serial()?,
-          name: Cow::<str>::Owned(name).into()
-        })
-      )
-    )).await
-  }
-
-  async fn commit_to_eeprom(&self) -> anyhow::Result<()>  {
-    self.sender.send(TaggedGrappleMessage::new(
-      DEVICE_ID_BROADCAST,
-      grapple_frc_msgs::grapple::GrappleDeviceMessage::Broadcast(
-        grapple_frc_msgs::grapple::GrappleBroadcastMessage::DeviceInfo(grapple_frc_msgs::grapple::device_info::GrappleDeviceInfo::CommitConfig {
-          serial: self.info.read().await.require_serial()?,
-        })
-      )
-    )).await
-  }
-}
-
-impl Device for GrappleDevice {}
-
-/* FIRMWARE UPGRADE DEVICE */
-
-pub struct FirmwareUpgradeDevice<T: FirmwareValidatingDevice> {
-  sender: SendWrapper,
-  info: SharedInfo,
-  progress: Arc<RwLock<Option<f64>>>,
-  ack: Arc<Notify>,
-  chunk_size: usize,
-  _t: PhantomData<T>
-}
-
-impl<T: FirmwareValidatingDevice> FirmwareUpgradeDevice<T> {
-  pub fn new(sender: SendWrapper, info: SharedInfo, chunk_size: usize) -> Self {
-    Self { sender, info, progress: Arc::new(RwLock::new(None)), ack: Arc::new(Notify::new()), chunk_size, _t: PhantomData }
-  }
-
-  pub async fn field_upgrade_worker(sender: SendWrapper, id: u8, data: &[u8], progress: Arc<RwLock<Option<f64>>>, ack: Arc<Notify>, chunk_size: usize) -> anyhow::Result<()> {
-    *progress.write().await = Some(0.0);
-    let chunks = data.chunks(chunk_size);
-    let nchunks = chunks.len();
-    for (i, chunk) in chunks.enumerate() {
-      info!("Chunk {} (len: {})", i, chunk.len());
-
-      sender.send(TaggedGrappleMessage::new(
-        id,
-        GrappleDeviceMessage::FirmwareUpdate(
-          GrappleFirmwareMessage::UpdatePart(AsymmetricCow(Cow::<Payload>::Borrowed(Into::into(chunk))).into_static())
-        )
-      )).await?;
-      tokio::time::timeout(Duration::from_millis(1000), ack.notified()).await?;
-      *progress.write().await = Some((i + 1) as f64 / (nchunks as f64) * 100.0);
-    }
-
-    *progress.write().await = Some(100.0); 
-    sender.send(TaggedGrappleMessage::new(
-      id,
-      GrappleDeviceMessage::FirmwareUpdate(
-        GrappleFirmwareMessage::UpdateDone
-      )
-    )).await?;
-    *progress.write().await = None;
-
-    Ok(())
-  }
-}
-
-pub async fn start_field_upgrade(sender: &SendWrapper, serial: u32) -> anyhow::Result<()> {
-  sender.send(TaggedGrappleMessage::new(
-    DEVICE_ID_BROADCAST,
-    GrappleDeviceMessage::FirmwareUpdate(
-      GrappleFirmwareMessage::StartFieldUpgrade { serial }
-    )
-  )).await
-}
-
-fn maybe_unpack_firmware(data: &[u8]) -> anyhow::Result<Vec<u8>> {
-  let mut archive = zip::ZipArchive::new(Cursor::new(data))?;
-
-  // TODO: Use https://github.com/GrappleRobotics/bundle/tree/master/grapple-bundle-lib
-
-  let mut index_file = archive.by_name("index")?;
-  let mut index_content = String::new();
-  index_file.read_to_string(&mut index_content)?;
-  let index: serde_json::Value = serde_json::from_str(&index_content)?;
-  drop(index_file);
-
-  let mut firmware_f = archive.by_name(index.get("firmware_update_bin").map(|x| x.as_str()).flatten().ok_or(anyhow::anyhow!("Invalid Index"))?)?;
-  let mut v = vec![];
-  firmware_f.read_to_end(&mut v)?;
-  Ok(v)
-}
-
-#[rpc]
-impl<T: FirmwareValidatingDevice + HasFirmwareUpdateURLDevice + Send + Sync> FirmwareUpgradeDevice<T> {
-  async fn do_field_upgrade(&self, data: Vec<u8>) -> anyhow::Result<()> {
-    let buf = match maybe_unpack_firmware(&data) {
-      Ok(buf) => buf,
-      Err(_) => {
-        let d = data;
-        <T>::validate_firmware(&*self.info.read().await, &d).map_err(|e| anyhow::anyhow!("Not a valid firmware file: {}", e))?;
-        d
-      }
-    };
-
-    let sender = self.sender.clone();
-    let progress = self.progress.clone();
-    let id = self.info.read().await.require_device_id()?;
-    let notify = self.ack.clone();
-    let chunk_size = self.chunk_size;
-
-    tokio::task::spawn(async move {
-      let d = buf;
-      Self::field_upgrade_worker(sender, id, &d[..], progress, notify, chunk_size).await.ok();
-    });
-    Ok(())
-  }
-
-  async fn progress(&self) -> anyhow::Result<Option<f64>> {
-    Ok(self.progress.read().await.clone())
-  }
-
-  async fn get_firmware_url(&self) -> anyhow::Result<Option<String>> {
-    Ok(T::firmware_url())
-  }
-}
-
-impl<T: FirmwareValidatingDevice + HasFirmwareUpdateURLDevice + Send + Sync> RootDevice for FirmwareUpgradeDevice<T> {
-  fn device_class(&self) ->  &'static str {
-    "GrappleFirmwareUpgrade"
-  }
-}
-
-#[async_trait::async_trait]
-impl<T: FirmwareValidatingDevice + HasFirmwareUpdateURLDevice + Send + Sync> Device for FirmwareUpgradeDevice<T> {
-  async fn handle(&self, msg: TaggedGrappleMessage<'static>) -> anyhow::Result<()> {
-    if msg.device_id == DEVICE_ID_BROADCAST || Some(msg.device_id) == self.info.read().await.device_id {
-      match msg.clone().msg {
-        GrappleDeviceMessage::FirmwareUpdate(fw) => match fw {
-          GrappleFirmwareMessage::UpdatePartAck => {
-            self.ack.notify_one();
-          },
-          _ => ()
-        },
-        _ => ()
-      }
-    }
-    Ok(())
-  }
-}
-
-pub trait HasFirmwareUpdateURLDevice {
-  fn firmware_url() -> Option<String>;
-}
-
-pub trait FirmwareValidatingDevice {
-  fn validate_firmware(info: &DeviceInfo, buf: &[u8]) -> anyhow::Result<()>;
-}
-
-#[async_trait::async_trait]
-pub trait VersionGatedDevice : HasFirmwareUpdateURLDevice + RootDevice + Sized + Sync + 'static {
-  fn validate_version(version: Option<String>) -> anyhow::Result<()>;
-  async fn check_for_new_firmware_release(current_version: &str) -> Option<LightReleaseResponse>;
-
-  fn require_version(version: Option<String>, req: &str) -> anyhow::Result<()> {
-    if let Some(v) = version {
-      let v = Version::parse(&v)?;
-      if !VersionReq::parse(req)?.matches(&v) {
-        anyhow::bail!("Invalid version: {}, expected: {}", v, req);
-      }
-    }
-    Ok(())
-  }
-
-  async fn maybe_gate<F: FnOnce(SendWrapper, Arc<RwLock<DeviceInfo>>) -> Self + Send>(send: SendWrapper, info: Arc<RwLock<DeviceInfo>>, create_fn: F) -> Box<dyn RootDevice + Send + Sync + 'static> {
-    match Self::validate_version(info.clone().read().await.firmware_version.clone()) {
-      Ok(_) => Box::new(create_fn(send, info)),
-      Err(e) => Box::new(OldVersionDevice::new(send, info, format!("{}", e), Self::firmware_url()))
-    }
-  }
-}
-
-async fn check_for_new_firmware_release_rpc_target<T: VersionGatedDevice>(info: &SharedInfo) -> anyhow::Result<Option<LightReleaseResponse>> {
-  let fw = &info.read().await.firmware_version;
-  match fw {
-    Some(x) => Ok(T::check_for_new_firmware_release(&x).await),
-    None => anyhow::bail!("Can't check for new releases - device has no version")
-  }
-}
-
-pub struct OldVersionDevice {
-  grapple_device: GrappleDevice,
-  error: String,
-  firmware_url: Option<String>
-}
-
-impl OldVersionDevice {
-  pub fn new(sender: SendWrapper, info: SharedInfo, error: String, firmware_url: Option<String>) -> Self {
-    Self {
-      grapple_device: GrappleDevice::new(sender.clone(), info.clone()),
-      error, firmware_url
-    }
-  }
-}
-
-#[async_trait::async_trait]
-impl Device for OldVersionDevice {
-  async fn handle(&self, msg: TaggedGrappleMessage<'static>) -> anyhow::Result<()> {
-    self.grapple_device.handle(msg.clone()).await?;
-    Ok(())
-  }
-}
-
-#[async_trait::async_trait]
-impl RootDevice for OldVersionDevice {
-  fn device_class(&self) -> &'static str {
-    "OldVersionDevice"
-  }
-}
-
-#[rpc]
-impl OldVersionDevice {
-  async fn start_field_upgrade(&self) -> anyhow::Result<()> {
-    let serial = self.grapple_device.info.read().await.require_serial()?;
-    start_field_upgrade(&self.grapple_device.sender, serial).await
-  }
-
-  async fn get_error(&self) -> anyhow::Result<String> {
-    Ok(self.error.clone())
-  }
-
-  async fn get_firmware_url(&self) -> anyhow::Result<Option<String>> {
-    Ok(self.firmware_url.clone())
-  }
-
-  async fn grapple(&self, msg: GrappleDeviceRequest) -> anyhow::Result<GrappleDeviceResponse> {
-    self.grapple_device.rpc_process(msg).await
-  }
-}
+pub mod activity;
+pub mod attention;
+pub mod automation;
+pub mod bom;
+pub mod bus_load;
+pub mod can_conflicts;
+pub mod can_id;
+pub mod changes;
+pub mod capabilities;
+pub mod checklist;
+pub mod clock;
+pub mod chunked;
+pub mod compatibility;
+pub mod config_clipboard;
+pub mod dashboard;
+pub mod device_class;
+pub mod device_manager;
+pub mod dfu_identify;
+pub mod domain_settings;
+pub mod provider;
+pub mod provider_manager;
+pub mod remote_assist;
+pub mod quarantine;
+pub mod rail_monitor;
+pub mod registry;
+pub mod reminders;
+pub mod reply_routing;
+pub mod reports;
+pub mod roborio;
+pub mod search;
+pub mod session;
+pub mod lasercan;
+pub mod lasercan_geometry;
+pub mod lasercan_interference;
+pub mod feature_flags;
+pub mod firmware_catalog;
+pub mod firmware_file;
+pub mod firmware_update;
+pub mod fixtures;
+pub mod id_plan;
+pub mod impairment;
+pub mod flexican;
+pub mod foreign;
+pub mod metadata;
+pub mod pairing;
+pub mod pcan;
+pub mod poller;
+pub mod mitocandria;
+pub mod mitocandria_faults;
+pub mod generic_grapple;
+pub mod generic_usb;
+pub mod gs_usb;
+pub mod simulator;
+pub mod slcan;
+#[cfg(target_os = "linux")]
+pub mod socketcan;
+pub mod soak;
+pub mod spiderlan;
+pub mod status_summary;
+pub mod templates;
+pub mod transcript;
+pub mod tutorial;
+pub mod unknown_model;
+pub mod latency;
+pub mod limits;
+pub mod link_health;
+pub mod usb_permissions;
+pub mod watchdog;
+// PowerfulPanda can't be enumerated or talked to with the grapple-frc-msgs we build against (2024.4.3, see Cargo.lock):
+//  - grapple::device_info::GrappleModelId only has LaserCan, SpiderLan, FlexiCAN and MitoCANdria (generated into
+//    src/schema.d.ts as `GrappleModelId`), so a PowerfulPanda's EnumerateResponse wouldn't even parse.
+//  - Of grapple::GrappleDeviceMessage's device families, the ones GrappleHook handles are DistanceSensor (LaserCAN) and
+//    PowerDistributionModule (MitoCANdria). Without a model ID, any PowerfulPanda settings messages would have no device
+//    to be sent to, which is also why FlexiCanStatus has its powerful_panda::StatusFrame commented out.
+// Bring this back once the protocol crate has them.
+// pub mod powerful_panda;
+
+use std::{borrow::Cow, io::{Cursor, Read}, marker::PhantomData, path::Path, sync::Arc, time::Duration};
+
+use bounded_static::IntoBoundedStatic;
+use grapple_frc_msgs::{Validate, grapple::{device_info::GrappleModelId, GrappleDeviceMessage, firmware::GrappleFirmwareMessage, TaggedGrappleMessage, GrappleMessageId}, DEVICE_ID_BROADCAST, binmarshal::{MarshalUpdate, AsymmetricCow, Payload}, MessageId};
+use grapple_hook_macros::rpc;
+use log::{info, warn};
+use semver::{Version, VersionReq};
+use serde::{Serialize, Deserialize};
+use tokio::sync::{mpsc, RwLock};
+
+use crate::{errors::{coded, ErrorCode}, firmware_library::firmware_library, operations::{journal, OperationKind}, rpc::RpcBase, updates::LightReleaseResponse};
+
+use self::chunked::{AckTracker, FlashStats, MIN_ACK_TIMEOUT_MS};
+use self::device_class::resolve_device_class;
+use self::firmware_catalog::firmware_catalog;
+use self::firmware_file::{FirmwareFile, FirmwareSource};
+use self::device_manager::RepliesWaiting;
+use self::reply_routing::reply_policy;
+use self::impairment::SharedImpairment;
+use self::latency::LatencyEstimator;
+use self::limits::RequestLimiter;
+use self::transcript::{Direction, Transcript};
+
+#[derive(Clone)]
+pub struct SendWrapper {
+  sender: mpsc::Sender<TaggedGrappleMessage<'static>>,
+  replies: RepliesWaiting,
+  latency: Arc<LatencyEstimator>,
+  limiter: Arc<RequestLimiter>,
+  impairment: SharedImpairment,
+  transcript: Arc<Transcript>,
+}
+
+impl SendWrapper {
+  pub fn new(sender: mpsc::Sender<TaggedGrappleMessage<'static>>, replies: RepliesWaiting, latency: Arc<LatencyEstimator>, limiter: Arc<RequestLimiter>, impairment: SharedImpairment, transcript: Arc<Transcript>) -> Self {
+    Self { sender, replies, latency, limiter, impairment, transcript }
+  }
+
+  /* Latency-compensated timestamp for telemetry received on this domain */
+  pub fn timestamp_ms(&self) -> i64 {
+    self.latency.timestamp_ms()
+  }
+
+  async fn send(&self, msg: TaggedGrappleMessage<'static>) -> anyhow::Result<()> {
+    msg.msg.validate()?;
+    self.transcript.record(Direction::Sent, self.latency.timestamp_ms(), &msg);
+    let hold = self.impairment.read().unwrap().sample();
+    match hold {
+      None => (),
+      Some(delay) if delay.is_zero() => self.sender.send(msg).await?,
+      Some(delay) => {
+        let sender = self.sender.clone();
+        tokio::task::spawn(async move {
+          tokio::time::sleep(delay).await;
+          sender.send(msg).await.ok();
+        });
+      }
+    }
+    Ok(())
+  }
+
+  async fn request_inner(&self, msg: TaggedGrappleMessage<'static>, reply_id: GrappleMessageId, timeout_ms: usize) -> anyhow::Result<TaggedGrappleMessage> {
+    let _permit = tokio::time::timeout(Duration::from_millis(timeout_ms as u64), self.limiter.acquire()).await
+      .map_err(|_| coded(ErrorCode::TooManyRequests, "Too many requests in flight, try again shortly"))??;
+
+    let complement_id_u32: u32 = Into::<MessageId>::into(reply_id).into();
+
+    // Registered before sending so a quick reply can't beat us to it. Dropped (and so unregistered) on any early return.
+    let pending = self.replies.register(complement_id_u32, msg.device_id, reply_policy(&msg.msg), Duration::from_millis(timeout_ms as u64));
+    self.send(msg).await?;
+    pending.wait().await
+  }
+
+  async fn request(&self, mut msg: TaggedGrappleMessage<'static>, timeout_ms: usize, retry: usize) -> anyhow::Result<TaggedGrappleMessage> {
+    let mut id = GrappleMessageId::new(msg.device_id);
+    msg.msg.update(&mut id);
+
+    let mut complement_id = id.clone();
+    complement_id.ack_flag = true;
+    
+    match self.request_inner(msg.clone(), complement_id, timeout_ms).await {
+      Ok(x) => Ok(x),
+      Err(_) if retry >= 1 => Box::pin(self.request(msg, timeout_ms, retry - 1)).await,
+      Err(e) => Err(e)
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub enum DeviceType {
+  Grapple(GrappleModelId),
+  /* A Grapple device whose model ID grapple-frc-msgs doesn't know (see unknown_model), by its raw model byte */
+  UnknownGrapple(u8),
+  RoboRIO,
+  Unknown
+}
+
+impl DeviceType {
+  /* The model byte a Grapple device enumerated with, known to grapple-frc-msgs or not */
+  pub fn grapple_model_byte(&self) -> Option<u8> {
+    match self {
+      DeviceType::Grapple(model_id) => Some(model_id.clone() as u8),
+      DeviceType::UnknownGrapple(model_id) => Some(*model_id),
+      _ => None
+    }
+  }
+}
+
+/* Where a device is in its lifecycle, so the UI doesn't have to piece it together from flags */
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub enum DeviceState {
+  /* Just appeared on the bus */
+  #[default]
+  Discovered,
+  /* Firmware is too old (or too new) for this version of GrappleHook */
+  IncompatibleFirmware(String),
+  Ready,
+  /* In the bootloader, waiting for or receiving new firmware */
+  Updating,
+  /* Left the bootloader and hasn't come back up yet */
+  Rebooting,
+  /* Stopped responding, and will be removed shortly if it doesn't come back */
+  Lost,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct DeviceInfo {
+  pub device_type: DeviceType,
+  pub firmware_version: Option<String>,
+  pub serial: Option<u32>,
+  pub is_dfu: bool,
+  pub is_dfu_in_progress: bool,
+  pub name: Option<String>,
+  pub device_id: Option<u8>,
+
+  /* Host-side annotations, filled in by the DeviceManager when listing devices */
+  #[serde(default)]
+  pub activity: Vec<u32>,
+  #[serde(default)]
+  pub state: DeviceState,
+  #[serde(default)]
+  pub pinned: bool,
+  #[serde(default)]
+  pub tags: Vec<String>,
+  /* From the domain's settings */
+  #[serde(default)]
+  pub domain_display_name: Option<String>,
+  #[serde(default)]
+  pub domain_color: Option<String>,
+}
+
+impl DeviceInfo {
+  pub fn require_serial(&self) -> anyhow::Result<u32> {
+    return self.serial.ok_or(coded(ErrorCode::MissingDeviceInfo, "No Serial Number for Device!"))
+  }
+
+  pub fn require_device_id(&self) -> anyhow::Result<u8> {
+    return self.device_id.ok_or(coded(ErrorCode::MissingDeviceInfo, "No Device ID for Device!"))
+  }
+}
+
+#[async_trait::async_trait]
+pub trait Device : RpcBase {
+  async fn handle(&self, _msg: TaggedGrappleMessage<'static>) -> anyhow::Result<()> { Ok(()) }
+}
+
+#[async_trait::async_trait]
+pub trait RootDevice : Device {
+  fn device_class(&self) -> &'static str;
+
+  /* Why this device is being held back from its normal driver, if it is */
+  fn gated_reason(&self) -> Option<String> { None }
+
+  /* Estimated fraction of the CAN bus used by this device's periodic output */
+  async fn bus_load(&self) -> f64 { 0.0 }
+
+  /* Emergency stop: command every output this device controls to its safe (off) state. Returns how many outputs
+     were commanded. */
+  async fn freeze_outputs(&self) -> anyhow::Result<usize> { Ok(0) }
+
+  /* Devices with request/response-only data poll it through a Poller, which is configured from here */
+  fn poller(&self) -> Option<&poller::Poller> { None }
+
+  /* Make the physical device stand out (e.g. blink its status LED for a few seconds) so it can be found on the robot */
+  async fn identify(&self) -> anyhow::Result<()> {
+    Err(coded(ErrorCode::FeatureUnsupported, format!("{} devices can't identify themselves", self.device_class())))
+  }
+}
+
+pub type SharedInfo = Arc<RwLock<DeviceInfo>>;
+
+/* GRAPPLE DEVICE */
+
+pub struct GrappleDevice {
+  sender: SendWrapper,
+  info: SharedInfo
+}
+
+impl GrappleDevice {
+  pub fn new(sender: SendWrapper, info: SharedInfo) -> Self {
+    Self { sender, info }
+  }
+}
+
+#[rpc]
+impl GrappleDevice {
+  async fn blink(&self) -> anyhow::Result<()> {
+    self.sender.send(TaggedGrappleMessage::new(
+      DEVICE_ID_BROADCAST,
+      grapple_frc_msgs::grapple::GrappleDeviceMessage::Broadcast(
+        grapple_frc_msgs::grapple::GrappleBroadcastMessage::DeviceInfo(grapple_frc_msgs::grapple::device_info::GrappleDeviceInfo::Blink {
+          serial: self.info.read().await.require_serial()?
+        })
+      )
+    )).await
+  }
+
+  async fn set_id(&self, id: u8) -> anyhow::Result<()>  {
+    self.sender.send(TaggedGrappleMessage::new(
+      DEVICE_ID_BROADCAST,
+      grapple_frc_msgs::grapple::GrappleDeviceMessage::Broadcast(
+        grapple_frc_msgs::grapple::GrappleBroadcastMessage::DeviceInfo(grapple_frc_msgs::grapple::device_info::GrappleDeviceInfo::SetId {
+          serial: self.info.read().await.require_serial()?, new_id: id
+        })
+      )
+    )).await
+  }
+
+  async fn set_name(&self, name: String) -> anyhow::Result<()>  {
+    self.sender.send(TaggedGrappleMessage::new(
+      DEVICE_ID_BROADCAST,
+      grapple_frc_msgs::grapple::GrappleDeviceMessage::Broadcast(
+        grapple_frc_msgs::grapple::GrappleBroadcastMessage::DeviceInfo(grapple_frc_msgs::grapple::device_info::GrappleDeviceInfo::SetName {
+          serial: self.info.read().await.require_serial()?,
+          name: Cow::<str>::Owned(name).into()
+        })
+      )
+    )).await
+  }
+
+  async fn commit_to_eeprom(&self) -> anyhow::Result<()>  {
+    self.sender.send(TaggedGrappleMessage::new(
+      DEVICE_ID_BROADCAST,
+      grapple_frc_msgs::grapple::GrappleDeviceMessage::Broadcast(
+        grapple_frc_msgs::grapple::GrappleBroadcastMessage::DeviceInfo(grapple_frc_msgs::grapple::device_info::GrappleDeviceInfo::CommitConfig {
+          serial: self.info.read().await.require_serial()?,
+        })
+      )
+    )).await
+  }
+}
+
+impl Device for GrappleDevice {}
+
+/* FIRMWARE UPGRADE DEVICE */
+
+pub struct FirmwareUpgradeDevice<T: FirmwareValidatingDevice> {
+  sender: SendWrapper,
+  info: SharedInfo,
+  progress: Arc<RwLock<Option<f64>>>,
+  stats: Arc<RwLock<Option<FlashStats>>>,
+  ack: Arc<AckTracker>,
+  chunk_size: usize,
+  _t: PhantomData<T>
+}
+
+impl<T: FirmwareValidatingDevice> FirmwareUpgradeDevice<T> {
+  pub fn new(sender: SendWrapper, info: SharedInfo, chunk_size: usize) -> Self {
+    Self { sender, info, progress: Arc::new(RwLock::new(None)), stats: Arc::new(RwLock::new(None)), ack: Arc::new(AckTracker::new()), chunk_size, _t: PhantomData }
+  }
+
+  pub async fn field_upgrade_worker(sender: SendWrapper, id: u8, source: &FirmwareSource, progress: Arc<RwLock<Option<f64>>>, stats: Arc<RwLock<Option<FlashStats>>>, ack: Arc<AckTracker>, chunk_size: usize) -> anyhow::Result<()> {
+    *progress.write().await = Some(0.0);
+    let len = source.len() as usize;
+    let nchunks = len.div_ceil(chunk_size);
+    *stats.write().await = Some(FlashStats::new(nchunks));
+
+    // Only the chunk being sent is in memory
+    let mut reader = source.reader()?;
+    let mut chunk = vec![0u8; chunk_size];
+    for i in 0..nchunks {
+      let chunk = &mut chunk[..chunk_size.min(len - i * chunk_size)];
+      reader.read_exact(chunk)?;
+      let chunk = &*chunk;
+      info!("Chunk {} (len: {})", i, chunk.len());
+
+      let timeout = stats.read().await.as_ref().map(|s| s.ack_timeout()).unwrap_or(Duration::from_millis(MIN_ACK_TIMEOUT_MS));
+      let waiter = ack.expect_ack(i);
+      let sent_at = std::time::Instant::now();
+      let sent = sender.send(TaggedGrappleMessage::new(
+        id,
+        GrappleDeviceMessage::FirmwareUpdate(
+          GrappleFirmwareMessage::UpdatePart(AsymmetricCow(Cow::<Payload>::Borrowed(Into::into(chunk))).into_static())
+        )
+      )).await;
+
+      // Chunks can't be resent: without sequence numbers, a resend after a lost ack gets written twice. Stop here and
+      // leave the device in its bootloader, where the update can be started again from scratch.
+      let acked = match sent {
+        Ok(()) => waiter.wait(timeout).await,
+        Err(e) => Err(e)
+      };
+      if let Err(e) = acked {
+        warn!("Chunk {} failed, aborting the update: {}", i, e);
+        if let Some(stats) = stats.write().await.as_mut() {
+          stats.on_abort(i, e.to_string());
+        }
+        *progress.write().await = None;
+        return Err(e);
+      }
+      if let Some(stats) = stats.write().await.as_mut() {
+        stats.on_ack(sent_at.elapsed().as_secs_f64() * 1000.0);
+      }
+      *progress.write().await = Some((i + 1) as f64 / (nchunks as f64) * 100.0);
+    }
+
+    *progress.write().await = Some(100.0); 
+    sender.send(TaggedGrappleMessage::new(
+      id,
+      GrappleDeviceMessage::FirmwareUpdate(
+        GrappleFirmwareMessage::UpdateDone
+      )
+    )).await?;
+    *progress.write().await = None;
+
+    Ok(())
+  }
+}
+
+pub async fn start_field_upgrade(sender: &SendWrapper, serial: u32) -> anyhow::Result<()> {
+  sender.send(TaggedGrappleMessage::new(
+    DEVICE_ID_BROADCAST,
+    GrappleDeviceMessage::FirmwareUpdate(
+      GrappleFirmwareMessage::StartFieldUpgrade { serial }
+    )
+  )).await
+}
+
+fn maybe_unpack_firmware(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+  let mut archive = zip::ZipArchive::new(Cursor::new(data))?;
+
+  // TODO: Use https://github.com/GrappleRobotics/bundle/tree/master/grapple-bundle-lib
+
+  let mut index_file = archive.by_name("index")?;
+  let mut index_content = String::new();
+  index_file.read_to_string(&mut index_content)?;
+  let index: serde_json::Value = serde_json::from_str(&index_content)?;
+  drop(index_file);
+
+  let mut firmware_f = archive.by_name(index.get("firmware_update_bin").map(|x| x.as_str()).flatten().ok_or(anyhow::anyhow!("Invalid Index"))?)?;
+  let mut v = vec![];
+  firmware_f.read_to_end(&mut v)?;
+  Ok(v)
+}
+
+impl<T: FirmwareValidatingDevice + HasFirmwareUpdateURLDevice + Send + Sync> FirmwareUpgradeDevice<T> {
+  fn library_label(info: &DeviceInfo) -> String {
+    match &info.device_type {
+      DeviceType::Grapple(model) => format!("{:?} firmware", model),
+      _ => "Firmware".to_owned()
+    }
+  }
+
+  /* Journal the image and flash it in the background */
+  async fn flash(&self, source: FirmwareSource) -> anyhow::Result<()> {
+    let sender = self.sender.clone();
+    let progress = self.progress.clone();
+    let id = self.info.read().await.require_device_id()?;
+    let serial = self.info.read().await.require_serial()?;
+    let notify = self.ack.clone();
+    let stats = self.stats.clone();
+    let chunk_size = self.chunk_size;
+
+    let operation = journal().begin(OperationKind::FirmwareUpdate, serial, Some(&mut *source.reader()?));
+
+    tokio::task::spawn(async move {
+      let result = Self::field_upgrade_worker(sender, id, &source, progress, stats.clone(), notify, chunk_size).await;
+      journal().record_flash_stats(&operation, stats.read().await.clone());
+      journal().finish(&operation, result.map_err(|e| e.to_string()));
+    });
+    Ok(())
+  }
+}
+
+#[rpc]
+impl<T: FirmwareValidatingDevice + HasFirmwareUpdateURLDevice + Send + Sync> FirmwareUpgradeDevice<T> {
+  async fn do_field_upgrade(&self, data: Vec<u8>) -> anyhow::Result<()> {
+    let info = self.info.read().await.clone();
+    let buf = match maybe_unpack_firmware(&data) {
+      Ok(buf) => Some(buf),
+      Err(_) => {
+        <T>::validate_firmware(&info, &data).map_err(|e| anyhow::anyhow!("Not a valid firmware file: {}", e))?;
+        None
+      }
+    };
+
+    // Keep a copy of everything we flash, so it can be flashed again later
+    match firmware_library().stage(&data, Self::library_label(&info), None) {
+      Ok(image) => firmware_library().record_flash(&image.sha256, info.require_serial()?),
+      Err(e) => warn!("Could not add firmware to the library: {}", e)
+    }
+
+    self.flash(FirmwareSource::Memory(buf.unwrap_or(data))).await
+  }
+
+  /// As do_field_upgrade, but streamed from a file (or bundle) on disk rather than passed in whole
+  async fn do_field_upgrade_from_file(&self, path: String) -> anyhow::Result<()> {
+    let info = self.info.read().await.clone();
+    let file = FirmwareFile::open(Path::new(&path))?;
+    let original = file.source().to_owned();
+    let is_bundle = file.is_bundle();
+    let source = FirmwareSource::File(file);
+    if !is_bundle {
+      <T>::validate_firmware(&info, &source.header()?).map_err(|e| anyhow::anyhow!("Not a valid firmware file: {}", e))?;
+    }
+
+    match firmware_library().stage_file(&original, Self::library_label(&info), None) {
+      Ok(image) => firmware_library().record_flash(&image.sha256, info.require_serial()?),
+      Err(e) => warn!("Could not add firmware to the library: {}", e)
+    }
+
+    self.flash(source).await
+  }
+
+  async fn progress(&self) -> anyhow::Result<Option<f64>> {
+    Ok(self.progress.read().await.clone())
+  }
+
+  /// Retransmissions and ack latency for the current (or last) flash
+  async fn flash_stats(&self) -> anyhow::Result<Option<FlashStats>> {
+    Ok(self.stats.read().await.clone())
+  }
+
+  async fn get_firmware_url(&self) -> anyhow::Result<Option<String>> {
+    Ok(T::firmware_url())
+  }
+}
+
+impl<T: FirmwareValidatingDevice + HasFirmwareUpdateURLDevice + Send + Sync> RootDevice for FirmwareUpgradeDevice<T> {
+  fn device_class(&self) ->  &'static str {
+    "GrappleFirmwareUpgrade"
+  }
+}
+
+#[async_trait::async_trait]
+impl<T: FirmwareValidatingDevice + HasFirmwareUpdateURLDevice + Send + Sync> Device for FirmwareUpgradeDevice<T> {
+  async fn handle(&self, msg: TaggedGrappleMessage<'static>) -> anyhow::Result<()> {
+    if msg.device_id == DEVICE_ID_BROADCAST || Some(msg.device_id) == self.info.read().await.device_id {
+      match msg.clone().msg {
+        GrappleDeviceMessage::FirmwareUpdate(fw) => match fw {
+          GrappleFirmwareMessage::UpdatePartAck => {
+            self.ack.on_ack();
+          },
+          _ => ()
+        },
+        _ => ()
+      }
+    }
+    Ok(())
+  }
+}
+
+pub trait HasFirmwareUpdateURLDevice {
+  fn firmware_url() -> Option<String>;
+}
+
+pub trait FirmwareValidatingDevice {
+  fn validate_firmware(info: &DeviceInfo, buf: &[u8]) -> anyhow::Result<()>;
+}
+
+#[async_trait::async_trait]
+pub trait VersionGatedDevice : HasFirmwareUpdateURLDevice + RootDevice + Sized + Sync + 'static {
+  fn validate_version(version: Option<String>) -> anyhow::Result<()>;
+  async fn check_for_new_firmware_release(current_version: &str) -> Option<LightReleaseResponse>;
+
+  fn require_version(version: Option<String>, req: &str) -> anyhow::Result<()> {
+    if let Some(v) = version {
+      let v = Version::parse(&v)?;
+      if !VersionReq::parse(req)?.matches(&v) {
+        return Err(coded(ErrorCode::IncompatibleFirmware, format!("Invalid version: {}, expected: {}", v, req)));
+      }
+    }
+    Ok(())
+  }
+
+  async fn maybe_gate<F: FnOnce(SendWrapper, Arc<RwLock<DeviceInfo>>) -> Self + Send>(send: SendWrapper, info: Arc<RwLock<DeviceInfo>>, create_fn: F) -> Box<dyn RootDevice + Send + Sync + 'static> {
+    match Self::validate_version(info.clone().read().await.firmware_version.clone()) {
+      Ok(_) => Box::new(create_fn(send, info)),
+      Err(e) => {
+        let device = OldVersionDevice::new(send, info.clone(), format!("{}", e), Self::firmware_url());
+        // The catalog holds the newest release the gate accepts for each class, and only goes to the network the first
+        // time a class is looked up. That's done in the background so we're not holding up enumeration.
+        if let Some(class) = resolve_device_class(&info.read().await.device_type) {
+          let release = device.recovery_release.clone();
+          tokio::task::spawn(async move {
+            let found = firmware_catalog().release_for(class).await;
+            *release.write().await = Some(found);
+          });
+        } else {
+          *device.recovery_release.write().await = Some(None);
+        }
+        Box::new(device)
+      }
+    }
+  }
+}
+
+async fn check_for_new_firmware_release_rpc_target<T: VersionGatedDevice>(info: &SharedInfo) -> anyhow::Result<Option<LightReleaseResponse>> {
+  let fw = &info.read().await.firmware_version;
+  match fw {
+    Some(x) => Ok(T::check_for_new_firmware_release(&x).await),
+    None => anyhow::bail!("Can't check for new releases - device has no version")
+  }
+}
+
+/* What it'll take to get a gated device working again */
+#[derive(Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct GatedRecovery {
+  pub explanation: String,
+  pub current_version: Option<String>,
+  /* False while we're still looking for a compatible release */
+  pub lookup_done: bool,
+  /* The newest release this build of GrappleHook accepts, if there is one */
+  pub release: Option<LightReleaseResponse>,
+  /* Set if the release can be downloaded and flashed without the user having to find the file */
+  pub download_url: Option<String>,
+}
+
+pub struct OldVersionDevice {
+  grapple_device: GrappleDevice,
+  error: String,
+  firmware_url: Option<String>,
+  /* None until the lookup finishes */
+  recovery_release: Arc<RwLock<Option<Option<LightReleaseResponse>>>>,
+}
+
+impl OldVersionDevice {
+  pub fn new(sender: SendWrapper, info: SharedInfo, error: String, firmware_url: Option<String>) -> Self {
+    Self {
+      grapple_device: GrappleDevice::new(sender.clone(), info.clone()),
+      error, firmware_url,
+      recovery_release: Arc::new(RwLock::new(None)),
+    }
+  }
+}
+
+#[async_trait::async_trait]
+impl Device for OldVersionDevice {
+  async fn handle(&self, msg: TaggedGrappleMessage<'static>) -> anyhow::Result<()> {
+    self.grapple_device.handle(msg.clone()).await?;
+    Ok(())
+  }
+}
+
+#[async_trait::async_trait]
+impl RootDevice for OldVersionDevice {
+  fn device_class(&self) -> &'static str {
+    "OldVersionDevice"
+  }
+
+  async fn identify(&self) -> anyhow::Result<()> {
+    self.grapple_device.blink().await
+  }
+
+  fn gated_reason(&self) -> Option<String> {
+    Some(self.error.clone())
+  }
+}
+
+#[rpc]
+impl OldVersionDevice {
+  async fn start_field_upgrade(&self) -> anyhow::Result<()> {
+    let serial = self.grapple_device.info.read().await.require_serial()?;
+    start_field_upgrade(&self.grapple_device.sender, serial).await
+  }
+
+  async fn get_error(&self) -> anyhow::Result<String> {
+    Ok(self.error.clone())
+  }
+
+  async fn get_firmware_url(&self) -> anyhow::Result<Option<String>> {
+    Ok(self.firmware_url.clone())
+  }
+
+  async fn recovery(&self) -> anyhow::Result<GatedRecovery> {
+    let current_version = self.grapple_device.info.read().await.firmware_version.clone();
+    let lookup = self.recovery_release.read().await.clone();
+    let release = lookup.clone().flatten();
+
+    let explanation = match (&release, &lookup) {
+      (Some(release), _) => format!("This device's firmware isn't compatible with this version of GrappleHook ({}). Updating it to {} will fix this.", self.error, release.version()),
+      (None, Some(None)) => format!("This device's firmware isn't compatible with this version of GrappleHook ({}), and we couldn't find a compatible release. Check for a GrappleHook update, or download firmware manually.", self.error),
+      (None, None) => format!("This device's firmware isn't compatible with this version of GrappleHook ({}). Looking for a compatible release...", self.error),
+    };
+
+    Ok(GatedRecovery {
+      explanation,
+      current_version,
+      lookup_done: lookup.is_some(),
+      download_url: release.as_ref().and_then(|r| r.firmware_asset()).map(|a| a.browser_download_url.clone()),
+      release,
+    })
+  }
+
+  async fn grapple(&self, msg: GrappleDeviceRequest) -> anyhow::Result<GrappleDeviceResponse> {
+    self.grapple_device.rpc_process(msg).await
+  }
+}
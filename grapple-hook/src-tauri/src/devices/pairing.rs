@@ -0,0 +1,82 @@
+use super::device_manager::DeviceId;
+
+/* Helps match physical devices to list entries when several identical, unlabelled devices are connected (e.g. four
+   brand new LaserCANs). We step through them one at a time, blinking each, and the user names the one that lit up
+   before moving on to the next. */
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct PairingEntry {
+  pub device_id: DeviceId,
+  pub serial: u32,
+  /* What the device was called when pairing started */
+  pub previous_nickname: Option<String>,
+  pub assigned_nickname: Option<String>,
+  pub location: Option<String>,
+  pub skipped: bool,
+}
+
+impl PairingEntry {
+  fn done(&self) -> bool {
+    self.assigned_nickname.is_some() || self.skipped
+  }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct PairingStatus {
+  pub device_class: String,
+  pub entries: Vec<PairingEntry>,
+  /* Index into entries of the device being blinked, None once every device is done */
+  pub current: Option<usize>,
+}
+
+pub struct PairingSession {
+  device_class: String,
+  entries: Vec<PairingEntry>,
+}
+
+impl PairingSession {
+  pub fn new(device_class: String, mut entries: Vec<PairingEntry>) -> anyhow::Result<Self> {
+    if entries.is_empty() {
+      anyhow::bail!("No {} devices to pair", device_class);
+    }
+    entries.sort_by_key(|e| e.serial);
+    Ok(Self { device_class, entries })
+  }
+
+  pub fn current(&self) -> Option<&PairingEntry> {
+    self.entries.iter().find(|e| !e.done())
+  }
+
+  pub fn status(&self) -> PairingStatus {
+    PairingStatus {
+      device_class: self.device_class.clone(),
+      entries: self.entries.clone(),
+      current: self.entries.iter().position(|e| !e.done()),
+    }
+  }
+
+  fn current_mut(&mut self) -> anyhow::Result<&mut PairingEntry> {
+    self.entries.iter_mut().find(|e| !e.done()).ok_or(anyhow::anyhow!("Every device has already been paired"))
+  }
+
+  /* Record what the current device should be called and move on. Returns the entry that was assigned. */
+  pub fn assign(&mut self, nickname: String, location: Option<String>) -> anyhow::Result<PairingEntry> {
+    let nickname = nickname.trim().to_owned();
+    if nickname.is_empty() {
+      anyhow::bail!("Give the device a name, or skip it");
+    }
+    if self.entries.iter().any(|e| e.assigned_nickname.as_deref() == Some(nickname.as_str())) {
+      anyhow::bail!("Another device has already been named {}", nickname);
+    }
+
+    let entry = self.current_mut()?;
+    entry.assigned_nickname = Some(nickname);
+    entry.location = location.map(|l| l.trim().to_owned()).filter(|l| !l.is_empty());
+    Ok(entry.clone())
+  }
+
+  pub fn skip(&mut self) -> anyhow::Result<()> {
+    self.current_mut()?.skipped = true;
+    Ok(())
+  }
+}
@@ -0,0 +1,149 @@
+use std::{ffi::{c_char, c_void, CStr}, sync::{Arc, OnceLock}};
+
+/* Bindings to PEAK's PCANBasic library, loaded at runtime so GrappleHook still starts on machines without it. It's
+   PCANBasic.dll on Windows (installed with PEAK's driver), libPCBUSB on macOS (from mac-can.com), and libpcanbasic on
+   Linux, though there the mainline kernel driver usually has the adapter as a SocketCAN interface instead. */
+
+#[cfg(target_os = "windows")]
+const LIBRARY: &str = "PCANBasic.dll";
+#[cfg(target_os = "macos")]
+const LIBRARY: &str = "libPCBUSB.dylib";
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const LIBRARY: &str = "libpcanbasic.so";
+
+pub const PCAN_ERROR_OK: u32 = 0x00000;
+pub const PCAN_ERROR_BUSLIGHT: u32 = 0x00004;
+pub const PCAN_ERROR_BUSHEAVY: u32 = 0x00008;
+pub const PCAN_ERROR_BUSOFF: u32 = 0x00010;
+pub const PCAN_ERROR_QRCVEMPTY: u32 = 0x00020;
+
+pub const PCAN_MESSAGE_EXTENDED: u8 = 0x02;
+pub const PCAN_MESSAGE_RTR: u8 = 0x01;
+pub const PCAN_MESSAGE_STATUS: u8 = 0x80;
+
+const PCAN_CHANNEL_CONDITION: u8 = 0x0D;
+const PCAN_CHANNEL_AVAILABLE: u32 = 0x01;
+
+/* PCAN_USBBUS1 to PCAN_USBBUS16 */
+pub const USB_CHANNELS: &[(u16, &str)] = &[
+  (0x51, "PCAN_USBBUS1"), (0x52, "PCAN_USBBUS2"), (0x53, "PCAN_USBBUS3"), (0x54, "PCAN_USBBUS4"),
+  (0x55, "PCAN_USBBUS5"), (0x56, "PCAN_USBBUS6"), (0x57, "PCAN_USBBUS7"), (0x58, "PCAN_USBBUS8"),
+  (0x509, "PCAN_USBBUS9"), (0x50A, "PCAN_USBBUS10"), (0x50B, "PCAN_USBBUS11"), (0x50C, "PCAN_USBBUS12"),
+  (0x50D, "PCAN_USBBUS13"), (0x50E, "PCAN_USBBUS14"), (0x50F, "PCAN_USBBUS15"), (0x510, "PCAN_USBBUS16"),
+];
+
+/* BTR0/BTR1 register values, by bitrate */
+pub const PCAN_BITRATES: &[(u32, u16)] = &[
+  (10_000, 0x672F), (20_000, 0x532F), (50_000, 0x472F), (100_000, 0x432F), (125_000, 0x031C),
+  (250_000, 0x011C), (500_000, 0x001C), (800_000, 0x0016), (1_000_000, 0x0014),
+];
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TPCANMsg {
+  pub id: u32,
+  pub msg_type: u8,
+  pub len: u8,
+  pub data: [u8; 8],
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct TPCANTimestamp {
+  millis: u32,
+  millis_overflow: u16,
+  micros: u16,
+}
+
+// PCANBasic.dll is stdcall on 32-bit Windows, which "system" picks for us
+type Initialize = unsafe extern "system" fn(u16, u16, u8, u32, u16) -> u32;
+type Uninitialize = unsafe extern "system" fn(u16) -> u32;
+type Read = unsafe extern "system" fn(u16, *mut TPCANMsg, *mut TPCANTimestamp) -> u32;
+type Write = unsafe extern "system" fn(u16, *mut TPCANMsg) -> u32;
+type GetValue = unsafe extern "system" fn(u16, u8, *mut c_void, u32) -> u32;
+type GetErrorText = unsafe extern "system" fn(u32, u16, *mut c_char) -> u32;
+
+pub struct PcanBasic {
+  initialize: Initialize,
+  uninitialize: Uninitialize,
+  read: Read,
+  write: Write,
+  get_value: GetValue,
+  get_error_text: GetErrorText,
+  /* Keeps the functions above valid */
+  _library: libloading::Library,
+}
+
+impl PcanBasic {
+  fn load() -> anyhow::Result<Self> {
+    unsafe {
+      let library = libloading::Library::new(LIBRARY)?;
+      Ok(Self {
+        initialize: *library.get::<Initialize>(b"CAN_Initialize\0")?,
+        uninitialize: *library.get::<Uninitialize>(b"CAN_Uninitialize\0")?,
+        read: *library.get::<Read>(b"CAN_Read\0")?,
+        write: *library.get::<Write>(b"CAN_Write\0")?,
+        get_value: *library.get::<GetValue>(b"CAN_GetValue\0")?,
+        get_error_text: *library.get::<GetErrorText>(b"CAN_GetErrorText\0")?,
+        _library: library,
+      })
+    }
+  }
+
+  fn check(&self, status: u32) -> anyhow::Result<()> {
+    match status {
+      PCAN_ERROR_OK => Ok(()),
+      status => anyhow::bail!("{}", self.error_text(status))
+    }
+  }
+
+  pub fn error_text(&self, status: u32) -> String {
+    let mut buf = [0 as c_char; 256];
+    // 0x09 asks for English
+    match unsafe { (self.get_error_text)(status, 0x09, buf.as_mut_ptr()) } {
+      PCAN_ERROR_OK => unsafe { CStr::from_ptr(buf.as_ptr()) }.to_string_lossy().into_owned(),
+      _ => format!("PCAN error 0x{:x}", status)
+    }
+  }
+
+  pub fn is_available(&self, channel: u16) -> bool {
+    let mut condition: u32 = 0;
+    let status = unsafe { (self.get_value)(channel, PCAN_CHANNEL_CONDITION, &mut condition as *mut u32 as *mut c_void, 4) };
+    status == PCAN_ERROR_OK && condition & PCAN_CHANNEL_AVAILABLE != 0
+  }
+
+  pub fn initialize(&self, channel: u16, btr0btr1: u16) -> anyhow::Result<()> {
+    // The hardware type, port and interrupt are only for non-plug-and-play adapters
+    self.check(unsafe { (self.initialize)(channel, btr0btr1, 0, 0, 0) })
+  }
+
+  pub fn uninitialize(&self, channel: u16) {
+    unsafe { (self.uninitialize)(channel) };
+  }
+
+  /* The next frame in the receive queue, or the status if there isn't one (PCAN_ERROR_QRCVEMPTY if it's just empty) */
+  pub fn read(&self, channel: u16) -> Result<TPCANMsg, u32> {
+    let mut msg = TPCANMsg::default();
+    let mut timestamp = TPCANTimestamp::default();
+    match unsafe { (self.read)(channel, &mut msg, &mut timestamp) } {
+      PCAN_ERROR_OK => Ok(msg),
+      status => Err(status)
+    }
+  }
+
+  pub fn write(&self, channel: u16, mut msg: TPCANMsg) -> anyhow::Result<()> {
+    self.check(unsafe { (self.write)(channel, &mut msg) })
+  }
+}
+
+/* None if PCANBasic isn't installed. Only tried once, so installing it means restarting GrappleHook. */
+pub fn pcan_basic() -> Option<Arc<PcanBasic>> {
+  static PCAN_BASIC: OnceLock<Option<Arc<PcanBasic>>> = OnceLock::new();
+  PCAN_BASIC.get_or_init(|| match PcanBasic::load() {
+    Ok(pcan) => Some(Arc::new(pcan)),
+    Err(e) => {
+      log::info!("PCANBasic isn't available, PCAN-USB adapters won't be detected: {}", e);
+      None
+    }
+  }).clone()
+}
@@ -0,0 +1,2 @@
+pub mod basic;
+pub mod usb;
\ No newline at end of file
@@ -0,0 +1,260 @@
+use std::{collections::HashMap, sync::{atomic::AtomicBool, Arc, OnceLock}, time::Duration};
+
+use bounded_static::ToBoundedStatic;
+use grapple_frc_msgs::{binmarshal::{BitView, Demarshal}, grapple::{fragments::FragmentReassembler, TaggedGrappleMessage}, ManufacturerMessage, MessageId};
+use grapple_hook_macros::rpc;
+use log::{info, warn};
+use tokio::sync::{mpsc, Mutex};
+
+use crate::errors::{coded, ErrorCode};
+use crate::{persistence::Persisted, rpc::RpcBase};
+
+use super::basic::{pcan_basic, PcanBasic, TPCANMsg, PCAN_BITRATES, PCAN_ERROR_BUSHEAVY, PCAN_ERROR_BUSLIGHT, PCAN_ERROR_BUSOFF, PCAN_ERROR_QRCVEMPTY, PCAN_MESSAGE_EXTENDED, PCAN_MESSAGE_RTR, PCAN_MESSAGE_STATUS, USB_CHANNELS};
+use crate::devices::{device_manager::{DeviceManager, DeviceManagerRequest, DeviceManagerResponse}, provider::{DeviceProvider, ProviderInfo}};
+
+/* A PEAK PCAN-USB adapter, through PCANBasic. PCANBasic has no async API, so rather than blocking on reads we drain
+   its receive queue every POLL_INTERVAL. */
+
+pub const PCAN_DOMAIN: &str = "PCAN";
+const FRC_BITRATE: u32 = 1_000_000;
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+/* The bitrate each channel is initialised at, by channel name. FRC_BITRATE if it's never been changed. */
+fn bitrates() -> &'static Persisted<HashMap<String, u32>> {
+  static BITRATES: OnceLock<Persisted<HashMap<String, u32>>> = OnceLock::new();
+  BITRATES.get_or_init(|| Persisted::load("pcan_bitrates"))
+}
+
+fn bitrate_for(channel: &str) -> u32 {
+  bitrates().read(|b| b.get(channel).cloned()).unwrap_or(FRC_BITRATE)
+}
+
+/* Names of the USB channels with an adapter plugged in (and not in use by something else) */
+pub fn available_channels() -> Vec<String> {
+  match pcan_basic() {
+    Some(pcan) => USB_CHANNELS.iter().filter(|(handle, _)| pcan.is_available(*handle)).map(|(_, name)| name.to_string()).collect(),
+    None => vec![]
+  }
+}
+
+pub struct PcanUsbInner {
+  channel: String,
+  handle: u16,
+  running: AtomicBool,
+  device_manager: DeviceManager,
+
+  stop_signal_tx: mpsc::Sender<()>,
+  stop_signal_rx: Mutex<mpsc::Receiver<()>>,
+
+  send_tx: mpsc::Sender<TaggedGrappleMessage<'static>>,
+  send_rx: Mutex<mpsc::Receiver<TaggedGrappleMessage<'static>>>,
+
+  last_error: std::sync::Mutex<Option<String>>,
+}
+
+pub struct PcanUsb {
+  inner: Arc<PcanUsbInner>
+}
+
+impl PcanUsb {
+  pub fn new(channel: String) -> Self {
+    let (send_tx, send_rx) = mpsc::channel(100);
+    let (stop_signal_tx, stop_signal_rx) = mpsc::channel(5);
+    let handle = USB_CHANNELS.iter().find(|(_, name)| *name == channel).map(|(handle, _)| *handle).unwrap_or(0);
+
+    Self {
+      inner: Arc::new(
+        PcanUsbInner {
+          channel,
+          handle,
+          running: AtomicBool::new(false),
+          device_manager: DeviceManager::new(),
+          stop_signal_tx, stop_signal_rx: Mutex::new(stop_signal_rx),
+          send_tx, send_rx: Mutex::new(send_rx),
+          last_error: std::sync::Mutex::new(None),
+        }
+      )
+    }
+  }
+
+  async fn do_loop(pcan: Arc<PcanBasic>, inner: Arc<PcanUsbInner>) -> anyhow::Result<()> {
+    let mut send_rx = inner.send_rx.try_lock().map_err(|_| anyhow::anyhow!("This RootDevice is already running!"))?;
+    let mut stop_signal_rx = inner.stop_signal_rx.try_lock()?;
+
+    let (mut reassemble_rx, mut reassemble_tx) = FragmentReassembler::new(1000, 8).split();
+    let mut device_manager_interval = tokio::time::interval(Duration::from_millis(500));
+    let mut poll_interval = tokio::time::interval(POLL_INTERVAL);
+    poll_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut last_bus_status = None;
+
+    loop {
+      tokio::select! {
+        _ = poll_interval.tick() => {
+          let mut frames = vec![];
+          loop {
+            match pcan.read(inner.handle) {
+              Ok(frame) => {
+                last_bus_status = None;
+                frames.push(frame);
+              },
+              Err(PCAN_ERROR_QRCVEMPTY) => break,
+              // Bus errors come back from a read too. Only report each once, rather than on every poll while it lasts.
+              Err(status) if status & (PCAN_ERROR_BUSLIGHT | PCAN_ERROR_BUSHEAVY | PCAN_ERROR_BUSOFF) != 0 => {
+                if last_bus_status != Some(status) {
+                  let e = anyhow::anyhow!("{}", pcan.error_text(status));
+                  warn!("{} reported a bus error: {}", inner.channel, e);
+                  inner.device_manager.on_transport_error(&e);
+                  last_bus_status = Some(status);
+                }
+                break;
+              },
+              Err(status) => anyhow::bail!("Adapter disconnected ({})", pcan.error_text(status))
+            }
+          }
+
+          // FRC devices only use extended data frames
+          for frame in frames.iter().filter(|f| f.msg_type & PCAN_MESSAGE_EXTENDED != 0 && f.msg_type & (PCAN_MESSAGE_RTR | PCAN_MESSAGE_STATUS) == 0) {
+            let id = MessageId::from(frame.id);
+            let data = &frame.data[..(frame.len as usize).min(8)];
+            match ManufacturerMessage::read(&mut BitView::new(data), id.clone()) {
+              Ok(ManufacturerMessage::Grapple(grpl_msg)) => {
+                let mut storage = Vec::new();
+                match reassemble_rx.defragment(chrono::Utc::now().timestamp_millis(), &id, grpl_msg, &mut storage) {
+                  Ok(Some(grpl_unfragmented)) => {
+                    inner.device_manager.on_message(PCAN_DOMAIN.to_owned(), id.clone().into(), TaggedGrappleMessage::new(id.device_id, grpl_unfragmented.to_static())).await?;
+                  },
+                  Ok(None) => (),
+                  Err(e) => inner.device_manager.on_malformed(PCAN_DOMAIN, id.clone().into(), data, format!("{:?}", e))
+                }
+              },
+              Ok(_) => inner.device_manager.on_foreign(PCAN_DOMAIN, id.clone().into()),
+              Err(e) => inner.device_manager.on_malformed(PCAN_DOMAIN, id.clone().into(), data, format!("{:?}", e))
+            }
+          }
+        },
+        msg = send_rx.recv() => match msg {
+          Some(TaggedGrappleMessage { device_id, msg }) => {
+            let mut frames = vec![];
+            reassemble_tx.maybe_fragment(device_id, msg, &mut |id, buf| {
+              let mut frame = TPCANMsg { id: id.into(), msg_type: PCAN_MESSAGE_EXTENDED, len: buf.len().min(8) as u8, data: [0; 8] };
+              frame.data[..frame.len as usize].copy_from_slice(&buf[..frame.len as usize]);
+              frames.push(frame);
+            }).ok();
+
+            for frame in frames {
+              pcan.write(inner.handle, frame)?;
+            }
+          },
+          None => ()
+        },
+        sig = stop_signal_rx.recv() => match sig {
+          Some(()) => {
+            break;
+          },
+          None => ()
+        },
+        _ = device_manager_interval.tick() => {
+          inner.device_manager.on_tick().await?;
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  async fn do_start(inner: Arc<PcanUsbInner>) -> anyhow::Result<()> {
+    info!("Connecting to {}...", inner.channel);
+
+    let result = (|| {
+      let pcan = pcan_basic().ok_or(coded(ErrorCode::UsbOpenFailed, "PCANBasic isn't installed. Install PEAK's PCAN-Basic (or PCBUSB on macOS) and restart GrappleHook."))?;
+      let bitrate = bitrate_for(&inner.channel);
+      let btr0btr1 = PCAN_BITRATES.iter().find(|(b, _)| *b == bitrate).ok_or(anyhow::anyhow!("Unsupported bitrate {}", bitrate))?.1;
+      pcan.initialize(inner.handle, btr0btr1).map_err(|e| coded(ErrorCode::UsbOpenFailed, format!("Couldn't open {} ({})", inner.channel, e)))?;
+      Ok::<_, anyhow::Error>(pcan)
+    })();
+    *inner.last_error.lock().unwrap() = result.as_ref().err().map(|e| e.to_string());
+    let pcan = result?;
+
+    info!("Connected!");
+    inner.device_manager.register_domain(PCAN_DOMAIN.to_owned(), inner.send_tx.clone()).await;
+
+    tokio::task::spawn(async move {
+      inner.running.store(true, std::sync::atomic::Ordering::Relaxed);
+      let r = Self::do_loop(pcan.clone(), inner.clone()).await;
+      pcan.uninitialize(inner.handle);
+      inner.running.store(false, std::sync::atomic::Ordering::Relaxed);
+      inner.device_manager.unregister_domain(&PCAN_DOMAIN.to_owned()).await;
+      match r {
+        Ok(_) => info!("PCAN runner stopped gracefully"),
+        Err(e) => {
+          warn!("PCAN runner stopped with error: {}", e);
+          *inner.last_error.lock().unwrap() = Some(e.to_string());
+        }
+      }
+    });
+
+    Ok(())
+  }
+}
+
+#[async_trait::async_trait]
+impl DeviceProvider for PcanUsb {
+  async fn connect(&self) -> anyhow::Result<()> {
+    Self::do_start(self.inner.clone()).await
+  }
+
+  async fn disconnect(&self) -> anyhow::Result<()> {
+    self.inner.stop_signal_tx.send(()).await.ok();
+    Ok(())
+  }
+
+  async fn info(&self) -> anyhow::Result<ProviderInfo> {
+    Ok(ProviderInfo {
+      ty: "PCAN-USB".to_owned(),
+      description: "PEAK PCAN-USB".to_owned(),
+      address: self.inner.channel.clone(),
+      connected: self.inner.running.load(std::sync::atomic::Ordering::Relaxed)
+    })
+  }
+
+  async fn device_manager_call(&self, req: DeviceManagerRequest) -> anyhow::Result<DeviceManagerResponse> {
+    self.inner.device_manager.rpc_process(req).await
+  }
+
+  async fn call(&self, req: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+    self.rpc_call(req).await
+  }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct PcanUsbStatus {
+  pub channel: String,
+  pub bitrate: u32,
+  pub supported_bitrates: Vec<u32>,
+  /* Why the channel couldn't be opened, or why the connection dropped */
+  pub last_error: Option<String>,
+}
+
+#[rpc]
+impl PcanUsb {
+  async fn status(&self) -> anyhow::Result<PcanUsbStatus> {
+    Ok(PcanUsbStatus {
+      channel: self.inner.channel.clone(),
+      bitrate: bitrate_for(&self.inner.channel),
+      supported_bitrates: PCAN_BITRATES.iter().map(|(b, _)| *b).collect(),
+      last_error: self.inner.last_error.lock().unwrap().clone(),
+    })
+  }
+
+  /// Choose the bitrate the channel is initialised at. Takes effect next time it's connected.
+  async fn set_bitrate(&self, bitrate: u32) -> anyhow::Result<()> {
+    if self.inner.running.load(std::sync::atomic::Ordering::Relaxed) {
+      anyhow::bail!("Disconnect from {} before changing its bitrate", self.inner.channel);
+    }
+    if !PCAN_BITRATES.iter().any(|(b, _)| *b == bitrate) {
+      anyhow::bail!("Unsupported bitrate {}", bitrate);
+    }
+    bitrates().update(|b| b.insert(self.inner.channel.clone(), bitrate));
+    Ok(())
+  }
+}
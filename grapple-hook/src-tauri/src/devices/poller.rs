@@ -0,0 +1,99 @@
+use std::{collections::HashSet, sync::{Arc, Mutex}, time::Duration};
+
+use futures::future::BoxFuture;
+use log::warn;
+use tokio::task::JoinHandle;
+
+use crate::telemetry::telemetry;
+use super::{SendWrapper, SharedInfo};
+
+/* Periodic polling for data that firmware only gives out when asked. A device lists the metrics it can poll, and the
+   poller requests the enabled ones on an interval and records the answers as telemetry, so they show up alongside
+   broadcast data. */
+
+pub const DEFAULT_POLL_INTERVAL_MS: u64 = 500;
+pub const MIN_POLL_INTERVAL_MS: u64 = 20;
+
+/* Issue the request(s) for one metric to the device with the given CAN ID and return its value */
+pub type PollFn = fn(SendWrapper, u8) -> BoxFuture<'static, anyhow::Result<f64>>;
+
+pub struct PolledMetric {
+  pub name: &'static str,
+  pub poll: PollFn,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct PollerConfig {
+  pub interval_ms: u64,
+  pub enabled_metrics: HashSet<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct PollerStatus {
+  pub config: PollerConfig,
+  pub available_metrics: Vec<String>,
+}
+
+pub struct Poller {
+  config: Arc<Mutex<PollerConfig>>,
+  available: Vec<&'static str>,
+  task: JoinHandle<()>,
+}
+
+impl Poller {
+  /* All metrics start enabled */
+  pub fn new(sender: SendWrapper, info: SharedInfo, metrics: Vec<PolledMetric>) -> Self {
+    let config = Arc::new(Mutex::new(PollerConfig {
+      interval_ms: DEFAULT_POLL_INTERVAL_MS,
+      enabled_metrics: metrics.iter().map(|m| m.name.to_owned()).collect(),
+    }));
+    let available = metrics.iter().map(|m| m.name).collect();
+
+    let task_config = config.clone();
+    let task = tokio::task::spawn(async move {
+      loop {
+        let PollerConfig { interval_ms, enabled_metrics } = task_config.lock().unwrap().clone();
+        tokio::time::sleep(Duration::from_millis(interval_ms)).await;
+
+        let (serial, id) = {
+          let info = info.read().await;
+          match (info.serial, info.device_id, info.is_dfu) {
+            (Some(serial), Some(id), false) => (serial, id),
+            _ => continue
+          }
+        };
+
+        for metric in metrics.iter().filter(|m| enabled_metrics.contains(m.name)) {
+          match (metric.poll)(sender.clone(), id).await {
+            Ok(value) => telemetry().record(serial, metric.name, sender.timestamp_ms(), value),
+            Err(e) => warn!("Couldn't poll {} from {:x}: {}", metric.name, serial, e)
+          }
+        }
+      }
+    });
+
+    Self { config, available, task }
+  }
+
+  pub fn status(&self) -> PollerStatus {
+    PollerStatus {
+      config: self.config.lock().unwrap().clone(),
+      available_metrics: self.available.iter().map(|m| m.to_string()).collect(),
+    }
+  }
+
+  pub fn configure(&self, mut config: PollerConfig) -> anyhow::Result<()> {
+    if let Some(unknown) = config.enabled_metrics.iter().find(|m| !self.available.contains(&m.as_str())) {
+      anyhow::bail!("This device can't poll {}", unknown);
+    }
+    config.interval_ms = config.interval_ms.max(MIN_POLL_INTERVAL_MS);
+    *self.config.lock().unwrap() = config;
+    Ok(())
+  }
+}
+
+impl Drop for Poller {
+  fn drop(&mut self) {
+    self.task.abort();
+  }
+}
@@ -1,111 +1,939 @@
-use std::collections::HashMap;
-
-use grapple_hook_macros::rpc;
-use network_interface::{NetworkInterface, NetworkInterfaceConfig};
-use tokio::sync::RwLock;
-
-
-use super::{generic_usb::GenericUSB, provider::{DeviceProvider, ProviderInfo, WrappedDeviceProvider, WrappedDeviceProviderRequest, WrappedDeviceProviderResponse}, roborio::daemon::RoboRioDaemon};
-use crate::rpc::RpcBase;
-
-pub struct ProviderContainer {
-  provider: WrappedDeviceProvider,
-  is_autodetect: bool,
-  last_autodetect: std::time::Instant
-}
-
-pub struct ProviderManager {
-  providers: RwLock<HashMap<String, ProviderContainer>>,
-  last_detect: RwLock<std::time::Instant>,
-}
-
-impl ProviderManager {
-  pub async fn new() -> Self {
-    let mut hm = HashMap::new();
-    let rr = RoboRioDaemon::new();
-    hm.insert(rr.info().await.unwrap().address, ProviderContainer { 
-      provider: WrappedDeviceProvider::new(Box::new(rr)),
-      is_autodetect: false,
-      last_autodetect: std::time::Instant::now()
-    });
-    Self {
-      providers: RwLock::new(hm),
-      last_detect: RwLock::new(std::time::Instant::now())
-    }
-  }
-
-  pub fn interfaces() -> Vec<NetworkInterface> {
-    NetworkInterface::show().unwrap_or(vec![])
-  }
-
-  pub async fn detect_devices(&self) -> anyhow::Result<()> {
-    let mut providers: tokio::sync::RwLockWriteGuard<HashMap<String, ProviderContainer>> = self.providers.write().await;
-
-    // Look for USB devices
-    let now = std::time::Instant::now();
-    if self.last_detect.read().await.elapsed().as_millis() > 500 {
-
-      if let Ok(ports) = tokio_serial::available_ports() {
-        for port in ports {
-          match port.port_type {
-            tokio_serial::SerialPortType::UsbPort(usbi) => {
-              if usbi.vid == 0x3580 && usbi.pid == 0x4000 {
-                let addr = port.port_name;
-                if !providers.contains_key(&addr) {
-                  providers.insert(addr.clone(), ProviderContainer {
-                    provider: WrappedDeviceProvider::new(Box::new(GenericUSB::new(addr))),
-                    is_autodetect: true,
-                    last_autodetect: now
-                  });
-                } else {
-                  providers.get_mut(&addr).unwrap().last_autodetect = now;
-                }
-              }
-            },
-            _ => ()
-          }
-        }
-      }
-
-      *self.last_detect.write().await = now;
-    }
-
-    // Age off old detections
-    let mut is_connected = HashMap::new();
-    for (k, v) in providers.iter() {
-      is_connected.insert(k.clone(), v.provider.info().await?.connected);
-    }
-    providers.retain(|k, v| *is_connected.get(k).unwrap() || !v.is_autodetect || v.last_autodetect.elapsed().as_secs() < 2);
-    Ok(())
-  }
-}
-
-#[rpc]
-impl ProviderManager {
-  async fn delete(&self, address: String) -> anyhow::Result<()> {
-    if self.providers.read().await.contains_key(&address) {
-      let v = self.providers.write().await.remove(&address);
-      if let Some(provider) = v {
-        if provider.provider.info().await?.connected {
-          provider.provider.disconnect().await?;
-        }
-      }
-    }
-    Ok(())
-  }
-
-  async fn provider(&self, address: String, msg: WrappedDeviceProviderRequest) -> anyhow::Result<WrappedDeviceProviderResponse> {
-    self.providers.read().await.get(&address).unwrap().provider.rpc_process(msg).await
-  }
-
-  async fn providers(&self) -> anyhow::Result<HashMap<String, ProviderInfo>> {
-    self.detect_devices().await.ok();
-
-    let mut map = HashMap::new();
-    let providers = self.providers.read().await;
-    for (address, provider) in providers.iter() {
-      map.insert(address.clone(), provider.provider.info().await?);
-    }
-    Ok(map)
-  }
-}
+use std::{collections::HashMap, sync::Arc};
+
+use grapple_hook_macros::rpc;
+use network_interface::{NetworkInterface, NetworkInterfaceConfig};
+use tokio::sync::RwLock;
+
+
+use super::{automation::{self, automation, AutomationAction, AutomationRule, AutomationRun, AutomationTrigger}, can_id::{self, FrcCanId}, device_class::{resolve_device_class, DeviceClass}, firmware_catalog::{firmware_catalog, FirmwareCatalog}, firmware_file::FirmwarePayload, config_clipboard::DeviceConfig, lasercan_interference::{analyse, InterferenceFinding, SensorHistory}, firmware_update::{self, update_statuses, UpdateStatus}, registry::{registry, RegistryEntry}, reminders::{due_reminders, DueReminder, Reminder}, bom::{bom, parse_csv, reconcile, BomEntry, BomReconciliation}, dashboard::dashboard_versions, remote_assist::{self, RemoteAssist, RemoteAssistStatus, RemoteSnapshot}, fixtures::{fixtures, require_developer_mode}, feature_flags::{flag_states, set_flag, FeatureFlagState}, metadata::{metadata, DeviceMetadata, MetadataHistory, QuickAction}, reports::{diagnostics_report, inventory_csv}, checklist::{checklists, ChecklistProgress, ChecklistStep}, device_manager::{DeviceId, DeviceManagerRequest, DeviceManagerResponse, Domain}, dfu_identify::{identify, DfuIdentification}, search::{best_match, search_fields, SearchResult}, session::{session_history, SessionSummary}, status_summary::{summarise, StatusSummary}, DeviceInfo, GatedRecovery, generic_usb::GenericUSB, gs_usb::{address_of, gs_usb_devices, GsUsb}, slcan::{Slcan, SLCAN_USB_IDS}, FirmwareUpgradeDeviceRequest, simulator::SIMULATOR_ADDRESS, templates::{assign, builtin_templates, template, RobotTemplate, TemplateApplication}, tutorial::{Tutorial, TutorialStatus}, pairing::{PairingEntry, PairingSession, PairingStatus}, pcan::usb::{available_channels, PcanUsb}, provider::{DeviceProvider, ProviderInfo, WrappedDeviceProvider, WrappedDeviceProviderRequest, WrappedDeviceProviderResponse}, roborio::daemon::RoboRioDaemon, usb_permissions::{fix_for, write_udev_rule, UsbIssueKind, UsbPermissionFix, GRAPPLE_USB_VID, GRAPPLE_USB_PID}};
+use crate::{anomaly::{anomalies, DetectorInfo}, errors::{catalog, coded, ErrorCatalogEntry, ErrorCode}, events::{aggregate, events, Event, Notification, AGGREGATION_WINDOW_MS}, firmware_library::{firmware_library, FirmwareImage}, logs::{log_files, LogFile}, operations::{journal, OperationKind, OperationRecord}, persistence::{recoveries, RecoveryRecord}, rpc::RpcBase, telemetry::{downsample, telemetry, TelemetryBucket, TelemetrySample, TimelineEntry}, telemetry_archive::{self, ArchivedChannel, RecordingInfo}, telemetry_csv::{export_combined_csv, ChannelSelection}, telemetry_stream::telemetry_streams, updates::download, usage_stats::{usage_stats, UsageStatsReport, UsageStatsSettings}, visibility::set_visible, wpilog::export_telemetry};
+
+pub struct ProviderContainer {
+  provider: WrappedDeviceProvider,
+  is_autodetect: bool,
+  last_autodetect: std::time::Instant
+}
+
+pub struct ProviderManager {
+  providers: Arc<RwLock<HashMap<String, ProviderContainer>>>,
+  last_detect: RwLock<std::time::Instant>,
+  tutorial: RwLock<Option<Tutorial>>,
+  pairing: RwLock<Option<PairingSession>>,
+  remote_assist: RemoteAssist,
+}
+
+impl ProviderManager {
+  pub async fn new() -> Self {
+    let mut hm = HashMap::new();
+    let rr = RoboRioDaemon::new();
+    hm.insert(rr.info().await.unwrap().address, ProviderContainer { 
+      provider: WrappedDeviceProvider::new(Box::new(rr)),
+      is_autodetect: false,
+      last_autodetect: std::time::Instant::now()
+    });
+    Self {
+      providers: Arc::new(RwLock::new(hm)),
+      last_detect: RwLock::new(std::time::Instant::now()),
+      tutorial: RwLock::new(None),
+      pairing: RwLock::new(None),
+      remote_assist: RemoteAssist::new(),
+    }
+  }
+
+  pub fn interfaces() -> Vec<NetworkInterface> {
+    NetworkInterface::show().unwrap_or(vec![])
+  }
+
+  pub async fn detect_devices(&self) -> anyhow::Result<()> {
+    let mut providers: tokio::sync::RwLockWriteGuard<HashMap<String, ProviderContainer>> = self.providers.write().await;
+
+    // Look for USB devices
+    let now = std::time::Instant::now();
+    if self.last_detect.read().await.elapsed().as_millis() > 500 {
+
+      if let Ok(ports) = tokio_serial::available_ports() {
+        for port in ports {
+          match port.port_type {
+            tokio_serial::SerialPortType::UsbPort(usbi) => {
+              let is_grapple = usbi.vid == GRAPPLE_USB_VID && usbi.pid == GRAPPLE_USB_PID;
+              let is_slcan = SLCAN_USB_IDS.contains(&(usbi.vid, usbi.pid));
+              if is_grapple || is_slcan {
+                let addr = port.port_name;
+                if !providers.contains_key(&addr) {
+                  let provider: Box<dyn DeviceProvider + Send + Sync> = if is_grapple { Box::new(GenericUSB::new(addr.clone())) } else { Box::new(Slcan::new(addr.clone())) };
+                  providers.insert(addr.clone(), ProviderContainer {
+                    provider: WrappedDeviceProvider::new(provider),
+                    is_autodetect: true,
+                    last_autodetect: now
+                  });
+                } else {
+                  providers.get_mut(&addr).unwrap().last_autodetect = now;
+                }
+              }
+            },
+            _ => ()
+          }
+        }
+      }
+
+      // candleLight adapters don't show up as serial ports, we find them on the USB bus instead
+      for device in gs_usb_devices() {
+        let addr = address_of(&device);
+        if !providers.contains_key(&addr) {
+          providers.insert(addr.clone(), ProviderContainer {
+            provider: WrappedDeviceProvider::new(Box::new(GsUsb::new(addr))),
+            is_autodetect: true,
+            last_autodetect: now
+          });
+        } else {
+          providers.get_mut(&addr).unwrap().last_autodetect = now;
+        }
+      }
+
+      // PCAN-USB adapters, if PEAK's PCANBasic is installed
+      for channel in available_channels() {
+        if !providers.contains_key(&channel) {
+          providers.insert(channel.clone(), ProviderContainer {
+            provider: WrappedDeviceProvider::new(Box::new(PcanUsb::new(channel))),
+            is_autodetect: true,
+            last_autodetect: now
+          });
+        } else {
+          providers.get_mut(&channel).unwrap().last_autodetect = now;
+        }
+      }
+
+      // CAN interfaces on Linux (e.g. can0), which we talk to directly through SocketCAN
+      #[cfg(target_os = "linux")]
+      for interface in super::socketcan::can_interfaces() {
+        if !providers.contains_key(&interface) {
+          providers.insert(interface.clone(), ProviderContainer {
+            provider: WrappedDeviceProvider::new(Box::new(super::socketcan::SocketCan::new(interface))),
+            is_autodetect: true,
+            last_autodetect: now
+          });
+        } else {
+          providers.get_mut(&interface).unwrap().last_autodetect = now;
+        }
+      }
+
+      *self.last_detect.write().await = now;
+    }
+
+    // Age off old detections
+    let mut is_connected = HashMap::new();
+    for (k, v) in providers.iter() {
+      is_connected.insert(k.clone(), v.provider.info().await?.connected);
+    }
+    providers.retain(|k, v| *is_connected.get(k).unwrap() || !v.is_autodetect || v.last_autodetect.elapsed().as_secs() < 2);
+    Ok(())
+  }
+
+  pub async fn all_devices(&self) -> Vec<(String, Domain, DeviceId, DeviceInfo, String)> {
+    collect_devices(&self.providers).await
+  }
+
+  /* Check for new firmware for every model that's currently connected */
+  pub async fn refresh_catalog(&self) -> anyhow::Result<FirmwareCatalog> {
+    let mut connected: Vec<(DeviceClass, Vec<String>)> = vec![];
+    for (_, _, _, info, _) in self.all_devices().await {
+      if let Some(class) = resolve_device_class(&info.device_type) {
+        let versions = match connected.iter_mut().find(|(c, _)| *c == class) {
+          Some((_, versions)) => versions,
+          None => { connected.push((class, vec![])); &mut connected.last_mut().unwrap().1 }
+        };
+        versions.extend(info.firmware_version.filter(|_| !info.is_dfu));
+      }
+    }
+    firmware_catalog().refresh(connected).await
+  }
+
+  /* Write out session summaries for every device, e.g. when the app is closing */
+  pub async fn end_sessions(&self) {
+    for (address, container) in self.providers.read().await.iter() {
+      if let Err(e) = container.provider.device_manager_call(DeviceManagerRequest::end_sessions {}).await {
+        log::warn!("Couldn't end device sessions on {}: {}", address, e);
+      }
+    }
+  }
+
+  pub async fn find_device(&self, device_id: &DeviceId) -> anyhow::Result<(String, Domain, DeviceId, DeviceInfo, String)> {
+    find_device(&self.providers, device_id).await
+  }
+
+  /* Make an RPC call against a device, wherever it is */
+  pub async fn call_device(&self, device_id: DeviceId, data: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+    call_device(&self.providers, device_id, data).await
+  }
+
+  pub async fn identify_device(&self, device_id: DeviceId) -> anyhow::Result<()> {
+    let (address, domain, device_id, _, _) = find_device(&self.providers, &device_id).await?;
+    let providers = self.providers.read().await;
+    let container = providers.get(&address).ok_or(coded(ErrorCode::DeviceNotFound, format!("Provider {} has gone away", address)))?;
+    container.provider.device_manager_call(DeviceManagerRequest::identify { domain, device_id }).await?;
+    Ok(())
+  }
+
+  /* Blink whichever device the pairing assistant is up to */
+  async fn blink_pairing_device(&self) -> anyhow::Result<()> {
+    let current = self.pairing.read().await.as_ref().and_then(|p| p.current().map(|e| e.device_id.clone()));
+    match current {
+      Some(device_id) => self.identify_device(device_id).await,
+      None => Ok(())
+    }
+  }
+}
+
+pub async fn find_device(providers: &RwLock<HashMap<String, ProviderContainer>>, device_id: &DeviceId) -> anyhow::Result<(String, Domain, DeviceId, DeviceInfo, String)> {
+  collect_devices(providers).await.into_iter()
+    .find(|(_, _, id, _, _)| id == device_id)
+    .ok_or(coded(ErrorCode::DeviceNotFound, format!("No device {:?}. Is it connected?", device_id)))
+}
+
+pub async fn call_device(providers: &RwLock<HashMap<String, ProviderContainer>>, device_id: DeviceId, data: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+  let (address, domain, device_id, _, _) = find_device(providers, &device_id).await?;
+
+  let providers = providers.read().await;
+  let container = providers.get(&address).ok_or(coded(ErrorCode::DeviceNotFound, format!("Provider {} has gone away", address)))?;
+  match container.provider.device_manager_call(DeviceManagerRequest::call { domain, device_id, data }).await? {
+    DeviceManagerResponse::call(result) => Ok(result),
+    _ => anyhow::bail!("Unexpected response from device manager")
+  }
+}
+
+/* Every device across every provider, as (provider address, domain, id, info, class) */
+pub async fn collect_devices(providers: &RwLock<HashMap<String, ProviderContainer>>) -> Vec<(String, Domain, DeviceId, DeviceInfo, String)> {
+  let mut all = vec![];
+  for (address, container) in providers.read().await.iter() {
+    if let Ok(DeviceManagerResponse::devices(domains)) = container.provider.device_manager_call(DeviceManagerRequest::devices {}).await {
+      for (domain, devices) in domains {
+        for (id, info, class) in devices {
+          all.push((address.clone(), domain.clone(), id, info, class));
+        }
+      }
+    }
+  }
+  all
+}
+
+#[rpc]
+impl ProviderManager {
+  async fn delete(&self, address: String) -> anyhow::Result<()> {
+    if self.providers.read().await.contains_key(&address) {
+      let v = self.providers.write().await.remove(&address);
+      if let Some(provider) = v {
+        if provider.provider.info().await?.connected {
+          provider.provider.disconnect().await?;
+        }
+      }
+    }
+    Ok(())
+  }
+
+  async fn provider(&self, address: String, msg: WrappedDeviceProviderRequest) -> anyhow::Result<WrappedDeviceProviderResponse> {
+    self.providers.read().await.get(&address).unwrap().provider.rpc_process(msg).await
+  }
+
+  async fn providers(&self) -> anyhow::Result<HashMap<String, ProviderInfo>> {
+    self.detect_devices().await.ok();
+
+    let mut map = HashMap::new();
+    let providers = self.providers.read().await;
+    for (address, provider) in providers.iter() {
+      map.insert(address.clone(), provider.provider.info().await?);
+    }
+    Ok(map)
+  }
+
+  async fn start_tutorial(&self) -> anyhow::Result<TutorialStatus> {
+    self.stop_tutorial().await?;
+
+    let tutorial = Tutorial::new();
+    self.providers.write().await.insert(SIMULATOR_ADDRESS.to_owned(), ProviderContainer {
+      provider: WrappedDeviceProvider::new(Box::new(tutorial.simulator())),
+      is_autodetect: false,
+      last_autodetect: std::time::Instant::now()
+    });
+    let status = tutorial.status();
+    *self.tutorial.write().await = Some(tutorial);
+    Ok(status)
+  }
+
+  /// Add an SLCAN adapter we can't recognise by its USB IDs, by its serial port (e.g. /dev/ttyACM0 or COM3)
+  async fn add_slcan_adapter(&self, port: String) -> anyhow::Result<()> {
+    let mut providers = self.providers.write().await;
+    if providers.contains_key(&port) {
+      anyhow::bail!("{} is already in use", port);
+    }
+    providers.insert(port.clone(), ProviderContainer {
+      provider: WrappedDeviceProvider::new(Box::new(Slcan::new(port))),
+      is_autodetect: false,
+      last_autodetect: std::time::Instant::now()
+    });
+    Ok(())
+  }
+
+  async fn tutorial_status(&self) -> anyhow::Result<Option<TutorialStatus>> {
+    Ok(self.tutorial.read().await.as_ref().map(|t| t.status()))
+  }
+
+  async fn stop_tutorial(&self) -> anyhow::Result<()> {
+    if self.tutorial.write().await.take().is_some() {
+      self.delete(SIMULATOR_ADDRESS.to_owned()).await?;
+    }
+    Ok(())
+  }
+
+  /// Walk through every connected device of one class (e.g. "LaserCAN"), blinking each in turn to be named
+  async fn start_pairing(&self, device_class: String) -> anyhow::Result<PairingStatus> {
+    let entries = self.all_devices().await.into_iter()
+      .filter(|(_, _, _, _, class)| *class == device_class)
+      .filter_map(|(_, _, id, _, _)| match id {
+        DeviceId::Serial(serial) => Some(PairingEntry {
+          device_id: id, serial, previous_nickname: metadata().get(serial).nickname, assigned_nickname: None, location: None, skipped: false
+        }),
+        DeviceId::Dfu(..) => None
+      })
+      .collect();
+
+    let session = PairingSession::new(device_class, entries)?;
+    let status = session.status();
+    *self.pairing.write().await = Some(session);
+    self.blink_pairing_device().await?;
+    Ok(status)
+  }
+
+  async fn pairing_status(&self) -> anyhow::Result<Option<PairingStatus>> {
+    Ok(self.pairing.read().await.as_ref().map(|p| p.status()))
+  }
+
+  /// Blink the current device again, e.g. if the user missed it
+  async fn pairing_blink(&self) -> anyhow::Result<()> {
+    self.blink_pairing_device().await
+  }
+
+  /// Name the device that's blinking (optionally with where it is on the robot, kept as a tag) and blink the next one
+  async fn pairing_assign(&self, nickname: String, location: Option<String>) -> anyhow::Result<PairingStatus> {
+    let status = {
+      let mut pairing = self.pairing.write().await;
+      let session = pairing.as_mut().ok_or(anyhow::anyhow!("Pairing hasn't been started"))?;
+      let entry = session.assign(nickname, location)?;
+      metadata().update(entry.serial, |m| {
+        m.nickname = entry.assigned_nickname.clone();
+        if let Some(location) = &entry.location {
+          if !m.tags.iter().any(|t| t.eq_ignore_ascii_case(location)) {
+            m.tags.push(location.clone());
+          }
+        }
+      });
+      session.status()
+    };
+    self.blink_pairing_device().await?;
+    Ok(status)
+  }
+
+  async fn pairing_skip(&self) -> anyhow::Result<PairingStatus> {
+    let status = {
+      let mut pairing = self.pairing.write().await;
+      let session = pairing.as_mut().ok_or(anyhow::anyhow!("Pairing hasn't been started"))?;
+      session.skip()?;
+      session.status()
+    };
+    self.blink_pairing_device().await?;
+    Ok(status)
+  }
+
+  /// Names already assigned are kept
+  async fn stop_pairing(&self) -> anyhow::Result<()> {
+    *self.pairing.write().await = None;
+    Ok(())
+  }
+
+  /// Events raised since the given event ID (or all recent events), oldest first
+  async fn events(&self, since: Option<u64>) -> anyhow::Result<Vec<Event>> {
+    Ok(events().since(since))
+  }
+
+  /// Like events, but with bursts of similar events rolled up for showing to the user
+  async fn notifications(&self, since: Option<u64>) -> anyhow::Result<Vec<Notification>> {
+    Ok(aggregate(&events().since(since), AGGREGATION_WINDOW_MS))
+  }
+
+  /// Build an FRC CAN arbitration ID from its fields, e.g. to find a device's frames in a capture
+  async fn encode_can_id(&self, device_type: u8, manufacturer: u8, api_class: u8, api_index: u8, device_number: u8) -> anyhow::Result<FrcCanId> {
+    can_id::encode(device_type, manufacturer, api_class, api_index, device_number)
+  }
+
+  async fn decode_can_id(&self, arbitration_id: u32) -> anyhow::Result<FrcCanId> {
+    can_id::decode(arbitration_id)
+  }
+
+  /// Look for pairs of LaserCANs whose noise spikes line up over the last window_ms (default a minute), which usually
+  /// means they can see each other's emitters
+  async fn lasercan_interference(&self, window_ms: Option<i64>) -> anyhow::Result<Vec<InterferenceFinding>> {
+    let since = chrono::Utc::now().timestamp_millis() - window_ms.unwrap_or(60_000);
+    let mut sensors = vec![];
+    for (_, _, id, _, class) in self.all_devices().await {
+      if let (DeviceId::Serial(serial), "LaserCAN") = (&id, class.as_str()) {
+        let budget = self.call_device(id.clone(), serde_json::json!({ "method": "config", "data": {} })).await.ok()
+          .and_then(|r| r.get("data")?.get("budget")?.as_str().map(str::to_owned));
+        sensors.push(SensorHistory { serial: *serial, samples: telemetry().history(*serial, "distance_mm", Some(since), None), budget });
+      }
+    }
+    Ok(analyse(&sensors))
+  }
+
+  /// Backend log files, the one being written to first, for attaching to bug reports
+  async fn log_files(&self) -> anyhow::Result<Vec<LogFile>> {
+    Ok(log_files())
+  }
+
+  async fn firmware_catalog(&self) -> anyhow::Result<FirmwareCatalog> {
+    Ok(firmware_catalog().get())
+  }
+
+  async fn refresh_firmware_catalog(&self) -> anyhow::Result<FirmwareCatalog> {
+    self.refresh_catalog().await
+  }
+
+  /// How everything is doing in a sentence or two, for screen readers and chat or notification integrations
+  async fn status_summary(&self) -> anyhow::Result<StatusSummary> {
+    let devices = self.all_devices().await.into_iter().map(|(_, domain, id, info, class)| (domain, id, info, class)).collect::<Vec<_>>();
+    Ok(summarise(&devices, &firmware_catalog().get()))
+  }
+
+  /// Stop (or resume) reaching out for new firmware in the background
+  async fn set_offline_mode(&self, offline: bool) -> anyhow::Result<()> {
+    firmware_catalog().set_offline(offline);
+    Ok(())
+  }
+
+  /// Anomaly detectors run over incoming telemetry, with their current settings
+  async fn anomaly_detectors(&self) -> anyhow::Result<Vec<DetectorInfo>> {
+    Ok(anomalies().detectors())
+  }
+
+  async fn set_anomaly_detector(&self, id: String, enabled: Option<bool>, sensitivity: Option<f64>) -> anyhow::Result<()> {
+    anomalies().configure(&id, enabled, sensitivity)
+  }
+
+  /// Stores found damaged at startup and what was done about them
+  async fn integrity_report(&self) -> anyhow::Result<Vec<RecoveryRecord>> {
+    Ok(recoveries())
+  }
+
+  async fn error_catalog(&self) -> anyhow::Result<Vec<ErrorCatalogEntry>> {
+    Ok(catalog())
+  }
+
+  /// Dashboard contract versions by device class, so the frontend can detect when it's out of step
+  async fn dashboard_versions(&self) -> anyhow::Result<HashMap<String, u32>> {
+    Ok(dashboard_versions())
+  }
+
+  /// Fuzzy search across all devices, best match first
+  async fn search(&self, query: String) -> anyhow::Result<Vec<SearchResult>> {
+    let mut results = self.all_devices().await.into_iter()
+      .filter_map(|(provider, domain, device_id, info, device_class)| {
+        let fields = search_fields(&domain, &device_class, &info);
+        best_match(&query, &fields).map(|(score, matched_field, matched_value)| SearchResult {
+          provider, domain, device_id, device_class, info, score, matched_field, matched_value
+        })
+      })
+      .collect::<Vec<_>>();
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    Ok(results)
+  }
+
+  async fn checklist(&self, device_class: String) -> anyhow::Result<Vec<ChecklistStep>> {
+    Ok(checklists().definition(&device_class))
+  }
+
+  async fn set_checklist(&self, device_class: String, steps: Vec<ChecklistStep>) -> anyhow::Result<()> {
+    checklists().set_definition(&device_class, steps);
+    Ok(())
+  }
+
+  async fn reset_checklist(&self, device_class: String) -> anyhow::Result<()> {
+    checklists().reset_definition(&device_class);
+    Ok(())
+  }
+
+  async fn checklist_progress(&self, serial: u32) -> anyhow::Result<ChecklistProgress> {
+    let (_, _, _, info, device_class) = self.all_devices().await.into_iter()
+      .find(|(_, _, _, info, _)| info.serial == Some(serial) && !info.is_dfu)
+      .ok_or(coded(ErrorCode::DeviceNotFound, format!("No device with serial 0x{:x}", serial)))?;
+    checklists().progress(&device_class, &info)
+  }
+
+  async fn set_checklist_step(&self, serial: u32, step_id: String, complete: bool) -> anyhow::Result<()> {
+    checklists().set_complete(serial, &step_id, complete);
+    Ok(())
+  }
+
+  async fn device_metadata(&self, serial: u32) -> anyhow::Result<DeviceMetadata> {
+    Ok(metadata().get(serial))
+  }
+
+  async fn set_nickname(&self, serial: u32, nickname: Option<String>) -> anyhow::Result<()> {
+    metadata().update(serial, |m| m.nickname = nickname.filter(|n| !n.trim().is_empty()));
+    Ok(())
+  }
+
+  async fn set_notes(&self, serial: u32, notes: String) -> anyhow::Result<()> {
+    metadata().update(serial, |m| m.notes = notes);
+    Ok(())
+  }
+
+  /// Host-side edits (nicknames, notes, tags, ...) made this session that can be undone or redone, most recent first
+  async fn metadata_history(&self) -> anyhow::Result<MetadataHistory> {
+    Ok(metadata().history())
+  }
+
+  /// Returns a description of what was undone, or None if there was nothing to undo
+  async fn undo_metadata_edit(&self) -> anyhow::Result<Option<String>> {
+    Ok(metadata().undo())
+  }
+
+  async fn redo_metadata_edit(&self) -> anyhow::Result<Option<String>> {
+    Ok(metadata().redo())
+  }
+
+  async fn add_reminder(&self, serial: u32, label: String, interval_hours: u32, notify: bool) -> anyhow::Result<Reminder> {
+    if interval_hours == 0 {
+      anyhow::bail!("Reminders need an interval of at least an hour");
+    }
+    let reminder = Reminder {
+      id: uuid::Uuid::new_v4().to_string(), label, interval_hours, notify,
+      last_done_ms: chrono::Utc::now().timestamp_millis(), last_notified_ms: None
+    };
+    metadata().update(serial, |m| m.reminders.push(reminder.clone()));
+    Ok(reminder)
+  }
+
+  async fn remove_reminder(&self, serial: u32, id: String) -> anyhow::Result<()> {
+    metadata().update(serial, |m| m.reminders.retain(|r| r.id != id));
+    Ok(())
+  }
+
+  /// Mark a reminder as done, restarting its interval from now
+  async fn complete_reminder(&self, serial: u32, id: String) -> anyhow::Result<()> {
+    metadata().update(serial, |m| match m.reminders.iter_mut().find(|r| r.id == id) {
+      Some(r) => { r.last_done_ms = chrono::Utc::now().timestamp_millis(); Ok(()) },
+      None => Err(anyhow::anyhow!("No such reminder"))
+    })
+  }
+
+  async fn due_reminders(&self) -> anyhow::Result<Vec<DueReminder>> {
+    Ok(due_reminders(chrono::Utc::now().timestamp_millis()))
+  }
+
+  /// Reports include each device's nickname and notes. Both return the number of devices included.
+  async fn export_inventory(&self, path: String) -> anyhow::Result<usize> {
+    let devices = self.all_devices().await;
+    std::fs::write(&path, inventory_csv(&devices))?;
+    Ok(devices.len())
+  }
+
+  async fn export_diagnostics(&self, path: String) -> anyhow::Result<usize> {
+    let devices = self.all_devices().await;
+    std::fs::write(&path, diagnostics_report(&devices, &events().since(None)))?;
+    Ok(devices.len())
+  }
+
+  async fn bom(&self) -> anyhow::Result<Vec<BomEntry>> {
+    Ok(bom().get())
+  }
+
+  async fn set_bom(&self, entries: Vec<BomEntry>) -> anyhow::Result<()> {
+    bom().update(|b| *b = entries);
+    Ok(())
+  }
+
+  /// Replaces the BOM with one parsed from "model,quantity" CSV
+  async fn import_bom_csv(&self, csv: String) -> anyhow::Result<Vec<BomEntry>> {
+    let entries = parse_csv(&csv)?;
+    bom().update(|b| *b = entries.clone());
+    Ok(entries)
+  }
+
+  /// Compare what's connected against the BOM
+  async fn reconcile_bom(&self) -> anyhow::Result<BomReconciliation> {
+    Ok(reconcile(&bom().get(), &self.all_devices().await))
+  }
+
+  async fn robot_templates(&self) -> anyhow::Result<Vec<RobotTemplate>> {
+    Ok(builtin_templates())
+  }
+
+  /// Replace the BOM with the template's devices, name connected devices after the template's roles and give them its
+  /// baseline configuration. Devices already nicknamed keep their names.
+  async fn apply_robot_template(&self, id: String) -> anyhow::Result<TemplateApplication> {
+    let template = template(&id)?;
+    let bom_entries = template.bom();
+    bom().update(|b| *b = bom_entries.clone());
+
+    let devices = self.all_devices().await;
+    let nicknamed = metadata().all().into_iter().filter(|(_, m)| m.nickname.is_some()).map(|(serial, _)| serial).collect();
+    let (assigned, unfilled) = assign(&template, &devices, &nicknamed);
+
+    // Undone all at once, rather than one device at a time
+    metadata().batch(|| for assignment in &assigned {
+      metadata().update(assignment.serial, |m| if m.nickname.is_none() { m.nickname = Some(assignment.role.clone()) });
+    });
+
+    let mut config_errors = vec![];
+    for assignment in &assigned {
+      let baseline = template.slots.iter().find(|s| s.role == assignment.role).and_then(|s| s.baseline.clone());
+      if let Some(fields) = baseline {
+        if let Err(e) = self.call_device(assignment.device_id.clone(), serde_json::json!({ "method": "apply_config", "data": { "config": fields } })).await {
+          config_errors.push((assignment.serial, e.to_string()));
+        }
+      }
+    }
+
+    Ok(TemplateApplication { bom: bom_entries, assigned, unfilled, config_errors })
+  }
+
+  /// The frontend tells us when it's hidden (e.g. minimised), so we can slow down
+  async fn set_app_visible(&self, visible: bool) -> anyhow::Result<()> {
+    set_visible(visible);
+    Ok(())
+  }
+
+  async fn usage_stats_settings(&self) -> anyhow::Result<UsageStatsSettings> {
+    Ok(usage_stats().settings())
+  }
+
+  /// Opt in to (or out of) sending anonymous usage statistics
+  async fn set_usage_stats_enabled(&self, enabled: bool) -> anyhow::Result<UsageStatsSettings> {
+    usage_stats().set_enabled(enabled)
+  }
+
+  /// Exactly what the next usage statistics report would contain, whether or not they're enabled
+  async fn preview_usage_stats(&self) -> anyhow::Result<UsageStatsReport> {
+    Ok(usage_stats().preview(chrono::Utc::now().timestamp_millis()))
+  }
+
+  /// Summaries of past device sessions, newest first. All devices if serial isn't given.
+  async fn sessions(&self, serial: Option<u32>) -> anyhow::Result<Vec<SessionSummary>> {
+    Ok(session_history().list(serial))
+  }
+
+  async fn feature_flags(&self, serial: u32) -> anyhow::Result<Vec<FeatureFlagState>> {
+    Ok(flag_states(serial))
+  }
+
+  /// Developer setting. enabled: None goes back to the flag's default.
+  async fn set_feature_flag(&self, serial: u32, flag: String, enabled: Option<bool>) -> anyhow::Result<()> {
+    require_developer_mode()?;
+    set_flag(serial, &flag, enabled)
+  }
+
+  /// Returns the config as JSON text, ready to go on the OS clipboard
+  async fn copy_config(&self, device_id: DeviceId) -> anyhow::Result<String> {
+    let (_, _, _, info, class) = self.find_device(&device_id).await?;
+    let response = self.call_device(device_id, serde_json::json!({ "method": "config", "data": {} })).await?;
+    let fields = response.get("data").cloned().ok_or(anyhow::anyhow!("A {} doesn't have a configuration to copy", class))?;
+    DeviceConfig::new(&class, info.firmware_version, fields).to_clipboard()
+  }
+
+  async fn paste_config(&self, device_id: DeviceId, payload: String) -> anyhow::Result<()> {
+    let config = DeviceConfig::from_clipboard(&payload)?;
+    let (_, _, _, _, class) = self.find_device(&device_id).await?;
+    config.validate_for(&class)?;
+    self.call_device(device_id, serde_json::json!({ "method": "apply_config", "data": { "config": config.fields } })).await?;
+    Ok(())
+  }
+
+  async fn quick_actions(&self, serial: u32) -> anyhow::Result<Vec<QuickAction>> {
+    Ok(metadata().get(serial).quick_actions)
+  }
+
+  async fn add_quick_action(&self, serial: u32, label: String, request: serde_json::Value) -> anyhow::Result<QuickAction> {
+    let action = QuickAction { id: uuid::Uuid::new_v4().to_string(), label, request };
+    metadata().update(serial, |m| m.quick_actions.push(action.clone()));
+    Ok(action)
+  }
+
+  async fn remove_quick_action(&self, serial: u32, id: String) -> anyhow::Result<()> {
+    metadata().update(serial, |m| m.quick_actions.retain(|a| a.id != id));
+    Ok(())
+  }
+
+  async fn run_quick_action(&self, serial: u32, id: String) -> anyhow::Result<serde_json::Value> {
+    let action = metadata().get(serial).quick_actions.into_iter()
+      .find(|a| a.id == id)
+      .ok_or(anyhow::anyhow!("No such quick action"))?;
+    self.call_device(DeviceId::Serial(serial), action.request).await
+  }
+
+  async fn automation_rules(&self) -> anyhow::Result<Vec<AutomationRule>> {
+    Ok(automation().rules())
+  }
+
+  async fn add_automation_rule(&self, name: String, trigger: AutomationTrigger, device_class: Option<String>, factory_fresh_only: bool, actions: Vec<AutomationAction>) -> anyhow::Result<AutomationRule> {
+    automation().add(AutomationRule { id: String::new(), name, enabled: true, trigger, device_class, factory_fresh_only, actions })
+  }
+
+  async fn update_automation_rule(&self, rule: AutomationRule) -> anyhow::Result<()> {
+    automation().replace(rule)
+  }
+
+  async fn remove_automation_rule(&self, id: String) -> anyhow::Result<()> {
+    automation().remove(&id);
+    Ok(())
+  }
+
+  /// Run a rule against a device now, regardless of its trigger, to try it out
+  async fn run_automation_rule(&self, id: String, serial: u32) -> anyhow::Result<AutomationRun> {
+    let rule = automation().rule(&id)?;
+    Ok(automation::execute(self, &rule, serial).await)
+  }
+
+  /// What the rules have done this session, most recent first
+  async fn automation_runs(&self) -> anyhow::Result<Vec<AutomationRun>> {
+    Ok(automation().runs())
+  }
+
+  /// Developer mode: record every RPC call made against a device, to turn into a regression test fixture
+  async fn start_fixture_capture(&self, serial: u32) -> anyhow::Result<()> {
+    require_developer_mode()?;
+    fixtures().start(serial);
+    Ok(())
+  }
+
+  async fn finish_fixture_capture(&self, serial: u32) -> anyhow::Result<String> {
+    require_developer_mode()?;
+    Ok(fixtures().finish(serial)?.display().to_string())
+  }
+
+  async fn firmware_library(&self) -> anyhow::Result<Vec<FirmwareImage>> {
+    Ok(firmware_library().list())
+  }
+
+  async fn stage_firmware(&self, data: Vec<u8>, label: String, source_url: Option<String>) -> anyhow::Result<FirmwareImage> {
+    firmware_library().stage(&data, label, source_url)
+  }
+
+  async fn delete_firmware(&self, sha256: String) -> anyhow::Result<()> {
+    firmware_library().delete(&sha256)
+  }
+
+  /// Flash an image from the library onto a device that's in firmware update mode
+  async fn flash_from_library(&self, serial: u32, sha256: String) -> anyhow::Result<()> {
+    let path = firmware_library().file(&sha256)?;
+    self.call_device(DeviceId::Dfu(serial), serde_json::to_value(FirmwarePayload::File(path).request())?).await?;
+    Ok(())
+  }
+
+  /// The whole update in one go, from the device's normal mode through to it running the new firmware. Follow along
+  /// with update_status. If expected_version is given, the update fails unless the device comes back running it.
+  async fn update_firmware(&self, serial: u32, data: Vec<u8>, expected_version: Option<String>) -> anyhow::Result<()> {
+    firmware_update::start(self.providers.clone(), serial, FirmwarePayload::Bytes(data), expected_version).await
+  }
+
+  /// As update_firmware, but the image (or bundle) is streamed from a file rather than sent over in whole
+  async fn update_firmware_from_file(&self, serial: u32, path: String, expected_version: Option<String>) -> anyhow::Result<()> {
+    firmware_update::start(self.providers.clone(), serial, FirmwarePayload::File(path.into()), expected_version).await
+  }
+
+  async fn update_firmware_from_library(&self, serial: u32, sha256: String, expected_version: Option<String>) -> anyhow::Result<()> {
+    let path = firmware_library().file(&sha256)?;
+    firmware_update::start(self.providers.clone(), serial, FirmwarePayload::File(path), expected_version).await
+  }
+
+  /// Every device seen before, including those not connected now, with their last known configuration
+  async fn device_registry(&self) -> anyhow::Result<Vec<RegistryEntry>> {
+    let online = collect_devices(&self.providers).await.into_iter().filter_map(|(_, _, _, info, _)| info.serial).collect::<std::collections::HashSet<_>>();
+    Ok(registry().list().into_iter().map(|device| RegistryEntry { online: online.contains(&device.serial), device }).collect())
+  }
+
+  async fn forget_device(&self, serial: u32) -> anyhow::Result<()> {
+    if !registry().forget(serial) {
+      anyhow::bail!("Device {:x} isn't in the registry", serial);
+    }
+    Ok(())
+  }
+
+  /// For a board sitting in its bootloader that we know nothing about: which product it is, and what to flash it with
+  async fn identify_dfu_device(&self, serial: u32) -> anyhow::Result<DfuIdentification> {
+    let (address, domain, device_id, _, _) = self.find_device(&DeviceId::Dfu(serial)).await
+      .map_err(|_| coded(ErrorCode::DeviceNotFound, format!("Device {:x} isn't in firmware update mode", serial)))?;
+
+    // Ask the bootloader directly, rather than going on what it said at the last enumeration
+    let info = {
+      let providers = self.providers.read().await;
+      let container = providers.get(&address).ok_or(coded(ErrorCode::DeviceNotFound, format!("Provider {} has gone away", address)))?;
+      match container.provider.device_manager_call(DeviceManagerRequest::probe { domain, device_id: device_id.clone() }).await? {
+        DeviceManagerResponse::probe(info) => info,
+        _ => anyhow::bail!("Unexpected response from device manager")
+      }
+    };
+
+    let mut catalog = firmware_catalog().get();
+    if let Some(class) = resolve_device_class(&info.device_type) {
+      if catalog.latest(class).is_none() {
+        match firmware_catalog().refresh(vec![(class, vec![])]).await {
+          Ok(refreshed) => catalog = refreshed,
+          Err(e) => log::info!("Couldn't look up firmware for {:x}: {}", serial, e)
+        }
+      }
+    }
+
+    let firmware_url = self.call_device(device_id, serde_json::to_value(FirmwareUpgradeDeviceRequest::get_firmware_url {})?).await.ok()
+      .and_then(|r| serde_json::from_value(r.get("data").cloned().unwrap_or_default()).ok())
+      .flatten();
+    let last_known = registry().list().into_iter().find(|d| d.serial == serial);
+
+    identify(&info, last_known, &catalog, firmware_url, firmware_library().list())
+  }
+
+  /// One-click fix for a device held back because its firmware is too old: download the compatible release found for it
+  /// and run the full update
+  async fn recover_gated_device(&self, serial: u32) -> anyhow::Result<()> {
+    let response = self.call_device(DeviceId::Serial(serial), serde_json::json!({ "method": "recovery", "data": {} })).await
+      .map_err(|_| anyhow::anyhow!("Device {:x} isn't waiting on a firmware update", serial))?;
+    let recovery: GatedRecovery = serde_json::from_value(response.get("data").cloned().unwrap_or_default())?;
+
+    let release = recovery.release.ok_or(anyhow::anyhow!("No compatible firmware release has been found for this device"))?;
+    let url = recovery.download_url.ok_or(anyhow::anyhow!("Release {} has no firmware file we can flash automatically. Download it from {}", release.tag_name, release.html_url))?;
+
+    let data = download(&url).await?;
+    let image = firmware_library().stage(&data, release.tag_name.clone(), Some(url))?;
+    // Flash from the library's copy, so the image isn't held in memory for the whole update
+    let path = firmware_library().file(&image.sha256)?;
+    firmware_update::start(self.providers.clone(), serial, FirmwarePayload::File(path), Some(release.version())).await
+  }
+
+  async fn update_status(&self, serial: u32) -> anyhow::Result<Option<UpdateStatus>> {
+    Ok(update_statuses().lock().unwrap().get(&serial).cloned())
+  }
+
+  /// Start sharing a read-only view of devices and telemetry through the given relay. Give the code to your mentor.
+  async fn start_remote_assist(&self, relay_url: String) -> anyhow::Result<RemoteAssistStatus> {
+    Ok(self.remote_assist.start(relay_url, self.providers.clone()))
+  }
+
+  async fn stop_remote_assist(&self) -> anyhow::Result<()> {
+    self.remote_assist.stop();
+    Ok(())
+  }
+
+  async fn remote_assist_status(&self) -> anyhow::Result<RemoteAssistStatus> {
+    Ok(self.remote_assist.status())
+  }
+
+  /// For the mentor: view the team's shared session
+  async fn view_remote_session(&self, relay_url: String, code: String) -> anyhow::Result<RemoteSnapshot> {
+    remote_assist::view(&relay_url, &code).await
+  }
+
+  async fn usb_permission_fix(&self, kind: UsbIssueKind) -> anyhow::Result<UsbPermissionFix> {
+    Ok(fix_for(kind))
+  }
+
+  /// Returns where the rule was written, so the user can copy it into /etc/udev/rules.d
+  async fn write_udev_rule(&self) -> anyhow::Result<String> {
+    Ok(write_udev_rule()?.display().to_string())
+  }
+
+  async fn recoverable_operations(&self) -> anyhow::Result<Vec<OperationRecord>> {
+    Ok(journal().recoverable())
+  }
+
+  /// Re-run an interrupted firmware update against the device, which will have come back up in DFU mode
+  async fn resume_operation(&self, id: String) -> anyhow::Result<()> {
+    let record = journal().get(&id).ok_or(anyhow::anyhow!("No such operation"))?;
+    let payload = journal().payload_file(&id).ok_or(anyhow::anyhow!("The firmware for this operation is no longer available"))?;
+
+    match record.kind {
+      OperationKind::FirmwareUpdate => {
+        let device_id = DeviceId::Dfu(record.serial);
+        let providers = self.providers.read().await;
+        for (_, container) in providers.iter() {
+          if let Ok(DeviceManagerResponse::devices(domains)) = container.provider.device_manager_call(DeviceManagerRequest::devices {}).await {
+            for (domain, devices) in domains {
+              if devices.iter().any(|(id, _, _)| *id == device_id) {
+                container.provider.device_manager_call(DeviceManagerRequest::call {
+                  domain,
+                  device_id: device_id.clone(),
+                  data: serde_json::to_value(FirmwarePayload::File(payload.clone()).request())?
+                }).await?;
+                journal().mark_resumed(&id);
+                return Ok(());
+              }
+            }
+          }
+        }
+        anyhow::bail!("Could not find serial 0x{:x} in firmware update mode. Is it connected?", record.serial)
+      }
+    }
+  }
+
+  async fn discard_operation(&self, id: String) -> anyhow::Result<()> {
+    journal().discard(&id);
+    Ok(())
+  }
+
+  async fn telemetry_channels(&self, serial: u32) -> anyhow::Result<Vec<String>> {
+    Ok(telemetry().channels(serial))
+  }
+
+  async fn telemetry_history(&self, serial: u32, channel: String, start_ms: Option<i64>, end_ms: Option<i64>) -> anyhow::Result<Vec<TelemetrySample>> {
+    Ok(telemetry().history(serial, &channel, start_ms, end_ms))
+  }
+
+  /// Telemetry and configuration changes for one device on a single timeline. channels defaults to all of them.
+  async fn device_timeline(&self, serial: u32, channels: Option<Vec<String>>, start_ms: Option<i64>, end_ms: Option<i64>) -> anyhow::Result<Vec<TimelineEntry>> {
+    Ok(telemetry().timeline(serial, channels, start_ms, end_ms))
+  }
+
+  /// Returns the number of samples written
+  async fn export_wpilog(&self, path: String, serials: Vec<u32>, origin_ms: Option<i64>, compress: Option<bool>) -> anyhow::Result<usize> {
+    export_telemetry(&path, &serials, origin_ms, compress.unwrap_or(false))
+  }
+
+  /// Have new samples for a device pushed as "telemetry" events, rather than polling for them. channels defaults to
+  /// all of them. Subscriptions lapse unless renewed at least every 30 seconds.
+  async fn subscribe_telemetry(&self, serial: u32, channels: Option<Vec<String>>) -> anyhow::Result<String> {
+    Ok(telemetry_streams().subscribe(serial, channels))
+  }
+
+  async fn renew_telemetry_subscription(&self, subscription: String) -> anyhow::Result<()> {
+    telemetry_streams().renew(&subscription)
+  }
+
+  async fn unsubscribe_telemetry(&self, subscription: String) -> anyhow::Result<()> {
+    telemetry_streams().unsubscribe(&subscription);
+    Ok(())
+  }
+
+  /// Selected channels from any number of devices, resampled every period_ms (20ms by default) onto one timebase.
+  /// Returns the number of rows written.
+  async fn export_combined_csv(&self, path: String, channels: Vec<ChannelSelection>, period_ms: Option<i64>, start_ms: Option<i64>, end_ms: Option<i64>) -> anyhow::Result<usize> {
+    export_combined_csv(&path, &channels, period_ms, start_ms, end_ms)
+  }
+
+  /// Compressed telemetry recordings, kept in the data directory. save_recording returns the number of samples saved.
+  async fn save_recording(&self, name: String, serials: Vec<u32>) -> anyhow::Result<usize> {
+    telemetry_archive::save_recording(&name, &serials)
+  }
+
+  async fn recordings(&self) -> anyhow::Result<Vec<RecordingInfo>> {
+    Ok(telemetry_archive::list_recordings())
+  }
+
+  async fn load_recording(&self, name: String) -> anyhow::Result<Vec<ArchivedChannel>> {
+    telemetry_archive::load_recording(&name)
+  }
+
+  async fn delete_recording(&self, name: String) -> anyhow::Result<()> {
+    telemetry_archive::delete_recording(&name)
+  }
+
+  /// As telemetry_history, but reduced to at most max_points min/max/avg buckets (e.g. one per pixel of chart width)
+  async fn telemetry_history_downsampled(&self, serial: u32, channel: String, start_ms: Option<i64>, end_ms: Option<i64>, max_points: usize) -> anyhow::Result<Vec<TelemetryBucket>> {
+    Ok(downsample(&telemetry().history(serial, &channel, start_ms, end_ms), max_points))
+  }
+}
@@ -0,0 +1,61 @@
+use std::{collections::HashMap, sync::Mutex};
+
+/* Frames that arrived but couldn't be decoded. Rather than dropping them, identical frames are counted up so firmware
+   developers can see exactly what a prototype is putting on the bus. */
+
+/* Distinct frames kept per domain, so a device spewing garbage can't eat all our memory */
+const MAX_QUARANTINED: usize = 256;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct QuarantinedFrame {
+  pub arbitration_id: u32,
+  pub data: Vec<u8>,
+  pub error: String,
+  pub count: usize,
+  pub first_seen_ms: i64,
+  pub last_seen_ms: i64,
+}
+
+pub struct FrameQuarantine {
+  frames: Mutex<HashMap<(u32, Vec<u8>), QuarantinedFrame>>,
+  overflowed: Mutex<usize>,
+}
+
+impl FrameQuarantine {
+  pub fn new() -> Self {
+    Self { frames: Mutex::new(HashMap::new()), overflowed: Mutex::new(0) }
+  }
+
+  pub fn add(&self, arbitration_id: u32, data: &[u8], error: String, timestamp_ms: i64) {
+    let mut frames = self.frames.lock().unwrap();
+    let key = (arbitration_id, data.to_vec());
+    match frames.get_mut(&key) {
+      Some(frame) => {
+        frame.count += 1;
+        frame.last_seen_ms = timestamp_ms;
+        frame.error = error;
+      },
+      None if frames.len() >= MAX_QUARANTINED => *self.overflowed.lock().unwrap() += 1,
+      None => {
+        frames.insert(key, QuarantinedFrame { arbitration_id, data: data.to_vec(), error, count: 1, first_seen_ms: timestamp_ms, last_seen_ms: timestamp_ms });
+      }
+    }
+  }
+
+  /* Most frequent first */
+  pub fn frames(&self) -> Vec<QuarantinedFrame> {
+    let mut frames = self.frames.lock().unwrap().values().cloned().collect::<Vec<_>>();
+    frames.sort_by(|a, b| b.count.cmp(&a.count));
+    frames
+  }
+
+  /* Malformed frames that weren't kept because the quarantine was full */
+  pub fn overflowed(&self) -> usize {
+    *self.overflowed.lock().unwrap()
+  }
+
+  pub fn clear(&self) {
+    self.frames.lock().unwrap().clear();
+    *self.overflowed.lock().unwrap() = 0;
+  }
+}
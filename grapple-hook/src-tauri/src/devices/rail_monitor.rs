@@ -0,0 +1,88 @@
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct RailAlertConfig {
+  pub enabled: bool,
+  /* A drop faster than this (V/s) is a sag */
+  pub sag_v_per_s: f64,
+  /* Swings faster than this (V/s) count towards oscillation */
+  pub oscillation_v_per_s: f64,
+  /* How many direction reversals within the window make an oscillation */
+  pub oscillation_reversals: usize,
+  pub window_ms: i64,
+  /* Don't repeat an alert for the same rail more often than this */
+  pub cooldown_ms: i64,
+}
+
+impl Default for RailAlertConfig {
+  fn default() -> Self {
+    Self { enabled: true, sag_v_per_s: 10.0, oscillation_v_per_s: 5.0, oscillation_reversals: 4, window_ms: 1000, cooldown_ms: 5000 }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RailAlert {
+  Sag { dv_dt: f64 },
+  Oscillation { reversals: usize },
+}
+
+struct RailState {
+  last: (i64, f64),
+  /* Timestamps and slopes of the fast swings seen within the window */
+  swings: VecDeque<(i64, f64)>,
+  last_alert_ms: Option<i64>,
+}
+
+/* Watches the rate of change of each adjustable rail's voltage, catching a failing regulator or an overloaded rail
+   before it browns out whatever it's powering */
+pub struct RailMonitor {
+  pub config: RailAlertConfig,
+  rails: HashMap<usize, RailState>,
+}
+
+impl RailMonitor {
+  pub fn new() -> Self {
+    Self { config: RailAlertConfig::default(), rails: HashMap::new() }
+  }
+
+  pub fn observe(&mut self, channel: usize, timestamp_ms: i64, voltage: f64) -> Option<RailAlert> {
+    if !self.config.enabled {
+      return None;
+    }
+
+    let config = &self.config;
+    let state = self.rails.entry(channel).or_insert_with(|| RailState { last: (timestamp_ms, voltage), swings: VecDeque::new(), last_alert_ms: None });
+
+    let (last_ts, last_v) = state.last;
+    state.last = (timestamp_ms, voltage);
+    if timestamp_ms <= last_ts {
+      return None;
+    }
+
+    let dv_dt = (voltage - last_v) / ((timestamp_ms - last_ts) as f64 / 1000.0);
+
+    if dv_dt.abs() >= config.oscillation_v_per_s {
+      state.swings.push_back((timestamp_ms, dv_dt));
+    }
+    while state.swings.front().map(|s| s.0 < timestamp_ms - config.window_ms).unwrap_or(false) {
+      state.swings.pop_front();
+    }
+
+    let alert = if -dv_dt >= config.sag_v_per_s {
+      Some(RailAlert::Sag { dv_dt })
+    } else {
+      let reversals = state.swings.iter().zip(state.swings.iter().skip(1)).filter(|(a, b)| a.1.signum() != b.1.signum()).count();
+      (reversals >= config.oscillation_reversals).then(|| RailAlert::Oscillation { reversals })
+    };
+
+    match alert {
+      Some(_) if state.last_alert_ms.map(|t| timestamp_ms - t < config.cooldown_ms).unwrap_or(false) => None,
+      Some(alert) => {
+        state.last_alert_ms = Some(timestamp_ms);
+        state.swings.clear();
+        Some(alert)
+      },
+      None => None
+    }
+  }
+}
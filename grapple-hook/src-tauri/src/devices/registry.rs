@@ -0,0 +1,86 @@
+use std::{collections::HashMap, sync::OnceLock};
+
+use crate::persistence::Persisted;
+use super::{DeviceInfo, DeviceType};
+
+/* Every device we've ever seen and how it was last configured, so a device that won't enumerate any more can still be
+   shown (as offline) with its last known name, firmware and CAN ID. */
+
+/* last_seen_ms is only written back to disk this often, rather than on every enumerate */
+const LAST_SEEN_RESOLUTION_MS: i64 = 60_000;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct RegisteredDevice {
+  pub serial: u32,
+  pub device_type: DeviceType,
+  pub name: Option<String>,
+  pub firmware_version: Option<String>,
+  pub domain: String,
+  pub can_id: Option<u8>,
+  pub first_seen_ms: i64,
+  pub last_seen_ms: i64,
+}
+
+impl RegisteredDevice {
+  fn same_config(&self, info: &DeviceInfo, domain: &str) -> bool {
+    self.name == info.name && self.firmware_version == info.firmware_version && self.can_id == info.device_id && self.domain == domain
+  }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct RegistryEntry {
+  pub device: RegisteredDevice,
+  pub online: bool,
+}
+
+pub struct DeviceRegistry {
+  devices: Persisted<HashMap<u32, RegisteredDevice>>,
+}
+
+impl DeviceRegistry {
+  /* Called on every enumerate response. Only touches the disk if something changed. */
+  pub fn observe(&self, domain: &str, info: &DeviceInfo, now_ms: i64) {
+    let Some(serial) = info.serial else {
+      return
+    };
+    // DFU bootloaders don't report the device's name or application firmware version
+    if info.is_dfu {
+      return;
+    }
+
+    let unchanged = self.devices.read(|d| d.get(&serial).map(|r| r.same_config(info, domain) && now_ms - r.last_seen_ms < LAST_SEEN_RESOLUTION_MS)).unwrap_or(false);
+    if unchanged {
+      return;
+    }
+
+    self.devices.update(|d| {
+      let first_seen_ms = d.get(&serial).map(|r| r.first_seen_ms).unwrap_or(now_ms);
+      d.insert(serial, RegisteredDevice {
+        serial,
+        device_type: info.device_type.clone(),
+        name: info.name.clone(),
+        firmware_version: info.firmware_version.clone(),
+        domain: domain.to_owned(),
+        can_id: info.device_id,
+        first_seen_ms,
+        last_seen_ms: now_ms,
+      });
+    });
+  }
+
+  /* Most recently seen first */
+  pub fn list(&self) -> Vec<RegisteredDevice> {
+    let mut devices = self.devices.read(|d| d.values().cloned().collect::<Vec<_>>());
+    devices.sort_by_key(|d| -d.last_seen_ms);
+    devices
+  }
+
+  pub fn forget(&self, serial: u32) -> bool {
+    self.devices.update(|d| d.remove(&serial).is_some())
+  }
+}
+
+pub fn registry() -> &'static DeviceRegistry {
+  static REGISTRY: OnceLock<DeviceRegistry> = OnceLock::new();
+  REGISTRY.get_or_init(|| DeviceRegistry { devices: Persisted::load("device_registry") })
+}
@@ -0,0 +1,62 @@
+use super::metadata::metadata;
+use crate::events::{events, EventSeverity};
+
+/* Recurring maintenance reminders attached to a device, e.g. "re-check LaserCAN lens for dust" every week. A reminder
+   is due once its interval has passed since it was last marked done (or created). */
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct Reminder {
+  pub id: String,
+  pub label: String,
+  pub interval_hours: u32,
+  pub last_done_ms: i64,
+  /* Show a desktop notification when it comes due, not just in the reminders list */
+  pub notify: bool,
+  #[serde(default)]
+  pub last_notified_ms: Option<i64>,
+}
+
+impl Reminder {
+  pub fn due_at_ms(&self) -> i64 {
+    self.last_done_ms + self.interval_hours as i64 * 3_600_000
+  }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct DueReminder {
+  pub serial: u32,
+  pub nickname: Option<String>,
+  pub reminder: Reminder,
+  pub overdue_ms: i64,
+}
+
+pub fn due_reminders(now_ms: i64) -> Vec<DueReminder> {
+  let mut due = metadata().all().into_iter()
+    .flat_map(|(serial, m)| {
+      let nickname = m.nickname.clone();
+      m.reminders.into_iter()
+        .filter(move |r| r.due_at_ms() <= now_ms)
+        .map(move |r| DueReminder { serial, nickname: nickname.clone(), overdue_ms: now_ms - r.due_at_ms(), reminder: r })
+    })
+    .collect::<Vec<_>>();
+  due.sort_by_key(|d| -d.overdue_ms);
+  due
+}
+
+/* Reminders that have come due since we last told the user about them. Each is only returned once per due period, and
+   is also put in the event log so it shows up there regardless. */
+pub fn take_notifications(now_ms: i64) -> Vec<DueReminder> {
+  let due = due_reminders(now_ms).into_iter()
+    .filter(|d| d.reminder.last_notified_ms.map(|t| t < d.reminder.due_at_ms()).unwrap_or(true))
+    .collect::<Vec<_>>();
+
+  for d in &due {
+    metadata().update(d.serial, |m| {
+      if let Some(r) = m.reminders.iter_mut().find(|r| r.id == d.reminder.id) {
+        r.last_notified_ms = Some(now_ms);
+      }
+    });
+    events().emit(Some(d.serial), "reminder_due", EventSeverity::Info, format!("Maintenance due: {}", d.reminder.label));
+  }
+
+  due.into_iter().filter(|d| d.reminder.notify).collect()
+}
@@ -0,0 +1,134 @@
+use std::{collections::HashMap, sync::{Arc, Mutex}, time::Duration};
+
+use log::warn;
+use reqwest::header::USER_AGENT;
+use tokio::sync::RwLock;
+
+use crate::telemetry::telemetry;
+
+use super::{device_manager::{DeviceId, Domain}, provider_manager::{collect_devices, ProviderContainer}, DeviceInfo};
+
+/* How often the snapshot is pushed to the relay */
+const PUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct RemoteDevice {
+  pub domain: Domain,
+  pub device_id: DeviceId,
+  pub device_class: String,
+  pub info: DeviceInfo,
+  /* Latest value of each telemetry channel */
+  pub telemetry: HashMap<String, f64>,
+}
+
+/* What the mentor sees. Read-only by construction - there's nothing in here that can be used to call back into a device. */
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct RemoteSnapshot {
+  pub timestamp_ms: i64,
+  pub devices: Vec<RemoteDevice>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct RemoteAssistStatus {
+  pub active: bool,
+  pub relay_url: Option<String>,
+  pub code: Option<String>,
+  pub last_sent_ms: Option<i64>,
+  pub last_error: Option<String>,
+}
+
+fn session_url(relay_url: &str, code: &str) -> String {
+  format!("{}/session/{}", relay_url.trim_end_matches('/'), code)
+}
+
+fn one_time_code() -> String {
+  // Skip the characters that are easy to misread over a call
+  uuid::Uuid::new_v4().simple().to_string().to_uppercase().chars().filter(|c| !"0O1I".contains(*c)).take(6).collect()
+}
+
+/* Opt-in remote assistance. While active, a snapshot of the device list and latest telemetry is PUT to
+   <relay>/session/<code> once a second, where a mentor's GrappleHook can fetch it with the same code. The relay only
+   ever sees what's in the snapshot, and nothing comes back the other way. */
+pub struct RemoteAssist {
+  status: Arc<Mutex<RemoteAssistStatus>>,
+  task: Mutex<Option<tokio::task::JoinHandle<()>>>,
+}
+
+impl RemoteAssist {
+  pub fn new() -> Self {
+    Self { status: Arc::new(Mutex::new(RemoteAssistStatus::default())), task: Mutex::new(None) }
+  }
+
+  pub fn status(&self) -> RemoteAssistStatus {
+    self.status.lock().unwrap().clone()
+  }
+
+  pub fn start(&self, relay_url: String, providers: Arc<RwLock<HashMap<String, ProviderContainer>>>) -> RemoteAssistStatus {
+    self.stop();
+
+    let code = one_time_code();
+    *self.status.lock().unwrap() = RemoteAssistStatus { active: true, relay_url: Some(relay_url.clone()), code: Some(code.clone()), last_sent_ms: None, last_error: None };
+
+    let status = self.status.clone();
+    let url = session_url(&relay_url, &code);
+    *self.task.lock().unwrap() = Some(tokio::task::spawn(async move {
+      let client = reqwest::Client::new();
+      let mut interval = tokio::time::interval(PUSH_INTERVAL);
+      loop {
+        interval.tick().await;
+        let snapshot = snapshot(&providers).await;
+        let result = client.put(&url).header(USER_AGENT, "GrappleHook").json(&snapshot).send().await
+          .and_then(|r| r.error_for_status());
+
+        let mut status = status.lock().unwrap();
+        match result {
+          Ok(_) => { status.last_sent_ms = Some(snapshot.timestamp_ms); status.last_error = None; },
+          Err(e) => {
+            warn!("Remote assistance push failed: {}", e);
+            status.last_error = Some(e.to_string());
+          }
+        }
+      }
+    }));
+
+    self.status()
+  }
+
+  pub fn stop(&self) {
+    if let Some(task) = self.task.lock().unwrap().take() {
+      task.abort();
+    }
+
+    let mut status = self.status.lock().unwrap();
+    if let (Some(relay_url), Some(code)) = (status.relay_url.clone(), status.code.clone()) {
+      // Tell the relay the session is over so the code can't be used again
+      tokio::task::spawn(async move {
+        reqwest::Client::new().delete(session_url(&relay_url, &code)).header(USER_AGENT, "GrappleHook").send().await.ok();
+      });
+    }
+    *status = RemoteAssistStatus::default();
+  }
+}
+
+async fn snapshot(providers: &RwLock<HashMap<String, ProviderContainer>>) -> RemoteSnapshot {
+  let devices = collect_devices(providers).await.into_iter().map(|(_, domain, device_id, info, device_class)| {
+    let telemetry = info.serial.map(|serial| {
+      telemetry().channels(serial).into_iter()
+        .filter_map(|c| telemetry().latest(serial, &c).map(|s| (c, s.value)))
+        .collect()
+    }).unwrap_or_default();
+    RemoteDevice { domain, device_id, device_class, info, telemetry }
+  }).collect();
+
+  RemoteSnapshot { timestamp_ms: chrono::Utc::now().timestamp_millis(), devices }
+}
+
+/* The mentor's side: fetch the latest snapshot for a session */
+pub async fn view(relay_url: &str, code: &str) -> anyhow::Result<RemoteSnapshot> {
+  let snapshot = reqwest::Client::new().get(session_url(relay_url, &code.trim().to_uppercase()))
+    .header(USER_AGENT, "GrappleHook")
+    .send().await?
+    .error_for_status()?
+    .json().await?;
+  Ok(snapshot)
+}
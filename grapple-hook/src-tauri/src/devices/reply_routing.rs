@@ -0,0 +1,166 @@
+use std::{collections::HashMap, sync::{Arc, Mutex}, time::Duration};
+
+use grapple_frc_msgs::{grapple::{GrappleBroadcastMessage, GrappleDeviceMessage, TaggedGrappleMessage}, DEVICE_ID_BROADCAST};
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+use crate::errors::{coded, ErrorCode};
+use super::clock::Clock;
+
+/* Waiters are only cleared out by expire() this long after their deadline, so a requester that's still around always
+   sees its own timeout rather than having the waiter pulled out from under it */
+const EXPIRY_GRACE: Duration = Duration::from_secs(1);
+
+/* Who gets a reply when several requests are waiting on the same message ID */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub enum ReplyPolicy {
+  /* Every waiter gets a copy. For requests where any reply answers everyone, e.g. enumeration. */
+  Broadcast,
+  /* Only the oldest waiter for the replying device gets it, the rest keep waiting for their own reply. For
+     request/ack pairs, where each reply answers exactly one request. */
+  FirstMatch,
+  /* Every waiter for the replying device gets a copy */
+  DeviceFiltered,
+}
+
+/* Decided by the request being sent */
+pub fn reply_policy(request: &GrappleDeviceMessage) -> ReplyPolicy {
+  match request {
+    GrappleDeviceMessage::Broadcast(GrappleBroadcastMessage::DeviceInfo(_)) => ReplyPolicy::Broadcast,
+    GrappleDeviceMessage::DistanceSensor(_) | GrappleDeviceMessage::PowerDistributionModule(_) => ReplyPolicy::FirstMatch,
+    _ => ReplyPolicy::DeviceFiltered,
+  }
+}
+
+pub struct ReplyWaiter {
+  pub id: Uuid,
+  /* The device the request went to */
+  pub device_id: u8,
+  pub policy: ReplyPolicy,
+  /* When the requester gives up, by the domain's clock */
+  pub deadline_ms: i64,
+  pub tx: oneshot::Sender<TaggedGrappleMessage<'static>>,
+}
+
+impl ReplyWaiter {
+  fn accepts(&self, reply: &TaggedGrappleMessage<'static>) -> bool {
+    self.policy == ReplyPolicy::Broadcast || self.device_id == DEVICE_ID_BROADCAST || self.device_id == reply.device_id
+  }
+}
+
+/* Hand a reply to the waiters it's meant for (oldest first), leaving the rest waiting. Returns how many got it. */
+pub fn dispatch(waiters: &mut Vec<ReplyWaiter>, reply: &TaggedGrappleMessage<'static>) -> usize {
+  let mut delivered = 0;
+  let mut remaining = vec![];
+  for waiter in waiters.drain(..) {
+    let first_match_taken = waiter.policy == ReplyPolicy::FirstMatch && delivered > 0;
+    if waiter.accepts(reply) && !first_match_taken {
+      // ok since it's fine if the channel is closed, e.g. timeouts.
+      waiter.tx.send(reply.clone()).ok();
+      delivered += 1;
+    } else {
+      remaining.push(waiter);
+    }
+  }
+  *waiters = remaining;
+  delivered
+}
+
+/* Every request on a domain that's waiting for a reply, by the message ID the reply will arrive with. A waiter is
+   removed when it's answered, when its request times out or is dropped part way through (see PendingReply), or by
+   expire() if it's somehow outlived its deadline, so a device that never answers can't leave waiters behind. */
+pub struct ReplyRegistry {
+  clock: Clock,
+  waiting: Mutex<HashMap<u32, Vec<ReplyWaiter>>>,
+}
+
+impl ReplyRegistry {
+  pub fn new(clock: Clock) -> Self {
+    Self { clock, waiting: Mutex::new(HashMap::new()) }
+  }
+
+  pub fn register(self: &Arc<Self>, reply_id: u32, device_id: u8, policy: ReplyPolicy, timeout: Duration) -> PendingReply {
+    let (tx, rx) = oneshot::channel();
+    let id = Uuid::new_v4();
+    let deadline_ms = self.clock.now_ms() + timeout.as_millis() as i64;
+    self.waiting.lock().unwrap().entry(reply_id).or_default().push(ReplyWaiter { id, device_id, policy, deadline_ms, tx });
+    PendingReply { registry: self.clone(), reply_id, id, deadline_ms, rx }
+  }
+
+  fn remove(&self, reply_id: u32, id: Uuid) {
+    let mut waiting = self.waiting.lock().unwrap();
+    if let Some(waiters) = waiting.get_mut(&reply_id) {
+      waiters.retain(|w| w.id != id);
+      if waiters.is_empty() {
+        waiting.remove(&reply_id);
+      }
+    }
+  }
+
+  fn remove_where(&self, predicate: impl Fn(&ReplyWaiter) -> bool) -> usize {
+    let mut removed = 0;
+    self.waiting.lock().unwrap().retain(|_, waiters| {
+      let before = waiters.len();
+      waiters.retain(|w| !predicate(w));
+      removed += before - waiters.len();
+      !waiters.is_empty()
+    });
+    removed
+  }
+
+  /* Hand a received message to whoever is waiting on its ID. Returns how many got it. */
+  pub fn deliver(&self, reply_id: u32, reply: &TaggedGrappleMessage<'static>) -> usize {
+    let mut waiting = self.waiting.lock().unwrap();
+    let Some(waiters) = waiting.get_mut(&reply_id) else { return 0 };
+    let delivered = dispatch(waiters, reply);
+    if waiters.is_empty() {
+      waiting.remove(&reply_id);
+    }
+    delivered
+  }
+
+  /* Clear out waiters whose requester has gone away or is long past its deadline */
+  pub fn expire(&self) -> usize {
+    let now = self.clock.now_ms();
+    self.remove_where(|w| w.tx.is_closed() || now > w.deadline_ms + EXPIRY_GRACE.as_millis() as i64)
+  }
+
+  /* Fail every request waiting on a reply from the given CAN ID (or every request, for None) straight away, rather
+     than leaving them to time out */
+  pub fn cancel(&self, device_id: Option<u8>) -> usize {
+    self.remove_where(|w| device_id.map(|id| w.device_id == id).unwrap_or(true))
+  }
+
+  pub fn pending(&self) -> usize {
+    self.waiting.lock().unwrap().values().map(|w| w.len()).sum()
+  }
+}
+
+/* A request's place in the registry. Dropping it, e.g. because the RPC waiting on it was cancelled, takes the waiter
+   out of the registry. */
+pub struct PendingReply {
+  registry: Arc<ReplyRegistry>,
+  reply_id: u32,
+  id: Uuid,
+  deadline_ms: i64,
+  rx: oneshot::Receiver<TaggedGrappleMessage<'static>>,
+}
+
+impl PendingReply {
+  pub async fn wait(mut self) -> anyhow::Result<TaggedGrappleMessage<'static>> {
+    let clock = self.registry.clock.clone();
+    tokio::select! {
+      reply = &mut self.rx => match reply {
+        Ok(reply) => Ok(reply),
+        Err(_) => Err(coded(ErrorCode::RequestCancelled, "The request was cancelled before the device replied"))
+      },
+      _ = clock.sleep_until(self.deadline_ms) => Err(coded(ErrorCode::RequestTimeout, "Timed out waiting for response"))
+    }
+  }
+}
+
+impl Drop for PendingReply {
+  fn drop(&mut self) {
+    self.registry.remove(self.reply_id, self.id);
+  }
+}
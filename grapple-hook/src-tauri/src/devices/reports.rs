@@ -0,0 +1,76 @@
+use crate::events::Event;
+use super::{device_manager::{DeviceId, Domain}, metadata::metadata, DeviceInfo};
+
+/* Printable reports about the devices we can see, for pit checklists and RMA requests. Nicknames and notes from the
+   metadata store are included so the people reading them know which device is which. */
+
+pub fn csv_field(s: &str) -> String {
+  if s.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+    format!("\"{}\"", s.replace('"', "\"\""))
+  } else {
+    s.to_owned()
+  }
+}
+
+fn serial_of(id: &DeviceId) -> u32 {
+  match id {
+    DeviceId::Dfu(serial) | DeviceId::Serial(serial) => *serial
+  }
+}
+
+pub fn inventory_csv(devices: &[(String, Domain, DeviceId, DeviceInfo, String)]) -> String {
+  let mut out = "serial,nickname,class,name,can_id,firmware,dfu,provider,domain,notes\n".to_owned();
+  for (address, domain, id, info, class) in devices {
+    let meta = metadata().get(serial_of(id));
+    let row = [
+      format!("{:x}", serial_of(id)),
+      meta.nickname.unwrap_or_default(),
+      class.clone(),
+      info.name.clone().unwrap_or_default(),
+      info.device_id.map(|id| id.to_string()).unwrap_or_default(),
+      info.firmware_version.clone().unwrap_or_default(),
+      info.is_dfu.to_string(),
+      address.clone(),
+      domain.clone(),
+      meta.notes,
+    ];
+    out += &row.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(",");
+    out += "\n";
+  }
+  out
+}
+
+/* A plain text report of every device and the recent events concerning it */
+pub fn diagnostics_report(devices: &[(String, Domain, DeviceId, DeviceInfo, String)], events: &[Event]) -> String {
+  let mut out = format!("GrappleHook {} diagnostics report\nGenerated {}\n\n", env!("CARGO_PKG_VERSION"), chrono::Local::now().to_rfc2822());
+
+  for (address, domain, id, info, class) in devices {
+    let serial = serial_of(id);
+    let meta = metadata().get(serial);
+
+    out += &format!("== {} {:x}", class, serial);
+    if let Some(nickname) = &meta.nickname {
+      out += &format!(" ({})", nickname);
+    }
+    out += "\n";
+    out += &format!("Name: {}\n", info.name.as_deref().unwrap_or("-"));
+    out += &format!("CAN ID: {}\n", info.device_id.map(|id| id.to_string()).unwrap_or("-".to_owned()));
+    out += &format!("Firmware: {}{}\n", info.firmware_version.as_deref().unwrap_or("-"), if info.is_dfu { " (bootloader)" } else { "" });
+    out += &format!("Seen on: {} / {}\n", address, domain);
+    out += &format!("State: {:?}\n", info.state);
+    if !meta.notes.is_empty() {
+      out += &format!("Notes:\n  {}\n", meta.notes.replace('\n', "\n  "));
+    }
+
+    let device_events = events.iter().filter(|e| e.serial == Some(serial)).collect::<Vec<_>>();
+    if !device_events.is_empty() {
+      out += "Events:\n";
+      for event in device_events {
+        let time = chrono::DateTime::from_timestamp_millis(event.timestamp_ms).map(|t| t.to_rfc3339()).unwrap_or_default();
+        out += &format!("  {} [{:?}] {}: {}\n", time, event.severity, event.kind, event.message);
+      }
+    }
+    out += "\n";
+  }
+  out
+}
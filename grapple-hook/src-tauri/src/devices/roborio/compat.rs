@@ -0,0 +1,59 @@
+use std::sync::OnceLock;
+
+/* The roboRIO end of the bridge is libgrapplefrc, either in the daemon we deploy or in the team's robot code. The
+   bridge doesn't announce a version when we connect, so we check the version our daemon leaves behind whenever we
+   connect to it, and otherwise take a stream whose very first frame we can't decode as a mismatch. When either
+   happens we say which version we expected (the one the bundled daemon is built against), what we know about the
+   one that's running, and offer to deploy ours instead. */
+
+const BUNDLED_VENDORDEP: &str = include_str!("../../../../../GrappleHook-RoboRIO-Daemon/vendordeps/libgrapplefrc2025.json");
+/* Written alongside the daemon when we deploy it, so we can tell later which version is on the roboRIO */
+pub const DAEMON_VERSION_PATH: &str = "/tmp/grapple-hook-daemon.version";
+
+pub fn bundled_bridge_version() -> &'static str {
+  static VERSION: OnceLock<String> = OnceLock::new();
+  VERSION.get_or_init(|| {
+    serde_json::from_str::<serde_json::Value>(BUNDLED_VENDORDEP).ok()
+      .and_then(|v| v.get("version").and_then(|v| v.as_str()).map(str::to_owned))
+      .unwrap_or_else(|| "unknown".to_owned())
+  })
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq, Eq)]
+pub enum BridgeSource {
+  /* The daemon GrappleHook deployed */
+  Daemon,
+  /* CanBridge running in the team's robot code */
+  RobotCode,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct BridgeMismatch {
+  pub source: BridgeSource,
+  /* The libgrapplefrc version GrappleHook's bundled daemon is built against */
+  pub expected: String,
+  /* The version on the roboRIO, if we could find out */
+  pub found: Option<String>,
+  /* What we couldn't decode */
+  pub detail: String,
+  pub detected_at: i64,
+}
+
+impl BridgeMismatch {
+  pub fn new(source: BridgeSource, found: Option<String>, detail: String) -> Self {
+    Self { source, expected: bundled_bridge_version().to_owned(), found, detail, detected_at: chrono::Utc::now().timestamp_millis() }
+  }
+
+  pub fn describe(&self) -> String {
+    let found = match (&self.found, self.source) {
+      (Some(found), BridgeSource::Daemon) => format!("a daemon built against libgrapplefrc {}", found),
+      (None, BridgeSource::Daemon) => "a daemon from another version of GrappleHook".to_owned(),
+      (_, BridgeSource::RobotCode) => "the CanBridge in your robot code".to_owned(),
+    };
+    let remedy = match self.source {
+      BridgeSource::Daemon => "Upgrade the bridge to redeploy GrappleHook's daemon.",
+      BridgeSource::RobotCode => "Update the libgrapplefrc vendordep in your robot code and redeploy, or upgrade the bridge to use GrappleHook's daemon instead.",
+    };
+    format!("The roboRIO is running {}, which doesn't match libgrapplefrc {} that GrappleHook expects. {}", found, self.expected, remedy)
+  }
+}
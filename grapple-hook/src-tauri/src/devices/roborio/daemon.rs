@@ -1,225 +1,432 @@
-use std::{sync::{atomic::AtomicBool, Arc}, time::Duration, collections::HashMap, borrow::Cow};
-
-use bounded_static::ToBoundedStatic;
-use grapple_frc_msgs::{binmarshal::{BitView, Demarshal, LengthTaggedPayload, LengthTaggedPayloadOwned}, grapple::{fragments::FragmentReassembler, TaggedGrappleMessage}, ManufacturerMessage, bridge::BridgedCANMessage};
-use futures_util::{SinkExt, StreamExt};
-use grapple_hook_macros::rpc;
-use log::{info, warn};
-use rust_embed::RustEmbed;
-use tokio::{sync::{mpsc, Mutex}, net::TcpStream};
-use tokio_util::codec::Framed;
-
-use crate::rpc::RpcBase;
-
-use crate::{devices::{device_manager::{DeviceManager, DeviceManagerRequest, DeviceManagerResponse}, provider::{DeviceProvider, ProviderInfo}}, codecs::tcp_can_bridge::GrappleTcpCanBridgeCodec, ssh::SSHSession};
-
-const ROBORIO_ADDRESS: &'static str = "10.25.2.2";
-
-#[derive(RustEmbed)]
-#[folder="../../GrappleHook-RoboRIO-Daemon/build/exe/grappleHookRoboRioDaemon/release/"]
-struct Daemon;
-
-pub struct RoboRioDaemonInner {
-  running: AtomicBool,
-  device_manager: DeviceManager,
-
-  stop_signal_tx: mpsc::Sender<()>,
-  stop_signal_rx: Mutex<mpsc::Receiver<()>>,
-  can_send_rx: Mutex<mpsc::Receiver<TaggedGrappleMessage<'static>>>,
-
-  do_deploy: AtomicBool,
-  address: Mutex<String>,
-}
-
-pub struct RoboRioDaemon {
-  inner: Arc<RoboRioDaemonInner>
-}
-
-impl RoboRioDaemon {
-  pub fn new() -> Self {
-    let (can_send_tx, can_send_rx) = mpsc::channel(100);
-    let (stop_signal_tx, stop_signal_rx) = mpsc::channel(5);
-
-    let mut sends = HashMap::new();
-    sends.insert("CAN".to_owned(), can_send_tx);
-
-    Self {
-      inner: Arc::new(
-        RoboRioDaemonInner {
-          running: AtomicBool::new(false),
-          device_manager: DeviceManager::new(sends),
-          stop_signal_tx, stop_signal_rx: Mutex::new(stop_signal_rx),
-          can_send_rx: Mutex::new(can_send_rx),
-          do_deploy: AtomicBool::new(true),
-          address: Mutex::new(ROBORIO_ADDRESS.to_owned())
-        }
-      )
-    }
-  }
-  
-  async fn do_loop(mut framed: Framed<TcpStream, GrappleTcpCanBridgeCodec>, inner: Arc<RoboRioDaemonInner>) -> anyhow::Result<()> {
-    let mut can_send_rx = inner.can_send_rx.try_lock().map_err(|_| anyhow::anyhow!("This RootDevice is already running!"))?;
-    let mut stop_signal_rx = inner.stop_signal_rx.try_lock()?;
-
-    let (mut reassemble_rx, mut reassemble_tx) = FragmentReassembler::new(1000, 8).split();
-    let mut device_manager_interval = tokio::time::interval(Duration::from_millis(500));
-
-    loop {
-      tokio::select! {
-        msg = framed.next() => match msg {
-          Some(Ok(msg)) => {
-            let id2 = Into::<grapple_frc_msgs::grapple::GrappleMessageId>::into(msg.id);
-            // println!("{:?}", id2);
-            let manufacturer_msg = ManufacturerMessage::read(&mut BitView::new(&msg.data.0[..]), msg.id);
-            match manufacturer_msg {
-              Ok(ManufacturerMessage::Grapple(grpl_msg)) => {
-                let mut storage = Vec::new();
-                if let Ok(Some(grpl_unfragmented)) = reassemble_rx.defragment(msg.timestamp as i64, &msg.id, grpl_msg, &mut storage) {
-                  inner.device_manager.on_message("CAN".to_owned(), msg.id.clone().into(), TaggedGrappleMessage::new(msg.id.device_id, grpl_unfragmented.to_static())).await?;
-                }
-              },
-              _ => ()
-            }
-          },
-          Some(Err(e)) => anyhow::bail!(e),
-          None => ()
-        },
-        msg = can_send_rx.recv() => match msg {
-          Some(msg) => {
-            // Need to send something on the CAN bus
-            let TaggedGrappleMessage { device_id, msg } = msg;
-
-            let mut msgs = vec![];
-            reassemble_tx.maybe_fragment(device_id, msg, &mut |id, buf| {
-              msgs.push(BridgedCANMessage { id, timestamp: 0, data: Cow::<LengthTaggedPayload<u8>>::Owned(LengthTaggedPayloadOwned::new(buf.to_vec())).into() });
-            }).ok();
-
-            for msg in msgs {
-              framed.send(msg).await?;
-            }
-          },
-          None => ()
-        },
-        sig = stop_signal_rx.recv() => match sig {
-          Some(()) => {
-            break;
-          },
-          None => ()
-        },
-        _ = device_manager_interval.tick() => {
-          inner.device_manager.on_tick().await?;
-        }
-      }
-    }
-
-    Ok(())
-  }
-
-  async fn deploy(addr: String) -> anyhow::Result<()> {
-    info!("Deploy...");
-    let session = SSHSession::connect(&(addr + ":22"), "admin", "").await?;
-
-    let file = Daemon::get("grappleHookRoboRioDaemon").ok_or(anyhow::anyhow!("Embedded File Error"))?;
-    session.copy(file.data.to_vec(), "/tmp/grapple-hook-daemon").await?;
-    
-    tokio::spawn(async move {
-      session.run("frcKillRobot.sh -t; killall grapple-hook-daemon; frcKillRobot.sh -t; /tmp/grapple-hook-daemon > /tmp/grapple-hook-daemon.log 2>&1").await.ok();
-    });
-
-    info!("Deploy Successful!");
-
-    Ok(())
-  }
-
-  pub async fn revert_to_robot_code(addr: String) -> anyhow::Result<()> {
-    info!("Reverting to user code...");
-    let session = SSHSession::connect(&(addr + ":22"), "admin", "").await?;
-    session.run("killall grapple-hook-daemon; frcKillRobot.sh -t -r").await.ok();
-    info!("Reverted to user code!");
-    Ok(())
-  }
-
-  async fn do_start(inner: Arc<RoboRioDaemonInner>) -> anyhow::Result<()> {
-    info!("Connecting...");
-
-    let will_deploy = inner.do_deploy.load(std::sync::atomic::Ordering::Relaxed);
-    let addr = inner.address.lock().await.clone();
-
-    if will_deploy {
-      Self::deploy(addr.clone()).await?;
-    }
-
-    let stream = tokio::time::timeout(Duration::from_millis(3000), TcpStream::connect(ROBORIO_ADDRESS.to_owned() + ":8006")).await.map_err(|_| anyhow::anyhow!("Connection Timed Out!"))??;
-    let framed = Framed::new(stream, GrappleTcpCanBridgeCodec);
-
-    info!("Connected!");
-
-    tokio::task::spawn(async move {
-      inner.running.store(true, std::sync::atomic::Ordering::Relaxed);
-      let r = Self::do_loop(framed, inner.clone()).await;
-      inner.running.store(false, std::sync::atomic::Ordering::Relaxed);
-      if will_deploy {
-        tokio::time::timeout(tokio::time::Duration::from_secs(10), Self::revert_to_robot_code(addr.clone())).await.ok();
-      }
-      inner.device_manager.reset().await;
-      match r {
-        Ok(_) => info!("RoboRioDaemon runner stopped gracefully"),
-        Err(e) => warn!("RoboRioDaemon runner stopped with error: {}", e),
-      }
-    });
-
-    Ok(())
-  }
-}
-
-#[async_trait::async_trait]
-impl DeviceProvider for RoboRioDaemon {
-  async fn connect(&self) -> anyhow::Result<()> {
-    Self::do_start(self.inner.clone()).await
-  }
-
-  async fn disconnect(&self) -> anyhow::Result<()> {
-    self.inner.stop_signal_tx.send(()).await.ok();
-    Ok(())
-  }
-
-  async fn info(&self) -> anyhow::Result<ProviderInfo> {
-    Ok(ProviderInfo {
-      ty: "RoboRIO".to_owned(),
-      description: format!("RoboRIO"),
-      address: self.inner.address.lock().await.clone(),
-      connected: self.inner.running.load(std::sync::atomic::Ordering::Relaxed)
-    })
-  }
-
-  async fn device_manager_call(&self, req: DeviceManagerRequest) -> anyhow::Result<DeviceManagerResponse> {
-    self.inner.device_manager.rpc_process(req).await
-  }
-
-  async fn call(&self, req: serde_json::Value) -> anyhow::Result<serde_json::Value> {
-    self.rpc_call(req).await
-  }
-}
-
-#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
-pub struct RoboRIOStatus {
-  pub using_daemon: bool
-}
-
-#[rpc]
-impl RoboRioDaemon {
-  async fn status(&self) -> anyhow::Result<RoboRIOStatus> {
-    Ok(RoboRIOStatus { using_daemon: self.inner.do_deploy.load(std::sync::atomic::Ordering::Relaxed) })
-  }
-
-  async fn set_use_daemon(&self, use_daemon: bool) -> anyhow::Result<()> {
-    self.inner.do_deploy.store(use_daemon, std::sync::atomic::Ordering::Relaxed);
-    Ok(())
-  }
-
-  async fn set_address(&self, address: String) -> anyhow::Result<()> {
-    let mut addr = self.inner.address.lock().await;
-    *addr = address;
-    Ok(())
-  }
+use std::{sync::{atomic::AtomicBool, Arc}, time::Duration, borrow::Cow};
+
+use bounded_static::ToBoundedStatic;
+use grapple_frc_msgs::{binmarshal::{BitView, Demarshal, LengthTaggedPayload, LengthTaggedPayloadOwned}, grapple::{fragments::FragmentReassembler, TaggedGrappleMessage}, ManufacturerMessage, bridge::BridgedCANMessage};
+use futures_util::{SinkExt, StreamExt};
+use grapple_hook_macros::rpc;
+use log::{info, warn};
+use rust_embed::RustEmbed;
+use tokio::{sync::{mpsc, Mutex}, net::TcpStream};
+use tokio_util::codec::Framed;
+
+use crate::errors::{coded, has_code, ErrorCode};
+use crate::events::{events, EventSeverity};
+use crate::rpc::RpcBase;
+
+use super::compat::{bundled_bridge_version, BridgeMismatch, BridgeSource, DAEMON_VERSION_PATH};
+use super::discovery::{discovery, DiscoveredRoboRio};
+
+use crate::{devices::{device_manager::{DeviceManager, DeviceManagerRequest, DeviceManagerResponse}, provider::{DeviceProvider, ProviderInfo}}, codecs::tcp_can_bridge::GrappleTcpCanBridgeCodec, ssh::SSHSession};
+
+const ROBORIO_ADDRESS: &'static str = "10.25.2.2";
+
+const KEEPALIVE_TIME: Duration = Duration::from_millis(1000);
+const KEEPALIVE_INTERVAL: Duration = Duration::from_millis(500);
+#[cfg(not(windows))]
+const KEEPALIVE_RETRIES: u32 = 3;
+/* Keepalive only probes an idle socket, and ours never is (we enumerate every 500ms), so a pulled cable otherwise waits
+   out the kernel's retransmission timeout. This bounds how long sent data can go unacknowledged instead. */
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const USER_TIMEOUT: Duration = Duration::from_millis(2000);
+/* The PDP/PDH alone puts status frames on the bus many times a second, so once the bridge has sent us anything, this
+   long without a frame means the link is gone. Covers the platforms without USER_TIMEOUT. */
+const READ_DEADLINE: Duration = Duration::from_millis(2000);
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(RustEmbed)]
+#[folder="../../GrappleHook-RoboRIO-Daemon/build/exe/grappleHookRoboRioDaemon/release/"]
+struct Daemon;
+
+pub struct RoboRioDaemonInner {
+  running: AtomicBool,
+  device_manager: DeviceManager,
+
+  stop_signal_tx: mpsc::Sender<()>,
+  stop_signal_rx: Mutex<mpsc::Receiver<()>>,
+  can_send_tx: mpsc::Sender<TaggedGrappleMessage<'static>>,
+  can_send_rx: Mutex<mpsc::Receiver<TaggedGrappleMessage<'static>>>,
+
+  do_deploy: AtomicBool,
+  address: Mutex<String>,
+  /* Set when the bridge turned out to be incompatible, until it's upgraded */
+  mismatch: std::sync::Mutex<Option<BridgeMismatch>>,
+}
+
+pub struct RoboRioDaemon {
+  inner: Arc<RoboRioDaemonInner>
+}
+
+impl RoboRioDaemon {
+  pub fn new() -> Self {
+    let (can_send_tx, can_send_rx) = mpsc::channel(100);
+    let (stop_signal_tx, stop_signal_rx) = mpsc::channel(5);
+
+    Self {
+      inner: Arc::new(
+        RoboRioDaemonInner {
+          running: AtomicBool::new(false),
+          device_manager: DeviceManager::new(),
+          stop_signal_tx, stop_signal_rx: Mutex::new(stop_signal_rx),
+          can_send_tx, can_send_rx: Mutex::new(can_send_rx),
+          do_deploy: AtomicBool::new(true),
+          address: Mutex::new(ROBORIO_ADDRESS.to_owned()),
+          mismatch: std::sync::Mutex::new(None),
+        }
+      )
+    }
+  }
+  
+  async fn do_loop(mut framed: Framed<TcpStream, GrappleTcpCanBridgeCodec>, inner: Arc<RoboRioDaemonInner>) -> anyhow::Result<()> {
+    let mut can_send_rx = inner.can_send_rx.try_lock().map_err(|_| anyhow::anyhow!("This RootDevice is already running!"))?;
+    let mut stop_signal_rx = inner.stop_signal_rx.try_lock()?;
+
+    let (mut reassemble_rx, mut reassemble_tx) = FragmentReassembler::new(1000, 8).split();
+    let mut device_manager_interval = tokio::time::interval(Duration::from_millis(500));
+    // Not armed until the first frame, an empty bus is quiet for good reason
+    let mut last_frame: Option<tokio::time::Instant> = None;
+
+    loop {
+      tokio::select! {
+        msg = framed.next() => match msg {
+          Some(Ok(msg)) => {
+            last_frame = Some(tokio::time::Instant::now());
+            let id2 = Into::<grapple_frc_msgs::grapple::GrappleMessageId>::into(msg.id);
+            // println!("{:?}", id2);
+            let manufacturer_msg = ManufacturerMessage::read(&mut BitView::new(&msg.data.0[..]), msg.id);
+            match manufacturer_msg {
+              Ok(ManufacturerMessage::Grapple(grpl_msg)) => {
+                let mut storage = Vec::new();
+                match reassemble_rx.defragment(msg.timestamp as i64, &msg.id, grpl_msg, &mut storage) {
+                  Ok(Some(grpl_unfragmented)) => {
+                    inner.device_manager.on_bridged_message("CAN".to_owned(), msg.id.clone().into(), TaggedGrappleMessage::new(msg.id.device_id, grpl_unfragmented.to_static()), msg.timestamp as u32).await?;
+                  },
+                  Ok(None) => (),
+                  Err(e) => inner.device_manager.on_malformed("CAN", msg.id.clone().into(), &msg.data.0[..], format!("{:?}", e))
+                }
+              },
+              Ok(_) => inner.device_manager.on_foreign("CAN", msg.id.clone().into()),
+              Err(e) => inner.device_manager.on_malformed("CAN", msg.id.clone().into(), &msg.data.0[..], format!("{:?}", e))
+            }
+          },
+          // Garbage from the start is a bridge speaking another protocol, rather than a frame that got mangled
+          Some(Err(e)) if last_frame.is_none() && has_code(&e, ErrorCode::BridgeDecodeFailed) => {
+            return Err(coded(ErrorCode::BridgeVersionMismatch, format!("The bridge's first frame couldn't be decoded: {}", e)));
+          },
+          Some(Err(e)) => anyhow::bail!(e),
+          None => anyhow::bail!("Bridge connection closed")
+        },
+        msg = can_send_rx.recv() => match msg {
+          Some(msg) => {
+            // Need to send something on the CAN bus
+            let TaggedGrappleMessage { device_id, msg } = msg;
+
+            let mut msgs = vec![];
+            reassemble_tx.maybe_fragment(device_id, msg, &mut |id, buf| {
+              msgs.push(BridgedCANMessage { id, timestamp: 0, data: Cow::<LengthTaggedPayload<u8>>::Owned(LengthTaggedPayloadOwned::new(buf.to_vec())).into() });
+            }).ok();
+
+            for msg in msgs {
+              framed.send(msg).await?;
+            }
+          },
+          None => ()
+        },
+        sig = stop_signal_rx.recv() => match sig {
+          Some(()) => {
+            break;
+          },
+          None => ()
+        },
+        _ = device_manager_interval.tick() => {
+          inner.device_manager.on_tick().await?;
+        },
+        _ = async { tokio::time::sleep_until(last_frame.unwrap() + READ_DEADLINE).await }, if last_frame.is_some() => {
+          anyhow::bail!("No frames from the bridge for {}ms", READ_DEADLINE.as_millis());
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  async fn deploy(addr: String) -> anyhow::Result<()> {
+    info!("Deploy...");
+    let session = SSHSession::connect(&(addr + ":22"), "admin", "").await?;
+
+    let file = Daemon::get("grappleHookRoboRioDaemon").ok_or(anyhow::anyhow!("Embedded File Error"))?;
+    session.copy(file.data.to_vec(), "/tmp/grapple-hook-daemon").await?;
+    session.run(&format!("echo {} > {}", bundled_bridge_version(), DAEMON_VERSION_PATH)).await?;
+    
+    tokio::spawn(async move {
+      session.run("frcKillRobot.sh -t; killall grapple-hook-daemon; frcKillRobot.sh -t; /tmp/grapple-hook-daemon > /tmp/grapple-hook-daemon.log 2>&1").await.ok();
+    });
+
+    info!("Deploy Successful!");
+
+    Ok(())
+  }
+
+  pub async fn revert_to_robot_code(addr: String) -> anyhow::Result<()> {
+    info!("Reverting to user code...");
+    let session = SSHSession::connect(&(addr + ":22"), "admin", "").await?;
+    session.run("killall grapple-hook-daemon; frcKillRobot.sh -t -r").await.ok();
+    info!("Reverted to user code!");
+    Ok(())
+  }
+
+  async fn verify_daemon_version(addr: String) -> anyhow::Result<()> {
+    let found = tokio::time::timeout(Duration::from_secs(5), async {
+      let session = SSHSession::connect(&(addr + ":22"), "admin", "").await?;
+      session.run(&format!("cat {}", DAEMON_VERSION_PATH)).await
+    }).await.map_err(|_| anyhow::anyhow!("Timed out"))??;
+    if !found.success() {
+      anyhow::bail!("The roboRIO has no record of the daemon's version");
+    }
+    let found = found.output().trim().to_owned();
+    if found != bundled_bridge_version() {
+      return Err(coded(ErrorCode::BridgeVersionMismatch, format!("The daemon on the roboRIO is built against libgrapplefrc {}", found)));
+    }
+    Ok(())
+  }
+
+  /* Work out what we can about a bridge that doesn't match us. Only a daemon we deployed leaves a
+     version behind, robot code doesn't say which libgrapplefrc it was built with. */
+  async fn on_mismatch(inner: &Arc<RoboRioDaemonInner>, will_deploy: bool, addr: String, error: &anyhow::Error) {
+    let (source, found) = match will_deploy {
+      true => {
+        let found = tokio::time::timeout(Duration::from_secs(5), async {
+          let session = SSHSession::connect(&(addr + ":22"), "admin", "").await?;
+          session.run(&format!("cat {}", DAEMON_VERSION_PATH)).await
+        }).await;
+        let found = match found {
+          Ok(Ok(result)) if result.success() => Some(result.output().trim().to_owned()).filter(|v| !v.is_empty()),
+          _ => None
+        };
+        (BridgeSource::Daemon, found)
+      },
+      false => (BridgeSource::RobotCode, None)
+    };
+
+    let mismatch = BridgeMismatch::new(source, found, error.to_string());
+    warn!("{}", mismatch.describe());
+    events().emit(None, "bridge_mismatch", EventSeverity::Error, mismatch.describe());
+    *inner.mismatch.lock().unwrap() = Some(mismatch);
+  }
+
+  fn configure_keepalive(stream: &TcpStream) -> anyhow::Result<()> {
+    // The bridge can go half-open when the RIO reboots underneath us, which a plain read will never notice.
+    // Aggressive TCP keepalive probes let the OS tear the socket down so the loop errors out and we can reconnect.
+    let keepalive = socket2::TcpKeepalive::new()
+      .with_time(KEEPALIVE_TIME)
+      .with_interval(KEEPALIVE_INTERVAL);
+    #[cfg(not(windows))]
+    let keepalive = keepalive.with_retries(KEEPALIVE_RETRIES);
+
+    let socket = socket2::SockRef::from(stream);
+    socket.set_tcp_keepalive(&keepalive)?;
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    socket.set_tcp_user_timeout(Some(USER_TIMEOUT))?;
+    Ok(())
+  }
+
+  /* Whatever's listening may not be the daemon we deployed (e.g. another GrappleHook deployed over it), so when
+     we're using the daemon its version is checked on every connect, not just when we deploy it. */
+  async fn open_transport(inner: &Arc<RoboRioDaemonInner>, will_deploy: bool, deploy_now: bool) -> anyhow::Result<Framed<TcpStream, GrappleTcpCanBridgeCodec>> {
+    let addr = inner.address.lock().await.clone();
+
+    if deploy_now {
+      Self::deploy(addr.clone()).await?;
+    }
+
+    let stream = tokio::time::timeout(Duration::from_millis(3000), TcpStream::connect(addr.clone() + ":8006")).await.map_err(|_| coded(ErrorCode::ConnectionTimeout, "Connection Timed Out!"))??;
+    Self::configure_keepalive(&stream)?;
+
+    if will_deploy {
+      match Self::verify_daemon_version(addr).await {
+        Err(e) if has_code(&e, ErrorCode::BridgeVersionMismatch) => return Err(e),
+        Err(e) => warn!("Couldn't check the daemon's version: {}", e),
+        Ok(()) => ()
+      }
+    }
+    Ok(Framed::new(stream, GrappleTcpCanBridgeCodec))
+  }
+
+  /* Keep trying to re-establish the bridge until it comes back or the user asks us to stop. The daemon only needs
+     deploying again if the roboRIO is up but nothing's listening on the bridge port (it rebooted and /tmp went with
+     it), and then only once, rather than over SSH on every attempt. */
+  async fn reconnect(inner: &Arc<RoboRioDaemonInner>, will_deploy: bool) -> Option<Framed<TcpStream, GrappleTcpCanBridgeCodec>> {
+    let mut redeployed = false;
+    loop {
+      tokio::time::sleep(RECONNECT_INTERVAL).await;
+
+      if let Ok(mut stop_signal_rx) = inner.stop_signal_rx.try_lock() {
+        if let Ok(()) = stop_signal_rx.try_recv() {
+          return None;
+        }
+      }
+
+      info!("Attempting to reconnect...");
+      match Self::open_transport(inner, will_deploy, false).await {
+        Ok(framed) => {
+          info!("Reconnected!");
+          return Some(framed)
+        },
+        Err(e) if has_code(&e, ErrorCode::BridgeVersionMismatch) => {
+          let addr = inner.address.lock().await.clone();
+          Self::on_mismatch(inner, will_deploy, addr, &e).await;
+          return None;
+        },
+        Err(e) => {
+          warn!("Reconnect failed: {}", e);
+          inner.device_manager.on_transport_error(&e);
+
+          let refused = e.downcast_ref::<std::io::Error>().map(|e| e.kind() == std::io::ErrorKind::ConnectionRefused).unwrap_or(false);
+          if will_deploy && refused && !redeployed {
+            match Self::deploy(inner.address.lock().await.clone()).await {
+              Ok(()) => redeployed = true,
+              Err(e) => warn!("Couldn't redeploy the daemon: {}", e)
+            }
+          }
+        }
+      }
+    }
+  }
+
+  async fn do_start(inner: Arc<RoboRioDaemonInner>) -> anyhow::Result<()> {
+    info!("Connecting...");
+
+    let will_deploy = inner.do_deploy.load(std::sync::atomic::Ordering::Relaxed);
+    let addr = inner.address.lock().await.clone();
+
+    let framed = match Self::open_transport(&inner, will_deploy, will_deploy).await {
+      Ok(framed) => framed,
+      Err(e) => {
+        if has_code(&e, ErrorCode::BridgeVersionMismatch) {
+          Self::on_mismatch(&inner, will_deploy, addr.clone(), &e).await;
+        }
+        return Err(e);
+      }
+    };
+
+    info!("Connected!");
+    // Stays registered while we're reconnecting, so its status shows the link as down rather than it disappearing
+    inner.device_manager.register_domain("CAN".to_owned(), inner.can_send_tx.clone()).await;
+
+    tokio::task::spawn(async move {
+      inner.running.store(true, std::sync::atomic::Ordering::Relaxed);
+      let mut framed = framed;
+      loop {
+        let r = Self::do_loop(framed, inner.clone()).await;
+        inner.device_manager.reset().await;
+        match r {
+          Ok(_) => {
+            info!("RoboRioDaemon runner stopped gracefully");
+            break;
+          },
+          Err(e) => {
+            warn!("RoboRioDaemon runner stopped with error: {}", e);
+            inner.device_manager.on_transport_error(&e);
+            // Reconnecting would only get the same bridge again
+            if has_code(&e, ErrorCode::BridgeVersionMismatch) {
+              Self::on_mismatch(&inner, will_deploy, addr.clone(), &e).await;
+              break;
+            }
+            match Self::reconnect(&inner, will_deploy).await {
+              Some(f) => framed = f,
+              None => break
+            }
+          }
+        }
+      }
+      inner.running.store(false, std::sync::atomic::Ordering::Relaxed);
+      inner.device_manager.unregister_domain(&"CAN".to_owned()).await;
+      if will_deploy {
+        tokio::time::timeout(tokio::time::Duration::from_secs(10), Self::revert_to_robot_code(addr.clone())).await.ok();
+      }
+    });
+
+    Ok(())
+  }
+}
+
+#[async_trait::async_trait]
+impl DeviceProvider for RoboRioDaemon {
+  async fn connect(&self) -> anyhow::Result<()> {
+    Self::do_start(self.inner.clone()).await
+  }
+
+  async fn disconnect(&self) -> anyhow::Result<()> {
+    self.inner.stop_signal_tx.send(()).await.ok();
+    Ok(())
+  }
+
+  async fn info(&self) -> anyhow::Result<ProviderInfo> {
+    Ok(ProviderInfo {
+      ty: "RoboRIO".to_owned(),
+      description: format!("RoboRIO"),
+      address: self.inner.address.lock().await.clone(),
+      connected: self.inner.running.load(std::sync::atomic::Ordering::Relaxed)
+    })
+  }
+
+  async fn device_manager_call(&self, req: DeviceManagerRequest) -> anyhow::Result<DeviceManagerResponse> {
+    self.inner.device_manager.rpc_process(req).await
+  }
+
+  async fn call(&self, req: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+    self.rpc_call(req).await
+  }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct RoboRIOStatus {
+  pub using_daemon: bool,
+  pub bridge_mismatch: Option<BridgeMismatch>,
+  /* The libgrapplefrc version the bundled daemon is built against */
+  pub bundled_bridge_version: String,
+}
+
+#[rpc]
+impl RoboRioDaemon {
+  async fn status(&self) -> anyhow::Result<RoboRIOStatus> {
+    Ok(RoboRIOStatus {
+      using_daemon: self.inner.do_deploy.load(std::sync::atomic::Ordering::Relaxed),
+      bridge_mismatch: self.inner.mismatch.lock().unwrap().clone(),
+      bundled_bridge_version: bundled_bridge_version().to_owned(),
+    })
+  }
+
+  /// Replace an incompatible bridge with the daemon bundled with GrappleHook, and connect to it
+  async fn upgrade_bridge(&self) -> anyhow::Result<()> {
+    if self.inner.running.load(std::sync::atomic::Ordering::Relaxed) {
+      anyhow::bail!("Disconnect from the roboRIO before upgrading its bridge");
+    }
+    self.inner.do_deploy.store(true, std::sync::atomic::Ordering::Relaxed);
+    *self.inner.mismatch.lock().unwrap() = None;
+    Self::do_start(self.inner.clone()).await
+  }
+
+  async fn set_use_daemon(&self, use_daemon: bool) -> anyhow::Result<()> {
+    self.inner.do_deploy.store(use_daemon, std::sync::atomic::Ordering::Relaxed);
+    Ok(())
+  }
+
+  async fn set_address(&self, address: String) -> anyhow::Result<()> {
+    let mut addr = self.inner.address.lock().await;
+    *addr = address;
+    Ok(())
+  }
+
+  /// roboRIOs found over mDNS or USB, for connect_discovered
+  async fn discovered(&self) -> anyhow::Result<Vec<DiscoveredRoboRio>> {
+    Ok(discovery().targets())
+  }
+
+  async fn connect_discovered(&self, address: String) -> anyhow::Result<()> {
+    if self.inner.running.load(std::sync::atomic::Ordering::Relaxed) {
+      anyhow::bail!("Already connected to a roboRIO, disconnect first");
+    }
+    *self.inner.address.lock().await = address;
+    Self::do_start(self.inner.clone()).await
+  }
 }
\ No newline at end of file
@@ -0,0 +1,128 @@
+use std::{collections::HashMap, net::IpAddr, sync::{Mutex, OnceLock}, time::Duration};
+
+use log::{info, warn};
+use mdns_sd::{ServiceDaemon, ServiceEvent};
+use tokio::net::TcpStream;
+
+/* Finds roboRIOs so nobody has to type an address. Every roboRIO advertises itself over mDNS as roboRIO-<team>-FRC.local
+   (under NI's _ni._tcp service), and one plugged in over USB is always at USB_ADDRESS, which doesn't need mDNS at all. */
+
+const NI_SERVICE: &str = "_ni._tcp.local.";
+pub const USB_ADDRESS: &str = "172.22.11.2";
+const USB_PROBE_INTERVAL: Duration = Duration::from_secs(2);
+const USB_PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub enum DiscoveryMethod {
+  Mdns,
+  Usb,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct DiscoveredRoboRio {
+  /* What to connect to. For mDNS this is the address it resolved to, as not every OS can look up .local names itself. */
+  pub address: String,
+  pub hostname: Option<String>,
+  pub team: Option<u32>,
+  pub method: DiscoveryMethod,
+}
+
+#[derive(Default)]
+pub struct Discovery {
+  /* By mDNS full name, so we can drop it again when it goes away */
+  mdns: Mutex<HashMap<String, DiscoveredRoboRio>>,
+  usb: Mutex<Option<DiscoveredRoboRio>>,
+}
+
+pub fn discovery() -> &'static Discovery {
+  static DISCOVERY: OnceLock<Discovery> = OnceLock::new();
+  DISCOVERY.get_or_init(Discovery::default)
+}
+
+/* roboRIO-2502-FRC.local. -> 2502 */
+fn team_of(hostname: &str) -> Option<u32> {
+  let lower = hostname.to_lowercase();
+  lower.strip_prefix("roborio-")?.split('-').next()?.parse().ok()
+}
+
+impl Discovery {
+  /* USB first, as that's the one you're most likely to mean if it's there */
+  pub fn targets(&self) -> Vec<DiscoveredRoboRio> {
+    let mut targets: Vec<DiscoveredRoboRio> = self.usb.lock().unwrap().iter().cloned().collect();
+    let mut mdns: Vec<DiscoveredRoboRio> = self.mdns.lock().unwrap().values().cloned().collect();
+    mdns.sort_by(|a, b| a.team.cmp(&b.team).then(a.address.cmp(&b.address)));
+    for target in mdns {
+      // A roboRIO on USB advertises itself over mDNS on that link too
+      if !targets.iter().any(|t| t.address == target.address) {
+        targets.push(target);
+      }
+    }
+    targets
+  }
+
+  fn on_mdns(&self, event: ServiceEvent) {
+    match event {
+      ServiceEvent::ServiceResolved(service) => {
+        let hostname = service.get_hostname().trim_end_matches('.').to_owned();
+        let Some(team) = team_of(&hostname) else { return };
+        // Prefer IPv4, the bridge only listens there
+        let mut addresses: Vec<&IpAddr> = service.get_addresses().iter().collect();
+        addresses.sort_by_key(|a| !a.is_ipv4());
+        let Some(address) = addresses.first() else { return };
+
+        let target = DiscoveredRoboRio { address: address.to_string(), hostname: Some(hostname), team: Some(team), method: DiscoveryMethod::Mdns };
+        if self.mdns.lock().unwrap().insert(service.get_fullname().to_owned(), target.clone()).is_none() {
+          info!("Discovered roboRIO for team {} at {}", team, target.address);
+        }
+      },
+      ServiceEvent::ServiceRemoved(_, fullname) => {
+        self.mdns.lock().unwrap().remove(&fullname);
+      },
+      _ => ()
+    }
+  }
+
+  async fn probe_usb(&self) {
+    // Anything that accepts SSH at the USB address is a roboRIO, nothing else hands out that subnet
+    let present = matches!(tokio::time::timeout(USB_PROBE_TIMEOUT, TcpStream::connect((USB_ADDRESS, 22))).await, Ok(Ok(_)));
+    let mut usb = self.usb.lock().unwrap();
+    match (present, usb.is_some()) {
+      (true, false) => {
+        info!("Discovered roboRIO over USB");
+        *usb = Some(DiscoveredRoboRio { address: USB_ADDRESS.to_owned(), hostname: None, team: None, method: DiscoveryMethod::Usb });
+      },
+      (false, true) => *usb = None,
+      _ => ()
+    }
+  }
+}
+
+pub async fn run() {
+  // Without mDNS (e.g. the socket's already taken) we can still find a roboRIO on USB
+  let browse = ServiceDaemon::new().and_then(|daemon| {
+    let receiver = daemon.browse(NI_SERVICE)?;
+    Ok((daemon, receiver))
+  });
+  let (_daemon, receiver) = match browse {
+    Ok((daemon, receiver)) => (Some(daemon), Some(receiver)),
+    Err(e) => {
+      warn!("Couldn't start mDNS discovery, only USB roboRIOs will be found: {}", e);
+      (None, None)
+    }
+  };
+
+  let mut mdns_running = receiver.is_some();
+  let mut usb_interval = tokio::time::interval(USB_PROBE_INTERVAL);
+  loop {
+    tokio::select! {
+      event = async { receiver.as_ref().unwrap().recv_async().await }, if mdns_running => match event {
+        Ok(event) => discovery().on_mdns(event),
+        Err(_) => {
+          warn!("mDNS discovery stopped");
+          mdns_running = false;
+        }
+      },
+      _ = usb_interval.tick() => discovery().probe_usb().await,
+    }
+  }
+}
@@ -1 +1,3 @@
-pub mod daemon;
\ No newline at end of file
+pub mod compat;
+pub mod daemon;
+pub mod discovery;
\ No newline at end of file
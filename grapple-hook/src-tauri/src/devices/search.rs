@@ -0,0 +1,94 @@
+use super::{device_manager::{DeviceId, Domain}, DeviceInfo, DeviceType};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct SearchResult {
+  pub provider: String,
+  pub domain: Domain,
+  pub device_id: DeviceId,
+  pub device_class: String,
+  pub info: DeviceInfo,
+  pub score: f64,
+  /* The field that matched best, e.g. "name" or "serial", and its value */
+  pub matched_field: String,
+  pub matched_value: String,
+}
+
+/* Fuzzy match of query against text, as a subsequence. Contiguous runs and matches at the start of words score
+   higher, so "lc" finds "LaserCAN" but "laser" ranks an exact prefix above a scattered match. None if it doesn't match. */
+pub fn fuzzy_score(query: &str, text: &str) -> Option<f64> {
+  let query = query.to_lowercase();
+  let text_lower = text.to_lowercase();
+  if query.is_empty() {
+    return None;
+  }
+
+  if text_lower == query {
+    return Some(100.0);
+  }
+
+  let text_chars = text_lower.chars().collect::<Vec<_>>();
+  let mut score = 0.0;
+  let mut ti = 0;
+  let mut last_match: Option<usize> = None;
+
+  for qc in query.chars() {
+    let pos = (ti..text_chars.len()).find(|&i| text_chars[i] == qc)?;
+    score += 1.0;
+    if last_match.map(|l| l + 1 == pos).unwrap_or(false) {
+      score += 2.0;
+    }
+    if pos == 0 || !text_chars[pos - 1].is_alphanumeric() {
+      score += 1.5;
+    }
+    last_match = Some(pos);
+    ti = pos + 1;
+  }
+
+  if text_lower.starts_with(&query) {
+    score += 5.0;
+  }
+
+  // Prefer shorter texts, where the query covers more of what's there
+  Some(score * (0.5 + 0.5 * query.len() as f64 / text_chars.len() as f64))
+}
+
+/* The searchable text for a device, as (field, value) */
+pub fn search_fields(domain: &Domain, device_class: &str, info: &DeviceInfo) -> Vec<(String, String)> {
+  let mut fields = vec![
+    ("class".to_owned(), device_class.to_owned()),
+    ("domain".to_owned(), domain.clone()),
+  ];
+  if let Some(name) = &info.name {
+    fields.push(("name".to_owned(), name.clone()));
+  }
+  if let Some(serial) = info.serial {
+    fields.push(("serial".to_owned(), format!("0x{:x}", serial)));
+    fields.push(("serial".to_owned(), format!("{:x}", serial)));
+  }
+  if let Some(id) = info.device_id {
+    fields.push(("can_id".to_owned(), format!("#{}", id)));
+  }
+  match &info.device_type {
+    DeviceType::Grapple(model) => fields.push(("model".to_owned(), format!("{:?}", model))),
+    DeviceType::UnknownGrapple(model_id) => fields.push(("model".to_owned(), format!("0x{:02X}", model_id))),
+    DeviceType::RoboRIO => fields.push(("model".to_owned(), "RoboRIO".to_owned())),
+    DeviceType::Unknown => (),
+  }
+  fields
+}
+
+/* Best match across all of the fields, if any match */
+pub fn best_match(query: &str, fields: &[(String, String)]) -> Option<(f64, String, String)> {
+  query.split_whitespace()
+    .map(|term| {
+      fields.iter()
+        .filter_map(|(field, value)| fuzzy_score(term, value).map(|s| (s, field.clone(), value.clone())))
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+    })
+    // Every term has to match something
+    .collect::<Option<Vec<_>>>()
+    .and_then(|matches| {
+      let total = matches.iter().map(|m| m.0).sum::<f64>();
+      matches.into_iter().max_by(|a, b| a.0.total_cmp(&b.0)).map(|(_, f, v)| (total, f, v))
+    })
+}
@@ -0,0 +1,107 @@
+use std::sync::{atomic::{AtomicU64, Ordering}, Mutex, OnceLock};
+
+use crate::{events::{events, EventSeverity}, persistence::Persisted};
+use super::DeviceInfo;
+
+/* A record of what each device got up to while it was connected, written when it goes away (or the app closes) so
+   teams have a log of each practice session. */
+
+/* Oldest summaries are dropped past this */
+const MAX_SESSIONS: usize = 1000;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct SessionSummary {
+  pub serial: Option<u32>,
+  pub device_class: String,
+  pub name: Option<String>,
+  pub domain: String,
+  pub started_at_ms: i64,
+  pub ended_at_ms: i64,
+  pub messages_received: u64,
+  /* Warning and error events raised for the device during the session */
+  pub faults: Vec<String>,
+  /* Configuration RPCs that succeeded, in order */
+  pub config_changes: Vec<String>,
+}
+
+pub struct SessionStats {
+  started_at_ms: i64,
+  messages: AtomicU64,
+  config_changes: Mutex<Vec<String>>,
+}
+
+impl SessionStats {
+  pub fn new() -> Self {
+    Self { started_at_ms: chrono::Utc::now().timestamp_millis(), messages: AtomicU64::new(0), config_changes: Mutex::new(vec![]) }
+  }
+
+  pub fn record_message(&self) {
+    self.messages.fetch_add(1, Ordering::Relaxed);
+  }
+
+  pub fn record_config_change(&self, change: String) {
+    self.config_changes.lock().unwrap().push(change);
+  }
+
+  pub fn summarise(&self, domain: &str, device_class: &str, info: &DeviceInfo) -> SessionSummary {
+    let faults = events().since(None).into_iter()
+      .filter(|e| e.serial.is_some() && e.serial == info.serial && e.timestamp_ms >= self.started_at_ms && e.severity != EventSeverity::Info)
+      .map(|e| format!("{}: {}", e.kind, e.message))
+      .collect();
+
+    SessionSummary {
+      serial: info.serial,
+      device_class: device_class.to_owned(),
+      name: info.name.clone(),
+      domain: domain.to_owned(),
+      started_at_ms: self.started_at_ms,
+      ended_at_ms: chrono::Utc::now().timestamp_millis(),
+      messages_received: self.messages.load(Ordering::Relaxed),
+      faults,
+      config_changes: self.config_changes.lock().unwrap().clone(),
+    }
+  }
+}
+
+/* The configuration change a device RPC request makes, if it makes one. Requests forwarded to the common Grapple
+   handler are nested, e.g. { "method": "grapple", "data": { "msg": { "method": "set_id", ... } } } */
+pub fn config_change(request: &serde_json::Value) -> Option<String> {
+  let method = request.get("method")?.as_str()?;
+  if method == "grapple" {
+    return config_change(request.get("data")?.get("msg")?);
+  }
+  match method.starts_with("set_") || method == "commit_to_eeprom" {
+    true => Some(match request.get("data") {
+      Some(data) if data.as_object().map(|o| !o.is_empty()).unwrap_or(false) => format!("{} {}", method, data),
+      _ => method.to_owned()
+    }),
+    false => None
+  }
+}
+
+pub struct SessionHistory {
+  sessions: Persisted<Vec<SessionSummary>>,
+}
+
+impl SessionHistory {
+  pub fn add(&self, summaries: Vec<SessionSummary>) {
+    if summaries.is_empty() {
+      return;
+    }
+    self.sessions.update(|s| {
+      s.extend(summaries);
+      let excess = s.len().saturating_sub(MAX_SESSIONS);
+      s.drain(0..excess);
+    });
+  }
+
+  /* Newest first */
+  pub fn list(&self, serial: Option<u32>) -> Vec<SessionSummary> {
+    self.sessions.read(|s| s.iter().rev().filter(|s| serial.is_none() || s.serial == serial).cloned().collect())
+  }
+}
+
+pub fn session_history() -> &'static SessionHistory {
+  static HISTORY: OnceLock<SessionHistory> = OnceLock::new();
+  HISTORY.get_or_init(|| SessionHistory { sessions: Persisted::load("session_history") })
+}
@@ -0,0 +1,213 @@
+use std::{borrow::Cow, sync::{atomic::AtomicBool, Arc}, time::Duration};
+
+use grapple_frc_msgs::{binmarshal::MarshalUpdate, grapple::{device_info::{GrappleDeviceInfo, GrappleModelId}, firmware::GrappleFirmwareMessage, GrappleBroadcastMessage, GrappleDeviceMessage, GrappleMessageId, TaggedGrappleMessage}, DEVICE_ID_BROADCAST};
+use log::{info, warn};
+use serde_json::json;
+use tokio::sync::{mpsc, Mutex};
+
+use super::{device_manager::{DeviceManager, DeviceManagerRequest, DeviceManagerResponse}, provider::{DeviceProvider, ProviderInfo}};
+
+pub const SIMULATOR_ADDRESS: &'static str = "simulator";
+pub const SIMULATOR_DOMAIN: &'static str = "SIM";
+/* The version a simulated device reports after it's been "flashed" */
+pub const SIMULATED_UPDATE_VERSION: &'static str = "2024.2.0";
+const SIMULATED_BOOTLOADER_VERSION: &'static str = "0.1.0";
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct SimulatedDevice {
+  pub model_id: GrappleModelId,
+  pub serial: u32,
+  pub can_id: u8,
+  pub name: String,
+  pub firmware_version: String,
+  pub is_dfu: bool,
+}
+
+pub struct SimulatorInner {
+  running: AtomicBool,
+  device_manager: DeviceManager,
+
+  stop_signal_tx: mpsc::Sender<()>,
+  stop_signal_rx: Mutex<mpsc::Receiver<()>>,
+
+  send_tx: mpsc::Sender<TaggedGrappleMessage<'static>>,
+  send_rx: Mutex<mpsc::Receiver<TaggedGrappleMessage<'static>>>,
+
+  devices: std::sync::Mutex<Vec<SimulatedDevice>>,
+}
+
+/* A virtual CAN bus full of pretend Grapple devices. Useful for learning the tool (see the tutorial), and for
+   exercising the backend without hardware. */
+#[derive(Clone)]
+pub struct Simulator {
+  inner: Arc<SimulatorInner>
+}
+
+impl Simulator {
+  pub fn new(devices: Vec<SimulatedDevice>) -> Self {
+    let (send_tx, send_rx) = mpsc::channel(100);
+    let (stop_signal_tx, stop_signal_rx) = mpsc::channel(5);
+
+    Self {
+      inner: Arc::new(
+        SimulatorInner {
+          running: AtomicBool::new(false),
+          device_manager: DeviceManager::new(),
+          stop_signal_tx, stop_signal_rx: Mutex::new(stop_signal_rx),
+          send_tx, send_rx: Mutex::new(send_rx),
+          devices: std::sync::Mutex::new(devices),
+        }
+      )
+    }
+  }
+
+  pub fn devices(&self) -> Vec<SimulatedDevice> {
+    self.inner.devices.lock().unwrap().clone()
+  }
+
+  /* Plug a device into the simulated bus */
+  pub fn add_device(&self, device: SimulatedDevice) {
+    self.inner.devices.lock().unwrap().push(device);
+  }
+
+  /* Unplug a device, returning whether it was there */
+  pub fn remove_device(&self, serial: u32) -> bool {
+    let mut devices = self.inner.devices.lock().unwrap();
+    let before = devices.len();
+    devices.retain(|d| d.serial != serial);
+    devices.len() != before
+  }
+
+  pub fn is_running(&self) -> bool {
+    self.inner.running.load(std::sync::atomic::Ordering::Relaxed)
+  }
+
+  fn enumerate_response(device: &SimulatedDevice) -> TaggedGrappleMessage<'static> {
+    TaggedGrappleMessage::new(
+      device.can_id,
+      GrappleDeviceMessage::Broadcast(GrappleBroadcastMessage::DeviceInfo(GrappleDeviceInfo::EnumerateResponse {
+        model_id: device.model_id.clone(),
+        serial: device.serial,
+        is_dfu: device.is_dfu,
+        is_dfu_in_progress: false,
+        name: Cow::<str>::Owned(device.name.clone()).into(),
+        version: Cow::<str>::Owned(if device.is_dfu { SIMULATED_BOOTLOADER_VERSION.to_owned() } else { device.firmware_version.clone() }).into(),
+      }))
+    )
+  }
+
+  /* Work out what the simulated bus says back to a message sent by the DeviceManager */
+  fn respond(inner: &SimulatorInner, msg: TaggedGrappleMessage<'static>) -> Vec<TaggedGrappleMessage<'static>> {
+    let mut devices = inner.devices.lock().unwrap();
+    let mut replies = vec![];
+
+    match msg.msg {
+      GrappleDeviceMessage::Broadcast(GrappleBroadcastMessage::DeviceInfo(dinfo)) => match dinfo {
+        GrappleDeviceInfo::EnumerateRequest => {
+          replies.extend(devices.iter().map(Self::enumerate_response));
+        },
+        GrappleDeviceInfo::SetName { serial, name } => {
+          devices.iter_mut().filter(|d| d.serial == serial).for_each(|d| d.name = name.clone().into_owned());
+        },
+        GrappleDeviceInfo::SetId { serial, new_id } => {
+          devices.iter_mut().filter(|d| d.serial == serial).for_each(|d| d.can_id = new_id);
+        },
+        _ => ()
+      },
+      GrappleDeviceMessage::FirmwareUpdate(fw) => match fw {
+        GrappleFirmwareMessage::StartFieldUpgrade { serial } => {
+          devices.iter_mut().filter(|d| d.serial == serial).for_each(|d| d.is_dfu = true);
+        },
+        GrappleFirmwareMessage::UpdatePart(_) => {
+          for d in devices.iter().filter(|d| d.is_dfu && (msg.device_id == DEVICE_ID_BROADCAST || d.can_id == msg.device_id)) {
+            replies.push(TaggedGrappleMessage::new(d.can_id, GrappleDeviceMessage::FirmwareUpdate(GrappleFirmwareMessage::UpdatePartAck)));
+          }
+        },
+        GrappleFirmwareMessage::UpdateDone => {
+          for d in devices.iter_mut().filter(|d| d.is_dfu && (msg.device_id == DEVICE_ID_BROADCAST || d.can_id == msg.device_id)) {
+            d.is_dfu = false;
+            d.firmware_version = SIMULATED_UPDATE_VERSION.to_owned();
+          }
+        },
+        _ => ()
+      },
+      _ => ()
+    }
+
+    replies
+  }
+
+  async fn do_loop(inner: Arc<SimulatorInner>) -> anyhow::Result<()> {
+    let mut send_rx = inner.send_rx.try_lock().map_err(|_| anyhow::anyhow!("This Simulator is already running!"))?;
+    let mut stop_signal_rx = inner.stop_signal_rx.try_lock()?;
+
+    let mut device_manager_interval = tokio::time::interval(Duration::from_millis(500));
+
+    loop {
+      tokio::select! {
+        msg = send_rx.recv() => match msg {
+          Some(msg) => {
+            for mut reply in Self::respond(&inner, msg) {
+              let mut id = GrappleMessageId::new(reply.device_id);
+              reply.msg.update(&mut id);
+              inner.device_manager.on_message(SIMULATOR_DOMAIN.to_owned(), id, reply).await?;
+            }
+          },
+          None => ()
+        },
+        sig = stop_signal_rx.recv() => match sig {
+          Some(()) => {
+            break;
+          },
+          None => ()
+        },
+        _ = device_manager_interval.tick() => {
+          inner.device_manager.on_tick().await?;
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+#[async_trait::async_trait]
+impl DeviceProvider for Simulator {
+  async fn connect(&self) -> anyhow::Result<()> {
+    let inner = self.inner.clone();
+    inner.device_manager.register_domain(SIMULATOR_DOMAIN.to_owned(), inner.send_tx.clone()).await;
+    tokio::task::spawn(async move {
+      inner.running.store(true, std::sync::atomic::Ordering::Relaxed);
+      let r = Self::do_loop(inner.clone()).await;
+      inner.running.store(false, std::sync::atomic::Ordering::Relaxed);
+      inner.device_manager.unregister_domain(&SIMULATOR_DOMAIN.to_owned()).await;
+      match r {
+        Ok(_) => info!("Simulator stopped gracefully"),
+        Err(e) => warn!("Simulator stopped with error: {}", e),
+      }
+    });
+    Ok(())
+  }
+
+  async fn disconnect(&self) -> anyhow::Result<()> {
+    self.inner.stop_signal_tx.send(()).await.ok();
+    Ok(())
+  }
+
+  async fn info(&self) -> anyhow::Result<ProviderInfo> {
+    Ok(ProviderInfo {
+      ty: "Simulator".to_owned(),
+      description: "Simulated CAN Bus".to_owned(),
+      address: SIMULATOR_ADDRESS.to_owned(),
+      connected: self.is_running()
+    })
+  }
+
+  async fn call(&self, _req: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+    Ok(json!({}))
+  }
+
+  async fn device_manager_call(&self, req: DeviceManagerRequest) -> anyhow::Result<DeviceManagerResponse> {
+    self.inner.device_manager.rpc_process(req).await
+  }
+}
@@ -0,0 +1,236 @@
+use std::{borrow::Cow, collections::HashMap, sync::{atomic::AtomicBool, Arc, OnceLock}, time::Duration};
+
+use bounded_static::ToBoundedStatic;
+use futures::{SinkExt, StreamExt};
+use grapple_frc_msgs::{binmarshal::{BitView, Demarshal, LengthTaggedPayload, LengthTaggedPayloadOwned}, bridge::BridgedCANMessage, grapple::{fragments::FragmentReassembler, TaggedGrappleMessage}, ManufacturerMessage};
+use grapple_hook_macros::rpc;
+use log::{info, warn};
+use tokio::sync::{mpsc, Mutex};
+use tokio_serial::SerialStream;
+use tokio_util::codec::Framed;
+
+use crate::codecs::slcan_codec::{SlcanCodec, SlcanCommand, SLCAN_BITRATES};
+use crate::errors::{coded, ErrorCode};
+use crate::{persistence::Persisted, rpc::RpcBase};
+
+use super::usb_permissions::{diagnose, fix_for};
+use super::{device_manager::{DeviceManager, DeviceManagerRequest, DeviceManagerResponse}, provider::{DeviceProvider, ProviderInfo}};
+
+/* A generic USB-CAN adapter speaking SLCAN over a serial port (CANable, CANtact, and plenty of unbranded sticks).
+   Unlike a Grapple USB device it's just a window onto the bus, so everything it hears goes in the SLCAN domain. */
+
+/* CANable and CANtact running the slcan firmware. Other adapters show up as anonymous serial ports, so they have to be
+   added by hand (see ProviderManager::add_slcan_adapter). */
+pub const SLCAN_USB_IDS: &[(u16, u16)] = &[(0x16D0, 0x117E)];
+pub const SLCAN_DOMAIN: &str = "SLCAN";
+const FRC_BITRATE: u32 = 1_000_000;
+
+/* The bitrate each adapter is opened at, by port. FRC_BITRATE if it's never been changed. */
+fn bitrates() -> &'static Persisted<HashMap<String, u32>> {
+  static BITRATES: OnceLock<Persisted<HashMap<String, u32>>> = OnceLock::new();
+  BITRATES.get_or_init(|| Persisted::load("slcan_bitrates"))
+}
+
+fn bitrate_for(port: &str) -> u32 {
+  bitrates().read(|b| b.get(port).cloned()).unwrap_or(FRC_BITRATE)
+}
+
+pub struct SlcanInner {
+  port: String,
+  running: AtomicBool,
+  device_manager: DeviceManager,
+
+  stop_signal_tx: mpsc::Sender<()>,
+  stop_signal_rx: Mutex<mpsc::Receiver<()>>,
+
+  send_tx: mpsc::Sender<TaggedGrappleMessage<'static>>,
+  send_rx: Mutex<mpsc::Receiver<TaggedGrappleMessage<'static>>>,
+
+  last_error: std::sync::Mutex<Option<String>>,
+}
+
+pub struct Slcan {
+  inner: Arc<SlcanInner>
+}
+
+impl Slcan {
+  pub fn new(port: String) -> Self {
+    let (send_tx, send_rx) = mpsc::channel(100);
+    let (stop_signal_tx, stop_signal_rx) = mpsc::channel(5);
+
+    Self {
+      inner: Arc::new(
+        SlcanInner {
+          port,
+          running: AtomicBool::new(false),
+          device_manager: DeviceManager::new(),
+          stop_signal_tx, stop_signal_rx: Mutex::new(stop_signal_rx),
+          send_tx, send_rx: Mutex::new(send_rx),
+          last_error: std::sync::Mutex::new(None),
+        }
+      )
+    }
+  }
+
+  async fn do_loop(mut framed: Framed<SerialStream, SlcanCodec>, inner: Arc<SlcanInner>) -> anyhow::Result<()> {
+    let mut send_rx = inner.send_rx.try_lock().map_err(|_| anyhow::anyhow!("This RootDevice is already running!"))?;
+    let mut stop_signal_rx = inner.stop_signal_rx.try_lock()?;
+
+    let (mut reassemble_rx, mut reassemble_tx) = FragmentReassembler::new(1000, 8).split();
+    let mut device_manager_interval = tokio::time::interval(Duration::from_millis(500));
+
+    loop {
+      tokio::select! {
+        msg = framed.next() => match msg {
+          Some(Ok(msg)) => {
+            let manufacturer_msg = ManufacturerMessage::read(&mut BitView::new(&msg.data[..]), msg.id.clone());
+            match manufacturer_msg {
+              Ok(ManufacturerMessage::Grapple(grpl_msg)) => {
+                let mut storage = Vec::new();
+                match reassemble_rx.defragment(chrono::Utc::now().timestamp_millis(), &msg.id, grpl_msg, &mut storage) {
+                  Ok(Some(grpl_unfragmented)) => {
+                    inner.device_manager.on_message(SLCAN_DOMAIN.to_owned(), msg.id.clone().into(), TaggedGrappleMessage::new(msg.id.device_id, grpl_unfragmented.to_static())).await?;
+                  },
+                  Ok(None) => (),
+                  Err(e) => inner.device_manager.on_malformed(SLCAN_DOMAIN, msg.id.clone().into(), &msg.data[..], format!("{:?}", e))
+                }
+              },
+              Ok(_) => inner.device_manager.on_foreign(SLCAN_DOMAIN, msg.id.clone().into()),
+              Err(e) => inner.device_manager.on_malformed(SLCAN_DOMAIN, msg.id.clone().into(), &msg.data[..], format!("{:?}", e))
+            }
+          },
+          Some(Err(e)) => anyhow::bail!(e),
+          None => anyhow::bail!("Adapter disconnected")
+        },
+        msg = send_rx.recv() => match msg {
+          Some(TaggedGrappleMessage { device_id, msg }) => {
+            let mut msgs = vec![];
+            reassemble_tx.maybe_fragment(device_id, msg, &mut |id, buf| {
+              msgs.push(BridgedCANMessage { id, timestamp: 0, data: Cow::<LengthTaggedPayload<u8>>::Owned(LengthTaggedPayloadOwned::new(buf.to_vec())).into() });
+            }).ok();
+
+            for msg in msgs {
+              framed.send(msg).await?;
+            }
+          },
+          None => ()
+        },
+        sig = stop_signal_rx.recv() => match sig {
+          Some(()) => {
+            break;
+          },
+          None => ()
+        },
+        _ = device_manager_interval.tick() => {
+          inner.device_manager.on_tick().await?;
+        }
+      }
+    }
+
+    // Leave the adapter closed, so the next thing to open it starts from a clean slate
+    framed.send(SlcanCommand::Close).await.ok();
+    Ok(())
+  }
+
+  async fn open(inner: &Arc<SlcanInner>) -> anyhow::Result<Framed<SerialStream, SlcanCodec>> {
+    let port = tokio_serial::SerialStream::open(&tokio_serial::new(inner.port.clone(), 115200)).map_err(|e| {
+      coded(ErrorCode::UsbOpenFailed, format!("{} ({})", fix_for(diagnose(&e)).description, e))
+    })?;
+    let mut framed = Framed::new(port, SlcanCodec);
+
+    // The adapter might have been left open by something else, and won't take a new bitrate until it's closed
+    framed.send(SlcanCommand::Close).await?;
+    framed.send(SlcanCommand::Bitrate(bitrate_for(&inner.port))).await?;
+    framed.send(SlcanCommand::Open).await?;
+    Ok(framed)
+  }
+
+  async fn do_start(inner: Arc<SlcanInner>) -> anyhow::Result<()> {
+    info!("Connecting to SLCAN adapter on {}...", inner.port);
+
+    let result = Self::open(&inner).await;
+    *inner.last_error.lock().unwrap() = result.as_ref().err().map(|e| e.to_string());
+    let framed = result?;
+
+    info!("Connected!");
+    inner.device_manager.register_domain(SLCAN_DOMAIN.to_owned(), inner.send_tx.clone()).await;
+
+    tokio::task::spawn(async move {
+      inner.running.store(true, std::sync::atomic::Ordering::Relaxed);
+      let r = Self::do_loop(framed, inner.clone()).await;
+      inner.running.store(false, std::sync::atomic::Ordering::Relaxed);
+      inner.device_manager.unregister_domain(&SLCAN_DOMAIN.to_owned()).await;
+      match r {
+        Ok(_) => info!("SLCAN runner stopped gracefully"),
+        Err(e) => {
+          warn!("SLCAN runner stopped with error: {}", e);
+          *inner.last_error.lock().unwrap() = Some(e.to_string());
+        }
+      }
+    });
+
+    Ok(())
+  }
+}
+
+#[async_trait::async_trait]
+impl DeviceProvider for Slcan {
+  async fn connect(&self) -> anyhow::Result<()> {
+    Self::do_start(self.inner.clone()).await
+  }
+
+  async fn disconnect(&self) -> anyhow::Result<()> {
+    self.inner.stop_signal_tx.send(()).await.ok();
+    Ok(())
+  }
+
+  async fn info(&self) -> anyhow::Result<ProviderInfo> {
+    Ok(ProviderInfo {
+      ty: "SLCAN".to_owned(),
+      description: "SLCAN Adapter".to_owned(),
+      address: self.inner.port.clone(),
+      connected: self.inner.running.load(std::sync::atomic::Ordering::Relaxed)
+    })
+  }
+
+  async fn device_manager_call(&self, req: DeviceManagerRequest) -> anyhow::Result<DeviceManagerResponse> {
+    self.inner.device_manager.rpc_process(req).await
+  }
+
+  async fn call(&self, req: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+    self.rpc_call(req).await
+  }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct SlcanStatus {
+  pub port: String,
+  pub bitrate: u32,
+  pub supported_bitrates: Vec<u32>,
+  /* Why the adapter couldn't be opened, or why the connection dropped */
+  pub last_error: Option<String>,
+}
+
+#[rpc]
+impl Slcan {
+  async fn status(&self) -> anyhow::Result<SlcanStatus> {
+    Ok(SlcanStatus {
+      port: self.inner.port.clone(),
+      bitrate: bitrate_for(&self.inner.port),
+      supported_bitrates: SLCAN_BITRATES.iter().map(|(b, _)| *b).collect(),
+      last_error: self.inner.last_error.lock().unwrap().clone(),
+    })
+  }
+
+  /// Choose the bitrate the adapter is opened at. Takes effect next time it's connected.
+  async fn set_bitrate(&self, bitrate: u32) -> anyhow::Result<()> {
+    if self.inner.running.load(std::sync::atomic::Ordering::Relaxed) {
+      anyhow::bail!("Disconnect from {} before changing its bitrate", self.inner.port);
+    }
+    if !SLCAN_BITRATES.iter().any(|(b, _)| *b == bitrate) {
+      anyhow::bail!("Unsupported bitrate {}", bitrate);
+    }
+    bitrates().update(|b| b.insert(self.inner.port.clone(), bitrate));
+    Ok(())
+  }
+}
@@ -0,0 +1,281 @@
+use std::{collections::HashMap, time::{Duration, Instant}};
+
+use grapple_frc_msgs::grapple::device_info::GrappleModelId;
+use log::{info, warn};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use super::{device_manager::{DeviceId, DeviceManagerRequest, DeviceManagerResponse, Domain}, lasercan::LaserCanRequest, provider::DeviceProvider, simulator::{SimulatedDevice, Simulator, SIMULATED_UPDATE_VERSION}, DeviceInfo, FirmwareUpgradeDeviceRequest};
+
+/* A long-running stress test of the backend against the simulated bus. Devices are plugged in and pulled out, RPCs
+   are made against whatever is on the bus, and LaserCANs are put through firmware updates, all chosen by a seeded RNG
+   so a failing run can be repeated. Every so often the device list is checked against what is actually on the bus.
+   Run it with `grapple-hook-cli soak` before a season to shake out leaks, deadlocks and stuck devices. */
+const ACTION_INTERVAL: Duration = Duration::from_millis(200);
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+/* A device that was just plugged in or pulled out needs time to be enumerated or aged off before it's checked */
+const SETTLE_TIME: Duration = Duration::from_secs(10);
+const DFU_TIMEOUT: Duration = Duration::from_secs(10);
+const FLASH_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_DEVICES: usize = 32;
+/* The report keeps a count of every failure, but only the details of the first few */
+const MAX_RECORDED_FAILURES: usize = 1000;
+
+const CHURN_CHANCE: f64 = 0.05;
+const FIRMWARE_CHANCE: f64 = 0.01;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct SoakConfig {
+  pub duration_s: u64,
+  pub seed: u64,
+  /* How many devices are on the bus to begin with */
+  pub devices: usize,
+}
+
+impl Default for SoakConfig {
+  fn default() -> Self {
+    Self { duration_s: 8 * 60 * 60, seed: 0, devices: 12 }
+  }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct SoakFailure {
+  pub elapsed_s: f64,
+  pub action: String,
+  pub error: String,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct SoakReport {
+  pub seed: u64,
+  pub started: String,
+  pub duration_s: f64,
+  pub rpc_calls: usize,
+  pub rpc_failures: usize,
+  pub mean_rpc_ms: f64,
+  pub max_rpc_ms: f64,
+  pub devices_added: usize,
+  pub devices_removed: usize,
+  pub firmware_updates: usize,
+  pub firmware_failures: usize,
+  pub consistency_checks: usize,
+  pub total_failures: usize,
+  pub failures: Vec<SoakFailure>,
+}
+
+impl SoakReport {
+  pub fn passed(&self) -> bool {
+    self.total_failures == 0
+  }
+}
+
+/* A synthetic image that passes LaserCan::validate_firmware */
+fn lasercan_image() -> Vec<u8> {
+  let mut image = vec![0u8; 0x200];
+  image[0x150..0x154].copy_from_slice(&[0xBE, 0xBA, 0xFE, 0xCA]);
+  image[0x15c] = GrappleModelId::LaserCan as u8;
+  image
+}
+
+struct SoakRun {
+  sim: Simulator,
+  rng: StdRng,
+  start: Instant,
+  report: SoakReport,
+  rpc_total_ms: f64,
+  /* When each serial was last plugged in or pulled out */
+  churned: HashMap<u32, Instant>,
+}
+
+impl SoakRun {
+  fn fail(&mut self, action: &str, error: impl std::fmt::Display) {
+    warn!("Soak test: {} failed: {}", action, error);
+    self.report.total_failures += 1;
+    if self.report.failures.len() < MAX_RECORDED_FAILURES {
+      self.report.failures.push(SoakFailure { elapsed_s: self.start.elapsed().as_secs_f64(), action: action.to_owned(), error: error.to_string() });
+    }
+  }
+
+  fn random_device(&mut self) -> SimulatedDevice {
+    let models = [GrappleModelId::LaserCan, GrappleModelId::MitoCANdria, GrappleModelId::FlexiCAN, GrappleModelId::SpiderLan];
+    SimulatedDevice {
+      model_id: models.choose(&mut self.rng).unwrap().clone(),
+      serial: self.rng.gen(),
+      can_id: self.rng.gen_range(0..63),
+      name: String::new(),
+      firmware_version: SIMULATED_UPDATE_VERSION.to_owned(),
+      is_dfu: false,
+    }
+  }
+
+  async fn devices(&self) -> anyhow::Result<Vec<(Domain, DeviceId, DeviceInfo)>> {
+    match self.sim.device_manager_call(DeviceManagerRequest::devices {}).await? {
+      DeviceManagerResponse::devices(domains) => Ok(domains.into_iter().flat_map(|(domain, devices)| devices.into_iter().map(move |(id, info, _)| (domain.clone(), id, info))).collect()),
+      _ => anyhow::bail!("Unexpected response from device manager")
+    }
+  }
+
+  async fn wait_for(&self, domain: &Domain, device_id: &DeviceId, timeout: Duration) -> anyhow::Result<DeviceInfo> {
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+      if let Some((_, _, info)) = self.devices().await?.into_iter().find(|(d, id, _)| d == domain && id == device_id) {
+        return Ok(info);
+      }
+      tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+    anyhow::bail!("{:?} didn't appear within {:?}", device_id, timeout)
+  }
+
+  /* Identify or rename a random device */
+  async fn rpc(&mut self) {
+    let devices = match self.devices().await {
+      Ok(devices) => devices,
+      Err(e) => return self.fail("list devices", e)
+    };
+    let Some((domain, device_id, _)) = devices.into_iter().filter(|(_, id, _)| matches!(id, DeviceId::Serial(..))).collect::<Vec<_>>().choose(&mut self.rng).cloned() else {
+      return;
+    };
+
+    let (action, request) = match self.rng.gen_bool(0.5) {
+      true => ("identify", DeviceManagerRequest::identify { domain, device_id }),
+      false => ("rename", DeviceManagerRequest::rename { domain, device_id, name: format!("soak-{}", self.rng.gen::<u16>()) })
+    };
+
+    let start = Instant::now();
+    let result = self.sim.device_manager_call(request).await;
+    let ms = start.elapsed().as_secs_f64() * 1000.0;
+    self.report.rpc_calls += 1;
+    self.rpc_total_ms += ms;
+    self.report.max_rpc_ms = self.report.max_rpc_ms.max(ms);
+    if let Err(e) = result {
+      self.report.rpc_failures += 1;
+      self.fail(action, e);
+    }
+  }
+
+  async fn churn(&mut self) {
+    let present = self.sim.devices();
+    if present.len() > 1 && (present.len() >= MAX_DEVICES || self.rng.gen_bool(0.5)) {
+      let serial = present.choose(&mut self.rng).unwrap().serial;
+      self.sim.remove_device(serial);
+      self.churned.insert(serial, Instant::now());
+      self.report.devices_removed += 1;
+    } else {
+      let device = self.random_device();
+      self.churned.insert(device.serial, Instant::now());
+      self.sim.add_device(device);
+      self.report.devices_added += 1;
+    }
+  }
+
+  /* Put a random LaserCAN into DFU, flash it, and wait for it to come back on the new version */
+  async fn firmware(&mut self) {
+    let candidates = self.sim.devices().into_iter().filter(|d| matches!(d.model_id, GrappleModelId::LaserCan) && !d.is_dfu && !self.churned.contains_key(&d.serial)).collect::<Vec<_>>();
+    let Some(serial) = candidates.choose(&mut self.rng).map(|d| d.serial) else { return };
+
+    self.report.firmware_updates += 1;
+    if let Err(e) = self.flash(serial).await {
+      self.report.firmware_failures += 1;
+      self.fail("firmware update", format!("{:x}: {}", serial, e));
+    }
+  }
+
+  async fn flash(&self, serial: u32) -> anyhow::Result<()> {
+    let domain = self.domain_of(serial).await?;
+    self.sim.device_manager_call(DeviceManagerRequest::call {
+      domain: domain.clone(), device_id: DeviceId::Serial(serial), data: serde_json::to_value(LaserCanRequest::start_field_upgrade {})?
+    }).await?;
+
+    self.wait_for(&domain, &DeviceId::Dfu(serial), DFU_TIMEOUT).await?;
+    self.sim.device_manager_call(DeviceManagerRequest::call {
+      domain: domain.clone(), device_id: DeviceId::Dfu(serial), data: serde_json::to_value(FirmwareUpgradeDeviceRequest::do_field_upgrade { data: lasercan_image() })?
+    }).await?;
+
+    let info = self.wait_for(&domain, &DeviceId::Serial(serial), FLASH_TIMEOUT).await?;
+    if info.firmware_version.as_deref() != Some(SIMULATED_UPDATE_VERSION) {
+      anyhow::bail!("Came back on {:?} rather than {}", info.firmware_version, SIMULATED_UPDATE_VERSION);
+    }
+    Ok(())
+  }
+
+  async fn domain_of(&self, serial: u32) -> anyhow::Result<Domain> {
+    self.devices().await?.into_iter().find(|(_, _, info)| info.serial == Some(serial)).map(|(domain, _, _)| domain)
+      .ok_or(anyhow::anyhow!("Device {:x} isn't in the device list", serial))
+  }
+
+  /* Every settled device on the bus should be listed, and nothing else */
+  async fn check(&mut self) {
+    self.report.consistency_checks += 1;
+    let listed = match self.devices().await {
+      Ok(devices) => devices.into_iter().filter_map(|(_, _, info)| info.serial).collect::<Vec<_>>(),
+      Err(e) => return self.fail("list devices", e)
+    };
+    let present = self.sim.devices().into_iter().map(|d| d.serial).collect::<Vec<_>>();
+
+    self.churned.retain(|_, at| at.elapsed() < SETTLE_TIME);
+    let settled = |serial: &u32| !self.churned.contains_key(serial);
+    let missing = present.iter().filter(|s| settled(s) && !listed.contains(s)).map(|s| format!("{:x}", s)).collect::<Vec<_>>();
+    let lingering = listed.iter().filter(|s| settled(s) && !present.contains(s)).map(|s| format!("{:x}", s)).collect::<Vec<_>>();
+
+    if !missing.is_empty() {
+      self.fail("consistency", format!("On the bus but not listed: {}", missing.join(", ")));
+    }
+    if !lingering.is_empty() {
+      self.fail("consistency", format!("Listed but not on the bus: {}", lingering.join(", ")));
+    }
+  }
+}
+
+pub async fn run_soak(config: SoakConfig) -> anyhow::Result<SoakReport> {
+  let mut rng = StdRng::seed_from_u64(config.seed);
+  let mut run = SoakRun {
+    sim: Simulator::new(vec![]),
+    rng: StdRng::seed_from_u64(rng.gen()),
+    start: Instant::now(),
+    report: SoakReport { seed: config.seed, started: chrono::Utc::now().to_rfc3339(), ..Default::default() },
+    rpc_total_ms: 0.0,
+    churned: HashMap::new(),
+  };
+  for _ in 0..config.devices {
+    let device = run.random_device();
+    run.sim.add_device(device);
+  }
+
+  run.sim.connect().await?;
+  // Give the simulator's task a moment to start
+  tokio::time::sleep(ACTION_INTERVAL).await;
+  let duration = Duration::from_secs(config.duration_s);
+  let mut last_check = Instant::now();
+  info!("Soak test started with seed {} for {:?}", config.seed, duration);
+
+  while run.start.elapsed() < duration {
+    if !run.sim.is_running() {
+      run.fail("simulator", "The simulator stopped, restarting it");
+      run.sim.connect().await?;
+      tokio::time::sleep(ACTION_INTERVAL).await;
+    }
+
+    let roll: f64 = run.rng.gen();
+    if roll < FIRMWARE_CHANCE {
+      run.firmware().await;
+    } else if roll < FIRMWARE_CHANCE + CHURN_CHANCE {
+      run.churn().await;
+    } else {
+      run.rpc().await;
+    }
+
+    if last_check.elapsed() >= CHECK_INTERVAL {
+      run.check().await;
+      last_check = Instant::now();
+    }
+    tokio::time::sleep(ACTION_INTERVAL).await;
+  }
+
+  run.sim.disconnect().await?;
+  let mut report = run.report;
+  report.duration_s = run.start.elapsed().as_secs_f64();
+  if report.rpc_calls > 0 {
+    report.mean_rpc_ms = run.rpc_total_ms / report.rpc_calls as f64;
+  }
+  info!("Soak test finished: {} failures over {} RPC calls", report.total_failures, report.rpc_calls);
+  Ok(report)
+}
@@ -0,0 +1,276 @@
+use std::{collections::HashMap, path::Path, sync::{atomic::AtomicBool, Arc, OnceLock}, time::Duration};
+
+use bounded_static::ToBoundedStatic;
+use grapple_frc_msgs::{binmarshal::{BitView, Demarshal}, grapple::{fragments::FragmentReassembler, TaggedGrappleMessage}, ManufacturerMessage, MessageId};
+use grapple_hook_macros::rpc;
+use log::{info, warn};
+use socketcan::{tokio::CanSocket, CanError, CanFrame, CanInterface, EmbeddedFrame, ExtendedId, Frame};
+use tokio::sync::{mpsc, Mutex};
+
+use crate::{errors::{coded, ErrorCode}, persistence::Persisted, rpc::RpcBase};
+
+use super::{device_manager::{DeviceManager, DeviceManagerRequest, DeviceManagerResponse}, provider::{DeviceProvider, ProviderInfo}};
+
+/* A CAN interface on a Linux machine (e.g. can0 on a Raspberry Pi with a CAN HAT, or a USB adapter handled by the
+   kernel), talking directly to the bus through SocketCAN. Devices on it appear in a domain named after the interface. */
+
+/* From linux/if_arp.h, what /sys/class/net/<iface>/type reads for a CAN interface */
+const ARPHRD_CAN: &str = "280";
+const IFF_UP: u32 = 0x1;
+/* The FRC CAN bus always runs at 1Mbit/s */
+pub const FRC_BITRATE: u32 = 1_000_000;
+pub const SUPPORTED_BITRATES: &[u32] = &[10_000, 20_000, 50_000, 125_000, 250_000, 500_000, 800_000, 1_000_000];
+
+/* The bitrate to bring each interface up at, by interface name. Interfaces without one are left as the system
+   configured them, or brought up at FRC_BITRATE if they're down. */
+fn bitrates() -> &'static Persisted<HashMap<String, u32>> {
+  static BITRATES: OnceLock<Persisted<HashMap<String, u32>>> = OnceLock::new();
+  BITRATES.get_or_init(|| Persisted::load("socketcan_bitrates"))
+}
+
+/* Every CAN interface the kernel knows about, up or not */
+pub fn can_interfaces() -> Vec<String> {
+  let mut interfaces: Vec<String> = std::fs::read_dir("/sys/class/net").into_iter().flatten().flatten()
+    .filter(|e| std::fs::read_to_string(e.path().join("type")).map(|t| t.trim() == ARPHRD_CAN).unwrap_or(false))
+    .map(|e| e.file_name().to_string_lossy().into_owned())
+    .collect();
+  interfaces.sort();
+  interfaces
+}
+
+fn is_up(interface: &str) -> bool {
+  std::fs::read_to_string(format!("/sys/class/net/{}/flags", interface)).ok()
+    .and_then(|f| u32::from_str_radix(f.trim().trim_start_matches("0x"), 16).ok())
+    .map(|f| f & IFF_UP != 0)
+    .unwrap_or(false)
+}
+
+/* vcan and friends have no bitrate, they can only be brought up */
+fn is_virtual(interface: &str) -> bool {
+  Path::new(&format!("/sys/devices/virtual/net/{}", interface)).exists()
+}
+
+/* Configuring an interface needs CAP_NET_ADMIN, which GrappleHook usually won't have, so tell the user what to run */
+fn configure(interface: &str, bitrate: u32) -> anyhow::Result<()> {
+  let manual = if is_virtual(interface) {
+    format!("sudo ip link set {} up", interface)
+  } else {
+    format!("sudo ip link set {} type can bitrate {} && sudo ip link set {} up", interface, bitrate, interface)
+  };
+  let fail = |e: String| coded(ErrorCode::InterfaceConfigFailed, format!("Couldn't bring up {} ({}). Run `{}`, then connect again.", interface, e, manual));
+
+  let iface = CanInterface::open(interface).map_err(|e| fail(e.to_string()))?;
+  if !is_virtual(interface) {
+    iface.bring_down().map_err(|e| fail(e.to_string()))?;
+    iface.set_bitrate(bitrate, None).map_err(|e| fail(e.to_string()))?;
+  }
+  iface.bring_up().map_err(|e| fail(e.to_string()))?;
+  info!("Brought up {} at {}bit/s", interface, bitrate);
+  Ok(())
+}
+
+pub struct SocketCanInner {
+  interface: String,
+  running: AtomicBool,
+  device_manager: DeviceManager,
+
+  stop_signal_tx: mpsc::Sender<()>,
+  stop_signal_rx: Mutex<mpsc::Receiver<()>>,
+
+  send_tx: mpsc::Sender<TaggedGrappleMessage<'static>>,
+  send_rx: Mutex<mpsc::Receiver<TaggedGrappleMessage<'static>>>,
+
+  last_error: std::sync::Mutex<Option<String>>,
+}
+
+pub struct SocketCan {
+  inner: Arc<SocketCanInner>
+}
+
+impl SocketCan {
+  pub fn new(interface: String) -> Self {
+    let (send_tx, send_rx) = mpsc::channel(100);
+    let (stop_signal_tx, stop_signal_rx) = mpsc::channel(5);
+
+    Self {
+      inner: Arc::new(
+        SocketCanInner {
+          interface,
+          running: AtomicBool::new(false),
+          device_manager: DeviceManager::new(),
+          stop_signal_tx, stop_signal_rx: Mutex::new(stop_signal_rx),
+          send_tx, send_rx: Mutex::new(send_rx),
+          last_error: std::sync::Mutex::new(None),
+        }
+      )
+    }
+  }
+
+  async fn do_loop(socket: CanSocket, inner: Arc<SocketCanInner>) -> anyhow::Result<()> {
+    let mut send_rx = inner.send_rx.try_lock().map_err(|_| anyhow::anyhow!("This RootDevice is already running!"))?;
+    let mut stop_signal_rx = inner.stop_signal_rx.try_lock()?;
+
+    let domain = inner.interface.as_str();
+    let (mut reassemble_rx, mut reassemble_tx) = FragmentReassembler::new(1000, 8).split();
+    let mut device_manager_interval = tokio::time::interval(Duration::from_millis(500));
+
+    loop {
+      tokio::select! {
+        frame = socket.read_frame() => match frame? {
+          // FRC devices only ever use extended IDs, anything else on the bus isn't ours to decode
+          CanFrame::Data(frame) if frame.is_extended() => {
+            let id = MessageId::from(frame.raw_id());
+            match ManufacturerMessage::read(&mut BitView::new(frame.data()), id.clone()) {
+              Ok(ManufacturerMessage::Grapple(grpl_msg)) => {
+                let mut storage = Vec::new();
+                match reassemble_rx.defragment(chrono::Utc::now().timestamp_millis(), &id, grpl_msg, &mut storage) {
+                  Ok(Some(grpl_unfragmented)) => {
+                    inner.device_manager.on_message(domain.to_owned(), id.clone().into(), TaggedGrappleMessage::new(id.device_id, grpl_unfragmented.to_static())).await?;
+                  },
+                  Ok(None) => (),
+                  Err(e) => inner.device_manager.on_malformed(domain, id.clone().into(), frame.data(), format!("{:?}", e))
+                }
+              },
+              Ok(_) => inner.device_manager.on_foreign(domain, id.clone().into()),
+              Err(e) => inner.device_manager.on_malformed(domain, id.clone().into(), frame.data(), format!("{:?}", e))
+            }
+          },
+          CanFrame::Error(frame) => {
+            let e = anyhow::anyhow!("{}", CanError::from(frame));
+            warn!("{} reported a bus error: {}", domain, e);
+            inner.device_manager.on_transport_error(&e);
+          },
+          _ => ()
+        },
+        msg = send_rx.recv() => match msg {
+          Some(TaggedGrappleMessage { device_id, msg }) => {
+            let mut frames = vec![];
+            reassemble_tx.maybe_fragment(device_id, msg, &mut |id, buf| {
+              frames.extend(ExtendedId::new(id.into()).and_then(|id| CanFrame::new(id, buf)));
+            }).ok();
+
+            for frame in frames {
+              socket.write_frame(frame).await?;
+            }
+          },
+          None => ()
+        },
+        sig = stop_signal_rx.recv() => match sig {
+          Some(()) => {
+            break;
+          },
+          None => ()
+        },
+        _ = device_manager_interval.tick() => {
+          inner.device_manager.on_tick().await?;
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  async fn do_start(inner: Arc<SocketCanInner>) -> anyhow::Result<()> {
+    info!("Connecting to {}...", inner.interface);
+
+    let result = async {
+      let chosen = bitrates().read(|b| b.get(&inner.interface).cloned());
+      if chosen.is_some() || !is_up(&inner.interface) {
+        configure(&inner.interface, chosen.unwrap_or(FRC_BITRATE))?;
+      }
+      CanSocket::open(&inner.interface).map_err(|e| anyhow::anyhow!("Couldn't open {}: {}", inner.interface, e))
+    }.await;
+    *inner.last_error.lock().unwrap() = result.as_ref().err().map(|e| e.to_string());
+    let socket = result?;
+
+    info!("Connected!");
+    inner.device_manager.register_domain(inner.interface.clone(), inner.send_tx.clone()).await;
+
+    tokio::task::spawn(async move {
+      inner.running.store(true, std::sync::atomic::Ordering::Relaxed);
+      let r = Self::do_loop(socket, inner.clone()).await;
+      inner.running.store(false, std::sync::atomic::Ordering::Relaxed);
+      inner.device_manager.unregister_domain(&inner.interface).await;
+      match r {
+        Ok(_) => info!("SocketCAN runner stopped gracefully"),
+        Err(e) => {
+          warn!("SocketCAN runner stopped with error: {}", e);
+          *inner.last_error.lock().unwrap() = Some(e.to_string());
+        }
+      }
+    });
+
+    Ok(())
+  }
+}
+
+#[async_trait::async_trait]
+impl DeviceProvider for SocketCan {
+  async fn connect(&self) -> anyhow::Result<()> {
+    Self::do_start(self.inner.clone()).await
+  }
+
+  async fn disconnect(&self) -> anyhow::Result<()> {
+    self.inner.stop_signal_tx.send(()).await.ok();
+    Ok(())
+  }
+
+  async fn info(&self) -> anyhow::Result<ProviderInfo> {
+    Ok(ProviderInfo {
+      ty: "SocketCAN".to_owned(),
+      description: "SocketCAN Interface".to_owned(),
+      address: self.inner.interface.clone(),
+      connected: self.inner.running.load(std::sync::atomic::Ordering::Relaxed)
+    })
+  }
+
+  async fn device_manager_call(&self, req: DeviceManagerRequest) -> anyhow::Result<DeviceManagerResponse> {
+    self.inner.device_manager.rpc_process(req).await
+  }
+
+  async fn call(&self, req: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+    self.rpc_call(req).await
+  }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct SocketCanStatus {
+  pub interface: String,
+  pub up: bool,
+  pub is_virtual: bool,
+  /* The bitrate we bring the interface up at, if one's been chosen */
+  pub bitrate: Option<u32>,
+  pub supported_bitrates: Vec<u32>,
+  /* Why the interface couldn't be brought up or opened, or why the connection dropped */
+  pub last_error: Option<String>,
+}
+
+#[rpc]
+impl SocketCan {
+  async fn status(&self) -> anyhow::Result<SocketCanStatus> {
+    let interface = self.inner.interface.clone();
+    Ok(SocketCanStatus {
+      up: is_up(&interface),
+      is_virtual: is_virtual(&interface),
+      bitrate: bitrates().read(|b| b.get(&interface).cloned()),
+      supported_bitrates: SUPPORTED_BITRATES.to_vec(),
+      last_error: self.inner.last_error.lock().unwrap().clone(),
+      interface,
+    })
+  }
+
+  /// Choose the bitrate the interface is brought up at when we connect, or None to leave it as the system has it
+  async fn set_bitrate(&self, bitrate: Option<u32>) -> anyhow::Result<()> {
+    if self.inner.running.load(std::sync::atomic::Ordering::Relaxed) {
+      anyhow::bail!("Disconnect from {} before changing its bitrate", self.inner.interface);
+    }
+    if let Some(bitrate) = bitrate.filter(|b| !SUPPORTED_BITRATES.contains(b)) {
+      anyhow::bail!("Unsupported bitrate {}", bitrate);
+    }
+    bitrates().update(|b| match bitrate {
+      Some(bitrate) => { b.insert(self.inner.interface.clone(), bitrate); },
+      None => { b.remove(&self.inner.interface); }
+    });
+    Ok(())
+  }
+}
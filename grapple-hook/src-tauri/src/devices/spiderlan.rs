@@ -0,0 +1,87 @@
+use grapple_frc_msgs::{grapple::TaggedGrappleMessage, DEVICE_ID_BROADCAST};
+use grapple_hook_macros::rpc;
+use tokio::sync::RwLock;
+
+use crate::rpc::RpcBase;
+use super::compatibility::{compatibility_report, CompatibilityReport};
+use super::device_class::DeviceClass;
+use super::{SendWrapper, SharedInfo, GrappleDevice, Device, GrappleDeviceRequest, GrappleDeviceResponse, RootDevice, start_field_upgrade};
+
+/* The Grapple message set has no SpiderLAN messages (no port status, no configuration), so this driver only does what
+   every Grapple device can: name, CAN ID, blink and entering the bootloader, plus whether it's alive and talking. There's
+   no version gate or release lookup either, as there are no published SpiderLAN releases to base them on; a SpiderLAN in
+   its bootloader is flashed through the generic driver. */
+#[derive(Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct SpiderLanStatus {
+  messages_received: u64,
+  last_message_ms: Option<i64>,
+}
+
+pub struct SpiderLan {
+  sender: SendWrapper,
+  info: SharedInfo,
+
+  grapple_device: GrappleDevice,
+
+  status: RwLock<SpiderLanStatus>
+}
+
+impl SpiderLan {
+  pub fn new(sender: SendWrapper, info: SharedInfo) -> Self {
+    Self {
+      sender: sender.clone(),
+      info: info.clone(),
+
+      grapple_device: GrappleDevice::new(sender.clone(), info.clone()),
+
+      status: RwLock::new(SpiderLanStatus { messages_received: 0, last_message_ms: None })
+    }
+  }
+}
+
+#[async_trait::async_trait]
+impl RootDevice for SpiderLan {
+  fn device_class(&self) -> &'static str {
+    "SpiderLAN"
+  }
+
+  async fn identify(&self) -> anyhow::Result<()> {
+    self.grapple_device.blink().await
+  }
+}
+
+#[async_trait::async_trait]
+impl Device for SpiderLan {
+  async fn handle(&self, msg: TaggedGrappleMessage<'static>) -> anyhow::Result<()> {
+    if msg.device_id == DEVICE_ID_BROADCAST || Some(msg.device_id) == self.info.read().await.device_id {
+      if msg.device_id != DEVICE_ID_BROADCAST {
+        let mut status = self.status.write().await;
+        status.messages_received += 1;
+        status.last_message_ms = Some(self.sender.timestamp_ms());
+      }
+    }
+
+    self.grapple_device.handle(msg).await?;
+    Ok(())
+  }
+}
+
+#[rpc]
+impl SpiderLan {
+  async fn start_field_upgrade(&self) -> anyhow::Result<()> {
+    let serial = self.info.read().await.require_serial()?;
+    start_field_upgrade(&self.sender, serial).await
+  }
+
+  async fn grapple(&self, msg: GrappleDeviceRequest) -> anyhow::Result<GrappleDeviceResponse> {
+    self.grapple_device.rpc_process(msg).await
+  }
+
+  async fn status(&self) -> anyhow::Result<SpiderLanStatus> {
+    Ok(self.status.read().await.clone())
+  }
+
+  async fn compatibility(&self) -> anyhow::Result<CompatibilityReport> {
+    Ok(compatibility_report(DeviceClass::SpiderLan, &*self.info.read().await))
+  }
+}
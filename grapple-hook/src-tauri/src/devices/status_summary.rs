@@ -0,0 +1,127 @@
+use std::collections::{BTreeMap, HashSet};
+
+use super::{attention::{assess, AttentionKind}, can_conflicts::find_conflicts, device_class::resolve_device_class, device_manager::{DeviceId, Domain}, firmware_catalog::FirmwareCatalog, DeviceInfo, DeviceState};
+
+/* A plain-text account of how the connected devices are doing, e.g. "6 devices healthy, 1 needs a firmware update, no
+   faults". Worded here rather than in the frontend so the app, a screen reader and a chat integration all say the same
+   thing. */
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct StatusSummary {
+  /* One sentence covering everything */
+  pub headline: String,
+  /* A sentence for each thing worth doing something about, most important first */
+  pub details: Vec<String>,
+  pub healthy: usize,
+  pub updating: usize,
+  pub needs_update: usize,
+  pub faults: usize,
+}
+
+fn count(n: usize, singular: &str, plural: &str) -> String {
+  format!("{} {}", n, if n == 1 { singular } else { plural })
+}
+
+fn describe(info: &DeviceInfo, class: &str) -> String {
+  match (info.name.as_deref().map(str::trim).filter(|n| !n.is_empty()), info.serial) {
+    (Some(name), _) => name.to_owned(),
+    (None, Some(serial)) => format!("{} {:x}", class, serial),
+    (None, None) => class.to_owned()
+  }
+}
+
+/* devices holds (domain, id, info, device class) for everything connected */
+pub fn summarise(devices: &[(Domain, DeviceId, DeviceInfo, String)], catalog: &FirmwareCatalog) -> StatusSummary {
+  let mut faults = vec![];
+  let mut updates = vec![];
+  let mut notes = vec![];
+  let mut unhealthy: HashSet<(&Domain, &DeviceId)> = HashSet::new();
+  let mut updating = 0;
+
+  let mut by_domain: BTreeMap<&Domain, Vec<(u32, u8)>> = BTreeMap::new();
+  for (domain, _, info, _) in devices {
+    if let (false, Some(serial), Some(can_id)) = (info.is_dfu, info.serial, info.device_id) {
+      by_domain.entry(domain).or_default().push((serial, can_id));
+    }
+  }
+  for (domain, ids) in by_domain {
+    for conflict in find_conflicts(&ids) {
+      let names = devices.iter()
+        .filter(|(d, _, info, _)| d == domain && !info.is_dfu && info.serial.map(|s| conflict.serials.contains(&s)).unwrap_or(false))
+        .map(|(_, id, info, class)| { unhealthy.insert((domain, id)); describe(info, class) })
+        .collect::<Vec<_>>();
+      faults.push(format!("{} on {} share CAN ID {}.", names.join(" and "), domain, conflict.can_id));
+    }
+  }
+
+  for (domain, id, info, class) in devices {
+    let name = describe(info, class);
+    match &info.state {
+      DeviceState::Lost => {
+        faults.push(format!("{} has stopped responding.", name));
+        unhealthy.insert((domain, id));
+        continue;
+      },
+      DeviceState::Rebooting => {
+        updating += 1;
+        unhealthy.insert((domain, id));
+        continue;
+      },
+      DeviceState::Updating if info.is_dfu_in_progress => {
+        updating += 1;
+        unhealthy.insert((domain, id));
+        continue;
+      },
+      _ => ()
+    }
+
+    for item in assess(domain, id, info, class) {
+      match item.kind {
+        AttentionKind::StuckInDfu => { faults.push(format!("{} is stuck in firmware update mode.", name)); unhealthy.insert((domain, id)); },
+        AttentionKind::OutdatedFirmware => { updates.push(format!("{} needs a firmware update to work with GrappleHook.", name)); unhealthy.insert((domain, id)); },
+        AttentionKind::DefaultCanId => notes.push(format!("{} is still on the factory default CAN ID.", name)),
+        AttentionKind::DefaultName => notes.push(format!("{} hasn't been named.", name)),
+      }
+    }
+
+    // Gated devices were counted above
+    if info.is_dfu || class == "OldVersionDevice" {
+      continue;
+    }
+    let newer = resolve_device_class(&info.device_type)
+      .zip(info.firmware_version.as_deref())
+      .and_then(|(class, version)| catalog.update_available(class, version));
+    if let Some(version) = newer {
+      updates.push(format!("{} can be updated to firmware {}.", name, version));
+      unhealthy.insert((domain, id));
+    }
+  }
+
+  let healthy = devices.len().saturating_sub(unhealthy.len());
+
+  let headline = match devices.len() {
+    0 => "No devices connected.".to_owned(),
+    _ => {
+      let mut parts = vec![count(healthy, "device healthy", "devices healthy")];
+      if updating > 0 {
+        parts.push(count(updating, "updating", "updating"));
+      }
+      if !updates.is_empty() {
+        parts.push(count(updates.len(), "needs a firmware update", "need a firmware update"));
+      }
+      parts.push(match faults.len() {
+        0 => "no faults".to_owned(),
+        n => count(n, "fault", "faults")
+      });
+      format!("{}.", parts.join(", "))
+    }
+  };
+
+  StatusSummary {
+    headline,
+    healthy,
+    updating,
+    needs_update: updates.len(),
+    faults: faults.len(),
+    details: faults.into_iter().chain(updates).chain(notes).collect(),
+  }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashSet;
+
+use super::bom::{model_name, normalise, BomEntry};
+use super::device_manager::{DeviceId, Domain};
+use super::DeviceInfo;
+
+/* Starting points for common robot architectures. Applying one sets the BOM to the template's devices, and hands out
+   the template's roles (as nicknames) and baseline configuration to matching devices that are connected. */
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct TemplateSlot {
+  pub role: String,
+  /* As in the BOM, e.g. "LaserCan" */
+  pub model: String,
+  /* Fields for the device's apply_config RPC, if the role has a sensible default */
+  pub baseline: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct RobotTemplate {
+  pub id: String,
+  pub name: String,
+  pub description: String,
+  pub slots: Vec<TemplateSlot>,
+}
+
+impl RobotTemplate {
+  pub fn bom(&self) -> Vec<BomEntry> {
+    let mut entries: Vec<BomEntry> = vec![];
+    for slot in &self.slots {
+      match entries.iter_mut().find(|e| normalise(&e.model) == normalise(&slot.model)) {
+        Some(entry) => entry.quantity += 1,
+        None => entries.push(BomEntry { model: slot.model.clone(), quantity: 1 })
+      }
+    }
+    entries
+  }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct SlotAssignment {
+  pub role: String,
+  pub serial: u32,
+  pub device_id: DeviceId,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct TemplateApplication {
+  pub bom: Vec<BomEntry>,
+  pub assigned: Vec<SlotAssignment>,
+  /* Roles with no connected device to fill them yet */
+  pub unfilled: Vec<String>,
+  /* Devices whose baseline configuration couldn't be applied, and why */
+  pub config_errors: Vec<(u32, String)>,
+}
+
+fn lasercan(role: &str, mode: &str, budget: &str) -> TemplateSlot {
+  TemplateSlot {
+    role: role.to_owned(),
+    model: "LaserCan".to_owned(),
+    baseline: Some(serde_json::json!({ "mode": mode, "roi": { "x": 8, "y": 8, "w": 16, "h": 16 }, "budget": budget })),
+  }
+}
+
+fn mitocandria(role: &str) -> TemplateSlot {
+  // Rail voltages depend entirely on what's plugged in, so there's no safe default to apply
+  TemplateSlot { role: role.to_owned(), model: "MitoCANdria".to_owned(), baseline: None }
+}
+
+pub fn builtin_templates() -> Vec<RobotTemplate> {
+  vec![
+    RobotTemplate {
+      id: "swerve-4-lasercan".to_owned(),
+      name: "Swerve base, 4 LaserCANs and a MitoCANdria".to_owned(),
+      description: "A typical game piece handling robot: intake and indexer sensing, shooter staging and an elevator height sensor, with a MitoCANdria powering the coprocessor and cameras.".to_owned(),
+      slots: vec![
+        lasercan("Intake", "Short", "TB20ms"),
+        lasercan("Indexer", "Short", "TB20ms"),
+        lasercan("Shooter Staging", "Short", "TB20ms"),
+        lasercan("Elevator Height", "Long", "TB33ms"),
+        mitocandria("Coprocessor Power"),
+      ],
+    },
+    RobotTemplate {
+      id: "kitbot".to_owned(),
+      name: "Kitbot, 1 LaserCAN".to_owned(),
+      description: "Kit of parts drivetrain with a single game piece sensor.".to_owned(),
+      slots: vec![
+        lasercan("Game Piece", "Short", "TB20ms"),
+      ],
+    },
+    RobotTemplate {
+      id: "vision-coprocessor".to_owned(),
+      name: "Vision robot, 2 LaserCANs and a MitoCANdria".to_owned(),
+      description: "Coprocessor-heavy robot with a MitoCANdria for vision power and LaserCANs for game piece and wall distance.".to_owned(),
+      slots: vec![
+        lasercan("Game Piece", "Short", "TB20ms"),
+        lasercan("Wall Distance", "Long", "TB50ms"),
+        mitocandria("Vision Power"),
+      ],
+    },
+  ]
+}
+
+pub fn template(id: &str) -> anyhow::Result<RobotTemplate> {
+  builtin_templates().into_iter().find(|t| t.id == id).ok_or(anyhow::anyhow!("No robot template {}", id))
+}
+
+/* Match the template's roles to connected devices. Devices that already have a nickname are taken to already have a
+   role, so are only used once every unnamed device of that model has been. Lowest serial first, so it's repeatable. */
+pub fn assign(template: &RobotTemplate, devices: &[(String, Domain, DeviceId, DeviceInfo, String)], nicknamed: &HashSet<u32>) -> (Vec<SlotAssignment>, Vec<String>) {
+  let mut candidates = devices.iter()
+    .filter_map(|(_, _, id, info, _)| match id {
+      DeviceId::Serial(serial) => Some((*serial, id.clone(), normalise(&model_name(&info.device_type)))),
+      DeviceId::Dfu(..) => None
+    })
+    .collect::<Vec<_>>();
+  candidates.sort_by_key(|(serial, _, _)| (nicknamed.contains(serial), *serial));
+  candidates.dedup_by_key(|(serial, _, _)| *serial);
+
+  let (mut assigned, mut unfilled, mut used) = (vec![], vec![], HashSet::new());
+  for slot in &template.slots {
+    let model = normalise(&slot.model);
+    match candidates.iter().find(|(serial, _, m)| *m == model && !used.contains(serial)) {
+      Some((serial, id, _)) => {
+        used.insert(*serial);
+        assigned.push(SlotAssignment { role: slot.role.clone(), serial: *serial, device_id: id.clone() });
+      },
+      None => unfilled.push(slot.role.clone())
+    }
+  }
+  (assigned, unfilled)
+}
@@ -0,0 +1,64 @@
+use std::{collections::{HashMap, VecDeque}, sync::Mutex};
+
+use grapple_frc_msgs::{grapple::TaggedGrappleMessage, DEVICE_ID_BROADCAST};
+
+use super::fixtures::developer_mode;
+
+/* A rolling, decoded log of everything sent to and received from each device on a domain, for working out why
+   firmware is rejecting a request. Only recorded in developer mode. Entries are kept by CAN ID, since that's all a
+   frame carries, so a device's transcript starts over if its ID changes. */
+const TRANSCRIPT_LENGTH: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub enum Direction {
+  Sent,
+  Received,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct TranscriptEntry {
+  pub timestamp_ms: i64,
+  pub direction: Direction,
+  pub device_id: u8,
+  pub message: serde_json::Value,
+}
+
+pub struct Transcript {
+  entries: Mutex<HashMap<u8, VecDeque<TranscriptEntry>>>,
+}
+
+impl Transcript {
+  pub fn new() -> Self {
+    Self { entries: Mutex::new(HashMap::new()) }
+  }
+
+  /* Broadcasts (enumeration, ID changes) aren't part of any one device's conversation, so they're left out */
+  pub fn record(&self, direction: Direction, timestamp_ms: i64, msg: &TaggedGrappleMessage<'static>) {
+    if msg.device_id == DEVICE_ID_BROADCAST || !developer_mode() {
+      return;
+    }
+
+    let entry = TranscriptEntry {
+      timestamp_ms,
+      direction,
+      device_id: msg.device_id,
+      message: serde_json::to_value(&msg.msg).unwrap_or_else(|e| serde_json::Value::String(format!("<couldn't decode: {}>", e))),
+    };
+
+    let mut entries = self.entries.lock().unwrap();
+    let log = entries.entry(msg.device_id).or_default();
+    log.push_back(entry);
+    while log.len() > TRANSCRIPT_LENGTH {
+      log.pop_front();
+    }
+  }
+
+  /* Oldest first */
+  pub fn for_device(&self, device_id: u8) -> Vec<TranscriptEntry> {
+    self.entries.lock().unwrap().get(&device_id).map(|log| log.iter().cloned().collect()).unwrap_or_default()
+  }
+
+  pub fn clear(&self) {
+    self.entries.lock().unwrap().clear();
+  }
+}
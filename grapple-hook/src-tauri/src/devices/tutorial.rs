@@ -0,0 +1,73 @@
+use grapple_frc_msgs::grapple::device_info::GrappleModelId;
+
+use super::{lasercan::LaserCan, simulator::{SimulatedDevice, Simulator}, VersionGatedDevice};
+
+const TUTORIAL_SERIAL: u32 = 0x00C0FFEE;
+const TUTORIAL_OLD_FIRMWARE: &'static str = "2024.0.0";
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct TutorialStep {
+  pub title: String,
+  pub description: String,
+  pub complete: bool,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct TutorialStatus {
+  pub steps: Vec<TutorialStep>,
+  pub current_step: Option<usize>,
+}
+
+/* A scripted walkthrough on the simulator: a brand new LaserCAN shows up, and the student has to name it and
+   bring its firmware up to date, exactly like they would on the real robot. */
+pub struct Tutorial {
+  simulator: Simulator,
+}
+
+impl Tutorial {
+  pub fn new() -> Self {
+    Self {
+      simulator: Simulator::new(vec![
+        SimulatedDevice {
+          model_id: GrappleModelId::LaserCan,
+          serial: TUTORIAL_SERIAL,
+          can_id: 0,
+          name: "".to_owned(),
+          firmware_version: TUTORIAL_OLD_FIRMWARE.to_owned(),
+          is_dfu: false
+        }
+      ])
+    }
+  }
+
+  pub fn simulator(&self) -> Simulator {
+    self.simulator.clone()
+  }
+
+  pub fn status(&self) -> TutorialStatus {
+    let device = self.simulator.devices().into_iter().find(|d| d.serial == TUTORIAL_SERIAL);
+    let named = device.as_ref().map(|d| !d.name.trim().is_empty()).unwrap_or(false);
+    let updated = device.as_ref().map(|d| !d.is_dfu && LaserCan::validate_version(Some(d.firmware_version.clone())).is_ok()).unwrap_or(false);
+
+    let steps = vec![
+      TutorialStep {
+        title: "Connect".to_owned(),
+        description: "Connect to the Simulator in the device list. A new LaserCAN will appear.".to_owned(),
+        complete: self.simulator.is_running()
+      },
+      TutorialStep {
+        title: "Name your LaserCAN".to_owned(),
+        description: "Give the LaserCAN a name so you can tell it apart from the others on your robot.".to_owned(),
+        complete: named
+      },
+      TutorialStep {
+        title: "Update the firmware".to_owned(),
+        description: "This LaserCAN's firmware is out of date. Click \"Firmware Update\" and flash the latest release.".to_owned(),
+        complete: updated
+      },
+    ];
+
+    let current_step = steps.iter().position(|s| !s.complete);
+    TutorialStatus { steps, current_step }
+  }
+}
@@ -0,0 +1,82 @@
+use std::{borrow::Cow, sync::OnceLock};
+
+use bounded_static::IntoBoundedStatic;
+use grapple_frc_msgs::{binmarshal::{BitView, BitWriter, BufferBitWriter, Demarshal, Marshal, MarshalUpdate}, grapple::{device_info::{GrappleDeviceInfo, GrappleModelId}, GrappleBroadcastMessage, GrappleDeviceMessage, GrappleMessageId, TaggedGrappleMessage}, MessageId};
+
+/* GrappleModelId is a closed enum, so an EnumerateResponse from a model grapple-frc-msgs doesn't know (e.g. one
+   released after this build) fails to decode, and would only ever end up in the quarantine. The model ID is a plain
+   byte at a fixed offset though, so we can read it before the typed parse, swap in a model the crate does know, and
+   decode the rest as normal. The device keeps its real model byte as DeviceType::UnknownGrapple.
+
+   This needs the whole response. The transports only pass on_malformed the frame that failed, so a response that
+   was fragmented (and failed once reassembled) still goes to the quarantine. */
+
+/* Stands in for the unknown model while decoding. Any known model would do. */
+const STAND_IN: GrappleModelId = GrappleModelId::LaserCan;
+
+fn enumerate_response(model_id: GrappleModelId, name: &str) -> GrappleDeviceMessage<'static> {
+  GrappleDeviceMessage::Broadcast(GrappleBroadcastMessage::DeviceInfo(GrappleDeviceInfo::EnumerateResponse {
+    model_id,
+    serial: 0,
+    is_dfu: false,
+    is_dfu_in_progress: false,
+    name: Cow::<str>::Owned(name.to_owned()).into(),
+    version: Cow::<str>::Owned("0.0.0".to_owned()).into(),
+  }))
+}
+
+pub fn encode(mut msg: GrappleDeviceMessage<'static>, device_id: u8) -> Option<(GrappleMessageId, Vec<u8>)> {
+  let mut payload = [0u8; 1024];
+  let mut writer = BufferBitWriter::new(&mut payload);
+  let mut id = GrappleMessageId::new(device_id);
+  msg.update(&mut id);
+  msg.write(&mut writer, id.clone()).ok()?;
+  Some((id, writer.slice().to_vec()))
+}
+
+/* Where the model byte sits in an encoded EnumerateResponse, found by encoding two that differ only in model. The
+   names differ in length too, so if the model ever came after the strings (and had no fixed offset) we'd notice and
+   give up rather than read the wrong byte. */
+pub fn model_id_offset() -> Option<usize> {
+  static OFFSET: OnceLock<Option<usize>> = OnceLock::new();
+  *OFFSET.get_or_init(|| {
+    let find = |name: &str| -> Option<usize> {
+      let (_, a) = encode(enumerate_response(GrappleModelId::LaserCan, name), 0)?;
+      let (_, b) = encode(enumerate_response(GrappleModelId::SpiderLan, name), 0)?;
+      if a.len() != b.len() {
+        return None;
+      }
+      let differs = (0..a.len()).filter(|&i| a[i] != b[i]).collect::<Vec<_>>();
+      match differs[..] {
+        [i] if a[i] == GrappleModelId::LaserCan as u8 && b[i] == GrappleModelId::SpiderLan as u8 => Some(i),
+        _ => None
+      }
+    };
+    match (find("A"), find("A much longer name")) {
+      (Some(a), Some(b)) if a == b => Some(a),
+      _ => None
+    }
+  })
+}
+
+/* Decode a message that failed the typed parse as an EnumerateResponse from an unknown model. Gives the raw model
+   byte and the response, decoded with STAND_IN as its model, or None if it's anything else. */
+pub fn decode_enumerate_response(arbitration_id: u32, payload: &[u8]) -> Option<(u8, TaggedGrappleMessage<'static>)> {
+  let device_id = MessageId::from(arbitration_id).device_id;
+  let (expected_id, _) = encode(enumerate_response(STAND_IN, ""), device_id)?;
+  if Into::<u32>::into(Into::<MessageId>::into(expected_id.clone())) != arbitration_id {
+    return None;
+  }
+
+  let offset = model_id_offset()?;
+  let model_id = *payload.get(offset)?;
+  let mut patched = payload.to_vec();
+  patched[offset] = STAND_IN as u8;
+
+  match GrappleDeviceMessage::read(&mut BitView::new(&patched[..]), expected_id) {
+    Ok(msg @ GrappleDeviceMessage::Broadcast(GrappleBroadcastMessage::DeviceInfo(GrappleDeviceInfo::EnumerateResponse { .. }))) => {
+      Some((model_id, TaggedGrappleMessage::new(device_id, msg.into_static())))
+    },
+    _ => None
+  }
+}
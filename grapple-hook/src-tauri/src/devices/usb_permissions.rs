@@ -0,0 +1,98 @@
+use std::path::PathBuf;
+
+use crate::persistence::data_dir;
+
+pub const GRAPPLE_USB_VID: u16 = 0x3580;
+pub const GRAPPLE_USB_PID: u16 = 0x4000;
+
+const UDEV_RULE_FILE: &str = "99-grapple.rules";
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub enum UsbIssueKind {
+  /* Linux: the port exists but we aren't allowed to open it, almost always missing udev rules */
+  PermissionDenied,
+  /* Something else (another GrappleHook, a serial monitor) already has the port open */
+  PortInUse,
+  /* Windows: the port went missing or the driver didn't bind */
+  DriverIssue,
+  Other,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct UsbPermissionFix {
+  pub kind: UsbIssueKind,
+  pub description: String,
+  pub instructions: Vec<String>,
+  /* Contents of the udev rule to install, on Linux */
+  pub udev_rule: Option<String>,
+}
+
+pub fn udev_rule() -> String {
+  format!(
+    "# Grapple USB devices (LaserCAN, MitoCANdria, FlexiCAN), installed by GrappleHook\n\
+     SUBSYSTEM==\"usb\", ATTRS{{idVendor}}==\"{:04x}\", ATTRS{{idProduct}}==\"{:04x}\", MODE=\"0666\"\n\
+     SUBSYSTEM==\"tty\", ATTRS{{idVendor}}==\"{:04x}\", ATTRS{{idProduct}}==\"{:04x}\", MODE=\"0666\"\n",
+    GRAPPLE_USB_VID, GRAPPLE_USB_PID, GRAPPLE_USB_VID, GRAPPLE_USB_PID
+  )
+}
+
+/* Write the udev rule somewhere the user can copy it from. We can't install it ourselves without root. */
+pub fn write_udev_rule() -> anyhow::Result<PathBuf> {
+  let path = data_dir().join(UDEV_RULE_FILE);
+  std::fs::create_dir_all(data_dir())?;
+  std::fs::write(&path, udev_rule())?;
+  Ok(path)
+}
+
+pub fn fix_for(kind: UsbIssueKind) -> UsbPermissionFix {
+  match kind {
+    UsbIssueKind::PermissionDenied if cfg!(target_os = "linux") => UsbPermissionFix {
+      kind,
+      description: "GrappleHook doesn't have permission to open the USB device. Linux needs a udev rule to allow this.".to_owned(),
+      instructions: vec![
+        format!("Save the udev rule (GrappleHook can write it to {})", data_dir().join(UDEV_RULE_FILE).display()),
+        format!("sudo cp {} /etc/udev/rules.d/{}", data_dir().join(UDEV_RULE_FILE).display(), UDEV_RULE_FILE),
+        "sudo udevadm control --reload-rules && sudo udevadm trigger".to_owned(),
+        "Unplug the device and plug it back in".to_owned(),
+      ],
+      udev_rule: Some(udev_rule()),
+    },
+    UsbIssueKind::PermissionDenied | UsbIssueKind::PortInUse => UsbPermissionFix {
+      kind,
+      description: "The USB device is already in use by another program.".to_owned(),
+      instructions: vec![
+        "Close any other copies of GrappleHook, serial monitors or terminal programs using the device".to_owned(),
+        "Unplug the device and plug it back in".to_owned(),
+      ],
+      udev_rule: None,
+    },
+    UsbIssueKind::DriverIssue if cfg!(windows) => UsbPermissionFix {
+      kind,
+      description: "Windows couldn't open the device. Its USB serial driver may not have loaded.".to_owned(),
+      instructions: vec![
+        "Open Device Manager and look for the device under \"Ports (COM & LPT)\"".to_owned(),
+        "If it shows a warning icon, right click it and choose \"Update driver\", then \"Search automatically\"".to_owned(),
+        "Try a different USB port or cable, avoiding unpowered hubs".to_owned(),
+      ],
+      udev_rule: None,
+    },
+    _ => UsbPermissionFix {
+      kind,
+      description: "The USB device couldn't be opened.".to_owned(),
+      instructions: vec![
+        "Unplug the device and plug it back in".to_owned(),
+        "Try a different USB port or cable".to_owned(),
+      ],
+      udev_rule: None,
+    }
+  }
+}
+
+pub fn diagnose(err: &tokio_serial::Error) -> UsbIssueKind {
+  match err.kind {
+    tokio_serial::ErrorKind::Io(std::io::ErrorKind::PermissionDenied) => UsbIssueKind::PermissionDenied,
+    tokio_serial::ErrorKind::Io(std::io::ErrorKind::AddrInUse) => UsbIssueKind::PortInUse,
+    tokio_serial::ErrorKind::NoDevice | tokio_serial::ErrorKind::Io(std::io::ErrorKind::NotFound) => UsbIssueKind::DriverIssue,
+    _ => UsbIssueKind::Other,
+  }
+}
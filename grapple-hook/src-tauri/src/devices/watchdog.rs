@@ -0,0 +1,45 @@
+use std::{future::Future, sync::atomic::{AtomicU64, Ordering}, time::Duration};
+
+use log::error;
+
+use crate::{errors::{coded, ErrorCode}, events::{events, EventSeverity}};
+use super::device_manager::RepliesWaiting;
+
+/* The slowest legitimate call is a LaserCAN's apply_config: three writes in a row, each with up to six attempts of
+   300ms for a request permit and 300ms for the reply, so about 11s at worst. Anything still going well past that is
+   wedged, and would otherwise hold its request permits and reply waiters forever. */
+pub const DEFAULT_RPC_DEADLINE_MS: u64 = 15_000;
+
+pub struct RpcWatchdog {
+  deadline_ms: AtomicU64,
+}
+
+impl RpcWatchdog {
+  pub fn new() -> Self {
+    Self { deadline_ms: AtomicU64::new(DEFAULT_RPC_DEADLINE_MS) }
+  }
+
+  pub fn deadline_ms(&self) -> u64 {
+    self.deadline_ms.load(Ordering::Relaxed)
+  }
+
+  pub fn set_deadline_ms(&self, deadline_ms: u64) {
+    self.deadline_ms.store(deadline_ms.max(1000), Ordering::Relaxed);
+  }
+
+  /* Run an RPC, cancelling it (by dropping the future) if it's still going at the deadline. Dropping the future takes
+     its reply waiters with it, but anything else abandoned is cleared out too, and the trip is recorded in the event
+     log. */
+  pub async fn run<T>(&self, replies: &RepliesWaiting, serial: Option<u32>, method: &str, fut: impl Future<Output = anyhow::Result<T>>) -> anyhow::Result<T> {
+    let deadline = self.deadline_ms();
+    match tokio::time::timeout(Duration::from_millis(deadline), fut).await {
+      Ok(result) => result,
+      Err(_) => {
+        let purged = replies.expire();
+        error!("RPC {} on {:?} exceeded the {}ms watchdog deadline and was cancelled ({} reply waiters cleaned up)", method, serial, deadline, purged);
+        events().emit(serial, "rpc_watchdog", EventSeverity::Error, format!("{} didn't finish within {}s and was cancelled", method, deadline / 1000));
+        Err(coded(ErrorCode::RpcDeadlineExceeded, format!("{} exceeded the {}ms deadline and was cancelled", method, deadline)))
+      }
+    }
+  }
+}
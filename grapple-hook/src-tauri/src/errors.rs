@@ -0,0 +1,116 @@
+use std::fmt::Display;
+
+/* Structured error codes. Errors are still anyhow errors, but coded ones are tagged with their ID (e.g.
+   "[GH-001] Timed out waiting for response") so the frontend and API consumers can look up guidance in the catalog.
+   The tag may end up part-way through a message once an error has been given more context. */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub enum ErrorCode {
+  RequestTimeout,
+  TooManyRequests,
+  InvalidFirmware,
+  FeatureUnsupported,
+  IncompatibleFirmware,
+  DeviceNotFound,
+  ConnectionTimeout,
+  UsbOpenFailed,
+  ChunkAckTimeout,
+  MissingDeviceInfo,
+  RpcDeadlineExceeded,
+  IncompatibleConfig,
+  RequestCancelled,
+  BridgeVersionMismatch,
+  InterfaceConfigFailed,
+  BridgeDecodeFailed,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ErrorCatalogEntry {
+  pub code: ErrorCode,
+  pub id: String,
+  pub title: String,
+  pub description: String,
+  pub remediation: String,
+}
+
+struct Entry(ErrorCode, &'static str, &'static str, &'static str, &'static str);
+
+const CATALOG: &[Entry] = &[
+  Entry(ErrorCode::RequestTimeout, "GH-001", "Request timed out",
+    "The device didn't reply to a request in time.",
+    "Check the device is powered and its CAN wiring is secure. If the bus is very busy, try again."),
+  Entry(ErrorCode::TooManyRequests, "GH-002", "Too many requests",
+    "GrappleHook limits how many requests are outstanding at once so the bus isn't flooded.",
+    "Wait for earlier operations to finish, then try again."),
+  Entry(ErrorCode::InvalidFirmware, "GH-003", "Invalid firmware file",
+    "The file isn't firmware for this kind of device.",
+    "Make sure you've downloaded the firmware for the right product, and that the file isn't corrupted."),
+  Entry(ErrorCode::FeatureUnsupported, "GH-004", "Feature not supported",
+    "The device's firmware doesn't support this operation.",
+    "Update the device to the latest firmware."),
+  Entry(ErrorCode::IncompatibleFirmware, "GH-005", "Incompatible firmware",
+    "The device's firmware version isn't supported by this version of GrappleHook.",
+    "Update the device's firmware, or update GrappleHook if the firmware is newer than it understands."),
+  Entry(ErrorCode::DeviceNotFound, "GH-006", "Device not found",
+    "The device isn't (or is no longer) visible on the bus.",
+    "Check the device is connected and powered. It may have rebooted or changed mode."),
+  Entry(ErrorCode::ConnectionTimeout, "GH-007", "Connection timed out",
+    "Couldn't connect to the roboRIO.",
+    "Make sure you're connected to the robot's network, and that the roboRIO has finished booting."),
+  Entry(ErrorCode::UsbOpenFailed, "GH-009", "Couldn't open USB device",
+    "The operating system wouldn't let GrappleHook open the USB device.",
+    "Follow the USB permission instructions shown for the device (udev rules on Linux, drivers on Windows)."),
+  Entry(ErrorCode::ChunkAckTimeout, "GH-010", "Firmware transfer stalled",
+    "The device stopped acknowledging firmware chunks during an update.",
+    "Don't unplug the device. Retry the update, it will resume in the bootloader."),
+  Entry(ErrorCode::MissingDeviceInfo, "GH-011", "Device information missing",
+    "The device hasn't reported its serial number or CAN ID yet.",
+    "Wait a moment for the device to finish enumerating, then try again."),
+  Entry(ErrorCode::RpcDeadlineExceeded, "GH-012", "Operation cancelled by watchdog",
+    "A device operation ran far longer than it ever should and was cancelled so it couldn't block the device.",
+    "Try again. If it keeps happening, power cycle the device and report a bug with the event log attached."),
+  Entry(ErrorCode::IncompatibleConfig, "GH-013", "Incompatible configuration",
+    "The pasted configuration was copied from a different kind of device, or from a newer version of GrappleHook.",
+    "Copy the configuration from a device of the same model, and make sure both laptops are running the same GrappleHook version."),
+  Entry(ErrorCode::RequestCancelled, "GH-014", "Request cancelled",
+    "The request was cancelled before the device replied.",
+    "Try again once whatever cancelled it (e.g. disconnecting, or stopping the operation) is done."),
+  Entry(ErrorCode::BridgeVersionMismatch, "GH-015", "Bridge version mismatch",
+    "The bridge on the roboRIO is built against a different libgrapplefrc: either the daemon there reports another version, or the first thing it sent couldn't be decoded.",
+    "Upgrade the bridge from the roboRIO panel to deploy GrappleHook's own, or update the libgrapplefrc vendordep in your robot code and redeploy."),
+  Entry(ErrorCode::InterfaceConfigFailed, "GH-016", "Couldn't configure CAN interface",
+    "GrappleHook wasn't allowed to set the CAN interface's bitrate or bring it up. On Linux this needs root (CAP_NET_ADMIN).",
+    "Run the `ip link` commands shown with sudo, or configure the interface at boot (e.g. with systemd-networkd), then connect again."),
+  Entry(ErrorCode::BridgeDecodeFailed, "GH-017", "Couldn't decode bridge traffic",
+    "A frame from the roboRIO bridge was corrupt, after others from it had decoded fine.",
+    "GrappleHook reconnects on its own. If it keeps happening, check the connection to the roboRIO."),
+];
+
+impl ErrorCode {
+  fn entry(&self) -> &'static Entry {
+    CATALOG.iter().find(|e| e.0 == *self).expect("Error code missing from catalog")
+  }
+
+  pub fn id(&self) -> &'static str {
+    self.entry().1
+  }
+}
+
+/* Make an error carrying a code */
+pub fn coded(code: ErrorCode, msg: impl Display) -> anyhow::Error {
+  anyhow::anyhow!("[{}] {}", code.id(), msg)
+}
+
+/* Whether an error (or the context it was wrapped in) carries a code */
+pub fn has_code(err: &anyhow::Error, code: ErrorCode) -> bool {
+  err.chain().any(|e| e.to_string().contains(&format!("[{}]", code.id())))
+}
+
+pub fn catalog() -> Vec<ErrorCatalogEntry> {
+  CATALOG.iter().map(|Entry(code, id, title, description, remediation)| ErrorCatalogEntry {
+    code: *code,
+    id: id.to_string(),
+    title: title.to_string(),
+    description: description.to_string(),
+    remediation: remediation.to_string(),
+  }).collect()
+}
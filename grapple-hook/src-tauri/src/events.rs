@@ -0,0 +1,133 @@
+use std::{collections::VecDeque, sync::{atomic::{AtomicU64, Ordering}, Mutex, OnceLock}};
+
+use tokio::sync::broadcast;
+
+/* How many events we keep for the frontend to catch up on */
+const EVENT_HISTORY: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub enum EventSeverity {
+  Info,
+  Warning,
+  Error,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct Event {
+  pub id: u64,
+  pub timestamp_ms: i64,
+  pub serial: Option<u32>,
+  pub kind: String,
+  pub severity: EventSeverity,
+  pub message: String,
+}
+
+/* Things that happened that the user should hear about, e.g. alerts raised by device monitors. Kept in a ring so the
+   frontend can poll with the last ID it saw, and broadcast for anything in the backend that wants to react live. */
+pub struct EventLog {
+  next_id: AtomicU64,
+  recent: Mutex<VecDeque<Event>>,
+  tx: broadcast::Sender<Event>,
+}
+
+impl EventLog {
+  fn new() -> Self {
+    let (tx, _) = broadcast::channel(64);
+    Self { next_id: AtomicU64::new(1), recent: Mutex::new(VecDeque::new()), tx }
+  }
+
+  pub fn emit(&self, serial: Option<u32>, kind: &str, severity: EventSeverity, message: String) -> Event {
+    let event = Event {
+      id: self.next_id.fetch_add(1, Ordering::Relaxed),
+      timestamp_ms: chrono::Utc::now().timestamp_millis(),
+      serial,
+      kind: kind.to_owned(),
+      severity,
+      message
+    };
+
+    let mut recent = self.recent.lock().unwrap();
+    recent.push_back(event.clone());
+    while recent.len() > EVENT_HISTORY {
+      recent.pop_front();
+    }
+    drop(recent);
+
+    self.tx.send(event.clone()).ok();   // ok since there may be nobody listening
+    event
+  }
+
+  /* Events after the given ID, or all of them if None */
+  pub fn since(&self, id: Option<u64>) -> Vec<Event> {
+    self.recent.lock().unwrap().iter().filter(|e| id.map(|id| e.id > id).unwrap_or(true)).cloned().collect()
+  }
+
+  pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+    self.tx.subscribe()
+  }
+}
+
+pub fn events() -> &'static EventLog {
+  static EVENTS: OnceLock<EventLog> = OnceLock::new();
+  EVENTS.get_or_init(EventLog::new)
+}
+
+/* Bursts of the same kind of event closer together than this are reported as a single notification */
+pub const AGGREGATION_WINDOW_MS: i64 = 2000;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct Notification {
+  /* ID of the newest event included, to pass as `since` next time */
+  pub last_id: u64,
+  pub kind: String,
+  pub severity: EventSeverity,
+  pub count: usize,
+  pub serials: Vec<u32>,
+  pub first_timestamp_ms: i64,
+  pub last_timestamp_ms: i64,
+  pub message: String,
+}
+
+/* Coalesce events into notifications, so e.g. 20 devices reappearing after a reconnect is one toast rather than 20.
+   Errors are never merged, so they can't get lost in a crowd. A burst that straddles two polls is reported in two
+   parts. */
+pub fn aggregate(events: &[Event], window_ms: i64) -> Vec<Notification> {
+  let mut notifications: Vec<Notification> = vec![];
+  for event in events {
+    let group = match event.severity {
+      EventSeverity::Error => None,
+      _ => notifications.iter_mut().rev()
+        .find(|n| n.kind == event.kind && n.severity == event.severity && event.timestamp_ms - n.last_timestamp_ms <= window_ms)
+    };
+
+    match group {
+      Some(n) => {
+        n.count += 1;
+        n.last_id = event.id;
+        n.last_timestamp_ms = event.timestamp_ms;
+        if let Some(serial) = event.serial.filter(|s| !n.serials.contains(s)) {
+          n.serials.push(serial);
+        }
+      },
+      None => notifications.push(Notification {
+        last_id: event.id,
+        kind: event.kind.clone(),
+        severity: event.severity,
+        count: 1,
+        serials: event.serial.into_iter().collect(),
+        first_timestamp_ms: event.timestamp_ms,
+        last_timestamp_ms: event.timestamp_ms,
+        message: event.message.clone(),
+      })
+    }
+  }
+
+  for n in notifications.iter_mut().filter(|n| n.count > 1) {
+    n.message = match n.serials.len() {
+      0 | 1 => format!("{} (and {} more like it)", n.message, n.count - 1),
+      devices => format!("{} (and {} more like it, across {} devices)", n.message, n.count - 1, devices)
+    };
+  }
+  notifications.sort_by_key(|n| n.last_id);
+  notifications
+}
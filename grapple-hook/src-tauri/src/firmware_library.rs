@@ -0,0 +1,117 @@
+use std::{path::{Path, PathBuf}, sync::OnceLock};
+
+use sha2::{Digest, Sha256};
+
+use crate::persistence::{data_dir, Persisted};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct FlashRecord {
+  pub serial: u32,
+  pub timestamp_ms: i64,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct FirmwareImage {
+  pub sha256: String,
+  pub size: usize,
+  pub label: String,
+  pub source_url: Option<String>,
+  pub added_at: i64,
+  #[serde(default)]
+  pub flashed: Vec<FlashRecord>,
+}
+
+/* Every firmware image we've flashed or been given, stored by content hash so the same image is only kept once and
+   can always be flashed again exactly as it was */
+pub struct FirmwareLibrary {
+  index: Persisted<Vec<FirmwareImage>>,
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+  Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn sha256_file(path: &Path) -> anyhow::Result<String> {
+  let mut hasher = Sha256::new();
+  std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+  Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+impl FirmwareLibrary {
+  fn image_path(sha256: &str) -> PathBuf {
+    data_dir().join("firmware").join(format!("{}.bin", sha256))
+  }
+
+  /* Add an image to the library. If we already have it, the existing entry is returned as-is. */
+  pub fn stage(&self, data: &[u8], label: String, source_url: Option<String>) -> anyhow::Result<FirmwareImage> {
+    let sha256 = sha256_hex(data);
+    if let Some(existing) = self.get(&sha256) {
+      return Ok(existing);
+    }
+
+    let path = Self::image_path(&sha256);
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    std::fs::write(&path, data)?;
+
+    let image = FirmwareImage { sha256, size: data.len(), label, source_url, added_at: chrono::Utc::now().timestamp_millis(), flashed: vec![] };
+    self.index.update(|index| index.push(image.clone()));
+    Ok(image)
+  }
+
+  /* As stage, but hashed and copied from a file without reading it all in */
+  pub fn stage_file(&self, source: &Path, label: String, source_url: Option<String>) -> anyhow::Result<FirmwareImage> {
+    let sha256 = sha256_file(source)?;
+    if let Some(existing) = self.get(&sha256) {
+      return Ok(existing);
+    }
+
+    let path = Self::image_path(&sha256);
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    let size = std::fs::copy(source, &path)? as usize;
+
+    let image = FirmwareImage { sha256, size, label, source_url, added_at: chrono::Utc::now().timestamp_millis(), flashed: vec![] };
+    self.index.update(|index| index.push(image.clone()));
+    Ok(image)
+  }
+
+  pub fn get(&self, sha256: &str) -> Option<FirmwareImage> {
+    self.index.read(|index| index.iter().find(|i| i.sha256 == sha256).cloned())
+  }
+
+  pub fn list(&self) -> Vec<FirmwareImage> {
+    let mut images = self.index.get();
+    images.sort_by_key(|i| -i.added_at);
+    images
+  }
+
+  /* The image on disk, for flashing straight from the library */
+  pub fn file(&self, sha256: &str) -> anyhow::Result<PathBuf> {
+    self.get(sha256).ok_or(anyhow::anyhow!("No firmware image {} in the library", sha256))?;
+    let path = Self::image_path(sha256);
+    if sha256_file(&path)? != sha256 {
+      anyhow::bail!("Firmware image {} is corrupted on disk", sha256);
+    }
+    Ok(path)
+  }
+
+  pub fn delete(&self, sha256: &str) -> anyhow::Result<()> {
+    self.index.update(|index| index.retain(|i| i.sha256 != sha256));
+    match std::fs::remove_file(Self::image_path(sha256)) {
+      Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+      _ => Ok(())
+    }
+  }
+
+  pub fn record_flash(&self, sha256: &str, serial: u32) {
+    self.index.update(|index| {
+      if let Some(image) = index.iter_mut().find(|i| i.sha256 == sha256) {
+        image.flashed.push(FlashRecord { serial, timestamp_ms: chrono::Utc::now().timestamp_millis() });
+      }
+    });
+  }
+}
+
+pub fn firmware_library() -> &'static FirmwareLibrary {
+  static LIBRARY: OnceLock<FirmwareLibrary> = OnceLock::new();
+  LIBRARY.get_or_init(|| FirmwareLibrary { index: Persisted::load("firmware_library") })
+}
@@ -1,7 +1,21 @@
-extern crate alloc;
-
-pub mod codecs;
-pub mod devices;
-pub mod rpc;
-pub mod ssh;
-pub mod updates;
\ No newline at end of file
+extern crate alloc;
+
+pub mod anomaly;
+pub mod codecs;
+pub mod devices;
+pub mod errors;
+pub mod events;
+pub mod firmware_library;
+pub mod logs;
+pub mod operations;
+pub mod persistence;
+pub mod rpc;
+pub mod ssh;
+pub mod telemetry;
+pub mod telemetry_archive;
+pub mod telemetry_csv;
+pub mod telemetry_stream;
+pub mod updates;
+pub mod usage_stats;
+pub mod visibility;
+pub mod wpilog;
\ No newline at end of file
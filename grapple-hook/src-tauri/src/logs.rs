@@ -0,0 +1,143 @@
+use std::{fs::File, io::Write, path::{Path, PathBuf}};
+
+use crate::persistence::data_dir;
+
+/* Backend logs go to <data_dir>/logs as well as stderr. The current log is rotated out once it reaches
+   MAX_LOG_BYTES (and at every startup, so each session starts its own file), then compressed. Old logs are deleted
+   once there are too many of them or they take up too much space, so a laptop left in the pits for weeks doesn't fill
+   its disk. */
+const MAX_LOG_BYTES: u64 = 10 * 1024 * 1024;
+const MAX_ARCHIVED_LOGS: usize = 20;
+const MAX_ARCHIVED_BYTES: u64 = 50 * 1024 * 1024;
+const ZSTD_LEVEL: i32 = 3;
+
+const CURRENT_LOG: &str = "grapple-hook.log";
+const ARCHIVE_EXTENSION: &str = "zst";
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct LogFile {
+  pub path: String,
+  pub size_bytes: u64,
+  pub compressed: bool,
+  /* The file currently being written to */
+  pub current: bool,
+}
+
+pub fn logs_dir() -> PathBuf {
+  data_dir().join("logs")
+}
+
+fn compress(path: &Path) -> anyhow::Result<()> {
+  let mut target = path.as_os_str().to_owned();
+  target.push(format!(".{}", ARCHIVE_EXTENSION));
+  let mut encoder = zstd::Encoder::new(File::create(&target)?, ZSTD_LEVEL)?;
+  std::io::copy(&mut File::open(path)?, &mut encoder)?;
+  encoder.finish()?;
+  std::fs::remove_file(path)?;
+  Ok(())
+}
+
+/* Delete the oldest archived logs until we're within both caps */
+fn enforce_caps() {
+  let mut archived = log_files().into_iter().filter(|f| !f.current).collect::<Vec<_>>();
+  // Names carry the rotation time, so they sort oldest first
+  archived.sort_by(|a, b| a.path.cmp(&b.path));
+  let mut total: u64 = archived.iter().map(|f| f.size_bytes).sum();
+  let mut count = archived.len();
+  for file in archived {
+    if count <= MAX_ARCHIVED_LOGS && total <= MAX_ARCHIVED_BYTES {
+      break;
+    }
+    if std::fs::remove_file(&file.path).is_ok() {
+      count -= 1;
+      total -= file.size_bytes;
+    }
+  }
+}
+
+/* The log writer handed to env_logger */
+pub struct RotatingLog {
+  file: Option<File>,
+  written: u64,
+}
+
+impl RotatingLog {
+  pub fn open() -> Self {
+    let mut log = Self { file: None, written: 0 };
+    if let Err(e) = std::fs::create_dir_all(logs_dir()) {
+      eprintln!("Couldn't create log directory, only logging to stderr: {}", e);
+      return log;
+    }
+    log.rotate();
+    log
+  }
+
+  /* Move the current log aside (if it has anything in it) and start a new one */
+  fn rotate(&mut self) {
+    self.file = None;
+    let current = logs_dir().join(CURRENT_LOG);
+    if std::fs::metadata(&current).map(|m| m.len() > 0).unwrap_or(false) {
+      let rotated = logs_dir().join(format!("grapple-hook-{}.log", chrono::Utc::now().format("%Y%m%d-%H%M%S%.3f")));
+      if std::fs::rename(&current, &rotated).is_ok() {
+        // Compressing takes a moment, so don't hold up whoever is logging
+        std::thread::spawn(move || {
+          if let Err(e) = compress(&rotated) {
+            eprintln!("Couldn't compress {}: {}", rotated.display(), e);
+          }
+          enforce_caps();
+        });
+      }
+    }
+
+    match File::create(&current) {
+      Ok(file) => { self.file = Some(file); self.written = 0; },
+      Err(e) => eprintln!("Couldn't open {}, only logging to stderr: {}", current.display(), e)
+    }
+  }
+}
+
+impl Write for RotatingLog {
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    std::io::stderr().write_all(buf)?;
+    if self.written + buf.len() as u64 > MAX_LOG_BYTES {
+      self.rotate();
+    }
+    if let Some(file) = &mut self.file {
+      // A full disk shouldn't take logging to stderr down with it
+      if file.write_all(buf).is_ok() {
+        self.written += buf.len() as u64;
+      }
+    }
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> std::io::Result<()> {
+    if let Some(file) = &mut self.file {
+      file.flush().ok();
+    }
+    std::io::stderr().flush()
+  }
+}
+
+/* The current log first, then archived ones newest first */
+pub fn log_files() -> Vec<LogFile> {
+  let mut files = std::fs::read_dir(logs_dir()).into_iter()
+    .flatten()
+    .flatten()
+    .filter_map(|e| {
+      let path = e.path();
+      let name = path.file_name()?.to_string_lossy().into_owned();
+      if name != CURRENT_LOG && !name.starts_with("grapple-hook-") {
+        return None;
+      }
+      Some(LogFile {
+        size_bytes: e.metadata().ok()?.len(),
+        compressed: name.ends_with(&format!(".{}", ARCHIVE_EXTENSION)),
+        current: name == CURRENT_LOG,
+        path: path.to_string_lossy().into_owned(),
+      })
+    })
+    .collect::<Vec<_>>();
+  files.sort_by(|a, b| b.current.cmp(&a.current).then(b.path.cmp(&a.path)));
+  files
+}
@@ -5,7 +5,7 @@ use std::{sync::{Arc, Mutex}, time::Duration};
 
 // use devices::device_manager::DeviceManager;
 use env_logger::Builder;
-use grapple_hook::{devices::provider_manager::ProviderManager, rpc::RpcBase, updates::{most_recent_update_available, LightReleaseResponse}};
+use grapple_hook::{devices::{automation::{self, AUTOMATION_NOTIFICATION}, changes::{device_changes, DEVICE_CHANGE_EVENT, HOTPLUG_EVENT}, firmware_catalog::CATALOG_REFRESH_INTERVAL, provider_manager::ProviderManager, reminders, roborio}, events::events, logs::RotatingLog, persistence, rpc::RpcBase, telemetry_stream::{telemetry_streams, PUSH_INTERVAL, TELEMETRY_PUSH_EVENT}, updates::{most_recent_update_available, LightReleaseResponse}, usage_stats, visibility};
 use tauri::Manager;
 
 static NEW_UPDATE: Mutex<Option<LightReleaseResponse>> = Mutex::new(None);
@@ -23,7 +23,8 @@ async fn is_update_available() -> Result<Option<LightReleaseResponse>, String> {
 
 #[tokio::main]
 async fn main() {
-  Builder::new().filter_level(log::LevelFilter::Info).init();
+  Builder::new().filter_level(log::LevelFilter::Info).target(env_logger::Target::Pipe(Box::new(RotatingLog::open()))).init();
+  persistence::check_integrity();
 
   let provider_manager = Arc::new(ProviderManager::new().await);
   let most_recent = tokio::time::timeout(Duration::from_secs(2), most_recent_update_available("https://api.github.com/repos/GrappleRobotics/GrappleHook/releases", |_| true)).await;
@@ -35,6 +36,9 @@ async fn main() {
   let most_recent = most_recent.ok().map(|x| x.ok()).flatten().flatten();
 
   tauri::async_runtime::set(tokio::runtime::Handle::current());
+
+  let catalog_manager = provider_manager.clone();
+  let automation_manager = provider_manager.clone();
   
   tauri::Builder::default()
     .manage(provider_manager.clone())
@@ -50,9 +54,116 @@ async fn main() {
         }
       }
 
+      // Maintenance reminders can come due while the app is just sitting open, so check for them in the background
+      let identifier = app.config().tauri.bundle.identifier.clone();
+      tokio::task::spawn(async move {
+        loop {
+          for due in reminders::take_notifications(chrono::Utc::now().timestamp_millis()) {
+            let device = due.nickname.unwrap_or(format!("{:x}", due.serial));
+            if let Err(e) = tauri::api::notification::Notification::new(&identifier).title(format!("Maintenance due: {}", device)).body(due.reminder.label).show() {
+              log::warn!("Couldn't show reminder notification: {}", e);
+            }
+          }
+          tokio::time::sleep(Duration::from_secs(60)).await;
+        }
+      });
+
+      // Same for new firmware. Give devices a moment to enumerate first so we know which models to check.
+      tokio::task::spawn(async move {
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        loop {
+          if let Err(e) = catalog_manager.refresh_catalog().await {
+            log::info!("Skipped firmware catalog refresh: {}", e);
+          }
+          tokio::time::sleep(CATALOG_REFRESH_INTERVAL).await;
+        }
+      });
+
+      // Automation rules act on devices as they turn up, whether or not anyone's looking
+      tokio::task::spawn(automation::run(automation_manager));
+
+      // Only sends anything if the user has opted in
+      tokio::task::spawn(usage_stats::run());
+
+      // Keep a list of roboRIOs on the network (or USB), so connecting is just picking one
+      tokio::task::spawn(roborio::discovery::run());
+
+      // Notification actions pop up on the desktop as well as going in the event log
+      let identifier = app.config().tauri.bundle.identifier.clone();
+      let mut automation_events = events().subscribe();
+      tokio::task::spawn(async move {
+        loop {
+          match automation_events.recv().await {
+            Ok(event) if event.kind == AUTOMATION_NOTIFICATION => {
+              if let Err(e) = tauri::api::notification::Notification::new(&identifier).title("GrappleHook").body(event.message).show() {
+                log::warn!("Couldn't show automation notification: {}", e);
+              }
+            },
+            Ok(_) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => (),
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => break
+          }
+        }
+      });
+
+      // Push device list changes to the frontend as they happen
+      let handle = app.handle();
+      let mut changes = device_changes().subscribe();
+      tokio::task::spawn(async move {
+        loop {
+          match changes.recv().await {
+            Ok(change) => if let Err(e) = handle.emit_all(DEVICE_CHANGE_EVENT, change) {
+              log::warn!("Couldn't push device change: {}", e);
+            },
+            // The frontend refreshes the whole list on its own every so often, so a missed change isn't lost for long
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => (),
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => break
+          }
+        }
+      });
+
+      let handle = app.handle();
+      let mut hotplugs = device_changes().subscribe_hotplug();
+      tokio::task::spawn(async move {
+        loop {
+          match hotplugs.recv().await {
+            Ok(hotplug) => if let Err(e) = handle.emit_all(HOTPLUG_EVENT, hotplug) {
+              log::warn!("Couldn't push hot-plug event: {}", e);
+            },
+            // These are in the event log as well
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => (),
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => break
+          }
+        }
+      });
+
+      // And telemetry for anything subscribed to it, batched up so we're not sending an event per sample
+      let handle = app.handle();
+      tokio::task::spawn(async move {
+        loop {
+          for push in telemetry_streams().take_pending() {
+            if let Err(e) = handle.emit_all(TELEMETRY_PUSH_EVENT, push) {
+              log::warn!("Couldn't push telemetry: {}", e);
+            }
+          }
+          tokio::time::sleep(PUSH_INTERVAL).await;
+        }
+      });
+
       Ok(())
     })
+    .on_window_event(|event| match event.event() {
+      // Hiding is reported by the frontend (it knows when it's minimised), but focus always means we're being looked at
+      tauri::WindowEvent::Focused(true) => visibility::set_visible(true),
+      _ => ()
+    })
     .invoke_handler(tauri::generate_handler![provider_manager_rpc, is_update_available])
-    .run(tauri::generate_context!())
-    .expect("error while running tauri application");
+    .build(tauri::generate_context!())
+    .expect("error while building tauri application")
+    .run(move |_app, event| {
+      if let tauri::RunEvent::Exit = event {
+        // Record how each device's session went before we go
+        let provider_manager = provider_manager.clone();
+        tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(provider_manager.end_sessions()));
+      }
+    });
 }
@@ -0,0 +1,139 @@
+use std::{path::PathBuf, sync::OnceLock};
+
+use log::warn;
+
+use crate::{devices::chunked::FlashStats, persistence::{data_dir, Persisted}};
+
+/* How many finished operations we keep around in the journal */
+const JOURNAL_HISTORY: usize = 50;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub enum OperationKind {
+  FirmwareUpdate,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub enum OperationState {
+  InProgress,
+  Completed,
+  Failed(String),
+  /* Was still in progress when GrappleHook last exited */
+  Interrupted,
+  /* Interrupted, then restarted as a new operation */
+  Resumed,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct OperationRecord {
+  pub id: String,
+  pub kind: OperationKind,
+  pub serial: u32,
+  pub started_at: i64,
+  pub state: OperationState,
+  /* Firmware updates only */
+  #[serde(default)]
+  pub flash_stats: Option<FlashStats>,
+}
+
+/* Journal of long-running operations (firmware updates), persisted as they start and finish so that if the app
+   crashes mid-update we can offer to pick up where we left off. */
+pub struct OperationJournal {
+  records: Persisted<Vec<OperationRecord>>,
+}
+
+impl OperationJournal {
+  fn load() -> Self {
+    let records = Persisted::<Vec<OperationRecord>>::load("operations");
+    // Anything still in progress belongs to a previous run that didn't finish
+    records.update(|records| {
+      for r in records.iter_mut().filter(|r| r.state == OperationState::InProgress) {
+        r.state = OperationState::Interrupted;
+      }
+    });
+    Self { records }
+  }
+
+  fn payload_path(id: &str) -> PathBuf {
+    data_dir().join("operations").join(format!("{}.bin", id))
+  }
+
+  /* Start an operation. The payload (e.g. the firmware image) is kept on disk until it finishes so it can be resumed. */
+  pub fn begin(&self, kind: OperationKind, serial: u32, payload: Option<&mut dyn std::io::Read>) -> String {
+    let id = uuid::Uuid::new_v4().to_string();
+
+    if let Some(payload) = payload {
+      let path = Self::payload_path(&id);
+      // Copied across rather than read in, since it may be a large image streamed from disk
+      let staged = path.parent().map(std::fs::create_dir_all).unwrap_or(Ok(()))
+        .and_then(|_| std::fs::File::create(&path))
+        .and_then(|mut file| std::io::copy(payload, &mut file));
+      if let Err(e) = staged {
+        warn!("Could not stage operation payload: {}", e);
+      }
+    }
+
+    self.records.update(|records| {
+      records.push(OperationRecord { id: id.clone(), kind, serial, started_at: chrono::Utc::now().timestamp_millis(), state: OperationState::InProgress, flash_stats: None });
+      let excess = records.len().saturating_sub(JOURNAL_HISTORY);
+      records.drain(0..excess);
+    });
+    id
+  }
+
+  pub fn finish(&self, id: &str, result: Result<(), String>) {
+    self.records.update(|records| {
+      if let Some(r) = records.iter_mut().find(|r| r.id == id) {
+        r.state = match result {
+          Ok(()) => OperationState::Completed,
+          Err(e) => OperationState::Failed(e)
+        };
+      }
+    });
+    std::fs::remove_file(Self::payload_path(id)).ok();
+  }
+
+  pub fn record_flash_stats(&self, id: &str, stats: Option<FlashStats>) {
+    self.records.update(|records| {
+      if let Some(r) = records.iter_mut().find(|r| r.id == id) {
+        r.flash_stats = stats;
+      }
+    });
+  }
+
+  pub fn get(&self, id: &str) -> Option<OperationRecord> {
+    self.records.read(|records| records.iter().find(|r| r.id == id).cloned())
+  }
+
+  /* Where the payload is kept, if it still is */
+  pub fn payload_file(&self, id: &str) -> Option<PathBuf> {
+    Some(Self::payload_path(id)).filter(|p| p.exists())
+  }
+
+  /* Operations started at or after since_ms, oldest first */
+  pub fn since(&self, since_ms: i64) -> Vec<OperationRecord> {
+    self.records.read(|records| records.iter().filter(|r| r.started_at >= since_ms).cloned().collect())
+  }
+
+  pub fn recoverable(&self) -> Vec<OperationRecord> {
+    self.records.read(|records| records.iter().filter(|r| r.state == OperationState::Interrupted).cloned().collect())
+  }
+
+  pub fn mark_resumed(&self, id: &str) {
+    self.records.update(|records| {
+      if let Some(r) = records.iter_mut().find(|r| r.id == id) {
+        r.state = OperationState::Resumed;
+      }
+    });
+    std::fs::remove_file(Self::payload_path(id)).ok();
+  }
+
+  /* Forget about an interrupted operation without resuming it */
+  pub fn discard(&self, id: &str) {
+    self.finish(id, Err("Discarded after interruption".to_owned()));
+  }
+}
+
+pub fn journal() -> &'static OperationJournal {
+  static JOURNAL: OnceLock<OperationJournal> = OnceLock::new();
+  JOURNAL.get_or_init(OperationJournal::load)
+}
@@ -0,0 +1,194 @@
+use std::{path::{Path, PathBuf}, sync::Mutex};
+
+use log::warn;
+use serde::{de::DeserializeOwned, Serialize};
+
+/* Where GrappleHook keeps its host-side state. GRAPPLEHOOK_DATA_DIR overrides it, which is handy for testing. */
+pub fn data_dir() -> PathBuf {
+  if let Ok(dir) = std::env::var("GRAPPLEHOOK_DATA_DIR") {
+    return PathBuf::from(dir);
+  }
+
+  let base = if cfg!(windows) {
+    std::env::var("APPDATA").map(PathBuf::from).ok()
+  } else if cfg!(target_os = "macos") {
+    std::env::var("HOME").map(|h| PathBuf::from(h).join("Library").join("Application Support")).ok()
+  } else {
+    std::env::var("XDG_DATA_HOME").map(PathBuf::from).ok()
+      .or_else(|| std::env::var("HOME").map(|h| PathBuf::from(h).join(".local").join("share")).ok())
+  };
+
+  base.unwrap_or_else(std::env::temp_dir).join("GrappleHook")
+}
+
+pub fn path_for(name: &str) -> PathBuf {
+  data_dir().join(format!("{}.json", name))
+}
+
+/* Stores are wrapped with a schema version and a checksum of their contents, so a file half-written by a crashed
+   laptop is noticed at startup rather than silently loaded. The checksum covers the compact JSON of the data, which
+   is stable since serde_json::Value keeps object keys sorted. */
+pub const STORE_SCHEMA_VERSION: u32 = 1;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Envelope {
+  schema_version: u32,
+  checksum: String,
+  data: serde_json::Value,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub enum RecoveryAction {
+  RestoredFromBackup,
+  ResetToDefaults,
+  Quarantined,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct RecoveryRecord {
+  pub store: String,
+  pub problem: String,
+  pub action: RecoveryAction,
+  /* Where the damaged file was moved to, so nothing is ever thrown away */
+  pub quarantined_to: Option<String>,
+  pub timestamp_ms: i64,
+}
+
+static RECOVERIES: Mutex<Vec<RecoveryRecord>> = Mutex::new(vec![]);
+
+pub fn record_recovery(store: &str, problem: String, action: RecoveryAction, quarantined_to: Option<PathBuf>) {
+  warn!("Recovered store {}: {} ({:?})", store, problem, action);
+  RECOVERIES.lock().unwrap().push(RecoveryRecord {
+    store: store.to_owned(),
+    problem,
+    action,
+    quarantined_to: quarantined_to.map(|p| p.display().to_string()),
+    timestamp_ms: chrono::Utc::now().timestamp_millis(),
+  });
+}
+
+/* Everything repaired since startup */
+pub fn recoveries() -> Vec<RecoveryRecord> {
+  RECOVERIES.lock().unwrap().clone()
+}
+
+/* Move a damaged file out of the way, keeping it for inspection */
+pub fn quarantine(path: &Path) -> Option<PathBuf> {
+  let mut target = path.as_os_str().to_owned();
+  target.push(format!(".corrupt-{}", chrono::Utc::now().format("%Y%m%d%H%M%S")));
+  let target = PathBuf::from(target);
+  match std::fs::rename(path, &target) {
+    Ok(()) => Some(target),
+    Err(e) => {
+      warn!("Could not quarantine {}: {}", path.display(), e);
+      None
+    }
+  }
+}
+
+fn checksum(data: &serde_json::Value) -> String {
+  crate::firmware_library::sha256_hex(data.to_string().as_bytes())
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+  path.with_extension("json.bak")
+}
+
+fn decode<T: DeserializeOwned>(content: &str) -> Result<T, String> {
+  let envelope = match serde_json::from_str::<Envelope>(content) {
+    Ok(envelope) => envelope,
+    // Files written before stores were checksummed are just the bare data
+    Err(_) => return serde_json::from_str(content).map_err(|e| format!("unreadable ({})", e)),
+  };
+
+  if envelope.schema_version > STORE_SCHEMA_VERSION {
+    return Err(format!("written by a newer GrappleHook (schema version {})", envelope.schema_version));
+  }
+  if checksum(&envelope.data) != envelope.checksum {
+    return Err("checksum mismatch".to_owned());
+  }
+  serde_json::from_value(envelope.data).map_err(|e| format!("doesn't match the expected structure ({})", e))
+}
+
+pub fn load<T: DeserializeOwned + Default>(name: &str) -> T {
+  let path = path_for(name);
+  let content = match std::fs::read_to_string(&path) {
+    Ok(content) => content,
+    Err(_) => return T::default()
+  };
+
+  let problem = match decode(&content) {
+    Ok(value) => return value,
+    Err(problem) => problem
+  };
+
+  let quarantined = quarantine(&path);
+  match std::fs::read_to_string(backup_path(&path)).map_err(|e| e.to_string()).and_then(|c| decode(&c)) {
+    Ok(value) => {
+      record_recovery(name, problem, RecoveryAction::RestoredFromBackup, quarantined);
+      value
+    },
+    Err(_) => {
+      record_recovery(name, problem, RecoveryAction::ResetToDefaults, quarantined);
+      T::default()
+    }
+  }
+}
+
+pub fn save<T: Serialize>(name: &str, value: &T) -> anyhow::Result<()> {
+  let path = path_for(name);
+  std::fs::create_dir_all(data_dir())?;
+
+  let data = serde_json::to_value(value)?;
+  let envelope = Envelope { schema_version: STORE_SCHEMA_VERSION, checksum: checksum(&data), data };
+
+  // Keep the last good copy around to repair from
+  if path.exists() {
+    std::fs::copy(&path, backup_path(&path)).ok();
+  }
+
+  // Write-then-rename so a crash mid-write never leaves a truncated file behind
+  let tmp = path.with_extension("json.tmp");
+  std::fs::write(&tmp, serde_json::to_string_pretty(&envelope)?)?;
+  std::fs::rename(&tmp, &path)?;
+  Ok(())
+}
+
+/* Load every store up front, so damage is found (and repaired) at startup rather than part way through a session */
+pub fn check_integrity() {
+  crate::devices::metadata::metadata();
+  crate::devices::checklist::checklists();
+  crate::devices::automation::automation();
+  crate::firmware_library::firmware_library();
+  crate::operations::journal();
+  crate::telemetry_archive::verify_recordings();
+}
+
+/* A value mirrored to <data_dir>/<name>.json, saved after every update */
+pub struct Persisted<T> {
+  name: &'static str,
+  value: Mutex<T>,
+}
+
+impl<T: Serialize + DeserializeOwned + Default + Clone> Persisted<T> {
+  pub fn load(name: &'static str) -> Self {
+    Self { name, value: Mutex::new(load(name)) }
+  }
+
+  pub fn get(&self) -> T {
+    self.value.lock().unwrap().clone()
+  }
+
+  pub fn read<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+    f(&*self.value.lock().unwrap())
+  }
+
+  pub fn update<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+    let mut value = self.value.lock().unwrap();
+    let r = f(&mut *value);
+    if let Err(e) = save(self.name, &*value) {
+      warn!("Could not save {}: {}", self.name, e);
+    }
+    r
+  }
+}
@@ -1,4 +1,19 @@
-#[async_trait::async_trait]
-pub trait RpcBase {
-  async fn rpc_call(&self, data: serde_json::Value) -> anyhow::Result<serde_json::Value>;
-}
+#[async_trait::async_trait]
+pub trait RpcBase {
+  async fn rpc_call(&self, data: serde_json::Value) -> anyhow::Result<serde_json::Value>;
+}
+
+/* Generated by #[rpc] for every RPC target, so tooling (e.g. grapple-hook-cli) never drifts from the backend */
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct RpcParamInfo {
+  pub name: String,
+  pub ty: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct RpcMethodInfo {
+  pub name: String,
+  pub docs: String,
+  pub params: Vec<RpcParamInfo>,
+  pub returns: String,
+}
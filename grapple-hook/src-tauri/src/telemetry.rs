@@ -0,0 +1,163 @@
+use std::{collections::{HashMap, VecDeque}, sync::{Mutex, OnceLock}};
+
+use crate::{anomaly::anomalies, telemetry_stream::telemetry_streams};
+
+/* How much history we keep in memory for each channel */
+pub const TELEMETRY_RETENTION_MS: i64 = 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct TelemetrySample {
+  /* Unix time (ms) the sample was measured, compensated for transport latency */
+  pub timestamp_ms: i64,
+  pub value: f64,
+}
+
+/* Summary of all the samples falling in one time bucket, for drawing long time ranges without shipping every point */
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct TelemetryBucket {
+  /* Start of the bucket */
+  pub timestamp_ms: i64,
+  pub min: f64,
+  pub max: f64,
+  pub avg: f64,
+  pub count: usize,
+}
+
+pub fn downsample(samples: &[TelemetrySample], max_points: usize) -> Vec<TelemetryBucket> {
+  let (first, last) = match (samples.first(), samples.last()) {
+    (Some(first), Some(last)) => (first.timestamp_ms, last.timestamp_ms),
+    _ => return vec![]
+  };
+
+  let max_points = max_points.max(1) as i64;
+  let width = ((last - first) / max_points + 1).max(1);
+
+  let mut buckets: Vec<TelemetryBucket> = vec![];
+  for sample in samples {
+    let start = first + (sample.timestamp_ms - first) / width * width;
+    match buckets.last_mut() {
+      Some(bucket) if bucket.timestamp_ms == start => {
+        bucket.min = bucket.min.min(sample.value);
+        bucket.max = bucket.max.max(sample.value);
+        bucket.avg += (sample.value - bucket.avg) / (bucket.count + 1) as f64;
+        bucket.count += 1;
+      },
+      _ => buckets.push(TelemetryBucket { timestamp_ms: start, min: sample.value, max: sample.value, avg: sample.value, count: 1 })
+    }
+  }
+  buckets
+}
+
+/* A configuration change made to a device, kept alongside its telemetry so the two can be lined up */
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ConfigChangeMarker {
+  pub timestamp_ms: i64,
+  pub change: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub enum TimelineEntry {
+  Sample { channel: String, timestamp_ms: i64, value: f64 },
+  ConfigChange { timestamp_ms: i64, change: String },
+}
+
+impl TimelineEntry {
+  pub fn timestamp_ms(&self) -> i64 {
+    match self {
+      TimelineEntry::Sample { timestamp_ms, .. } => *timestamp_ms,
+      TimelineEntry::ConfigChange { timestamp_ms, .. } => *timestamp_ms,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+struct ChannelKey {
+  serial: u32,
+  channel: String,
+}
+
+pub struct TelemetryStore {
+  channels: Mutex<HashMap<ChannelKey, VecDeque<TelemetrySample>>>,
+  config_changes: Mutex<HashMap<u32, VecDeque<ConfigChangeMarker>>>,
+}
+
+impl TelemetryStore {
+  fn new() -> Self {
+    Self { channels: Mutex::new(HashMap::new()), config_changes: Mutex::new(HashMap::new()) }
+  }
+
+  pub fn record(&self, serial: u32, channel: &str, timestamp_ms: i64, value: f64) {
+    let mut channels = self.channels.lock().unwrap();
+    let samples = channels.entry(ChannelKey { serial, channel: channel.to_owned() }).or_insert_with(VecDeque::new);
+    let sample = TelemetrySample { timestamp_ms, value };
+    samples.push_back(sample);
+
+    while samples.front().map(|s| s.timestamp_ms < timestamp_ms - TELEMETRY_RETENTION_MS).unwrap_or(false) {
+      samples.pop_front();
+    }
+    drop(channels);
+
+    anomalies().observe(serial, channel, timestamp_ms, value);
+    telemetry_streams().offer(serial, channel, &sample);
+  }
+
+  pub fn channels(&self, serial: u32) -> Vec<String> {
+    let mut channels = self.channels.lock().unwrap().keys().filter(|k| k.serial == serial).map(|k| k.channel.clone()).collect::<Vec<_>>();
+    channels.sort();
+    channels
+  }
+
+  pub fn latest(&self, serial: u32, channel: &str) -> Option<TelemetrySample> {
+    self.channels.lock().unwrap().get(&ChannelKey { serial, channel: channel.to_owned() }).and_then(|s| s.back().cloned())
+  }
+
+  pub fn history(&self, serial: u32, channel: &str, start_ms: Option<i64>, end_ms: Option<i64>) -> Vec<TelemetrySample> {
+    let channels = self.channels.lock().unwrap();
+    match channels.get(&ChannelKey { serial, channel: channel.to_owned() }) {
+      Some(samples) => samples.iter()
+        .filter(|s| start_ms.map(|start| s.timestamp_ms >= start).unwrap_or(true) && end_ms.map(|end| s.timestamp_ms <= end).unwrap_or(true))
+        .cloned()
+        .collect(),
+      None => vec![]
+    }
+  }
+}
+
+impl TelemetryStore {
+  pub fn record_config_change(&self, serial: u32, timestamp_ms: i64, change: String) {
+    let mut changes = self.config_changes.lock().unwrap();
+    let markers = changes.entry(serial).or_insert_with(VecDeque::new);
+    markers.push_back(ConfigChangeMarker { timestamp_ms, change });
+
+    while markers.front().map(|m| m.timestamp_ms < timestamp_ms - TELEMETRY_RETENTION_MS).unwrap_or(false) {
+      markers.pop_front();
+    }
+  }
+
+  pub fn config_changes(&self, serial: u32, start_ms: Option<i64>, end_ms: Option<i64>) -> Vec<ConfigChangeMarker> {
+    match self.config_changes.lock().unwrap().get(&serial) {
+      Some(markers) => markers.iter()
+        .filter(|m| start_ms.map(|start| m.timestamp_ms >= start).unwrap_or(true) && end_ms.map(|end| m.timestamp_ms <= end).unwrap_or(true))
+        .cloned()
+        .collect(),
+      None => vec![]
+    }
+  }
+
+  /* A device's telemetry (all channels, or just those given) and configuration changes merged into one list in time
+     order, e.g. to see whether a timing budget change is what made a reading settle down */
+  pub fn timeline(&self, serial: u32, channels: Option<Vec<String>>, start_ms: Option<i64>, end_ms: Option<i64>) -> Vec<TimelineEntry> {
+    let mut entries: Vec<TimelineEntry> = channels.unwrap_or_else(|| self.channels(serial)).into_iter()
+      .flat_map(|channel| self.history(serial, &channel, start_ms, end_ms).into_iter().map(move |s| TimelineEntry::Sample { channel: channel.clone(), timestamp_ms: s.timestamp_ms, value: s.value }))
+      .chain(self.config_changes(serial, start_ms, end_ms).into_iter().map(|m| TimelineEntry::ConfigChange { timestamp_ms: m.timestamp_ms, change: m.change }))
+      .collect();
+    // Stable, so a change made in the same millisecond as a sample stays after it
+    entries.sort_by_key(|e| e.timestamp_ms());
+    entries
+  }
+}
+
+pub fn telemetry() -> &'static TelemetryStore {
+  static STORE: OnceLock<TelemetryStore> = OnceLock::new();
+  STORE.get_or_init(TelemetryStore::new)
+}
@@ -0,0 +1,178 @@
+use std::{io::{Read, Write}, path::PathBuf};
+
+use crate::{persistence::{data_dir, quarantine, record_recovery, RecoveryAction}, telemetry::{telemetry, TelemetrySample}};
+
+/* Compact on-disk telemetry recordings. Timestamps are stored as zigzag varint deltas (samples arrive at a steady
+   rate, so these are tiny), and values as the XOR against the previous value's bits (slow-moving signals share most
+   of their bits). The whole body is then zstd compressed. Multi-hour 50Hz recordings of several devices come out at a
+   small fraction of their raw size. */
+const MAGIC: &[u8; 4] = b"GHTA";
+const FORMAT_VERSION: u8 = 1;
+const ZSTD_LEVEL: i32 = 9;
+
+pub const RECORDING_EXTENSION: &str = "ghta";
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ArchivedChannel {
+  pub serial: u32,
+  pub channel: String,
+  pub samples: Vec<TelemetrySample>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct RecordingInfo {
+  pub name: String,
+  pub size_bytes: u64,
+}
+
+fn write_varint(out: &mut Vec<u8>, mut v: u64) {
+  while v >= 0x80 {
+    out.push((v as u8) | 0x80);
+    v >>= 7;
+  }
+  out.push(v as u8);
+}
+
+fn read_varint(buf: &[u8], pos: &mut usize) -> anyhow::Result<u64> {
+  let mut v = 0u64;
+  for shift in (0..64).step_by(7) {
+    let b = *buf.get(*pos).ok_or(anyhow::anyhow!("Telemetry recording is truncated"))?;
+    *pos += 1;
+    v |= ((b & 0x7F) as u64) << shift;
+    if b & 0x80 == 0 {
+      return Ok(v);
+    }
+  }
+  anyhow::bail!("Telemetry recording is corrupt")
+}
+
+fn zigzag(v: i64) -> u64 {
+  ((v << 1) ^ (v >> 63)) as u64
+}
+
+fn unzigzag(v: u64) -> i64 {
+  ((v >> 1) as i64) ^ -((v & 1) as i64)
+}
+
+pub fn encode(channels: &[ArchivedChannel]) -> anyhow::Result<Vec<u8>> {
+  let mut body = vec![];
+  write_varint(&mut body, channels.len() as u64);
+  for channel in channels {
+    body.extend_from_slice(&channel.serial.to_le_bytes());
+    write_varint(&mut body, channel.channel.len() as u64);
+    body.extend_from_slice(channel.channel.as_bytes());
+    write_varint(&mut body, channel.samples.len() as u64);
+
+    let (mut last_ts, mut last_bits) = (0i64, 0u64);
+    for sample in &channel.samples {
+      write_varint(&mut body, zigzag(sample.timestamp_ms - last_ts));
+      write_varint(&mut body, sample.value.to_bits() ^ last_bits);
+      last_ts = sample.timestamp_ms;
+      last_bits = sample.value.to_bits();
+    }
+  }
+
+  let mut out = MAGIC.to_vec();
+  out.push(FORMAT_VERSION);
+  let mut encoder = zstd::Encoder::new(out, ZSTD_LEVEL)?;
+  encoder.include_checksum(true)?;
+  encoder.write_all(&body)?;
+  Ok(encoder.finish()?)
+}
+
+pub fn decode(buf: &[u8]) -> anyhow::Result<Vec<ArchivedChannel>> {
+  if buf.len() < 5 || &buf[0..4] != MAGIC {
+    anyhow::bail!("Not a GrappleHook telemetry recording");
+  }
+  if buf[4] != FORMAT_VERSION {
+    anyhow::bail!("Unsupported telemetry recording version {}", buf[4]);
+  }
+
+  let mut body = vec![];
+  zstd::Decoder::new(&buf[5..])?.read_to_end(&mut body)?;
+
+  let mut pos = 0;
+  let n_channels = read_varint(&body, &mut pos)?;
+  let mut channels = vec![];
+  for _ in 0..n_channels {
+    let serial_bytes = body.get(pos..pos + 4).ok_or(anyhow::anyhow!("Telemetry recording is truncated"))?;
+    let serial = u32::from_le_bytes(serial_bytes.try_into()?);
+    pos += 4;
+
+    let name_len = read_varint(&body, &mut pos)? as usize;
+    let name = body.get(pos..pos + name_len).ok_or(anyhow::anyhow!("Telemetry recording is truncated"))?;
+    let channel = String::from_utf8(name.to_vec())?;
+    pos += name_len;
+
+    let n_samples = read_varint(&body, &mut pos)?;
+    let (mut ts, mut bits) = (0i64, 0u64);
+    let mut samples = vec![];
+    for _ in 0..n_samples {
+      ts += unzigzag(read_varint(&body, &mut pos)?);
+      bits ^= read_varint(&body, &mut pos)?;
+      samples.push(TelemetrySample { timestamp_ms: ts, value: f64::from_bits(bits) });
+    }
+    channels.push(ArchivedChannel { serial, channel, samples });
+  }
+  Ok(channels)
+}
+
+fn recordings_dir() -> PathBuf {
+  data_dir().join("telemetry")
+}
+
+fn recording_path(name: &str) -> anyhow::Result<PathBuf> {
+  if name.is_empty() || name.contains(|c: char| c == '/' || c == '\\' || c == '.') {
+    anyhow::bail!("Invalid recording name: {}", name);
+  }
+  Ok(recordings_dir().join(format!("{}.{}", name, RECORDING_EXTENSION)))
+}
+
+/* Snapshot the in-memory telemetry for the given devices to a recording. Returns the number of samples saved. */
+pub fn save_recording(name: &str, serials: &[u32]) -> anyhow::Result<usize> {
+  let channels = serials.iter()
+    .flat_map(|&serial| telemetry().channels(serial).into_iter().map(move |c| (serial, c)))
+    .map(|(serial, channel)| ArchivedChannel { serial, samples: telemetry().history(serial, &channel, None, None), channel })
+    .filter(|c| !c.samples.is_empty())
+    .collect::<Vec<_>>();
+
+  let path = recording_path(name)?;
+  std::fs::create_dir_all(recordings_dir())?;
+  let tmp = path.with_extension("tmp");
+  std::fs::write(&tmp, encode(&channels)?)?;
+  std::fs::rename(&tmp, &path)?;
+
+  Ok(channels.iter().map(|c| c.samples.len()).sum())
+}
+
+pub fn load_recording(name: &str) -> anyhow::Result<Vec<ArchivedChannel>> {
+  decode(&std::fs::read(recording_path(name)?)?)
+}
+
+pub fn delete_recording(name: &str) -> anyhow::Result<()> {
+  Ok(std::fs::remove_file(recording_path(name)?)?)
+}
+
+pub fn list_recordings() -> Vec<RecordingInfo> {
+  let mut recordings = std::fs::read_dir(recordings_dir()).into_iter()
+    .flatten()
+    .flatten()
+    .filter(|e| e.path().extension().map(|ext| ext == RECORDING_EXTENSION).unwrap_or(false))
+    .filter_map(|e| Some(RecordingInfo {
+      name: e.path().file_stem()?.to_string_lossy().into_owned(),
+      size_bytes: e.metadata().ok()?.len(),
+    }))
+    .collect::<Vec<_>>();
+  recordings.sort_by(|a, b| a.name.cmp(&b.name));
+  recordings
+}
+
+/* Recordings are checksummed by zstd, so decoding them is enough to find damaged ones. Those are moved aside. */
+pub fn verify_recordings() {
+  for recording in list_recordings() {
+    if let Err(e) = load_recording(&recording.name) {
+      let quarantined = recording_path(&recording.name).ok().and_then(|p| quarantine(&p));
+      record_recovery(&format!("telemetry/{}", recording.name), e.to_string(), RecoveryAction::Quarantined, quarantined);
+    }
+  }
+}
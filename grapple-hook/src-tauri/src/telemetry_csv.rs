@@ -0,0 +1,72 @@
+use std::io::Write;
+
+use crate::{devices::{metadata::metadata, reports::csv_field}, telemetry::{telemetry, TelemetrySample}};
+
+/* Several devices' channels merged into one CSV with a row per tick of a common timebase, so e.g. a MitoCANdria
+   current trace and a LaserCAN distance trace can be compared in one spreadsheet. Devices report at their own rates,
+   so each column holds the channel's most recent sample at that tick (blank before its first sample, or once the
+   sample is older than STALE_TICKS ticks, e.g. because the device dropped off the bus). */
+pub const DEFAULT_PERIOD_MS: i64 = 20;
+const STALE_TICKS: i64 = 10;
+/* Guards against a tiny period over a long window producing a file no spreadsheet can open */
+const MAX_ROWS: i64 = 1_000_000;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ChannelSelection {
+  pub serial: u32,
+  pub channel: String,
+}
+
+fn column_name(selection: &ChannelSelection) -> String {
+  let device = metadata().get(selection.serial).nickname.unwrap_or(format!("{:x}", selection.serial));
+  format!("{}/{}", device, selection.channel)
+}
+
+/* Returns the number of rows written */
+pub fn export_combined_csv(path: &str, channels: &[ChannelSelection], period_ms: Option<i64>, start_ms: Option<i64>, end_ms: Option<i64>) -> anyhow::Result<usize> {
+  let period_ms = period_ms.unwrap_or(DEFAULT_PERIOD_MS);
+  if period_ms <= 0 {
+    anyhow::bail!("The resampling period must be positive");
+  }
+
+  let series: Vec<(String, Vec<TelemetrySample>)> = channels.iter()
+    .map(|c| (column_name(c), telemetry().history(c.serial, &c.channel, start_ms, end_ms)))
+    .collect();
+
+  let first = start_ms.or_else(|| series.iter().flat_map(|(_, s)| s.first()).map(|s| s.timestamp_ms).min());
+  let last = end_ms.or_else(|| series.iter().flat_map(|(_, s)| s.last()).map(|s| s.timestamp_ms).max());
+  let (first, last) = match (first, last) {
+    (Some(first), Some(last)) if last >= first => (first, last),
+    _ => anyhow::bail!("No telemetry has been recorded for these channels")
+  };
+  if (last - first) / period_ms + 1 > MAX_ROWS {
+    anyhow::bail!("That would be more than {} rows, use a longer period or a shorter window", MAX_ROWS);
+  }
+
+  let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+  let header = ["timestamp_ms".to_owned(), "time_s".to_owned()].into_iter().chain(series.iter().map(|(name, _)| name.clone()));
+  writeln!(out, "{}", header.map(|h| csv_field(&h)).collect::<Vec<_>>().join(","))?;
+
+  // One cursor per channel, advanced as the ticks go past, so this is linear in the number of samples
+  let mut cursors = vec![0usize; series.len()];
+  let mut rows = 0;
+  let mut t = first;
+  while t <= last {
+    let mut row = vec![t.to_string(), format!("{:.3}", (t - first) as f64 / 1000.0)];
+    for ((_, samples), cursor) in series.iter().zip(cursors.iter_mut()) {
+      while *cursor < samples.len() && samples[*cursor].timestamp_ms <= t {
+        *cursor += 1;
+      }
+      row.push(match cursor.checked_sub(1).map(|i| &samples[i]) {
+        Some(sample) if t - sample.timestamp_ms <= STALE_TICKS * period_ms => sample.value.to_string(),
+        _ => String::new()
+      });
+    }
+    writeln!(out, "{}", row.join(","))?;
+    rows += 1;
+    t += period_ms;
+  }
+
+  out.flush()?;
+  Ok(rows)
+}
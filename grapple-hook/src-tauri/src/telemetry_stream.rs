@@ -0,0 +1,90 @@
+use std::{collections::HashMap, sync::{Mutex, OnceLock}, time::Duration};
+
+use crate::telemetry::TelemetrySample;
+
+/* Push-based telemetry for the frontend. A view subscribes to a device's channels, and from then on new samples are
+   gathered up and pushed to it as an event every PUSH_INTERVAL, rather than it calling the device at the measurement
+   rate. Subscriptions that aren't renewed within SUBSCRIPTION_LEASE are dropped, so a view that goes away without
+   unsubscribing (e.g. the window reloading) doesn't leave samples being gathered forever. */
+pub const TELEMETRY_PUSH_EVENT: &str = "telemetry";
+pub const PUSH_INTERVAL: Duration = Duration::from_millis(50);
+const SUBSCRIPTION_LEASE: Duration = Duration::from_secs(30);
+/* Per channel, in case pushes stall */
+const MAX_PENDING_SAMPLES: usize = 1000;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct TelemetryPush {
+  pub subscription: String,
+  pub serial: u32,
+  pub channel: String,
+  pub samples: Vec<TelemetrySample>,
+}
+
+struct Subscription {
+  serial: u32,
+  /* None for every channel */
+  channels: Option<Vec<String>>,
+  renewed: std::time::Instant,
+  pending: HashMap<String, Vec<TelemetrySample>>,
+}
+
+impl Subscription {
+  fn wants(&self, serial: u32, channel: &str) -> bool {
+    self.serial == serial && self.channels.as_ref().map(|c| c.iter().any(|c| c == channel)).unwrap_or(true)
+  }
+}
+
+pub struct TelemetryStreams {
+  subscriptions: Mutex<HashMap<String, Subscription>>,
+}
+
+impl TelemetryStreams {
+  /* Returns the subscription ID, which pushes are tagged with */
+  pub fn subscribe(&self, serial: u32, channels: Option<Vec<String>>) -> String {
+    let id = uuid::Uuid::new_v4().to_string();
+    self.subscriptions.lock().unwrap().insert(id.clone(), Subscription {
+      serial, channels, renewed: std::time::Instant::now(), pending: HashMap::new()
+    });
+    id
+  }
+
+  pub fn renew(&self, id: &str) -> anyhow::Result<()> {
+    match self.subscriptions.lock().unwrap().get_mut(id) {
+      Some(subscription) => { subscription.renewed = std::time::Instant::now(); Ok(()) },
+      None => anyhow::bail!("No subscription {}, it may have expired", id)
+    }
+  }
+
+  pub fn unsubscribe(&self, id: &str) {
+    self.subscriptions.lock().unwrap().remove(id);
+  }
+
+  /* Called for every recorded sample */
+  pub fn offer(&self, serial: u32, channel: &str, sample: &TelemetrySample) {
+    let mut subscriptions = self.subscriptions.lock().unwrap();
+    for subscription in subscriptions.values_mut().filter(|s| s.wants(serial, channel)) {
+      let pending = subscription.pending.entry(channel.to_owned()).or_default();
+      if pending.len() >= MAX_PENDING_SAMPLES {
+        pending.remove(0);
+      }
+      pending.push(*sample);
+    }
+  }
+
+  /* Everything gathered since the last call, and expire stale subscriptions */
+  pub fn take_pending(&self) -> Vec<TelemetryPush> {
+    let mut subscriptions = self.subscriptions.lock().unwrap();
+    subscriptions.retain(|_, s| s.renewed.elapsed() < SUBSCRIPTION_LEASE);
+    subscriptions.iter_mut()
+      .flat_map(|(id, s)| {
+        let serial = s.serial;
+        s.pending.drain().map(move |(channel, samples)| TelemetryPush { subscription: id.clone(), serial, channel, samples })
+      })
+      .collect()
+  }
+}
+
+pub fn telemetry_streams() -> &'static TelemetryStreams {
+  static STREAMS: OnceLock<TelemetryStreams> = OnceLock::new();
+  STREAMS.get_or_init(|| TelemetryStreams { subscriptions: Mutex::new(HashMap::new()) })
+}
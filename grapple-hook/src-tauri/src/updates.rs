@@ -3,12 +3,44 @@ use reqwest::header::USER_AGENT;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReleaseAsset {
+  pub name: String,
+  pub browser_download_url: String,
+}
+
 #[derive(Clone, Serialize, Deserialize, JsonSchema)]
 pub struct LightReleaseResponse {
   pub name: String,
   pub tag_name: String,
   pub published_at: String,
-  pub html_url: String
+  pub html_url: String,
+  #[serde(default)]
+  pub assets: Vec<ReleaseAsset>,
+}
+
+impl LightReleaseResponse {
+  /* The firmware image attached to the release, if there's exactly one obvious candidate */
+  pub fn firmware_asset(&self) -> Option<&ReleaseAsset> {
+    let mut bins = self.assets.iter().filter(|a| a.name.ends_with(".bin"));
+    match (bins.next(), bins.next()) {
+      (Some(asset), None) => Some(asset),
+      _ => None
+    }
+  }
+
+  /* The version part of a tag like "lasercan-v2024.2.0" */
+  pub fn version(&self) -> String {
+    self.tag_name.rsplit('-').next().unwrap_or(&self.tag_name).trim_start_matches('v').to_owned()
+  }
+}
+
+pub async fn download(url: &str) -> anyhow::Result<Vec<u8>> {
+  let response = reqwest::Client::new().get(url)
+    .header(USER_AGENT, "GrappleHook")
+    .send().await?
+    .error_for_status()?;
+  Ok(response.bytes().await?.to_vec())
 }
 
 pub async fn most_recent_update_available<F: Fn(&LightReleaseResponse) -> bool>(repo: &str, acceptance_filter: F) -> anyhow::Result<Option<LightReleaseResponse>> {
@@ -0,0 +1,179 @@
+use std::{collections::BTreeMap, sync::OnceLock, time::Duration};
+
+use log::{info, warn};
+use reqwest::header::USER_AGENT;
+
+use crate::{devices::{registry::registry, DeviceType}, operations::{journal, OperationKind, OperationState}, persistence::Persisted};
+
+/* Opt-in usage statistics. Off until the user turns it on, and only ever sends counts: which models have been seen, on
+   which firmware, and how firmware updates went. Nothing that identifies a team, a device or a machine (serials, names,
+   CAN IDs, addresses) goes in the report, and there's no install ID tying one report to the next. preview() builds the
+   exact report that would be sent, so the user can see for themselves. */
+
+const REPORT_INTERVAL_MS: i64 = 24 * 60 * 60 * 1000;
+/* How often we check whether a report is due */
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/* Where reports go. There's no collection endpoint yet, so unless a build (or the environment) sets
+   GRAPPLEHOOK_STATS_URL, statistics can be previewed but can't be turned on, and nothing is ever sent. */
+pub fn stats_url() -> Option<String> {
+  std::env::var("GRAPPLEHOOK_STATS_URL").ok()
+    .or(option_env!("GRAPPLEHOOK_STATS_URL").map(|url| url.to_owned()))
+    .filter(|url| !url.is_empty())
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct UsageStatsSettings {
+  pub enabled: bool,
+  pub last_sent_ms: Option<i64>,
+  pub last_error: Option<String>,
+  /* Filled in from stats_url() when read, None if this build has nowhere to send reports */
+  #[serde(default)]
+  pub endpoint: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ModelCount {
+  pub model: String,
+  pub count: usize,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct FirmwareCount {
+  pub model: String,
+  pub version: String,
+  pub count: usize,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct UpdateOutcomes {
+  pub model: String,
+  pub succeeded: usize,
+  pub failed: usize,
+  /* GrappleHook exited (or crashed) part way through */
+  pub interrupted: usize,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct UsageStatsReport {
+  pub app_version: String,
+  pub os: String,
+  /* The report covers from here until it was built */
+  pub period_start_ms: i64,
+  pub models: Vec<ModelCount>,
+  pub firmware_versions: Vec<FirmwareCount>,
+  pub firmware_updates: Vec<UpdateOutcomes>,
+}
+
+fn model_name(device_type: &DeviceType) -> String {
+  match device_type {
+    DeviceType::Grapple(model) => format!("{:?}", model),
+    DeviceType::UnknownGrapple(model_id) => format!("Grapple model 0x{:02X}", model_id),
+    DeviceType::RoboRIO => "RoboRIO".to_owned(),
+    DeviceType::Unknown => "Unknown".to_owned(),
+  }
+}
+
+pub struct UsageStats {
+  settings: Persisted<UsageStatsSettings>,
+}
+
+impl UsageStats {
+  pub fn settings(&self) -> UsageStatsSettings {
+    UsageStatsSettings { endpoint: stats_url(), ..self.settings.get() }
+  }
+
+  pub fn set_enabled(&self, enabled: bool) -> anyhow::Result<UsageStatsSettings> {
+    if enabled && stats_url().is_none() {
+      anyhow::bail!("This build of GrappleHook has nowhere to send usage statistics");
+    }
+    self.settings.update(|s| {
+      // Turning it back on starts afresh, rather than reporting on the time it was off
+      if enabled && !s.enabled {
+        s.last_sent_ms = None;
+        s.last_error = None;
+      }
+      s.enabled = enabled;
+    });
+    Ok(self.settings())
+  }
+
+  /* Everything since the last report, or the last day if there hasn't been one */
+  pub fn preview(&self, now_ms: i64) -> UsageStatsReport {
+    let period_start_ms = self.settings.read(|s| s.last_sent_ms).unwrap_or(now_ms - REPORT_INTERVAL_MS);
+    let devices = registry().list();
+
+    let mut models = BTreeMap::<String, usize>::new();
+    let mut firmware_versions = BTreeMap::<(String, String), usize>::new();
+    for device in devices.iter().filter(|d| d.last_seen_ms >= period_start_ms) {
+      let model = model_name(&device.device_type);
+      *models.entry(model.clone()).or_default() += 1;
+      if let Some(version) = &device.firmware_version {
+        *firmware_versions.entry((model, version.clone())).or_default() += 1;
+      }
+    }
+
+    // The journal only knows the serial, which we look up to get the model and then leave behind
+    let mut updates = BTreeMap::<String, UpdateOutcomes>::new();
+    for record in journal().since(period_start_ms).into_iter().filter(|r| r.kind == OperationKind::FirmwareUpdate) {
+      let model = devices.iter().find(|d| d.serial == record.serial).map(|d| model_name(&d.device_type)).unwrap_or_else(|| "Unknown".to_owned());
+      let outcomes = updates.entry(model.clone()).or_insert_with(|| UpdateOutcomes { model, ..Default::default() });
+      match record.state {
+        OperationState::Completed => outcomes.succeeded += 1,
+        OperationState::Failed(_) => outcomes.failed += 1,
+        OperationState::Interrupted | OperationState::Resumed => outcomes.interrupted += 1,
+        OperationState::InProgress => ()
+      }
+    }
+
+    UsageStatsReport {
+      app_version: env!("CARGO_PKG_VERSION").to_owned(),
+      os: std::env::consts::OS.to_owned(),
+      period_start_ms,
+      models: models.into_iter().map(|(model, count)| ModelCount { model, count }).collect(),
+      firmware_versions: firmware_versions.into_iter().map(|((model, version), count)| FirmwareCount { model, version, count }).collect(),
+      firmware_updates: updates.into_values().collect(),
+    }
+  }
+
+  async fn send_if_due(&self, now_ms: i64) {
+    let settings = self.settings();
+    let Some(url) = settings.endpoint else { return };
+    if !settings.enabled || settings.last_sent_ms.map(|t| now_ms - t < REPORT_INTERVAL_MS).unwrap_or(false) {
+      return;
+    }
+
+    let report = self.preview(now_ms);
+    let result = reqwest::Client::new().post(url).header(USER_AGENT, "GrappleHook").json(&report).send().await
+      .and_then(|r| r.error_for_status());
+
+    self.settings.update(|s| match result {
+      Ok(_) => {
+        info!("Sent usage statistics");
+        s.last_sent_ms = Some(now_ms);
+        s.last_error = None;
+      },
+      Err(e) => {
+        warn!("Couldn't send usage statistics: {}", e);
+        s.last_error = Some(e.to_string());
+      }
+    });
+  }
+}
+
+pub fn usage_stats() -> &'static UsageStats {
+  static STATS: OnceLock<UsageStats> = OnceLock::new();
+  STATS.get_or_init(|| UsageStats { settings: Persisted::load("usage_stats") })
+}
+
+/* Sends a report a day while it's enabled. Does nothing at all otherwise. */
+pub async fn run() {
+  if stats_url().is_none() {
+    info!("No usage statistics endpoint in this build, statistics won't be sent");
+    return;
+  }
+  loop {
+    usage_stats().send_if_due(chrono::Utc::now().timestamp_millis()).await;
+    tokio::time::sleep(CHECK_INTERVAL).await;
+  }
+}
@@ -0,0 +1,22 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/* Whether the app window can be seen. While it can't (e.g. minimised in the pits), periodic work like enumeration
+   runs slower to save battery. */
+static VISIBLE: AtomicBool = AtomicBool::new(true);
+
+/* How much slower periodic work runs while hidden */
+pub const HIDDEN_RATE_DIVISOR: u32 = 4;
+
+pub fn is_visible() -> bool {
+  VISIBLE.load(Ordering::Relaxed)
+}
+
+pub fn set_visible(visible: bool) {
+  if VISIBLE.swap(visible, Ordering::Relaxed) != visible {
+    log::info!("App is now {}", if visible { "visible, running at full rate" } else { "hidden, slowing down" });
+  }
+}
+
+pub fn rate_divisor() -> u32 {
+  if is_visible() { 1 } else { HIDDEN_RATE_DIVISOR }
+}
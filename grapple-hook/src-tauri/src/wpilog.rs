@@ -0,0 +1,118 @@
+use std::io::Write;
+
+use crate::telemetry::telemetry;
+
+/* Minimal writer for WPILib's data log format (https://github.com/wpilibsuite/allwpilib/blob/main/wpiutil/doc/datalog.adoc),
+   enough for AdvantageScope to open GrappleHook telemetry next to a robot log. */
+pub struct WpiLogWriter<W: Write> {
+  out: W,
+  next_entry: u32,
+}
+
+// Every record uses 4 byte entry IDs, 4 byte payload sizes and 8 byte timestamps
+const RECORD_HEADER: u8 = 0b0111_1111;
+
+impl<W: Write> WpiLogWriter<W> {
+  pub fn new(mut out: W, extra_header: &str) -> anyhow::Result<Self> {
+    out.write_all(b"WPILOG")?;
+    out.write_all(&0x0100u16.to_le_bytes())?;
+    out.write_all(&(extra_header.len() as u32).to_le_bytes())?;
+    out.write_all(extra_header.as_bytes())?;
+    Ok(Self { out, next_entry: 1 })
+  }
+
+  fn record(&mut self, entry: u32, timestamp_us: u64, payload: &[u8]) -> anyhow::Result<()> {
+    self.out.write_all(&[RECORD_HEADER])?;
+    self.out.write_all(&entry.to_le_bytes())?;
+    self.out.write_all(&(payload.len() as u32).to_le_bytes())?;
+    self.out.write_all(&timestamp_us.to_le_bytes())?;
+    self.out.write_all(payload)?;
+    Ok(())
+  }
+
+  /* Start a new entry, returning its ID */
+  pub fn start(&mut self, name: &str, ty: &str, timestamp_us: u64) -> anyhow::Result<u32> {
+    let entry = self.next_entry;
+    self.next_entry += 1;
+
+    let mut payload = vec![0u8];   // Start control record
+    payload.extend(entry.to_le_bytes());
+    for s in [name, ty, ""] {
+      payload.extend((s.len() as u32).to_le_bytes());
+      payload.extend(s.as_bytes());
+    }
+    self.record(0, timestamp_us, &payload)?;
+    Ok(entry)
+  }
+
+  pub fn double(&mut self, entry: u32, timestamp_us: u64, value: f64) -> anyhow::Result<()> {
+    self.record(entry, timestamp_us, &value.to_le_bytes())
+  }
+
+  pub fn int64(&mut self, entry: u32, timestamp_us: u64, value: i64) -> anyhow::Result<()> {
+    self.record(entry, timestamp_us, &value.to_le_bytes())
+  }
+
+  pub fn string(&mut self, entry: u32, timestamp_us: u64, value: &str) -> anyhow::Result<()> {
+    self.record(entry, timestamp_us, value.as_bytes())
+  }
+
+  pub fn finish(mut self) -> anyhow::Result<W> {
+    self.out.flush()?;
+    Ok(self.out)
+  }
+}
+
+/* Write recorded telemetry for the given devices to a WPILog file. Log timestamps count from origin_ms (unix time,
+   e.g. the start of the robot log), or the first sample if not given. A systemTime entry is included so AdvantageScope
+   can line the file up with robot logs by wall clock. Configuration changes go in a string entry per device, so they
+   show up as markers on the same timeline. If compress is set the file is zstd compressed (.wpilog.zst),
+   which tools will need to decompress before opening. */
+pub fn export_telemetry(path: &str, serials: &[u32], origin_ms: Option<i64>, compress: bool) -> anyhow::Result<usize> {
+  let series = serials.iter()
+    .flat_map(|&serial| telemetry().channels(serial).into_iter().map(move |c| (serial, c)))
+    .map(|(serial, channel)| {
+      let samples = telemetry().history(serial, &channel, None, None);
+      (format!("GrappleHook/{:x}/{}", serial, channel), samples)
+    })
+    .filter(|(_, samples)| !samples.is_empty())
+    .collect::<Vec<_>>();
+
+  let origin_ms = origin_ms
+    .or_else(|| series.iter().flat_map(|(_, s)| s.first()).map(|s| s.timestamp_ms).min())
+    .ok_or(anyhow::anyhow!("No telemetry has been recorded for these devices"))?;
+  let to_us = |ts_ms: i64| ((ts_ms - origin_ms).max(0) as u64) * 1000;
+
+  let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+  let out: Box<dyn Write> = match compress {
+    true => Box::new(zstd::Encoder::new(file, 3)?.auto_finish()),
+    false => Box::new(file)
+  };
+  let mut log = WpiLogWriter::new(out, "GrappleHook")?;
+
+  let system_time = log.start("systemTime", "int64", 0)?;
+  log.int64(system_time, 0, origin_ms * 1000)?;
+
+  let mut written = 0;
+  for (name, samples) in series {
+    let entry = log.start(&name, "double", to_us(samples[0].timestamp_ms))?;
+    for sample in samples.iter().filter(|s| s.timestamp_ms >= origin_ms) {
+      log.double(entry, to_us(sample.timestamp_ms), sample.value)?;
+      written += 1;
+    }
+  }
+
+  for &serial in serials {
+    let changes = telemetry().config_changes(serial, Some(origin_ms), None);
+    if let Some(first) = changes.first() {
+      let entry = log.start(&format!("GrappleHook/{:x}/config_changes", serial), "string", to_us(first.timestamp_ms))?;
+      for change in changes {
+        log.string(entry, to_us(change.timestamp_ms), &change.change)?;
+      }
+    }
+  }
+
+  // Dropping the writer finishes the compressed frame, if there is one
+  log.finish()?;
+  Ok(written)
+}
@@ -1,13 +1,15 @@
 import { invoke } from "@tauri-apps/api/tauri";
 import { listen, Event, EventCallback } from "@tauri-apps/api/event";
-import React, { useEffect } from "react";
+import React, { useEffect, useRef } from "react";
 import { Alert, Button, Col, Form, InputGroup, Nav, Row, Tab, Toast, ToastContainer } from "react-bootstrap";
 import { FontAwesomeIcon } from "@fortawesome/react-fontawesome";
 import { faInfo, faInfoCircle, faTriangleExclamation, faUpload } from "@fortawesome/free-solid-svg-icons";
 import Bug from "./Bug";
 import ProviderManagerComponent from "./providers/ProviderManager";
 import { LightReleaseResponse, ProviderManagerRequest, ProviderManagerResponse } from "./schema";
+import { rpc } from "./rpc";
 import ToastProvider, { useToasts } from "./toasts";
+import UsageStats from "./UsageStats";
 
 export default class App extends React.Component<{}> {
   render() {
@@ -16,7 +18,7 @@ export default class App extends React.Component<{}> {
     </ToastProvider>
   }
 }
-const our_invoke = async (msg: ProviderManagerRequest): Promise<ProviderManagerResponse> => {
+export const our_invoke = async (msg: ProviderManagerRequest): Promise<ProviderManagerResponse> => {
   try {
     let result = await invoke("provider_manager_rpc", { msg: msg });
     return result as ProviderManagerResponse;
@@ -26,7 +28,8 @@ const our_invoke = async (msg: ProviderManagerRequest): Promise<ProviderManagerR
 }
 
 export function AppInner() {
-  const { toasts, addInfo, removeToast } = useToasts();
+  const { toasts, addError, addWarning, addInfo, removeToast } = useToasts();
+  const lastNotification = useRef<number | null>(null);
 
   useEffect(() => {
     const interval = setTimeout(() => {
@@ -40,9 +43,54 @@ export function AppInner() {
     return () => clearTimeout(interval)
   }, []);
 
+  useEffect(() => {
+    // Bursts of similar events come through already rolled up, so this can't flood the screen
+    const interval = setInterval(() => {
+      if (document.hidden) return;
+      rpc<ProviderManagerRequest, ProviderManagerResponse, "notifications">(our_invoke, "notifications", { since: lastNotification.current }).then(notifications => {
+        for (const n of notifications) {
+          lastNotification.current = n.last_id;
+          if (n.severity === "Error") addError(n.message, n.kind);
+          else if (n.severity === "Warning") addWarning(n.message, n.kind);
+          else addInfo(n.message, n.kind);
+        }
+      }).catch(e => {});
+    }, 2000);
+    return () => clearInterval(interval);
+  }, []);
+
+  useEffect(() => {
+    // Let the backend slow down while we're minimised
+    const onVisibilityChange = () => {
+      our_invoke({ method: "set_app_visible", data: { visible: !document.hidden } }).catch(e => {});
+    };
+    document.addEventListener("visibilitychange", onVisibilityChange);
+    return () => document.removeEventListener("visibilitychange", onVisibilityChange);
+  }, []);
+
+  useEffect(() => {
+    // Ctrl+Z / Ctrl+Shift+Z (Cmd on macOS) undo and redo edits to nicknames, notes, tags and the like. Text boxes keep their own.
+    const onKeyDown = (e: KeyboardEvent) => {
+      if (!(e.ctrlKey || e.metaKey) || e.key.toLowerCase() !== "z") return;
+      const target = e.target as HTMLElement;
+      if (target.tagName === "INPUT" || target.tagName === "TEXTAREA" || target.isContentEditable) return;
+      e.preventDefault();
+
+      const result = e.shiftKey
+        ? rpc<ProviderManagerRequest, ProviderManagerResponse, "redo_metadata_edit">(our_invoke, "redo_metadata_edit", {})
+        : rpc<ProviderManagerRequest, ProviderManagerResponse, "undo_metadata_edit">(our_invoke, "undo_metadata_edit", {});
+      result
+        .then(description => { if (description != null) addInfo(description, e.shiftKey ? "Redone" : "Undone"); })
+        .catch(addError);
+    };
+    document.addEventListener("keydown", onKeyDown);
+    return () => document.removeEventListener("keydown", onKeyDown);
+  }, []);
+
   return <div className="container">
     <img src="icon.png" height={30} style={{ marginRight: "20px" }} />
     <i style={{fontSize: "1.5em"}}>Grapple<strong>Hook</strong></i>
+    <span className="float-end"><UsageStats invoke={our_invoke} /></span>
     <hr />
     
     <ProviderManagerComponent invoke={our_invoke} />
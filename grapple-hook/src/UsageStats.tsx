@@ -0,0 +1,53 @@
+import React, { useEffect, useState } from "react";
+import { Button, Form, Modal } from "react-bootstrap";
+import { ProviderManagerRequest, ProviderManagerResponse, UsageStatsReport, UsageStatsSettings } from "./schema";
+import { rpc } from "./rpc";
+import { useToasts } from "./toasts";
+
+type UsageStatsProps = {
+  invoke: (msg: ProviderManagerRequest) => Promise<ProviderManagerResponse>
+}
+
+export default function UsageStats(props: UsageStatsProps) {
+  const { invoke } = props;
+  const { addError } = useToasts();
+  const [ show, setShow ] = useState(false);
+  const [ settings, setSettings ] = useState<UsageStatsSettings>();
+  const [ preview, setPreview ] = useState<UsageStatsReport>();
+
+  useEffect(() => {
+    if (!show) return;
+    rpc<ProviderManagerRequest, ProviderManagerResponse, "usage_stats_settings">(invoke, "usage_stats_settings", {}).then(setSettings).catch(addError);
+    rpc<ProviderManagerRequest, ProviderManagerResponse, "preview_usage_stats">(invoke, "preview_usage_stats", {}).then(setPreview).catch(addError);
+  }, [show]);
+
+  return <React.Fragment>
+    <Button variant="link" size="sm" onClick={() => setShow(true)}>Usage Statistics</Button>
+    <Modal show={show} onHide={() => setShow(false)} size="lg">
+      <Modal.Header closeButton>
+        <Modal.Title>Usage Statistics</Modal.Title>
+      </Modal.Header>
+      <Modal.Body>
+        <p>
+          If you opt in, GrappleHook sends a report once a day with counts of the device models it's seen, which firmware
+          they were running, and how firmware updates went. It helps us decide what to fix first.
+        </p>
+        <p>
+          Reports are anonymous. They never include serial numbers, device names, CAN IDs, your team number or anything
+          else that identifies you or your hardware, and reports can't be linked to each other.
+        </p>
+        { settings && settings.endpoint == null && <p className="text-muted">
+          This build of GrappleHook has nowhere to send reports, so statistics can't be turned on. You can still see what
+          a report would contain below.
+        </p> }
+        <Form.Check type="switch" id="usage-stats-enabled" label="Send anonymous usage statistics" checked={settings?.enabled ?? false}
+          disabled={settings?.endpoint == null}
+          onChange={e => rpc<ProviderManagerRequest, ProviderManagerResponse, "set_usage_stats_enabled">(invoke, "set_usage_stats_enabled", { enabled: e.target.checked }).then(setSettings).catch(addError)} />
+        { settings?.last_sent_ms != null && <p className="text-muted"> Last sent { new Date(settings.last_sent_ms).toLocaleString() } </p> }
+        { settings?.last_error && <p className="text-danger"> { settings.last_error } </p> }
+        <h5 className="mt-3">The next report</h5>
+        <pre>{ preview && JSON.stringify(preview, null, 2) }</pre>
+      </Modal.Body>
+    </Modal>
+  </React.Fragment>
+}
@@ -1,5 +1,5 @@
 import { useEffect, useState } from "react"
-import { DeviceInfo, FlexiCanRequest, FlexiCanResponse, FlexiCanStatus } from "../schema"
+import { DeviceInfo, FlexiCanRequest, FlexiCanResponse, FlexiCanStatus, LightReleaseResponse } from "../schema"
 import { useToasts } from "../toasts"
 import { rpc } from "../rpc"
 import { Button, Col, ProgressBar, Row } from "react-bootstrap"
@@ -15,13 +15,20 @@ export default function FlexiCanComponent(props: FlexiCanProps) {
   const { addError } = useToasts();
 
   const [ status, setStatus ] = useState<FlexiCanStatus>();
+  const [ updateDetails, setUpdateDetails ] = useState<LightReleaseResponse | null>(null);
 
   useEffect(() => {
     const interval = setInterval(() => {
+      if (document.hidden) return;   // Nobody's looking, save the battery
       rpc<FlexiCanRequest, FlexiCanResponse, "status">(invoke, "status", {})
         .then(setStatus)
         .catch(e => {});  // Discard, it's usually a message to say that the device is disconnected and the UI fragment just hasn't been evicted yet.
     }, 50);
+
+    rpc<FlexiCanRequest, FlexiCanResponse, "check_for_new_firmware">(invoke, "check_for_new_firmware", {})
+      .then(setUpdateDetails)
+      .catch(e => addError(e))
+
     return () => clearInterval(interval);
   }, []);
 
@@ -32,6 +39,7 @@ export default function FlexiCanComponent(props: FlexiCanProps) {
           info={info}
           invoke={async (msg) => await rpc<FlexiCanRequest, FlexiCanResponse, "grapple">(invoke, "grapple", { msg })}
           start_dfu={async () => await rpc<FlexiCanRequest, FlexiCanResponse, "start_field_upgrade">(invoke, "start_field_upgrade", {})}
+          update_details={updateDetails}
         />
       </Col>
     </Row>
@@ -0,0 +1,48 @@
+import { useEffect, useState } from "react";
+import { DeviceInfo, GenericGrappleDeviceRequest, GenericGrappleDeviceResponse } from "../schema"
+import { useToasts } from "../toasts";
+import { rpc } from "../rpc";
+import { Alert, Col, Row } from "react-bootstrap";
+import { GrappleDeviceHeaderComponent } from "./Device";
+
+export type GenericGrappleDeviceComponentProps = {
+  info: DeviceInfo,
+  invoke: (msg: GenericGrappleDeviceRequest) => Promise<GenericGrappleDeviceResponse>
+}
+
+export default function GenericGrappleDevice(props: GenericGrappleDeviceComponentProps) {
+  const { info, invoke } = props;
+  const { addError } = useToasts();
+
+  const [ firmwareUrl, setFirmwareUrl ] = useState<string | null>(null);
+
+  useEffect(() => {
+    rpc<GenericGrappleDeviceRequest, GenericGrappleDeviceResponse, "get_firmware_url">(invoke, "get_firmware_url", {})
+      .then(setFirmwareUrl)
+      .catch(addError);
+  }, []);
+
+  return <div>
+    <Row className="mb-2">
+      <Col>
+        <GrappleDeviceHeaderComponent
+          info={info}
+          invoke={async (msg) => await rpc<GenericGrappleDeviceRequest, GenericGrappleDeviceResponse, "grapple">(invoke, "grapple", { msg })}
+          start_dfu={async () => await rpc<GenericGrappleDeviceRequest, GenericGrappleDeviceResponse, "start_field_upgrade">(invoke, "start_field_upgrade", {})}
+        />
+      </Col>
+    </Row>
+    <Row className="mb-2">
+      <Alert variant="warning">
+        This device isn't fully supported by this version of GrappleHook. You can still rename it and update its firmware,
+        but check for a GrappleHook update to configure it.
+        <br />
+        <span> Firmware Version: <strong>{ info.firmware_version ?? "Unknown" }</strong> </span>
+        <br />
+        {
+          firmwareUrl && <span> Download Firmware Here: <a href={firmwareUrl} style={{ color: "blue" }} target="_blank">{ firmwareUrl }</a> </span>
+        }
+      </Alert>
+    </Row>
+  </div>
+}
@@ -32,6 +32,7 @@ export default function MitocandriaComponent(props: MitocandriaProps) {
 
   useEffect(() => {
     const interval = setInterval(() => {
+      if (document.hidden) return;   // Nobody's looking, save the battery
       rpc<MitocandriaRequest, MitocandriaResponse, "status">(invoke, "status", {})
         .then(setStatus)
         .catch(e => {});  // Discard, it's usually a message to say that the device is disconnected and the UI fragment just hasn't been evicted yet.
@@ -1,62 +1,94 @@
-import { useEffect, useState } from "react";
-import { DeviceInfo, OldVersionDeviceRequest, OldVersionDeviceResponse } from "../schema"
-import { useToasts } from "../toasts";
-import { rpc } from "../rpc";
-import { Alert, Col, Row } from "react-bootstrap";
-import { FirmwareUpdateComponent, GrappleDeviceHeaderComponent } from "./Device";
-import { FontAwesomeIcon } from "@fortawesome/react-fontawesome";
-import { faTriangleExclamation } from "@fortawesome/free-solid-svg-icons";
-
-export type OldVersionDeviceComponentProps = {
-  info: DeviceInfo,
-  invoke: (msg: OldVersionDeviceRequest) => Promise<OldVersionDeviceResponse>
-}
-
-export default function OldVersionDevice(props: OldVersionDeviceComponentProps) {
-  const { info, invoke } = props;
-  const { addError } = useToasts();
-
-  const [ versionError, setVersionError ] = useState<string>("Invalid Version");
-  const [ firmwareUrl, setFirmwareUrl ] = useState<string | null>(null);
-
-  useEffect(() => {
-    const timeout = setTimeout(() => {
-      rpc<OldVersionDeviceRequest, OldVersionDeviceResponse, "get_error">(invoke, "get_error", {})
-        .then(setVersionError)
-        .catch(addError);
-      
-      rpc<OldVersionDeviceRequest, OldVersionDeviceResponse, "get_firmware_url">(invoke, "get_firmware_url", {})
-        .then(setFirmwareUrl)
-        .catch(addError);
-    }, 100);
-    return () => clearTimeout(timeout);
-  }, []);
-
-  return <div>
-    <Row className="mb-2">
-      <Col>
-        <GrappleDeviceHeaderComponent
-          info={info}
-          invoke={async (msg) => await rpc<OldVersionDeviceRequest, OldVersionDeviceResponse, "grapple">(invoke, "grapple", { msg })}
-          start_dfu={async () => await rpc<OldVersionDeviceRequest, OldVersionDeviceResponse, "start_field_upgrade">(invoke, "start_field_upgrade", {})}
-        />
-      </Col>
-    </Row>
-    <Row className="mb-2">
-      <Alert variant="danger">
-        <FontAwesomeIcon icon={faTriangleExclamation} size="2x" /> &nbsp;
-        <span style={{ fontSize: '2em' }}> This device is out of date! </span>
-        <br />
-        <span> You have to update this device before you can configure it in GrappleHook. </span>
-        <br />
-        <span>Error: <strong>{ versionError }</strong></span>
-        <br />
-        <strong> Click <span className="text-purple">"Firmware Update"</span> and upload a new firmware version! </strong>
-        <br />
-        {
-          firmwareUrl && <span> Download Firmware Here: <a href={firmwareUrl} style={{ color: "blue" }} target="_blank">{ firmwareUrl }</a> </span>
-        }
-      </Alert>
-    </Row>
-  </div>
+import { useEffect, useState } from "react";
+import { DeviceInfo, GatedRecovery, OldVersionDeviceRequest, OldVersionDeviceResponse, ProviderManagerRequest, ProviderManagerResponse } from "../schema"
+import { useToasts } from "../toasts";
+import { rpc } from "../rpc";
+import { Alert, Button, Col, Row } from "react-bootstrap";
+import { our_invoke } from "../App";
+import { FirmwareUpdateComponent, GrappleDeviceHeaderComponent } from "./Device";
+import { FontAwesomeIcon } from "@fortawesome/react-fontawesome";
+import { faTriangleExclamation } from "@fortawesome/free-solid-svg-icons";
+
+export type OldVersionDeviceComponentProps = {
+  info: DeviceInfo,
+  invoke: (msg: OldVersionDeviceRequest) => Promise<OldVersionDeviceResponse>
+}
+
+export default function OldVersionDevice(props: OldVersionDeviceComponentProps) {
+  const { info, invoke } = props;
+  const { addError } = useToasts();
+
+  const [ versionError, setVersionError ] = useState<string>("Invalid Version");
+  const [ firmwareUrl, setFirmwareUrl ] = useState<string | null>(null);
+  const [ recovery, setRecovery ] = useState<GatedRecovery | null>(null);
+  const [ recovering, setRecovering ] = useState<boolean>(false);
+
+  // The compatible release is looked up in the background, so keep asking until it's found
+  useEffect(() => {
+    if (recovery?.lookup_done) return;
+    const interval = setInterval(() => {
+      rpc<OldVersionDeviceRequest, OldVersionDeviceResponse, "recovery">(invoke, "recovery", {})
+        .then(setRecovery)
+        .catch(addError);
+    }, 1000);
+    return () => clearInterval(interval);
+  }, [recovery?.lookup_done]);
+
+  const recover = async () => {
+    setRecovering(true);
+    try {
+      await rpc<ProviderManagerRequest, ProviderManagerResponse, "recover_gated_device">(our_invoke, "recover_gated_device", { serial: info.serial! });
+    } catch (e) {
+      addError(e);
+      setRecovering(false);
+    }
+  };
+
+  useEffect(() => {
+    const timeout = setTimeout(() => {
+      rpc<OldVersionDeviceRequest, OldVersionDeviceResponse, "get_error">(invoke, "get_error", {})
+        .then(setVersionError)
+        .catch(addError);
+      
+      rpc<OldVersionDeviceRequest, OldVersionDeviceResponse, "get_firmware_url">(invoke, "get_firmware_url", {})
+        .then(setFirmwareUrl)
+        .catch(addError);
+    }, 100);
+    return () => clearTimeout(timeout);
+  }, []);
+
+  return <div>
+    <Row className="mb-2">
+      <Col>
+        <GrappleDeviceHeaderComponent
+          info={info}
+          invoke={async (msg) => await rpc<OldVersionDeviceRequest, OldVersionDeviceResponse, "grapple">(invoke, "grapple", { msg })}
+          start_dfu={async () => await rpc<OldVersionDeviceRequest, OldVersionDeviceResponse, "start_field_upgrade">(invoke, "start_field_upgrade", {})}
+        />
+      </Col>
+    </Row>
+    <Row className="mb-2">
+      <Alert variant="danger">
+        <FontAwesomeIcon icon={faTriangleExclamation} size="2x" /> &nbsp;
+        <span style={{ fontSize: '2em' }}> This device is out of date! </span>
+        <br />
+        <span> You have to update this device before you can configure it in GrappleHook. </span>
+        <br />
+        <span>Error: <strong>{ versionError }</strong></span>
+        <br />
+        {
+          recovery && <span> { recovery.explanation } <br /> </span>
+        }
+        {
+          recovery?.download_url && recovery.release ? <Button className="my-2" variant="purple" disabled={recovering} onClick={recover}>
+            { recovering ? "Updating..." : `Update to ${recovery.release.tag_name}` }
+          </Button>
+          : <strong> Click <span className="text-purple">"Firmware Update"</span> and upload a new firmware version! </strong>
+        }
+        <br />
+        {
+          firmwareUrl && <span> Download Firmware Here: <a href={firmwareUrl} style={{ color: "blue" }} target="_blank">{ firmwareUrl }</a> </span>
+        }
+      </Alert>
+    </Row>
+  </div>
 }
\ No newline at end of file
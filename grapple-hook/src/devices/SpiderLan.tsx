@@ -0,0 +1,50 @@
+import { useEffect, useState } from "react"
+import { DeviceInfo, SpiderLanRequest, SpiderLanResponse, SpiderLanStatus } from "../schema"
+import { rpc } from "../rpc"
+import { Col, Row } from "react-bootstrap"
+import { GrappleDeviceHeaderComponent } from "./Device"
+
+export type SpiderLanProps = {
+  info: DeviceInfo,
+  invoke: (msg: SpiderLanRequest) => Promise<SpiderLanResponse>
+}
+
+export default function SpiderLanComponent(props: SpiderLanProps) {
+  const { info, invoke } = props;
+
+  const [ status, setStatus ] = useState<SpiderLanStatus>();
+
+  useEffect(() => {
+    const interval = setInterval(() => {
+      if (document.hidden) return;   // Nobody's looking, save the battery
+      rpc<SpiderLanRequest, SpiderLanResponse, "status">(invoke, "status", {})
+        .then(setStatus)
+        .catch(e => {});  // Discard, it's usually a message to say that the device is disconnected and the UI fragment just hasn't been evicted yet.
+    }, 50);
+
+    return () => clearInterval(interval);
+  }, []);
+
+  return <div className="powerful-panda">
+    <Row className="mb-2">
+      <Col>
+        <GrappleDeviceHeaderComponent
+          info={info}
+          invoke={async (msg) => await rpc<SpiderLanRequest, SpiderLanResponse, "grapple">(invoke, "grapple", { msg })}
+          start_dfu={async () => await rpc<SpiderLanRequest, SpiderLanResponse, "start_field_upgrade">(invoke, "start_field_upgrade", {})}
+        />
+      </Col>
+    </Row>
+    <Row className="mb-2">
+      <Col>
+        <p className="text-muted">
+          {
+            status?.last_message_ms
+              ? <span>Last heard from { ((Date.now() - status.last_message_ms) / 1000).toFixed(1) }s ago ({ status.messages_received } messages)</span>
+              : <span>Waiting to hear from this SpiderLAN...</span>
+          }
+        </p>
+      </Col>
+    </Row>
+  </div>
+}
\ No newline at end of file
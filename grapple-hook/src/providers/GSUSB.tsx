@@ -0,0 +1,54 @@
+import React, { useEffect, useState } from "react";
+import { Alert, Col, Form, FormLabel, Row } from "react-bootstrap";
+import { rpc } from "../rpc";
+import { ProviderInfo, GsUsbRequest, GsUsbResponse, GsUsbStatus } from "../schema";
+import { useToasts } from "../toasts";
+
+type GSUSBProps = {
+  info: ProviderInfo,
+  invoke: (msg: GsUsbRequest) => Promise<GsUsbResponse>
+}
+
+const formatBitrate = (bitrate: number) => bitrate >= 1_000_000 ? `${bitrate / 1_000_000} Mbit/s` : `${bitrate / 1000} kbit/s`;
+
+export default function GSUSB(props: GSUSBProps) {
+  const { info, invoke } = props;
+  const [ status, setStatus ] = useState<GsUsbStatus>();
+  const { addError } = useToasts();
+
+  const refresh = () => rpc<GsUsbRequest, GsUsbResponse, "status">(invoke, "status", {})
+    .then(setStatus)
+    .catch(() => {});
+
+  useEffect(() => {
+    refresh();
+    const interval = setInterval(refresh, 1000);
+    return () => clearInterval(interval);
+  }, []);
+
+  return <React.Fragment>
+    {
+      !info.connected && status?.last_error && <Alert variant="danger">
+        <h4>Couldn't connect to { status.address }</h4>
+        <p> { status.last_error } </p>
+      </Alert>
+    }
+    <Row>
+      <Col md={4}>
+        <FormLabel>Bitrate</FormLabel>
+        <Form.Select
+          disabled={info.connected}
+          value={status?.bitrate}
+          onChange={e => rpc<GsUsbRequest, GsUsbResponse, "set_bitrate">(invoke, "set_bitrate", { bitrate: Number(e.target.value) })
+            .then(refresh)
+            .catch(addError)}
+        >
+          { status?.supported_bitrates.map(b => <option key={b} value={b}>{ formatBitrate(b) }{ b === 1_000_000 ? " (FRC)" : "" }</option>) }
+        </Form.Select>
+        <Form.Text className="text-muted">
+          The adapter is opened at this bitrate when you connect.
+        </Form.Text>
+      </Col>
+    </Row>
+  </React.Fragment>
+}
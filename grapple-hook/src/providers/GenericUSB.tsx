@@ -0,0 +1,34 @@
+import React, { useEffect, useState } from "react";
+import { Alert } from "react-bootstrap";
+import { FontAwesomeIcon } from "@fortawesome/react-fontawesome";
+import { faPlug } from "@fortawesome/free-solid-svg-icons";
+import { ProviderInfo, UsbPermissionFix } from "../schema";
+
+type GenericUSBProps = {
+  info: ProviderInfo,
+  invoke: (msg: any) => Promise<any>
+}
+
+export default function GenericUSB(props: GenericUSBProps) {
+  const { info, invoke } = props;
+  const [ fix, setFix ] = useState<UsbPermissionFix | null>(null);
+
+  useEffect(() => {
+    const interval = setInterval(() => {
+      invoke({}).then(r => setFix(r.usb_fix)).catch(() => {});
+    }, 1000);
+    return () => clearInterval(interval);
+  }, []);
+
+  if (info.connected || fix == null)
+    return <React.Fragment />;
+
+  return <Alert variant="danger">
+    <h4><FontAwesomeIcon icon={faPlug} /> &nbsp; Couldn't open the USB device</h4>
+    <p> { fix.description } </p>
+    <ol>
+      { fix.instructions.map((step, i) => <li key={i}><code>{ step }</code></li>) }
+    </ol>
+    { fix.udev_rule && <pre>{ fix.udev_rule }</pre> }
+  </Alert>
+}
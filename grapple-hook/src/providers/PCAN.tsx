@@ -0,0 +1,54 @@
+import React, { useEffect, useState } from "react";
+import { Alert, Col, Form, FormLabel, Row } from "react-bootstrap";
+import { rpc } from "../rpc";
+import { ProviderInfo, PcanUsbRequest, PcanUsbResponse, PcanUsbStatus } from "../schema";
+import { useToasts } from "../toasts";
+
+type PCANProps = {
+  info: ProviderInfo,
+  invoke: (msg: PcanUsbRequest) => Promise<PcanUsbResponse>
+}
+
+const formatBitrate = (bitrate: number) => bitrate >= 1_000_000 ? `${bitrate / 1_000_000} Mbit/s` : `${bitrate / 1000} kbit/s`;
+
+export default function PCAN(props: PCANProps) {
+  const { info, invoke } = props;
+  const [ status, setStatus ] = useState<PcanUsbStatus>();
+  const { addError } = useToasts();
+
+  const refresh = () => rpc<PcanUsbRequest, PcanUsbResponse, "status">(invoke, "status", {})
+    .then(setStatus)
+    .catch(() => {});
+
+  useEffect(() => {
+    refresh();
+    const interval = setInterval(refresh, 1000);
+    return () => clearInterval(interval);
+  }, []);
+
+  return <React.Fragment>
+    {
+      !info.connected && status?.last_error && <Alert variant="danger">
+        <h4>Couldn't connect to { status.channel }</h4>
+        <p> { status.last_error } </p>
+      </Alert>
+    }
+    <Row>
+      <Col md={4}>
+        <FormLabel>Bitrate</FormLabel>
+        <Form.Select
+          disabled={info.connected}
+          value={status?.bitrate}
+          onChange={e => rpc<PcanUsbRequest, PcanUsbResponse, "set_bitrate">(invoke, "set_bitrate", { bitrate: Number(e.target.value) })
+            .then(refresh)
+            .catch(addError)}
+        >
+          { status?.supported_bitrates.map(b => <option key={b} value={b}>{ formatBitrate(b) }{ b === 1_000_000 ? " (FRC)" : "" }</option>) }
+        </Form.Select>
+        <Form.Text className="text-muted">
+          The adapter is initialised at this bitrate when you connect.
+        </Form.Text>
+      </Col>
+    </Row>
+  </React.Fragment>
+}
@@ -1,150 +1,249 @@
-import React, { useEffect, useState } from "react"
-import { Button, Col, InputGroup, Nav, Row, Tab } from "react-bootstrap"
-import ProviderComponent from "./Provider"
-import { renderDeviceType, DeviceComponent } from "../devices/Device"
-import { FontAwesomeIcon } from "@fortawesome/react-fontawesome"
-import { faPlus } from "@fortawesome/free-solid-svg-icons"
-import { confirmModal } from "../Confirm"
-import BufferedFormControl from "../BufferedFormControl"
-import { DeviceId, DeviceInfo, DeviceManagerRequest, DeviceManagerResponse, ProviderInfo, ProviderManagerRequest, ProviderManagerResponse, WrappedDeviceProviderRequest, WrappedDeviceProviderResponse } from "../schema"
-import { useToasts } from "../toasts"
-import { rpc } from "../rpc"
-import update from "immutability-helper";
-
-type ProviderManagerProps = {
-  invoke: (msg: ProviderManagerRequest) => Promise<ProviderManagerResponse>,
-}
-
-export default function ProviderManagerComponent(props: ProviderManagerProps) {
-  const { invoke } = props;
-  const { addError } = useToasts();
-
-  const [ providers, setProviders ] = useState<{ [key: string]: ProviderInfo }>({});
-  const [ devices, setDevices ] = useState<{ [key: string]: { [domain: string]: [DeviceId, DeviceInfo, string][] } }>({});
-
-  const provider_rpc = (address: string) => {
-    return async (msg: WrappedDeviceProviderRequest) => {
-      return await rpc<ProviderManagerRequest, ProviderManagerResponse, "provider">(invoke, "provider", {
-        address: address, msg: msg
-      })
-    }
-  }
-
-  const device_manager_rpc = (provider_address: string) => {
-    return async (msg: DeviceManagerRequest) => {
-      return await rpc<WrappedDeviceProviderRequest, WrappedDeviceProviderResponse, "device_manager_call">(provider_rpc(provider_address), "device_manager_call", {
-        req: msg
-      })
-    }
-  }
-
-  const device_rpc = (provider_address: string, domain: string, device_id: DeviceId) => {
-    return async (msg: any) => {
-      return await rpc<DeviceManagerRequest, DeviceManagerResponse, "call">(device_manager_rpc(provider_address), "call", {
-        device_id: device_id, domain: domain, data: msg
-      })
-    }
-  }
-
-  useEffect(() => {
-    const interval = setInterval(() => {
-      rpc<ProviderManagerRequest, ProviderManagerResponse, "providers">(invoke, "providers", {})
-        .then((providers) => {
-          setProviders(providers);
-          let futs = Promise.all(Object.keys(providers).map(provider => rpc<DeviceManagerRequest, DeviceManagerResponse, "devices">(device_manager_rpc(providers[provider].address), "devices", {}).catch(addError)));
-          futs.then(vals => {
-            let devs = update(devices, {});
-            vals.forEach((v, i) => {
-              if (v) {
-                devs = update(devs, { [Object.keys(providers)[i]]: { $set: v } });
-              }
-            });
-            setDevices(devs);
-          });
-        })
-        .catch(addError)
-    }, 500);
-    return () => clearInterval(interval);
-  }, [])
-
-  return <React.Fragment>
-    <Tab.Container>
-      <Row>
-        <Col md={4}>
-          <Nav variant="pills" className="flex-column">
-            {
-              Object.keys(providers).map(key => {
-                let p = providers[key];
-                return [
-                  <Nav.Item className="device-list-provider">
-                    <Nav.Link eventKey={"provider-" + key}>
-                      <span style={{fontSize: '1.5em'}}>{ p.description }</span> <br />
-                      <span className="text-muted">{ p.address }</span> &nbsp;
-                      <span className={ p.connected ? "text-success" : "text-danger" }>{ p.connected ? "CONNECTED" : "DISCONNECTED" }</span>
-                    </Nav.Link>
-                  </Nav.Item>,
-                  ...Object.keys(devices[key] || {}).flatMap(domain => [
-                    devices[key][domain].map(([device_id, device_info, device_class]) => (
-                      <DevicePillComponent provider_key={key} domain={domain} device_id={device_id} device_info={device_info} device_class={device_class} />
-                    ))
-                  ])
-                ]
-              })
-            }
-          </Nav>
-        </Col>
-        <Col md={8}>
-          <Tab.Content>
-            {
-              Object.keys(providers).map(key => {
-                let p = providers[key];
-                return [
-                  <Tab.Pane eventKey={"provider-" + key}>
-                    <ProviderComponent info={p} invoke={provider_rpc(p.address)} />
-                  </Tab.Pane>,
-                  ...Object.keys(devices[key] || {}).flatMap(domain => [
-                    devices[key][domain].map(([device_id, device_info, device_class]) => (
-                      <Tab.Pane eventKey={`device-${key}-${domain}-${JSON.stringify(device_id)}`}>
-                        <DeviceComponent id={device_id} info={device_info} device_class={device_class} invoke={device_rpc(p.address, domain, device_id)} />
-                      </Tab.Pane>
-                    ))
-                  ])
-                ]
-              })
-            }
-          </Tab.Content>
-        </Col>
-      </Row>
-    </Tab.Container>
-  </React.Fragment>
-}
-
-export function DevicePillComponent(props: { provider_key: string, domain: string, device_id: DeviceId, device_info: DeviceInfo, device_class: string }) {
-  const { provider_key, domain, device_id, device_info } = props;
-  return <Nav.Item className="device-list-device">
-     <Nav.Link eventKey={`device-${provider_key}-${domain}-${JSON.stringify(device_id)}`}>
-       {
-         device_info.is_dfu ? <React.Fragment>
-           { renderDeviceType(device_info.device_type) } &nbsp;
-           <span className="text-orange">F/W UPDATE</span>
-           <br />
-           <span className="tip">
-             { domain } &nbsp;
-             { device_info.serial != undefined && `Serial: 0x${device_info.serial.toString(16)}` } &nbsp;
-             { device_info.firmware_version != undefined && `BL: ${device_info.firmware_version}` }
-           </span>
-         </React.Fragment> : <React.Fragment>
-           { device_info.device_id != undefined && `#${device_info.device_id}` } &nbsp;
-           { renderDeviceType(device_info.device_type) } &nbsp;
-           { device_info.name != undefined && `(${device_info.name})` }
-           <br />
-           <span className="tip">
-             { domain } &nbsp;
-             { device_info.serial != undefined && `Serial: 0x${device_info.serial.toString(16)}` } &nbsp;
-             { device_info.firmware_version != undefined && `FW: ${device_info.firmware_version}` }
-           </span>
-         </React.Fragment>
-       }    
-     </Nav.Link>
-   </Nav.Item>
-}
+import React, { useEffect, useState } from "react"
+import { Badge, Button, Col, Form, InputGroup, Nav, Row, Tab } from "react-bootstrap"
+import ProviderComponent from "./Provider"
+import { renderDeviceType, DeviceComponent } from "../devices/Device"
+import { FontAwesomeIcon } from "@fortawesome/react-fontawesome"
+import { faLightbulb, faPlus, faPowerOff, faThumbtack } from "@fortawesome/free-solid-svg-icons"
+import confirmBool, { confirmModal } from "../Confirm"
+import BufferedFormControl from "../BufferedFormControl"
+import { BusLoadReport, DeviceId, DeviceInfo, DeviceState, DeviceManagerRequest, DeviceManagerResponse, ProviderInfo, ProviderManagerRequest, RegistryEntry, ProviderManagerResponse, WrappedDeviceProviderRequest, WrappedDeviceProviderResponse } from "../schema"
+import { useToasts } from "../toasts"
+import { rpc } from "../rpc"
+import update from "immutability-helper";
+import { listen } from "@tauri-apps/api/event";
+
+type ProviderManagerProps = {
+  invoke: (msg: ProviderManagerRequest) => Promise<ProviderManagerResponse>,
+}
+
+export default function ProviderManagerComponent(props: ProviderManagerProps) {
+  const { invoke } = props;
+  const { addError } = useToasts();
+
+  const [ providers, setProviders ] = useState<{ [key: string]: ProviderInfo }>({});
+  const [ devices, setDevices ] = useState<{ [key: string]: { [domain: string]: [DeviceId, DeviceInfo, string][] } }>({});
+  const [ busLoad, setBusLoad ] = useState<{ [key: string]: { [domain: string]: BusLoadReport } }>({});
+  const [ registry, setRegistry ] = useState<RegistryEntry[]>([]);
+  const [ slcanPort, setSlcanPort ] = useState<string>("");
+
+  const provider_rpc = (address: string) => {
+    return async (msg: WrappedDeviceProviderRequest) => {
+      return await rpc<ProviderManagerRequest, ProviderManagerResponse, "provider">(invoke, "provider", {
+        address: address, msg: msg
+      })
+    }
+  }
+
+  const device_manager_rpc = (provider_address: string) => {
+    return async (msg: DeviceManagerRequest) => {
+      return await rpc<WrappedDeviceProviderRequest, WrappedDeviceProviderResponse, "device_manager_call">(provider_rpc(provider_address), "device_manager_call", {
+        req: msg
+      })
+    }
+  }
+
+  const freezeOutputs = async (provider_address: string, domain: string) => {
+    if (await confirmBool(`Switch off every controllable output on ${domain}?`, { title: "Freeze Outputs", okText: "Switch Off" })) {
+      rpc<DeviceManagerRequest, DeviceManagerResponse, "freeze_outputs">(device_manager_rpc(provider_address), "freeze_outputs", { domain, confirm: true })
+        .then(report => report.failed.forEach(([id, err]) => addError(`${JSON.stringify(id)}: ${err}`)))
+        .catch(addError);
+    }
+  }
+
+  const device_rpc = (provider_address: string, domain: string, device_id: DeviceId) => {
+    return async (msg: any) => {
+      return await rpc<DeviceManagerRequest, DeviceManagerResponse, "call">(device_manager_rpc(provider_address), "call", {
+        device_id: device_id, domain: domain, data: msg
+      })
+    }
+  }
+
+  useEffect(() => {
+    const refresh = () => {
+      rpc<ProviderManagerRequest, ProviderManagerResponse, "providers">(invoke, "providers", {})
+        .then((providers) => {
+          setProviders(providers);
+          let futs = Promise.all(Object.keys(providers).map(provider => rpc<DeviceManagerRequest, DeviceManagerResponse, "devices">(device_manager_rpc(providers[provider].address), "devices", {}).catch(addError)));
+          futs.then(vals => {
+            let devs = update(devices, {});
+            vals.forEach((v, i) => {
+              if (v) {
+                devs = update(devs, { [Object.keys(providers)[i]]: { $set: v } });
+              }
+            });
+            setDevices(devs);
+          });
+
+          Promise.all(Object.keys(providers).map(provider => rpc<DeviceManagerRequest, DeviceManagerResponse, "bus_load">(device_manager_rpc(providers[provider].address), "bus_load", {}).catch(e => undefined)))
+            .then(vals => {
+              let loads: { [key: string]: { [domain: string]: BusLoadReport } } = {};
+              vals.forEach((v, i) => { if (v) loads[Object.keys(providers)[i]] = v; });
+              setBusLoad(loads);
+            });
+        })
+        .catch(addError)
+    };
+
+    // Devices coming and going are pushed to us, so polling only needs to keep states and activity fresh
+    // Changes tend to arrive in bursts (e.g. a whole bus dropping out), so gather them into one refresh
+    let pending: ReturnType<typeof setTimeout> | null = null;
+    const onChange = () => {
+      if (pending === null) pending = setTimeout(() => { pending = null; refresh(); }, 50);
+    };
+
+    const interval = setInterval(refresh, 2000);
+    const unlisten = listen("device_change", onChange);
+    return () => { clearInterval(interval); unlisten.then(f => f()); };
+  }, [])
+
+  useEffect(() => {
+    const interval = setInterval(() => {
+      rpc<ProviderManagerRequest, ProviderManagerResponse, "device_registry">(invoke, "device_registry", {})
+        .then(setRegistry)
+        .catch(addError);
+    }, 5000);
+    return () => clearInterval(interval);
+  }, [])
+
+  return <React.Fragment>
+    <Tab.Container>
+      <Row>
+        <Col md={4}>
+          <Nav variant="pills" className="flex-column">
+            {
+              Object.keys(providers).map(key => {
+                let p = providers[key];
+                return [
+                  <Nav.Item className="device-list-provider">
+                    <Nav.Link eventKey={"provider-" + key}>
+                      <span style={{fontSize: '1.5em'}}>{ p.description }</span> <br />
+                      <span className="text-muted">{ p.address }</span> &nbsp;
+                      <span className={ p.connected ? "text-success" : "text-danger" }>{ p.connected ? "CONNECTED" : "DISCONNECTED" }</span>
+                    </Nav.Link>
+                  </Nav.Item>,
+                  ...Object.keys(devices[key] || {}).flatMap(domain => [
+                    <Nav.Item className="device-list-device">
+                      <Button size="sm" variant="danger" onClick={() => freezeOutputs(p.address, domain)}>
+                        <FontAwesomeIcon icon={faPowerOff} /> Freeze Outputs ({ domain })
+                      </Button>
+                    </Nav.Item>,
+                    busLoad[key]?.[domain]?.warning && <Nav.Item className="device-list-device">
+                      <span className="text-warning tip"> { domain }: { busLoad[key][domain].warning } </span>
+                    </Nav.Item>,
+                    [...devices[key][domain]].sort((a, b) => Number(b[1].pinned) - Number(a[1].pinned)).map(([device_id, device_info, device_class]) => (
+                      <DevicePillComponent provider_key={key} domain={domain} device_id={device_id} device_info={device_info} device_class={device_class}
+                        onTogglePin={() => device_info.serial != undefined && rpc<DeviceManagerRequest, DeviceManagerResponse, "set_pinned">(device_manager_rpc(p.address), "set_pinned", { serial: device_info.serial, pinned: !device_info.pinned }).catch(addError)}
+                        onIdentify={() => rpc<DeviceManagerRequest, DeviceManagerResponse, "identify">(device_manager_rpc(p.address), "identify", { domain, device_id }).catch(addError)} />
+                    ))
+                  ])
+                ]
+              })
+            }
+            {
+              registry.filter(e => !e.online).map(({ device }) => <Nav.Item className="device-list-device text-muted">
+                { device.can_id != undefined && `#${device.can_id}` } &nbsp;
+                { renderDeviceType(device.device_type) } &nbsp;
+                { device.name != undefined && `(${device.name})` } &nbsp;
+                <span className="text-danger">OFFLINE</span>
+                <br />
+                <span className="tip">
+                  { device.domain } &nbsp;
+                  { `Serial: 0x${device.serial.toString(16)}` } &nbsp;
+                  { device.firmware_version != undefined && `FW: ${device.firmware_version}` } &nbsp;
+                  { `Last seen ${new Date(device.last_seen_ms).toLocaleString()}` }
+                </span>
+              </Nav.Item>)
+            }
+            <Nav.Item className="mt-2">
+              <InputGroup size="sm">
+                <Form.Control type="text" placeholder="Serial port, e.g. /dev/ttyACM0 or COM3" value={slcanPort} onChange={e => setSlcanPort(e.target.value)} />
+                <Button variant="secondary" disabled={slcanPort.trim() === ""} onClick={() => rpc<ProviderManagerRequest, ProviderManagerResponse, "add_slcan_adapter">(invoke, "add_slcan_adapter", { port: slcanPort.trim() })
+                  .then(() => setSlcanPort(""))
+                  .catch(addError)}>
+                  <FontAwesomeIcon icon={faPlus} /> Add SLCAN Adapter
+                </Button>
+              </InputGroup>
+            </Nav.Item>
+          </Nav>
+        </Col>
+        <Col md={8}>
+          <Tab.Content>
+            {
+              Object.keys(providers).map(key => {
+                let p = providers[key];
+                return [
+                  <Tab.Pane eventKey={"provider-" + key}>
+                    <ProviderComponent info={p} invoke={provider_rpc(p.address)} />
+                  </Tab.Pane>,
+                  ...Object.keys(devices[key] || {}).flatMap(domain => [
+                    devices[key][domain].map(([device_id, device_info, device_class]) => (
+                      <Tab.Pane eventKey={`device-${key}-${domain}-${JSON.stringify(device_id)}`}>
+                        <DeviceComponent id={device_id} info={device_info} device_class={device_class} invoke={device_rpc(p.address, domain, device_id)} />
+                      </Tab.Pane>
+                    ))
+                  ])
+                ]
+              })
+            }
+          </Tab.Content>
+        </Col>
+      </Row>
+    </Tab.Container>
+  </React.Fragment>
+}
+
+export function DevicePillComponent(props: { provider_key: string, domain: string, device_id: DeviceId, device_info: DeviceInfo, device_class: string, onTogglePin?: () => void, onIdentify?: () => void }) {
+  const { provider_key, domain, device_id, device_info, onTogglePin, onIdentify } = props;
+  return <Nav.Item className="device-list-device">
+     <Nav.Link eventKey={`device-${provider_key}-${domain}-${JSON.stringify(device_id)}`}>
+       {
+         device_info.is_dfu ? <React.Fragment>
+           { renderDeviceType(device_info.device_type) } &nbsp;
+           <span className="text-orange">{ device_info.state === "Rebooting" ? "REBOOTING" : "F/W UPDATE" }</span>
+           <br />
+           <span className="tip">
+             <span style={{ color: device_info.domain_color ?? undefined }}>{ device_info.domain_display_name ?? domain }</span> &nbsp;
+             { device_info.serial != undefined && `Serial: 0x${device_info.serial.toString(16)}` } &nbsp;
+             { device_info.firmware_version != undefined && `BL: ${device_info.firmware_version}` }
+           </span>
+         </React.Fragment> : <React.Fragment>
+           {
+             onTogglePin && <span className={device_info.pinned ? "text-purple" : "text-muted"} style={{ float: "right" }} onClick={e => { e.stopPropagation(); onTogglePin() }}>
+               <FontAwesomeIcon icon={faThumbtack} />
+             </span>
+           }
+           {
+             onIdentify && <span className="text-muted mx-2" style={{ float: "right" }} onClick={e => { e.stopPropagation(); onIdentify() }}>
+               <FontAwesomeIcon icon={faLightbulb} />
+             </span>
+           }
+           { device_info.device_id != undefined && `#${device_info.device_id}` } &nbsp;
+           { renderDeviceType(device_info.device_type) } &nbsp;
+           { device_info.name != undefined && `(${device_info.name})` } &nbsp;
+           <DeviceStateComponent state={device_info.state} />
+           <br />
+           <span className="tip">
+             <span style={{ color: device_info.domain_color ?? undefined }}>{ device_info.domain_display_name ?? domain }</span> &nbsp;
+             { device_info.serial != undefined && `Serial: 0x${device_info.serial.toString(16)}` } &nbsp;
+             { device_info.firmware_version != undefined && `FW: ${device_info.firmware_version}` } &nbsp;
+             { device_info.tags?.map(tag => <Badge key={tag} bg="secondary" className="mx-1">{ tag }</Badge>) }
+           </span>
+         </React.Fragment>
+       }    
+     </Nav.Link>
+   </Nav.Item>
+}
+
+export function DeviceStateComponent(props: { state?: DeviceState }) {
+  const { state } = props;
+  if (state === undefined || state === "Ready")
+    return <React.Fragment />;
+  else if (state === "Discovered")
+    return <span className="text-info">NEW</span>;
+  else if (state === "Lost")
+    return <span className="text-danger">LOST</span>;
+  else if (typeof state === "object" && "IncompatibleFirmware" in state)
+    return <span className="text-warning">INCOMPATIBLE F/W</span>;
+  else
+    return <span className="text-orange">{ String(state).toUpperCase() }</span>;
+}
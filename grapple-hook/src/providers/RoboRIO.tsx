@@ -1,11 +1,12 @@
 import { useEffect, useState } from "react";
 import { rpc } from "../rpc";
-import { ProviderInfo, RoboRioDaemonRequest, RoboRioDaemonResponse, RoboRIOStatus } from "../schema"
+import { DiscoveredRoboRio, ProviderInfo, RoboRioDaemonRequest, RoboRioDaemonResponse, RoboRIOStatus } from "../schema"
 import Bug from "../Bug";
-import { Col, FormLabel, Row, Tab, Tabs } from "react-bootstrap";
+import { Alert, Button, Col, FormLabel, ListGroup, Row, Tab, Tabs } from "react-bootstrap";
 import EnumToggleGroup from "../EnumToggleGroup";
 import BufferedFormControl from "../BufferedFormControl";
 import { CodeBlock } from "react-code-blocks";
+import { useToasts } from "../toasts";
 
 export type RoboRIOProps = {
   info: ProviderInfo,
@@ -16,6 +17,8 @@ export default function RoboRIO(props: RoboRIOProps) {
   const { info, invoke } = props;
 
   const [ status, setStatus ] = useState<RoboRIOStatus>();
+  const [ discovered, setDiscovered ] = useState<DiscoveredRoboRio[]>([]);
+  const { addError } = useToasts();
 
   useEffect(() => {
     const interval = setInterval(() => {
@@ -26,6 +29,15 @@ export default function RoboRIO(props: RoboRIOProps) {
     return () => clearInterval(interval);
   }, []);
 
+  useEffect(() => {
+    const refresh = () => rpc<RoboRioDaemonRequest, RoboRioDaemonResponse, "discovered">(invoke, "discovered", {})
+      .then(setDiscovered)
+      .catch(e => {});
+    refresh();
+    const interval = setInterval(refresh, 2000);
+    return () => clearInterval(interval);
+  }, []);
+
   const example_code_java = `  // Java
   import au.grapplerobotics.CanBridge;
   public class Robot extends <your robot template> {
@@ -56,6 +68,20 @@ export default function RoboRIO(props: RoboRIOProps) {
   `
 
   return <div>
+    {
+      status?.bridge_mismatch && <Row>
+        <Col>
+          <Alert variant="danger">
+            <h4>Bridge Version Mismatch</h4>
+            <p> { status.bridge_mismatch.detail } </p>
+            <p> The roboRIO's bridge is { status.bridge_mismatch.found ? <strong>libgrapplefrc { status.bridge_mismatch.found }</strong> : "an unknown version" }, GrappleHook expects <strong>libgrapplefrc { status.bridge_mismatch.expected }</strong>. </p>
+            <Button variant="danger" onClick={() => rpc<RoboRioDaemonRequest, RoboRioDaemonResponse, "upgrade_bridge">(invoke, "upgrade_bridge", {}).catch(addError)}>
+              Upgrade Bridge
+            </Button>
+          </Alert>
+        </Col>
+      </Row>
+    }
     <Row>
       <Col>
         <Bug
@@ -71,6 +97,29 @@ export default function RoboRIO(props: RoboRIOProps) {
         />
       </Col>
     </Row>
+    {
+      !info.connected && <Row className="mb-3">
+        <Col>
+          <FormLabel>Discovered roboRIOs</FormLabel>
+          {
+            discovered.length === 0 ? <p className="text-muted"> Looking for roboRIOs on the network and over USB... </p>
+              : <ListGroup>
+                {
+                  discovered.map(d => <ListGroup.Item key={d.address} className="d-flex align-items-center">
+                    <span className="me-auto">
+                      <strong>{ d.team != null ? `Team ${d.team}` : "roboRIO" }</strong> &nbsp;
+                      <span className="text-muted">{ d.method === "Usb" ? "USB" : d.hostname } ({ d.address })</span>
+                    </span>
+                    <Button size="sm" variant="success" onClick={() => rpc<RoboRioDaemonRequest, RoboRioDaemonResponse, "connect_discovered">(invoke, "connect_discovered", { address: d.address }).catch(addError)}>
+                      Connect
+                    </Button>
+                  </ListGroup.Item>)
+                }
+              </ListGroup>
+          }
+        </Col>
+      </Row>
+    }
     <Row>
       <Col md="auto">
         <EnumToggleGroup name="connection-type" values={[true, false]} names={["Deploy Daemon", "User Code"]} value={status?.using_daemon} variantActive="success" variant="secondary" onChange={() => invoke({ method: "set_use_daemon", data: { use_daemon: !status?.using_daemon } })} />
@@ -87,7 +136,7 @@ export default function RoboRIO(props: RoboRIOProps) {
           <br />
 
           In your Robot Code, merge in the following changes, redeploy, and then try and connect again. <br />
-          <strong>Make sure <span className="text-primary">libgrapplefrc</span> is up to date! (at least 2025.0.5)</strong> <br /> <br />
+          <strong>Make sure <span className="text-primary">libgrapplefrc</span> is up to date! (at least { status?.bundled_bridge_version ?? "2025.0.5" })</strong> <br /> <br />
 
           <Tabs id="lang-examples" defaultActiveKey="java">
             <Tab eventKey="java" title="JAVA">
@@ -0,0 +1,54 @@
+import React, { useEffect, useState } from "react";
+import { Alert, Col, Form, FormLabel, Row } from "react-bootstrap";
+import { rpc } from "../rpc";
+import { ProviderInfo, SlcanRequest, SlcanResponse, SlcanStatus } from "../schema";
+import { useToasts } from "../toasts";
+
+type SLCANProps = {
+  info: ProviderInfo,
+  invoke: (msg: SlcanRequest) => Promise<SlcanResponse>
+}
+
+const formatBitrate = (bitrate: number) => bitrate >= 1_000_000 ? `${bitrate / 1_000_000} Mbit/s` : `${bitrate / 1000} kbit/s`;
+
+export default function SLCAN(props: SLCANProps) {
+  const { info, invoke } = props;
+  const [ status, setStatus ] = useState<SlcanStatus>();
+  const { addError } = useToasts();
+
+  const refresh = () => rpc<SlcanRequest, SlcanResponse, "status">(invoke, "status", {})
+    .then(setStatus)
+    .catch(() => {});
+
+  useEffect(() => {
+    refresh();
+    const interval = setInterval(refresh, 1000);
+    return () => clearInterval(interval);
+  }, []);
+
+  return <React.Fragment>
+    {
+      !info.connected && status?.last_error && <Alert variant="danger">
+        <h4>Couldn't connect to { status.port }</h4>
+        <p> { status.last_error } </p>
+      </Alert>
+    }
+    <Row>
+      <Col md={4}>
+        <FormLabel>Bitrate</FormLabel>
+        <Form.Select
+          disabled={info.connected}
+          value={status?.bitrate}
+          onChange={e => rpc<SlcanRequest, SlcanResponse, "set_bitrate">(invoke, "set_bitrate", { bitrate: Number(e.target.value) })
+            .then(refresh)
+            .catch(addError)}
+        >
+          { status?.supported_bitrates.map(b => <option key={b} value={b}>{ formatBitrate(b) }{ b === 1_000_000 ? " (FRC)" : "" }</option>) }
+        </Form.Select>
+        <Form.Text className="text-muted">
+          The adapter is opened at this bitrate when you connect.
+        </Form.Text>
+      </Col>
+    </Row>
+  </React.Fragment>
+}
@@ -0,0 +1,59 @@
+import React, { useEffect, useState } from "react";
+import { Alert, Col, Form, FormLabel, Row } from "react-bootstrap";
+import { rpc } from "../rpc";
+import { ProviderInfo, SocketCanRequest, SocketCanResponse, SocketCanStatus } from "../schema";
+import { useToasts } from "../toasts";
+
+type SocketCANProps = {
+  info: ProviderInfo,
+  invoke: (msg: SocketCanRequest) => Promise<SocketCanResponse>
+}
+
+const formatBitrate = (bitrate: number) => bitrate >= 1_000_000 ? `${bitrate / 1_000_000} Mbit/s` : `${bitrate / 1000} kbit/s`;
+
+export default function SocketCAN(props: SocketCANProps) {
+  const { info, invoke } = props;
+  const [ status, setStatus ] = useState<SocketCanStatus>();
+  const { addError } = useToasts();
+
+  const refresh = () => rpc<SocketCanRequest, SocketCanResponse, "status">(invoke, "status", {})
+    .then(setStatus)
+    .catch(() => {});
+
+  useEffect(() => {
+    refresh();
+    const interval = setInterval(refresh, 1000);
+    return () => clearInterval(interval);
+  }, []);
+
+  return <React.Fragment>
+    {
+      !info.connected && status?.last_error && <Alert variant="danger">
+        <h4>Couldn't connect to { status.interface }</h4>
+        <p> { status.last_error } </p>
+      </Alert>
+    }
+    <Row>
+      <Col md={4}>
+        <FormLabel>Bitrate</FormLabel>
+        <Form.Select
+          disabled={info.connected || status?.is_virtual}
+          value={status?.bitrate ?? ""}
+          onChange={e => rpc<SocketCanRequest, SocketCanResponse, "set_bitrate">(invoke, "set_bitrate", { bitrate: e.target.value === "" ? null : Number(e.target.value) })
+            .then(refresh)
+            .catch(addError)}
+        >
+          <option value="">Leave as configured</option>
+          { status?.supported_bitrates.map(b => <option key={b} value={b}>{ formatBitrate(b) }{ b === 1_000_000 ? " (FRC)" : "" }</option>) }
+        </Form.Select>
+        <Form.Text className="text-muted">
+          { status?.is_virtual ? "Virtual interfaces don't have a bitrate." : `The interface is brought up at this bitrate when you connect${status?.bitrate == null ? ", or at 1 Mbit/s if it's down" : ""}.` }
+        </Form.Text>
+      </Col>
+      <Col>
+        <FormLabel>Interface</FormLabel>
+        <p className={status?.up ? "text-success" : "text-muted"}> { status?.interface } is { status?.up ? "UP" : "DOWN" } </p>
+      </Col>
+    </Row>
+  </React.Fragment>
+}
@@ -99,6 +99,62 @@ export type GrappleDeviceResponse =
       data: null;
       method: "commit_to_eeprom";
     };
+export type GenericGrappleDeviceRequest =
+  | {
+      data: {};
+      method: "info";
+    }
+  | {
+      data: {};
+      method: "get_firmware_url";
+    }
+  | {
+      data: {};
+      method: "start_field_upgrade";
+    }
+  | {
+      data: {
+        msg: GrappleDeviceRequest;
+      };
+      method: "grapple";
+    };
+export type GenericGrappleDeviceResponse =
+  | {
+      data: DeviceInfo;
+      method: "info";
+    }
+  | {
+      data: string | null;
+      method: "get_firmware_url";
+    }
+  | {
+      data: null;
+      method: "start_field_upgrade";
+    }
+  | {
+      data: GrappleDeviceResponse;
+      method: "grapple";
+    };
+export type GsUsbRequest =
+  | {
+      data: {};
+      method: "status";
+    }
+  | {
+      data: {
+        bitrate: number;
+      };
+      method: "set_bitrate";
+    };
+export type GsUsbResponse =
+  | {
+      data: GsUsbStatus;
+      method: "status";
+    }
+  | {
+      data: null;
+      method: "set_bitrate";
+    };
 export type LaserCanRequest =
   | {
       data: {};
@@ -122,6 +178,10 @@ export type LaserCanRequest =
       };
       method: "set_timing_budget";
     }
+  | {
+      data: {};
+      method: "output_rates";
+    }
   | {
       data: {
         msg: GrappleDeviceRequest;
@@ -155,6 +215,10 @@ export type LaserCanResponse =
       data: null;
       method: "set_timing_budget";
     }
+  | {
+      data: OutputRateOption[];
+      method: "output_rates";
+    }
   | {
       data: GrappleDeviceResponse;
       method: "grapple";
@@ -259,6 +323,10 @@ export type OldVersionDeviceRequest =
       data: {};
       method: "get_firmware_url";
     }
+  | {
+      data: {};
+      method: "recovery";
+    }
   | {
       data: {
         msg: GrappleDeviceRequest;
@@ -278,10 +346,34 @@ export type OldVersionDeviceResponse =
       data: string | null;
       method: "get_firmware_url";
     }
+  | {
+      data: GatedRecovery;
+      method: "recovery";
+    }
   | {
       data: GrappleDeviceResponse;
       method: "grapple";
     };
+export type PcanUsbRequest =
+  | {
+      data: {};
+      method: "status";
+    }
+  | {
+      data: {
+        bitrate: number;
+      };
+      method: "set_bitrate";
+    };
+export type PcanUsbResponse =
+  | {
+      data: PcanUsbStatus;
+      method: "status";
+    }
+  | {
+      data: null;
+      method: "set_bitrate";
+    };
 export type ProviderManagerRequest =
   | {
       data: {
@@ -299,6 +391,52 @@ export type ProviderManagerRequest =
   | {
       data: {};
       method: "providers";
+    }
+  | {
+      data: {
+        port: string;
+      };
+      method: "add_slcan_adapter";
+    }
+  | {
+      data: {};
+      method: "usage_stats_settings";
+    }
+  | {
+      data: {
+        enabled: boolean;
+      };
+      method: "set_usage_stats_enabled";
+    }
+  | {
+      data: {};
+      method: "preview_usage_stats";
+    }
+  | {
+      data: {};
+      method: "device_registry";
+    }
+  | {
+      data: {
+        serial: number;
+      };
+      method: "forget_device";
+    }
+  | {
+      data: {
+        serial: number;
+      };
+      method: "recover_gated_device";
+    }
+  | {
+      data: {
+        kind: UsbIssueKind;
+      };
+      method: "usb_permission_fix";
+    }
+  | {
+      data: {};
+      method: "write_udev_rule";
     };
 export type WrappedDeviceProviderRequest =
   | {
@@ -337,6 +475,10 @@ export type DeviceManagerRequest =
   | {
       data: {};
       method: "devices";
+    }
+  | {
+      data: {};
+      method: "bus_load";
     };
 export type DeviceId =
   | {
@@ -345,6 +487,7 @@ export type DeviceId =
   | {
       Serial: number;
     };
+export type UsbIssueKind = "PermissionDenied" | "PortInUse" | "DriverIssue" | "Other";
 export type ProviderManagerResponse =
   | {
       data: null;
@@ -359,6 +502,42 @@ export type ProviderManagerResponse =
         [k: string]: ProviderInfo;
       };
       method: "providers";
+    }
+  | {
+      data: null;
+      method: "add_slcan_adapter";
+    }
+  | {
+      data: UsageStatsSettings;
+      method: "usage_stats_settings";
+    }
+  | {
+      data: UsageStatsSettings;
+      method: "set_usage_stats_enabled";
+    }
+  | {
+      data: UsageStatsReport;
+      method: "preview_usage_stats";
+    }
+  | {
+      data: RegistryEntry[];
+      method: "device_registry";
+    }
+  | {
+      data: null;
+      method: "forget_device";
+    }
+  | {
+      data: null;
+      method: "recover_gated_device";
+    }
+  | {
+      data: UsbPermissionFix;
+      method: "usb_permission_fix";
+    }
+  | {
+      data: string;
+      method: "write_udev_rule";
     };
 export type WrappedDeviceProviderResponse =
   | {
@@ -391,13 +570,27 @@ export type DeviceManagerResponse =
         [k: string]: [DeviceId, DeviceInfo, string][];
       };
       method: "devices";
+    }
+  | {
+      data: {
+        [k: string]: BusLoadReport;
+      };
+      method: "bus_load";
     };
 export type DeviceType =
   | ("RoboRIO" | "Unknown")
   | {
       Grapple: GrappleModelId;
+    }
+  | {
+      UnknownGrapple: number;
     };
 export type GrappleModelId = "LaserCan" | "SpiderLan" | "FlexiCAN" | "MitoCANdria";
+export type DeviceState =
+  | ("Discovered" | "Ready" | "Updating" | "Rebooting" | "Lost")
+  | {
+      IncompatibleFirmware: string;
+    };
 export type RoboRioDaemonRequest =
   | {
       data: {};
@@ -414,6 +607,16 @@ export type RoboRioDaemonRequest =
         address: string;
       };
       method: "set_address";
+    }
+  | {
+      data: {};
+      method: "discovered";
+    }
+  | {
+      data: {
+        address: string;
+      };
+      method: "connect_discovered";
     };
 export type RoboRioDaemonResponse =
   | {
@@ -427,6 +630,83 @@ export type RoboRioDaemonResponse =
   | {
       data: null;
       method: "set_address";
+    }
+  | {
+      data: DiscoveredRoboRio[];
+      method: "discovered";
+    }
+  | {
+      data: null;
+      method: "connect_discovered";
+    };
+export type DiscoveryMethod = "Mdns" | "Usb";
+export type SlcanRequest =
+  | {
+      data: {};
+      method: "status";
+    }
+  | {
+      data: {
+        bitrate: number;
+      };
+      method: "set_bitrate";
+    };
+export type SlcanResponse =
+  | {
+      data: SlcanStatus;
+      method: "status";
+    }
+  | {
+      data: null;
+      method: "set_bitrate";
+    };
+export type SocketCanRequest =
+  | {
+      data: {};
+      method: "status";
+    }
+  | {
+      data: {
+        bitrate?: number | null;
+      };
+      method: "set_bitrate";
+    };
+export type SocketCanResponse =
+  | {
+      data: SocketCanStatus;
+      method: "status";
+    }
+  | {
+      data: null;
+      method: "set_bitrate";
+    };
+export type SpiderLanRequest =
+  | {
+      data: {};
+      method: "start_field_upgrade";
+    }
+  | {
+      data: {
+        msg: GrappleDeviceRequest;
+      };
+      method: "grapple";
+    }
+  | {
+      data: {};
+      method: "status";
+    };
+export type SpiderLanResponse =
+  | {
+      data: null;
+      method: "start_field_upgrade";
+    }
+  | {
+      data: GrappleDeviceResponse;
+      method: "grapple";
+    }
+  | {
+      data: SpiderLanStatus;
+      method: "status";
     };
 
 export interface MegaSchema {
@@ -434,6 +714,10 @@ export interface MegaSchema {
   firmware_rsp: FirmwareUpgradeDeviceResponse;
   flexican_req: FlexiCanRequest;
   flexican_rsp: FlexiCanResponse;
+  generic_grapple_req: GenericGrappleDeviceRequest;
+  generic_grapple_rsp: GenericGrappleDeviceResponse;
+  gs_usb_req: GsUsbRequest;
+  gs_usb_rsp: GsUsbResponse;
   lasercan_req: LaserCanRequest;
   lasercan_rsp: LaserCanResponse;
   light_release_response: LightReleaseResponse;
@@ -441,10 +725,18 @@ export interface MegaSchema {
   mitocandria_rsp: MitocandriaResponse;
   old_version_req: OldVersionDeviceRequest;
   old_version_rsp: OldVersionDeviceResponse;
+  pcan_usb_req: PcanUsbRequest;
+  pcan_usb_rsp: PcanUsbResponse;
   provider_manager_req: ProviderManagerRequest;
   provider_manager_rsp: ProviderManagerResponse;
   roborio_req: RoboRioDaemonRequest;
   roborio_rsp: RoboRioDaemonResponse;
+  slcan_req: SlcanRequest;
+  slcan_rsp: SlcanResponse;
+  socketcan_req: SocketCanRequest;
+  socketcan_rsp: SocketCanResponse;
+  spiderlan_req: SpiderLanRequest;
+  spiderlan_rsp: SpiderLanResponse;
 }
 export interface FlexiCanStatus {}
 export interface LaserCanRoi {
@@ -464,12 +756,22 @@ export interface LaserCanMeasurement {
   roi: LaserCanRoi;
   status: number;
 }
+export interface OutputRateOption {
+  budget: LaserCanTimingBudget;
+  bus_utilization: number;
+  rate_hz: number;
+}
 export interface LightReleaseResponse {
+  assets?: ReleaseAsset[];
   html_url: string;
   name: string;
   published_at: string;
   tag_name: string;
 }
+export interface ReleaseAsset {
+  browser_download_url: string;
+  name: string;
+}
 export interface MitocandriaSwitchableChannelRequest {
   channel: number;
   enabled: boolean;
@@ -494,6 +796,56 @@ export interface MitocandriaStatusFrame {
     MitocandriaChannelStatus
   ];
 }
+export interface UsageStatsSettings {
+  enabled: boolean;
+  endpoint?: string | null;
+  last_error?: string | null;
+  last_sent_ms?: number | null;
+}
+export interface UsageStatsReport {
+  app_version: string;
+  firmware_updates: UpdateOutcomes[];
+  firmware_versions: FirmwareCount[];
+  models: ModelCount[];
+  os: string;
+  period_start_ms: number;
+}
+export interface ModelCount {
+  count: number;
+  model: string;
+}
+export interface FirmwareCount {
+  count: number;
+  model: string;
+  version: string;
+}
+export interface UpdateOutcomes {
+  failed: number;
+  interrupted: number;
+  model: string;
+  succeeded: number;
+}
+export interface RegistryEntry {
+  device: RegisteredDevice;
+  online: boolean;
+}
+export interface RegisteredDevice {
+  can_id?: number | null;
+  device_type: DeviceType;
+  domain: string;
+  firmware_version?: string | null;
+  first_seen_ms: number;
+  last_seen_ms: number;
+  name?: string | null;
+  serial: number;
+}
+export interface GatedRecovery {
+  current_version?: string | null;
+  download_url?: string | null;
+  explanation: string;
+  lookup_done: boolean;
+  release?: LightReleaseResponse | null;
+}
 export interface ProviderInfo {
   address: string;
   connected: boolean;
@@ -508,7 +860,55 @@ export interface DeviceInfo {
   is_dfu_in_progress: boolean;
   name?: string | null;
   serial?: number | null;
+  state?: DeviceState;
+}
+export interface BusLoadReport {
+  devices: [DeviceId, number][];
+  total: number;
+  warning?: string | null;
+}
+export interface UsbPermissionFix {
+  description: string;
+  instructions: string[];
+  kind: UsbIssueKind;
+  udev_rule?: string | null;
 }
 export interface RoboRIOStatus {
   using_daemon: boolean;
 }
+export interface DiscoveredRoboRio {
+  address: string;
+  hostname?: string | null;
+  method: DiscoveryMethod;
+  team?: number | null;
+}
+export interface SpiderLanStatus {
+  last_message_ms?: number | null;
+  messages_received: number;
+}
+export interface SocketCanStatus {
+  bitrate?: number | null;
+  interface: string;
+  is_virtual: boolean;
+  last_error?: string | null;
+  supported_bitrates: number[];
+  up: boolean;
+}
+export interface SlcanStatus {
+  bitrate: number;
+  last_error?: string | null;
+  port: string;
+  supported_bitrates: number[];
+}
+export interface GsUsbStatus {
+  address: string;
+  bitrate: number;
+  last_error?: string | null;
+  supported_bitrates: number[];
+}
+export interface PcanUsbStatus {
+  bitrate: number;
+  channel: string;
+  last_error?: string | null;
+  supported_bitrates: number[];
+}